@@ -0,0 +1,197 @@
+use clap::Parser;
+use std::{io, process::Command};
+
+#[derive(Parser)]
+enum EnwiroAdapterAwesomeCLI {
+    GetActiveWorkspaceId(GetActiveWorkspaceIdArgs),
+    Activate(ActivateArgs),
+    Metadata(MetadataArgs),
+    HideWorkspaces(HideWorkspacesArgs),
+    ShowWorkspaces(ShowWorkspacesArgs),
+}
+
+#[derive(clap::Args)]
+pub struct GetActiveWorkspaceIdArgs {}
+
+#[derive(clap::Args)]
+pub struct ActivateArgs {
+    environment_name: String,
+}
+
+#[derive(clap::Args)]
+pub struct MetadataArgs {}
+
+#[derive(clap::Args)]
+pub struct HideWorkspacesArgs {
+    environment_names: Vec<String>,
+}
+
+#[derive(clap::Args)]
+pub struct ShowWorkspacesArgs {
+    environment_names: Vec<String>,
+}
+
+const METADATA: &str = r#"{"protocolVersion": 2, "description": "Tags in the Awesome window manager", "hideWorkspaces": true}"#;
+
+/// Returns the name of the selected tag of the focused screen, or nothing if
+/// no tag is selected.
+const GET_ACTIVE_TAG_SNIPPET: &str = r#"
+local awful = require("awful")
+local tag = awful.screen.focused().selected_tag
+return tag and tag.name or ""
+"#;
+
+/// Switches to the tag named `name`, creating it on the focused screen if
+/// there is no such tag on any screen yet.
+const ACTIVATE_TAG_SNIPPET: &str = r#"
+local awful = require("awful")
+local tag = awful.tag.find_by_name(nil, name)
+if tag == nil then
+    tag = awful.tag.add(name, {
+        screen = awful.screen.focused(),
+        layout = awful.layout.layouts[1],
+    })
+end
+awful.screen.focus(tag.screen)
+tag:view_only()
+"#;
+
+/// Hides the tags named in `names` from the taglist, or shows them again,
+/// depending on `hidden`. Tags that do not exist are skipped.
+const SET_TAGS_HIDDEN_SNIPPET: &str = r#"
+local awful = require("awful")
+for _, name in ipairs(names) do
+    local tag = awful.tag.find_by_name(nil, name)
+    if tag ~= nil then
+        tag.hidden = hidden
+    end
+end
+"#;
+
+/// Quotes a string as a Lua string literal, escaping quotes, backslashes and
+/// control characters, so that any environment name is passed safely.
+fn quote_lua_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' | '\\' => quoted.push_str(&format!("\\{}", character)),
+            character if character.is_ascii_control() => {
+                quoted.push_str(&format!("\\{:03}", character as u8))
+            }
+            character => quoted.push(character),
+        }
+    }
+    quoted.push('"');
+
+    quoted
+}
+
+fn set_tags_hidden(environment_names: &[String], hidden: bool) -> io::Result<()> {
+    let names: Vec<String> = environment_names
+        .iter()
+        .map(|name| quote_lua_string(name))
+        .collect();
+    run_snippet(&format!(
+        "local names = {{{}}}\nlocal hidden = {}\n{}",
+        names.join(", "),
+        hidden,
+        SET_TAGS_HIDDEN_SNIPPET
+    ))?;
+
+    Ok(())
+}
+
+/// Runs a Lua snippet inside Awesome, returning what it printed.
+fn run_snippet(snippet: &str) -> io::Result<String> {
+    let output = Command::new("awesome-client").arg(snippet).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "awesome-client failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Extracts a string returned by a snippet. `awesome-client` prints returned
+/// values along with their type, such as `string "name"`.
+fn parse_returned_string(output: &str) -> Option<&str> {
+    output
+        .trim()
+        .strip_prefix("string \"")
+        .and_then(|value| value.strip_suffix('"'))
+}
+
+fn main() -> io::Result<()> {
+    let args = EnwiroAdapterAwesomeCLI::parse();
+    let _ = enwiro_logging::init_logging("enwiro-adapter-awesome");
+
+    match args {
+        EnwiroAdapterAwesomeCLI::GetActiveWorkspaceId(_) => {
+            let output = run_snippet(GET_ACTIVE_TAG_SNIPPET)?;
+            let tag_name = parse_returned_string(&output).ok_or_else(|| {
+                io::Error::other(format!(
+                    "Unexpected output from awesome-client: {}",
+                    output.trim()
+                ))
+            })?;
+
+            print!("{}", tag_name);
+        }
+        EnwiroAdapterAwesomeCLI::Activate(args) => {
+            run_snippet(&format!(
+                "local name = {}\n{}",
+                quote_lua_string(&args.environment_name),
+                ACTIVATE_TAG_SNIPPET
+            ))?;
+        }
+        EnwiroAdapterAwesomeCLI::HideWorkspaces(args) => {
+            set_tags_hidden(&args.environment_names, true)?;
+        }
+        EnwiroAdapterAwesomeCLI::ShowWorkspaces(args) => {
+            set_tags_hidden(&args.environment_names, false)?;
+        }
+        EnwiroAdapterAwesomeCLI::Metadata(_) => {
+            println!("{}", METADATA);
+        }
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_lua_string() {
+        assert_eq!(quote_lua_string("enwiro"), r#""enwiro""#);
+        assert_eq!(quote_lua_string(""), r#""""#);
+        assert_eq!(quote_lua_string(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+        assert_eq!(quote_lua_string("ünïcödé: 1"), r#""ünïcödé: 1""#);
+    }
+
+    #[test]
+    fn test_quote_lua_string_control_characters() {
+        // Decimal escapes always have three digits, so that digits after them
+        // are not read as part of the escape
+        assert_eq!(quote_lua_string("a\n1"), r#""a\0101""#);
+        assert_eq!(quote_lua_string("\t\u{7f}"), r#""\009\127""#);
+    }
+
+    #[test]
+    fn test_parse_returned_string() {
+        assert_eq!(
+            parse_returned_string("   string \"enwiro\"\n"),
+            Some("enwiro")
+        );
+        assert_eq!(
+            parse_returned_string("string \"enwiro: \"docs\"\""),
+            Some("enwiro: \"docs\"")
+        );
+        assert_eq!(parse_returned_string("string \"\""), Some(""));
+        assert_eq!(parse_returned_string(""), None);
+        assert_eq!(parse_returned_string("double 3"), None);
+    }
+}
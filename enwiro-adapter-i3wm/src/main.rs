@@ -1,42 +1,554 @@
+mod workspace_name;
+
 use clap::Parser;
-use std::io;
+use enwiro_errors::ErrorCode;
+use i3ipc_types::{
+    event::{Event, Subscribe, WorkspaceChange, WorkspaceData},
+    reply::{Node, Workspace},
+};
+use std::{collections::HashSet, fs, io, path::PathBuf, process::Command};
 use tokio_i3ipc::I3;
+use workspace_name::{load_configuration, ConfigurationValues, WorkspaceNameTemplate};
 
 #[derive(Parser)]
 enum EnwiroAdapterI3WmCLI {
     GetActiveWorkspaceId(GetActiveWorkspaceIdArgs),
+    Activate(ActivateArgs),
+    Metadata(MetadataArgs),
+    RenameWorkspace(RenameWorkspaceArgs),
+    ListWorkspaces(ListWorkspacesArgs),
+    GetFocusedWindow(GetFocusedWindowArgs),
+    RenumberWorkspaces(RenumberWorkspacesArgs),
+    WatchFocus(WatchFocusArgs),
 }
 
 #[derive(clap::Args)]
 pub struct GetActiveWorkspaceIdArgs {}
 
+#[derive(clap::Args)]
+pub struct ActivateArgs {
+    environment_name: String,
+
+    /// Workspace of the environment to show on an output, as
+    /// `<output>=<workspace>`. An empty workspace is the one of the
+    /// environment itself.
+    #[arg(long = "output", value_parser = parse_output)]
+    outputs: Vec<(String, String)>,
+
+    /// Position of the environment among the most used ones, starting at 1
+    #[arg(long)]
+    rank: Option<usize>,
+}
+
+fn parse_output(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(output, workspace)| (output.to_string(), workspace.to_string()))
+        .ok_or_else(|| format!("Expected <output>=<workspace>, got {}", value))
+}
+
+#[derive(clap::Args)]
+pub struct MetadataArgs {}
+
+#[derive(clap::Args)]
+pub struct ListWorkspacesArgs {}
+
+#[derive(clap::Args)]
+pub struct GetFocusedWindowArgs {}
+
+#[derive(clap::Args)]
+pub struct RenameWorkspaceArgs {
+    /// Workspace as printed by `list-workspaces`
+    workspace_name: String,
+    new_environment_name: String,
+}
+
+#[derive(clap::Args)]
+pub struct WatchFocusArgs {}
+
+#[derive(clap::Args)]
+pub struct RenumberWorkspacesArgs {
+    /// Environments, the most used first
+    environment_names: Vec<String>,
+}
+
+const METADATA: &str = r#"{"protocolVersion": 2, "description": "Workspaces in the i3 window manager", "outputs": true, "workspaces": true, "focusedWindow": true, "ranks": true, "watchFocus": true}"#;
+
+fn load_template() -> io::Result<WorkspaceNameTemplate> {
+    WorkspaceNameTemplate::new(&load_configuration()?.workspace_name_template)
+}
+
+fn load_configuration_and_template() -> io::Result<(ConfigurationValues, WorkspaceNameTemplate)> {
+    let configuration = load_configuration()?;
+    let template = WorkspaceNameTemplate::new(&configuration.workspace_name_template)?;
+
+    Ok((configuration, template))
+}
+
+/// Strips the parts of the workspace name added by the template. The number
+/// is taken from i3 rather than from the name, since environment names may
+/// contain digits. Workspaces not named by the template have no environment.
+fn get_environment_name(template: &WorkspaceNameTemplate, workspace: &Workspace) -> Option<String> {
+    template.parse(workspace.num, &workspace.name)
+}
+
+/// The environment of the workspace that an event shows gaining focus, which
+/// includes the focused workspace being renamed, as happens when a workspace
+/// created by hand gets its name. `None` for other events.
+fn get_focused_environment_name(
+    template: &WorkspaceNameTemplate,
+    event: &WorkspaceData,
+) -> Option<Option<String>> {
+    let workspace = event.current.as_ref()?;
+    match event.change {
+        WorkspaceChange::Focus => {}
+        WorkspaceChange::Rename if workspace.focused => {}
+        _ => return None,
+    }
+
+    Some(template.parse(workspace.num.unwrap_or(-1), workspace.name.as_deref()?))
+}
+
+/// The slot of the given rank if it is one of the ranked slots and free, and
+/// the lowest workspace number above the ranked slots not used by any
+/// workspace otherwise.
+fn get_free_number(workspaces: &[Workspace], ranked_slots: i32, rank: Option<usize>) -> i32 {
+    let used_numbers: HashSet<i32> = workspaces.iter().map(|workspace| workspace.num).collect();
+    let ranked_slot = rank
+        .and_then(|rank| i32::try_from(rank).ok())
+        .filter(|slot| (1..=ranked_slots).contains(slot) && !used_numbers.contains(slot));
+
+    ranked_slot
+        .or_else(|| (ranked_slots.max(0) + 1..).find(|number| !used_numbers.contains(number)))
+        .expect("Could not find a free workspace number")
+}
+
+/// Quotes a workspace or output name for i3 commands, so that environment
+/// names containing spaces or quotes are passed safely.
+fn quote_name(workspace_name: &str) -> String {
+    let escaped_name = workspace_name.replace('\\', "\\\\").replace('"', "\\\"");
+
+    format!("\"{}\"", escaped_name)
+}
+
+/// Builds an i3 command switching to the given workspace.
+fn build_workspace_command(workspace_name: &str) -> String {
+    format!("workspace {}", quote_name(workspace_name))
+}
+
+/// Builds an i3 command renaming a workspace.
+fn build_rename_command(workspace: &Workspace, new_name: &str) -> String {
+    format!(
+        "rename workspace {} to {}",
+        quote_name(&workspace.name),
+        quote_name(new_name)
+    )
+}
+
+async fn run_command(i3: &mut I3, command: String, default_error: &str) -> io::Result<()> {
+    for result in i3.run_command(command).await? {
+        if !result.success {
+            return Err(io::Error::other(
+                result.error.unwrap_or_else(|| default_error.to_string()),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Switches to the workspace of an environment, creating it on the focused
+/// output if it does not exist yet.
+async fn switch_to_environment(
+    i3: &mut I3,
+    template: &WorkspaceNameTemplate,
+    environment_name: &str,
+    ranked_slots: i32,
+    rank: Option<usize>,
+) -> io::Result<()> {
+    let workspaces = i3.get_workspaces().await?;
+    let existing_workspace = workspaces.iter().find(|workspace| {
+        get_environment_name(template, workspace).as_deref() == Some(environment_name)
+    });
+
+    let workspace_name = match existing_workspace {
+        Some(workspace) => workspace.name.clone(),
+        None => template.format(
+            get_free_number(&workspaces, ranked_slots, rank),
+            environment_name,
+        ),
+    };
+
+    run_command(
+        i3,
+        build_workspace_command(&workspace_name),
+        "i3 could not switch workspace",
+    )
+    .await
+}
+
+/// Moves the workspaces of the most used environments to the ranked slot of
+/// their rank. Workspaces in the way move to a free number above the ranked
+/// slots.
+async fn renumber_workspaces(
+    i3: &mut I3,
+    template: &WorkspaceNameTemplate,
+    ranked_slots: i32,
+    environment_names: &[String],
+) -> io::Result<()> {
+    for (slot, environment_name) in (1..=ranked_slots).zip(environment_names) {
+        let workspaces = i3.get_workspaces().await?;
+        let Some(workspace) = workspaces.iter().find(|workspace| {
+            get_environment_name(template, workspace).as_ref() == Some(environment_name)
+        }) else {
+            continue;
+        };
+        if workspace.num == slot {
+            continue;
+        }
+
+        if let Some(occupant) = workspaces.iter().find(|workspace| workspace.num == slot) {
+            let number = get_free_number(&workspaces, ranked_slots, None);
+            // Workspaces not named by the template, such as plain numbered
+            // ones, become plain numbered workspaces
+            let new_name = match get_environment_name(template, occupant) {
+                Some(name) if occupant.name != occupant.num.to_string() => {
+                    template.format(number, &name)
+                }
+                _ => number.to_string(),
+            };
+            run_command(
+                i3,
+                build_rename_command(occupant, &new_name),
+                "i3 could not rename workspace",
+            )
+            .await?;
+        }
+        let new_name = template.format(slot, environment_name);
+        // Templates without a number leave nothing to renumber
+        if new_name != workspace.name {
+            run_command(
+                i3,
+                build_rename_command(workspace, &new_name),
+                "i3 could not rename workspace",
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the focused container in a layout tree.
+fn find_focused_node(node: &Node) -> Option<&Node> {
+    if node.focused {
+        return Some(node);
+    }
+
+    node.nodes
+        .iter()
+        .chain(node.floating_nodes.iter())
+        .find_map(find_focused_node)
+}
+
+/// Asks the X server for the process owning a window. i3 does not know it.
+fn get_window_pid(window: usize) -> Option<u32> {
+    let output = Command::new("xprop")
+        .args(["-id", &window.to_string(), "_NET_WM_PID"])
+        .output()
+        .ok()?;
+
+    // The output looks like `_NET_WM_PID(CARDINAL) = 1234`
+    String::from_utf8_lossy(&output.stdout)
+        .split_once('=')?
+        .1
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Working directory of the most recently started descendant of a process,
+/// such as the shell in a terminal, or of the process itself if it has no
+/// children.
+fn get_innermost_cwd(pid: u32) -> Option<PathBuf> {
+    let mut pid = pid;
+    while let Some(child) = fs::read_to_string(format!("/proc/{0}/task/{0}/children", pid))
+        .ok()
+        .and_then(|children| children.split_whitespace().last()?.parse().ok())
+    {
+        pid = child;
+    }
+
+    fs::read_link(format!("/proc/{}/cwd", pid)).ok()
+}
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> io::Result<()> {
     let args = EnwiroAdapterI3WmCLI::parse();
+    let _ = enwiro_logging::init_logging("enwiro-adapter-i3wm");
 
     match args {
         EnwiroAdapterI3WmCLI::GetActiveWorkspaceId(_) => {
+            let template = load_template()?;
             let mut i3 = I3::connect().await?;
             let workspaces = i3.get_workspaces().await?;
             let focused_workspace = workspaces
                 .into_iter()
                 .find(|workspace| workspace.focused)
-                .expect("No active workspace. This should never happen.");
+                .ok_or_else(|| {
+                    ErrorCode::AdapterFailed
+                        .error(io::ErrorKind::NotFound, "i3 reports no focused workspace")
+                })?;
             let mut environment_name: String = "".to_string();
             let is_active_environment = focused_workspace.id.to_string() != focused_workspace.name;
 
             if is_active_environment {
-                environment_name = focused_workspace
-                    .name
-                    .replace(':', "")
-                    .replace(&focused_workspace.num.to_string(), "")
-                    .trim()
-                    .to_string()
+                environment_name =
+                    get_environment_name(&template, &focused_workspace).unwrap_or_default();
             }
 
             print!("{}", environment_name);
         }
+        EnwiroAdapterI3WmCLI::Activate(args) => {
+            let (configuration, template) = load_configuration_and_template()?;
+            let mut i3 = I3::connect().await?;
+            // Further workspaces of the environment are named like lenses, so
+            // that enwiro recognizes the environment when one of them is focused
+            for (output, workspace) in &args.outputs {
+                let environment_name = match workspace.is_empty() {
+                    true => args.environment_name.clone(),
+                    false => format!("{}:{}", args.environment_name, workspace),
+                };
+                // Only the main workspace of the environment gets its ranked slot
+                let rank = args.rank.filter(|_| workspace.is_empty());
+                switch_to_environment(
+                    &mut i3,
+                    &template,
+                    &environment_name,
+                    configuration.ranked_slots,
+                    rank,
+                )
+                .await?;
+                run_command(
+                    &mut i3,
+                    format!("move workspace to output {}", quote_name(output)),
+                    "i3 could not move workspace",
+                )
+                .await?;
+            }
+
+            switch_to_environment(
+                &mut i3,
+                &template,
+                &args.environment_name,
+                configuration.ranked_slots,
+                args.rank,
+            )
+            .await?;
+        }
+        EnwiroAdapterI3WmCLI::RenameWorkspace(args) => {
+            let (configuration, template) = load_configuration_and_template()?;
+            let mut i3 = I3::connect().await?;
+            let workspaces = i3.get_workspaces().await?;
+            // Environments without a workspace have nothing to rename
+            if let Some(workspace) = workspaces.iter().find(|workspace| {
+                get_environment_name(&template, workspace) == Some(args.workspace_name.clone())
+            }) {
+                // Numbered workspaces keep their number so they stay in the same slot
+                let number = match workspace.num {
+                    number if number >= 0 => number,
+                    _ => get_free_number(&workspaces, configuration.ranked_slots, None),
+                };
+                let new_name = template.format(number, &args.new_environment_name);
+                // Workspaces that already have the right name are left alone
+                if new_name != workspace.name {
+                    run_command(
+                        &mut i3,
+                        build_rename_command(workspace, &new_name),
+                        "i3 could not rename workspace",
+                    )
+                    .await?;
+                }
+            }
+        }
+        EnwiroAdapterI3WmCLI::ListWorkspaces(_) => {
+            let template = load_template()?;
+            let mut i3 = I3::connect().await?;
+            for workspace in i3.get_workspaces().await? {
+                if let Some(environment_name) = get_environment_name(&template, &workspace) {
+                    println!("{}", environment_name);
+                }
+            }
+        }
+        EnwiroAdapterI3WmCLI::GetFocusedWindow(_) => {
+            let mut i3 = I3::connect().await?;
+            let tree = i3.get_tree().await?;
+            let focused_node = find_focused_node(&tree);
+            let title = focused_node.and_then(|node| node.name.clone());
+            let cwd = focused_node
+                .and_then(|node| node.window)
+                .and_then(get_window_pid)
+                .and_then(get_innermost_cwd);
+
+            println!(
+                "{}",
+                serde_json::json!({
+                    "title": title,
+                    "cwd": cwd.map(|cwd| cwd.to_string_lossy().to_string()),
+                })
+            );
+        }
+        EnwiroAdapterI3WmCLI::RenumberWorkspaces(args) => {
+            let (configuration, template) = load_configuration_and_template()?;
+            let mut i3 = I3::connect().await?;
+            renumber_workspaces(
+                &mut i3,
+                &template,
+                configuration.ranked_slots,
+                &args.environment_names,
+            )
+            .await?;
+        }
+        EnwiroAdapterI3WmCLI::WatchFocus(_) => {
+            let template = load_template()?;
+            let mut i3 = I3::connect().await?;
+            i3.subscribe([Subscribe::Workspace]).await?;
+            loop {
+                if let Event::Workspace(event) = i3.read_event().await? {
+                    if let Some(environment_name) = get_focused_environment_name(&template, &event)
+                    {
+                        println!("{}", environment_name.unwrap_or_default());
+                    }
+                }
+            }
+        }
+        EnwiroAdapterI3WmCLI::Metadata(_) => {
+            println!("{}", METADATA);
+        }
     };
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use i3ipc_types::{event::WorkspaceData, reply::Workspace};
+
+    use crate::{
+        get_environment_name, get_focused_environment_name, get_free_number, WorkspaceNameTemplate,
+    };
+
+    fn get_workspace(number: i32, name: &str) -> Workspace {
+        serde_json::from_value(serde_json::json!({
+            "num": number,
+            "name": name,
+            "visible": false,
+            "focused": false,
+            "urgent": false,
+            "rect": {"x": 0, "y": 0, "width": 0, "height": 0},
+            "output": "HDMI-1",
+            "focus": [],
+        }))
+        .unwrap()
+    }
+
+    fn get_workspace_event(change: &str, number: i32, name: &str, focused: bool) -> WorkspaceData {
+        let rect = serde_json::json!({"x": 0, "y": 0, "width": 0, "height": 0});
+        serde_json::from_value(serde_json::json!({
+            "change": change,
+            "current": {
+                "id": 1,
+                "name": name,
+                "num": number,
+                "type": "workspace",
+                "layout": "splith",
+                "output": "HDMI-1",
+                "orientation": "horizontal",
+                "border": "normal",
+                "scratchpad_state": "none",
+                "percent": null,
+                "rect": rect,
+                "window_rect": rect,
+                "deco_rect": rect,
+                "geometry": rect,
+                "window": null,
+                "current_border_width": -1,
+                "urgent": false,
+                "focused": focused,
+                "focus": [],
+                "sticky": false,
+                "floating_nodes": [],
+                "fullscreen_mode": 0,
+                "nodes": [],
+            },
+            "old": null,
+        }))
+        .unwrap()
+    }
+
+    fn get_workspaces(numbers: &[i32]) -> Vec<Workspace> {
+        numbers
+            .iter()
+            .map(|number| get_workspace(*number, &format!("{}: foo", number)))
+            .collect()
+    }
+
+    #[test]
+    fn test_environment_name_of_workspace() {
+        let template = WorkspaceNameTemplate::new("{num}: {env}").unwrap();
+
+        assert_eq!(
+            get_environment_name(&template, &get_workspace(3, "3: enwiro")),
+            Some("enwiro".to_string())
+        );
+        assert_eq!(
+            get_environment_name(&template, &get_workspace(3, "3")),
+            None
+        );
+        assert_eq!(
+            get_environment_name(&template, &get_workspace(-1, "mail")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_focused_environment_name() {
+        let template = WorkspaceNameTemplate::new("{num}: {env}").unwrap();
+        let get = |change: &str, name: &str, focused: bool| {
+            get_focused_environment_name(&template, &get_workspace_event(change, 3, name, focused))
+        };
+
+        assert_eq!(
+            get("focus", "3: newproject", true),
+            Some(Some("newproject".to_string()))
+        );
+        assert_eq!(
+            get("rename", "3: newproject", true),
+            Some(Some("newproject".to_string()))
+        );
+        assert_eq!(get("focus", "3", true), Some(None));
+        assert_eq!(get("rename", "3: newproject", false), None);
+        assert_eq!(get("init", "3: newproject", false), None);
+    }
+
+    #[test]
+    fn test_free_number_is_lowest_unused_one() {
+        assert_eq!(get_free_number(&get_workspaces(&[]), 0, None), 1);
+        assert_eq!(get_free_number(&get_workspaces(&[1, 2, 4]), 0, None), 3);
+        assert_eq!(get_free_number(&get_workspaces(&[1, 2, 4]), -1, None), 3);
+    }
+
+    #[test]
+    fn test_free_number_is_above_ranked_slots() {
+        assert_eq!(get_free_number(&get_workspaces(&[]), 3, None), 4);
+        assert_eq!(get_free_number(&get_workspaces(&[4, 5]), 3, None), 6);
+        assert_eq!(get_free_number(&get_workspaces(&[]), 3, Some(4)), 4);
+        assert_eq!(get_free_number(&get_workspaces(&[]), 0, Some(1)), 1);
+    }
+
+    #[test]
+    fn test_free_number_is_slot_of_rank_if_free() {
+        assert_eq!(get_free_number(&get_workspaces(&[1, 3]), 3, Some(2)), 2);
+        assert_eq!(get_free_number(&get_workspaces(&[1, 2]), 3, Some(2)), 4);
+    }
+}
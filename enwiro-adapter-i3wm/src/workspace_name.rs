@@ -0,0 +1,171 @@
+use std::io;
+
+use serde_derive::{Deserialize, Serialize};
+
+const NUMBER_PLACEHOLDER: &str = "{num}";
+const ENVIRONMENT_PLACEHOLDER: &str = "{env}";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigurationValues {
+    /// Names of the workspaces of environments, where `{env}` is replaced
+    /// with the name of the environment and `{num}` with the number of the
+    /// workspace.
+    pub workspace_name_template: String,
+
+    /// Number of low workspace numbers kept for the most used environments,
+    /// the one used most getting 1. Other workspaces get numbers above them.
+    pub ranked_slots: i32,
+}
+
+impl Default for ConfigurationValues {
+    fn default() -> Self {
+        Self {
+            workspace_name_template: "{num}: {env}".to_string(),
+            ranked_slots: 0,
+        }
+    }
+}
+
+pub fn load_configuration() -> Result<ConfigurationValues, io::Error> {
+    confy::load::<ConfigurationValues>("enwiro", "adapter-i3wm").map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Could not load configuration: {}", error),
+        )
+    })
+}
+
+/// Formats the names of the workspaces of environments and reads the
+/// environment back from them, the text around `{env}` being the part of the
+/// template before and after it.
+pub struct WorkspaceNameTemplate {
+    before: String,
+    after: String,
+}
+
+impl WorkspaceNameTemplate {
+    pub fn new(template: &str) -> Result<Self, io::Error> {
+        let invalid_template = |reason: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid workspace name template \"{}\": {}",
+                    template, reason
+                ),
+            )
+        };
+        let (before, after) = template
+            .split_once(ENVIRONMENT_PLACEHOLDER)
+            .ok_or_else(|| invalid_template("it does not contain {env}"))?;
+        if after.contains(ENVIRONMENT_PLACEHOLDER) {
+            return Err(invalid_template("it contains {env} more than once"));
+        }
+        if template.matches(NUMBER_PLACEHOLDER).count() > 1 {
+            return Err(invalid_template("it contains {num} more than once"));
+        }
+
+        Ok(Self {
+            before: before.to_string(),
+            after: after.to_string(),
+        })
+    }
+
+    pub fn format(&self, number: i32, environment_name: &str) -> String {
+        let number = number.to_string();
+
+        format!(
+            "{}{}{}",
+            self.before.replace(NUMBER_PLACEHOLDER, &number),
+            environment_name,
+            self.after.replace(NUMBER_PLACEHOLDER, &number)
+        )
+    }
+
+    /// Strips the text around the environment name. Whitespace next to the
+    /// environment name is not significant. Names that do not match the
+    /// template, such as plain numbered workspaces, have no environment.
+    pub fn parse(&self, number: i32, workspace_name: &str) -> Option<String> {
+        let number = number.to_string();
+        let before = self.before.replace(NUMBER_PLACEHOLDER, &number);
+        let after = self.after.replace(NUMBER_PLACEHOLDER, &number);
+
+        let environment_name = workspace_name
+            .strip_prefix(before.trim_end())?
+            .strip_suffix(after.trim_start())?
+            .trim();
+
+        match environment_name.is_empty() {
+            true => None,
+            false => Some(environment_name.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_round_trip() {
+        let templates = [
+            "{num}: {env}",
+            "{env}",
+            "{num}{env}",
+            "{env} ({num})",
+            "[{num}] {env} :",
+        ];
+        let names = [
+            "enwiro",
+            "2048",
+            "42: answer",
+            "enwiro: docs",
+            "a: b: c",
+            "v2 (3)",
+            ":",
+        ];
+
+        for template in templates {
+            let workspace_name_template = WorkspaceNameTemplate::new(template).unwrap();
+            for name in names {
+                for number in [1, 3, 10] {
+                    let workspace_name = workspace_name_template.format(number, name);
+
+                    assert_eq!(
+                        workspace_name_template.parse(number, &workspace_name),
+                        Some(name.to_string()),
+                        "{template} formatted {name} as {workspace_name}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let template = WorkspaceNameTemplate::new("{num}: {env}").unwrap();
+
+        assert_eq!(template.format(3, "enwiro"), "3: enwiro");
+        assert_eq!(template.format(10, "1: foo"), "10: 1: foo");
+    }
+
+    #[test]
+    fn test_parse_names_not_matching_the_template() {
+        let template = WorkspaceNameTemplate::new("{num}: {env}").unwrap();
+
+        assert_eq!(template.parse(3, "4: enwiro"), None);
+        assert_eq!(template.parse(3, "3:enwiro"), Some("enwiro".to_string()));
+        assert_eq!(template.parse(3, " enwiro "), None);
+        assert_eq!(template.parse(3, "3"), None);
+        assert_eq!(template.parse(3, "3: "), None);
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        for template in ["{num}", "{env} {env}", "{num} {env} {num}"] {
+            let error = WorkspaceNameTemplate::new(template).err().unwrap();
+
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
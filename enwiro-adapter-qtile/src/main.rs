@@ -0,0 +1,164 @@
+use clap::Parser;
+use std::{io, process::Command};
+
+#[derive(Parser)]
+enum EnwiroAdapterQtileCLI {
+    GetActiveWorkspaceId(GetActiveWorkspaceIdArgs),
+    Activate(ActivateArgs),
+    Metadata(MetadataArgs),
+}
+
+#[derive(clap::Args)]
+pub struct GetActiveWorkspaceIdArgs {}
+
+#[derive(clap::Args)]
+pub struct ActivateArgs {
+    environment_name: String,
+}
+
+#[derive(clap::Args)]
+pub struct MetadataArgs {}
+
+const METADATA: &str =
+    r#"{"protocolVersion": 2, "description": "Groups in the Qtile window manager"}"#;
+
+/// Calls a command of a Qtile object through its IPC, returning what Qtile
+/// printed. Values starting with `-` are refused, since `qtile cmd-obj` would
+/// read them as options.
+fn run_qtile_command(object: &[&str], function: &str, args: &[&str]) -> io::Result<String> {
+    if let Some(value) = object
+        .iter()
+        .chain(args)
+        .find(|value| value.starts_with('-'))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Qtile cannot be given names starting with \"-\": {}", value),
+        ));
+    }
+
+    let mut command = Command::new("qtile");
+    command
+        .arg("cmd-obj")
+        .arg("-o")
+        .args(object)
+        .arg("-f")
+        .arg(function);
+    if !args.is_empty() {
+        command.arg("-a").args(args);
+    }
+
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "qtile {} failed: {}",
+            function,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Reads a Python string literal at the start of `text`, as printed by Qtile
+/// in the representation of dictionaries.
+fn parse_python_string(text: &str) -> Option<String> {
+    let mut characters = text.chars();
+    let quote = characters
+        .next()
+        .filter(|quote| *quote == '\'' || *quote == '"')?;
+    let mut value = String::new();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => match characters.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                'r' => value.push('\r'),
+                escaped => value.push(escaped),
+            },
+            character if character == quote => return Some(value),
+            character => value.push(character),
+        }
+    }
+
+    None
+}
+
+/// Extracts the name of a group from the output of its `info` command.
+fn parse_group_name(info: &str) -> Option<String> {
+    let (_, rest) = info.split_once("'name': ")?;
+
+    parse_python_string(rest)
+}
+
+fn main() -> io::Result<()> {
+    let args = EnwiroAdapterQtileCLI::parse();
+    let _ = enwiro_logging::init_logging("enwiro-adapter-qtile");
+
+    match args {
+        EnwiroAdapterQtileCLI::GetActiveWorkspaceId(_) => {
+            // Without a name, the group object is the current group
+            let info = run_qtile_command(&["group"], "info", &[])?;
+            let group_name = parse_group_name(&info).ok_or_else(|| {
+                io::Error::other(format!("Unexpected group info from qtile: {}", info.trim()))
+            })?;
+
+            print!("{}", group_name);
+        }
+        EnwiroAdapterQtileCLI::Activate(args) => {
+            // Adding a group that already exists does nothing
+            run_qtile_command(&["root"], "add_group", &[&args.environment_name])?;
+            run_qtile_command(&["group", &args.environment_name], "toscreen", &[])?;
+        }
+        EnwiroAdapterQtileCLI::Metadata(_) => {
+            println!("{}", METADATA);
+        }
+    };
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_python_string() {
+        assert_eq!(parse_python_string("'enwiro'"), Some("enwiro".to_string()));
+        assert_eq!(
+            parse_python_string(r#""it's", 'layout': 'max'}"#),
+            Some("it's".to_string())
+        );
+        assert_eq!(
+            parse_python_string(r"'enwiro: \'docs\'\n\\'"),
+            Some("enwiro: 'docs'\n\\".to_string())
+        );
+        assert_eq!(parse_python_string("''"), Some(String::new()));
+    }
+
+    #[test]
+    fn test_parse_python_string_invalid() {
+        assert_eq!(parse_python_string("enwiro"), None);
+        assert_eq!(parse_python_string("'enwiro"), None);
+        assert_eq!(parse_python_string(r"'enwiro\"), None);
+        assert_eq!(parse_python_string(""), None);
+    }
+
+    #[test]
+    fn test_parse_group_name() {
+        assert_eq!(
+            parse_group_name("{'focus': None, 'name': '1: enwiro', 'screen': 0}"),
+            Some("1: enwiro".to_string())
+        );
+        assert_eq!(parse_group_name("{'focus': None}"), None);
+    }
+
+    #[test]
+    fn test_names_starting_with_dash_are_refused() {
+        let error = run_qtile_command(&["root"], "add_group", &["--help"]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let error = run_qtile_command(&["group", "-x"], "toscreen", &[]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
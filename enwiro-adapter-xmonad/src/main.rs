@@ -0,0 +1,96 @@
+use clap::Parser;
+use std::{
+    env, fs,
+    io::{self, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+};
+
+#[derive(Parser)]
+enum EnwiroAdapterXmonadCLI {
+    GetActiveWorkspaceId(GetActiveWorkspaceIdArgs),
+    Activate(ActivateArgs),
+    Metadata(MetadataArgs),
+}
+
+#[derive(clap::Args)]
+pub struct GetActiveWorkspaceIdArgs {}
+
+#[derive(clap::Args)]
+pub struct ActivateArgs {
+    environment_name: String,
+}
+
+#[derive(clap::Args)]
+pub struct MetadataArgs {}
+
+const METADATA: &str =
+    r#"{"protocolVersion": 2, "description": "Workspaces in the XMonad window manager"}"#;
+
+/// File the log hook of the XMonad configuration writes the current workspace
+/// to, every time it changes.
+const CURRENT_WORKSPACE_FILE: &str = "current-workspace";
+
+/// Named pipe read by the XMonad configuration. Every line written to it is
+/// the name of a workspace to switch to, which is created if it does not
+/// exist yet.
+const COMMAND_PIPE: &str = "commands";
+
+/// Directory shared with the XMonad configuration, see `xmonad/Enwiro.hs`.
+/// It is only looked for in `$XDG_RUNTIME_DIR`, which only the user can
+/// write to, unlike shared directories such as `/tmp`.
+fn get_state_directory() -> io::Result<PathBuf> {
+    let runtime_directory = env::var_os("XDG_RUNTIME_DIR").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "XDG_RUNTIME_DIR is not set, so XMonad cannot be reached",
+        )
+    })?;
+
+    Ok(PathBuf::from(runtime_directory).join("enwiro-xmonad"))
+}
+
+fn not_set_up(state_directory: &Path) -> impl FnOnce(io::Error) -> io::Error + '_ {
+    move |error| {
+        io::Error::new(
+            error.kind(),
+            format!(
+                "Could not reach XMonad through {}, is Enwiro.hs set up in your configuration? {}",
+                state_directory.display(),
+                error
+            ),
+        )
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args = EnwiroAdapterXmonadCLI::parse();
+    let _ = enwiro_logging::init_logging("enwiro-adapter-xmonad");
+
+    match args {
+        EnwiroAdapterXmonadCLI::GetActiveWorkspaceId(_) => {
+            let state_directory = get_state_directory()?;
+            let workspace_name = fs::read_to_string(state_directory.join(CURRENT_WORKSPACE_FILE))
+                .map_err(not_set_up(&state_directory))?;
+
+            print!("{}", workspace_name.trim_end_matches('\n'));
+        }
+        EnwiroAdapterXmonadCLI::Activate(args) => {
+            let state_directory = get_state_directory()?;
+            // Opening the pipe without blocking fails right away when XMonad
+            // is not reading from it, instead of waiting forever
+            let mut pipe = fs::OpenOptions::new()
+                .write(true)
+                .custom_flags(libc::O_NONBLOCK)
+                .open(state_directory.join(COMMAND_PIPE))
+                .map_err(not_set_up(&state_directory))?;
+
+            pipe.write_all(format!("{}\n", args.environment_name).as_bytes())?;
+        }
+        EnwiroAdapterXmonadCLI::Metadata(_) => {
+            println!("{}", METADATA);
+        }
+    };
+
+    Ok(())
+}
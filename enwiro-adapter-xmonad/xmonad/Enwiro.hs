@@ -0,0 +1,92 @@
+-- | Companion of enwiro-adapter-xmonad. Copy this file to
+-- ~/.config/xmonad/lib/Enwiro.hs and add its hooks to your configuration.
+--
+-- The adapter and XMonad talk through $XDG_RUNTIME_DIR/enwiro-xmonad: the log
+-- hook writes the current workspace to the current-workspace file, and the
+-- names of workspaces to switch to are read from the commands named pipe.
+-- Nothing is done when $XDG_RUNTIME_DIR is not set.
+module Enwiro (enwiroStartupHook, enwiroEventHook, enwiroLogHook) where
+
+import Control.Concurrent (ThreadId, forkIO, killThread)
+import Control.Exception (bracket)
+import Control.Monad (forever, unless)
+import Data.Monoid (All)
+import System.Directory (createDirectoryIfMissing, doesPathExist)
+import System.Environment (lookupEnv)
+import System.IO (IOMode (ReadMode), hGetContents, withFile)
+import System.Posix.Files (createNamedPipe, ownerReadMode, ownerWriteMode, unionFileModes)
+import XMonad
+import XMonad.Actions.DynamicWorkspaces (addWorkspace)
+import XMonad.Hooks.ServerMode (serverModeEventHookF)
+import qualified XMonad.StackSet as W
+import qualified XMonad.Util.ExtensibleState as XS
+
+activateAtom :: String
+activateAtom = "ENWIRO_ACTIVATE"
+
+-- | The thread reading the command pipe, if one was started.
+newtype Reader = Reader (Maybe ThreadId)
+
+instance ExtensionClass Reader where
+  initialValue = Reader Nothing
+
+-- | Only $XDG_RUNTIME_DIR is used, since other users can write to shared
+-- directories such as /tmp.
+stateDirectory :: IO (Maybe FilePath)
+stateDirectory = fmap (++ "/enwiro-xmonad") <$> lookupEnv "XDG_RUNTIME_DIR"
+
+-- | Starts reading workspace names from the command pipe, stopping the reader
+-- started before if there is one, so that the pipe only ever has one reader.
+enwiroStartupHook :: X ()
+enwiroStartupHook = do
+  Reader previous <- XS.get
+  io $ mapM_ killThread previous
+  directory <- io stateDirectory
+  case directory of
+    Nothing -> do
+      trace "Enwiro: XDG_RUNTIME_DIR is not set, environments cannot be activated"
+      XS.put (Reader Nothing)
+    Just directory' -> do
+      reader <- io $ startReader directory'
+      XS.put (Reader (Just reader))
+
+-- | Passes the names read from the command pipe on to 'enwiroEventHook' as
+-- client messages, since X actions can only run in XMonad's own thread. The
+-- pipe and the display are closed when the thread is stopped.
+startReader :: FilePath -> IO ThreadId
+startReader directory = do
+  createDirectoryIfMissing True directory
+  let pipe = directory ++ "/commands"
+  exists <- doesPathExist pipe
+  unless exists $ createNamedPipe pipe (ownerReadMode `unionFileModes` ownerWriteMode)
+  forkIO $ bracket (openDisplay "") closeDisplay $ \display ->
+    forever $ withFile pipe ReadMode $ \handle -> do
+      names <- lines <$> hGetContents handle
+      mapM_ (sendActivate display) names
+
+sendActivate :: Display -> String -> IO ()
+sendActivate display name = do
+  let root = defaultRootWindow display
+  messageType <- internAtom display activateAtom False
+  value <- internAtom display name False
+  allocaXEvent $ \event -> do
+    setEventType event clientMessage
+    setClientMessageEvent event root messageType 32 value currentTime
+    sendEvent display root False structureNotifyMask event
+  sync display False
+
+-- | Switches to the workspace named after an environment, adding it first if
+-- it does not exist yet.
+enwiroEventHook :: Event -> X All
+enwiroEventHook = serverModeEventHookF activateAtom activate
+  where
+    activate name = do
+      tags <- gets (map W.tag . W.workspaces . windowset)
+      if name `elem` tags then windows (W.greedyView name) else addWorkspace name
+
+enwiroLogHook :: X ()
+enwiroLogHook = do
+  tag <- gets (W.currentTag . windowset)
+  io $ do
+    directory <- stateDirectory
+    mapM_ (\directory' -> writeFile (directory' ++ "/current-workspace") tag) directory
@@ -0,0 +1,100 @@
+use clap::Parser;
+use serde_derive::Deserialize;
+use std::{io, process::Command};
+
+#[derive(Parser)]
+enum EnwiroAdapterYabaiCLI {
+    GetActiveWorkspaceId(GetActiveWorkspaceIdArgs),
+    Activate(ActivateArgs),
+    Metadata(MetadataArgs),
+}
+
+#[derive(clap::Args)]
+pub struct GetActiveWorkspaceIdArgs {}
+
+#[derive(clap::Args)]
+pub struct ActivateArgs {
+    environment_name: String,
+}
+
+#[derive(clap::Args)]
+pub struct MetadataArgs {}
+
+const METADATA: &str =
+    r#"{"protocolVersion": 2, "description": "Spaces in the yabai window manager"}"#;
+
+/// A space as returned by `yabai -m query --spaces`. Spaces of environments
+/// are labeled with the name of the environment.
+#[derive(Deserialize)]
+struct Space {
+    index: u32,
+    label: String,
+}
+
+fn run_yabai(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("yabai").arg("-m").args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "yabai {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn query_spaces(args: &[&str]) -> io::Result<Vec<Space>> {
+    let output = run_yabai(&[&["query", "--spaces"], args].concat())?;
+
+    serde_json::from_str(&output).map_err(io::Error::other)
+}
+
+fn query_focused_space() -> io::Result<Space> {
+    let output = run_yabai(&["query", "--spaces", "--space"])?;
+
+    serde_json::from_str(&output).map_err(io::Error::other)
+}
+
+fn main() -> io::Result<()> {
+    let args = EnwiroAdapterYabaiCLI::parse();
+    let _ = enwiro_logging::init_logging("enwiro-adapter-yabai");
+
+    match args {
+        EnwiroAdapterYabaiCLI::GetActiveWorkspaceId(_) => {
+            print!("{}", query_focused_space()?.label);
+        }
+        EnwiroAdapterYabaiCLI::Activate(args) => {
+            let existing_space = query_spaces(&[])?
+                .into_iter()
+                .find(|space| space.label == args.environment_name);
+
+            let index = match existing_space {
+                Some(space) => space.index,
+                None => {
+                    // New spaces are added at the end of the focused display
+                    run_yabai(&["space", "--create"])?;
+                    let new_space = query_spaces(&["--display"])?
+                        .into_iter()
+                        .max_by_key(|space| space.index)
+                        .ok_or_else(|| io::Error::other("yabai did not create a space"))?;
+                    run_yabai(&[
+                        "space",
+                        &new_space.index.to_string(),
+                        "--label",
+                        &args.environment_name,
+                    ])?;
+
+                    new_space.index
+                }
+            };
+
+            run_yabai(&["space", "--focus", &index.to_string()])?;
+        }
+        EnwiroAdapterYabaiCLI::Metadata(_) => {
+            println!("{}", METADATA);
+        }
+    };
+
+    Ok(())
+}
@@ -0,0 +1,329 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use enwiro_errors::ErrorCode;
+use serde_json::{json, Value};
+
+/// Cookbook name used by `list-all` for environments that already exist.
+const ENVIRONMENT_COOKBOOK_NAME: &str = "_";
+
+/// Separates the columns of a formatted entry.
+pub const COLUMN_SEPARATOR: char = '\t';
+
+/// An environment or recipe listed by `enwiro list-all`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    // None for environments that already exist
+    pub cookbook: Option<String>,
+    pub name: String,
+
+    // Section of the recipe, such as "review requests", if the cookbook has any
+    pub category: Option<String>,
+
+    // Only listed by `enwiro list-all --json`
+    pub description: Option<String>,
+}
+
+impl Entry {
+    /// Parses a `cookbook: name` line printed by `enwiro list-all`.
+    pub fn parse(list_all_line: &str) -> Option<Self> {
+        let (cookbook, name) = list_all_line.split_once(": ")?;
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            cookbook: match cookbook {
+                ENVIRONMENT_COOKBOOK_NAME => None,
+                _ => Some(cookbook.to_string()),
+            },
+            name: name.to_string(),
+            category: None,
+            description: None,
+        })
+    }
+
+    /// Parses a line printed by `enwiro list-all --json`.
+    pub fn parse_json(list_all_line: &str) -> Option<Self> {
+        let entry: Value = serde_json::from_str(list_all_line).ok()?;
+        let get_string = |key: &str| entry.get(key)?.as_str().map(str::to_string);
+
+        Some(Self {
+            cookbook: get_string("cookbook"),
+            name: get_string("name").filter(|name| !name.is_empty())?,
+            category: get_string("category"),
+            description: get_string("description"),
+        })
+    }
+
+    pub fn is_environment(&self) -> bool {
+        self.cookbook.is_none()
+    }
+
+    /// Formats the entry as `cookbook<TAB>name` columns. Existing environments
+    /// have an empty cookbook column.
+    pub fn to_columns(&self) -> String {
+        format!(
+            "{}{}{}",
+            self.cookbook.as_deref().unwrap_or(""),
+            COLUMN_SEPARATOR,
+            self.name
+        )
+    }
+}
+
+/// Extracts the environment or recipe name from a line formatted with
+/// [`Entry::to_columns`], such as the selection returned by a launcher.
+pub fn extract_name(selected_line: &str) -> Option<&str> {
+    selected_line
+        .split_once(COLUMN_SEPARATOR)
+        .map(|(_, name)| name.trim_end_matches('\n'))
+        .filter(|name| !name.is_empty())
+}
+
+/// Drops entries whose name was already listed. Since `list-all` prints
+/// environments first, recipes that were already cooked are hidden.
+#[derive(Default)]
+pub struct Deduplicator {
+    seen_names: HashSet<String>,
+}
+
+impl Deduplicator {
+    pub fn is_new(&mut self, entry: &Entry) -> bool {
+        self.seen_names.insert(entry.name.clone())
+    }
+}
+
+/// Runs `enwiro` with the given arguments and returns its trimmed output.
+pub fn run_enwiro(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("enwiro").args(args).output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "enwiro {} failed: {}",
+            args.join(" "),
+            stderr.trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Takes a pipe of a child process that was started with it piped, which
+/// only fails if it was taken already.
+pub fn take_pipe<T>(pipe: Option<T>, description: &str) -> io::Result<T> {
+    pipe.ok_or_else(|| {
+        ErrorCode::Other.error(
+            io::ErrorKind::BrokenPipe,
+            format!("Could not open {}", description),
+        )
+    })
+}
+
+/// Starts `enwiro list-all` with its output piped, so that entries can be
+/// read with [`read_entries`] while cookbooks are still being queried.
+pub fn spawn_list_all() -> io::Result<Child> {
+    Command::new("enwiro")
+        .args(["list-all", "--json"])
+        .stdout(Stdio::piped())
+        .spawn()
+}
+
+/// Reads deduplicated entries from `list-all --json` output, calling
+/// `on_entry` for each of them as soon as it is available.
+pub fn read_entries(
+    list_all_output: impl Read,
+    mut on_entry: impl FnMut(Entry) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut deduplicator = Deduplicator::default();
+
+    for line in BufReader::new(list_all_output).lines() {
+        if let Some(entry) = Entry::parse_json(&line?) {
+            if deduplicator.is_new(&entry) {
+                on_entry(entry)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Starts activating an environment without waiting for it to finish.
+pub fn spawn_activate(name: &str) -> io::Result<Child> {
+    Command::new("enwiro").arg("activate").arg(name).spawn()
+}
+
+/// A long-running `enwiro rpc` process, for bridges that would otherwise start
+/// a new enwiro process for every request.
+pub struct RpcClient {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl RpcClient {
+    pub fn spawn() -> io::Result<Self> {
+        let mut command = Command::new("enwiro");
+        command.arg("rpc");
+
+        Self::spawn_command(command)
+    }
+
+    fn spawn_command(mut command: Command) -> io::Result<Self> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = take_pipe(child.stdin.take(), "enwiro rpc input")?;
+        let stdout = take_pipe(child.stdout.take(), "enwiro rpc output")?;
+
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            next_id: 1,
+        })
+    }
+
+    /// Calls a method of `enwiro rpc`, returning its result. Errors keep the
+    /// `ErrorCode` reported by enwiro.
+    pub fn call(&mut self, method: &str, name: Option<&str>) -> io::Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({"id": id, "method": method, "params": {"name": name}});
+        writeln!(self.stdin, "{}", request)?;
+        self.stdin.flush()?;
+
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "enwiro rpc exited",
+            ));
+        }
+        let mut response: Value = serde_json::from_str(&line).map_err(io::Error::other)?;
+
+        match response["error"]["message"].as_str() {
+            Some(message) => {
+                // Codes added by newer versions of enwiro are not known here
+                let code = serde_json::from_value(response["error"]["code"].clone())
+                    .unwrap_or(ErrorCode::Other);
+                Err(code.error(io::ErrorKind::Other, message))
+            }
+            None => Ok(response["result"].take()),
+        }
+    }
+}
+
+impl Drop for RpcClient {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_environment_and_recipe() {
+        assert_eq!(
+            Entry::parse("_: foobar"),
+            Some(Entry {
+                cookbook: None,
+                name: "foobar".to_string(),
+                category: None,
+                description: None,
+            })
+        );
+        assert_eq!(
+            Entry::parse("git: enwiro: docs"),
+            Some(Entry {
+                cookbook: Some("git".to_string()),
+                name: "enwiro: docs".to_string(),
+                category: None,
+                description: None,
+            })
+        );
+        assert_eq!(Entry::parse(""), None);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        assert_eq!(
+            Entry::parse_json(
+                r#"{"cookbook": "github", "name": "pr-1", "category": "Review requests", "description": "Fix names"}"#
+            ),
+            Some(Entry {
+                cookbook: Some("github".to_string()),
+                name: "pr-1".to_string(),
+                category: Some("Review requests".to_string()),
+                description: Some("Fix names".to_string()),
+            })
+        );
+        assert_eq!(
+            Entry::parse_json(r#"{"cookbook": null, "name": "enwiro"}"#),
+            Entry::parse("_: enwiro")
+        );
+        assert_eq!(Entry::parse_json("_: enwiro"), None);
+    }
+
+    #[test]
+    fn test_columns_round_trip() {
+        let entry = Entry::parse("git: enwiro").unwrap();
+
+        assert_eq!(entry.to_columns(), "git\tenwiro");
+        assert_eq!(extract_name(&entry.to_columns()), Some("enwiro"));
+        assert_eq!(extract_name("typed text"), None);
+    }
+
+    #[test]
+    fn test_read_entries_hides_already_cooked_recipes() {
+        let list_all_output = concat!(
+            "{\"cookbook\": null, \"name\": \"enwiro\"}\n",
+            "{\"cookbook\": \"git\", \"name\": \"enwiro\"}\n",
+            "{\"cookbook\": \"git\", \"name\": \"dotfiles\"}\n\n",
+        );
+        let mut entries = vec![];
+
+        read_entries(list_all_output.as_bytes(), |entry| {
+            entries.push(entry);
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                Entry::parse("_: enwiro").unwrap(),
+                Entry::parse("git: dotfiles").unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rpc_client() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(
+            "read -r request; echo '{\"id\": 1, \"result\": \"foobar\"}'; \
+             read -r request; echo '{\"id\": 2, \"error\": {\"message\": \"Unknown\"}}'; \
+             read -r request; echo '{\"id\": 3, \"error\": {\"code\": \"environment-not-found\", \"message\": \"Missing\"}}'",
+        );
+        let mut client = RpcClient::spawn_command(command).unwrap();
+
+        assert_eq!(client.call("current", None).unwrap(), "foobar");
+        let error = client.call("frobnicate", None).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown");
+        assert_eq!(ErrorCode::of(&error), ErrorCode::Other);
+        let error = client.call("activate", Some("missing")).unwrap_err();
+        assert_eq!(error.to_string(), "Missing");
+        assert_eq!(ErrorCode::of(&error), ErrorCode::EnvironmentNotFound);
+        assert!(client.call("current", None).is_err());
+    }
+}
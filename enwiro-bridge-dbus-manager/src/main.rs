@@ -0,0 +1,199 @@
+use std::{
+    io,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use enwiro_bridge_core::{spawn_activate, RpcClient};
+use enwiro_errors::ErrorCode;
+use serde_json::Value;
+use zbus::{
+    blocking::{connection, Connection},
+    fdo, interface,
+    object_server::SignalEmitter,
+};
+
+const BUS_NAME: &str = "org.enwiro.Manager";
+const OBJECT_PATH: &str = "/org/enwiro/Manager";
+
+/// How often the active environment is checked, to notice switches made
+/// without this service.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A single `enwiro rpc` process shared by listing and by checking the active
+/// environment, restarted if it exits. It keeps the configuration it was
+/// started with, so changes to it only apply once the service is restarted.
+#[derive(Default)]
+struct Enwiro {
+    client: Mutex<Option<RpcClient>>,
+}
+
+impl Enwiro {
+    fn call(&self, method: &str, name: Option<&str>) -> io::Result<Value> {
+        let mut client = self.client.lock().expect("enwiro rpc client is poisoned");
+        if client.is_none() {
+            *client = Some(RpcClient::spawn()?);
+        }
+
+        let result = client.as_mut().unwrap().call(method, name);
+        if let Err(error) = &result {
+            if error.kind() == io::ErrorKind::BrokenPipe {
+                *client = None;
+            }
+        }
+
+        result
+    }
+}
+
+fn to_fdo_error(error: io::Error) -> fdo::Error {
+    let message = error.to_string();
+    match ErrorCode::of(&error) {
+        ErrorCode::InvalidInput | ErrorCode::EnvironmentNotFound | ErrorCode::RecipeNotFound => {
+            fdo::Error::InvalidArgs(message)
+        }
+        ErrorCode::AdapterUnavailable => fdo::Error::NotSupported(message),
+        _ => fdo::Error::Failed(message),
+    }
+}
+
+struct Manager {
+    enwiro: Arc<Enwiro>,
+
+    // Asks the watcher to check the active environment right away
+    checks: Mutex<Sender<()>>,
+}
+
+#[interface(name = "org.enwiro.Manager")]
+impl Manager {
+    /// Starts activating an environment, cooking it first if needed, without
+    /// blocking the call. `EnvironmentChanged` is emitted once it is active.
+    ///
+    /// Activation runs in its own enwiro process, since cooking can take a
+    /// while and would otherwise hold up `ListAll` and `Current`.
+    fn activate(&self, name: String) -> fdo::Result<()> {
+        let mut activation = spawn_activate(&name).map_err(to_fdo_error)?;
+        let checks = self.checks.lock().expect("Sender is poisoned").clone();
+        thread::spawn(move || {
+            match activation.wait() {
+                Ok(status) if status.success() => {}
+                Ok(status) => tracing::warn!(%status, name, "Could not activate environment"),
+                Err(error) => tracing::warn!(%error, name, "Could not activate environment"),
+            }
+            let _ = checks.send(());
+        });
+
+        Ok(())
+    }
+
+    /// Lists environments and recipes as name, cookbook and description, by
+    /// the same names as `enwiro list-all`. The cookbook is empty for
+    /// environments that already exist.
+    fn list_all(&self) -> fdo::Result<Vec<(String, String, String)>> {
+        let list = self.enwiro.call("list", None).map_err(to_fdo_error)?;
+        let environments = list["environments"].as_array().into_iter().flatten();
+        let recipes = list["recipes"].as_array().into_iter().flatten();
+        let as_string = |value: &Value| value.as_str().unwrap_or_default().to_string();
+
+        Ok(environments
+            .map(|name| (as_string(name), String::new(), String::new()))
+            .chain(recipes.map(|recipe| {
+                (
+                    as_string(&recipe["name"]),
+                    as_string(&recipe["cookbook"]),
+                    as_string(&recipe["description"]),
+                )
+            }))
+            .collect())
+    }
+
+    fn current(&self) -> fdo::Result<String> {
+        let current = self.enwiro.call("current", None).map_err(to_fdo_error)?;
+
+        Ok(current.as_str().unwrap_or_default().to_string())
+    }
+
+    #[zbus(signal)]
+    async fn environment_changed(emitter: &SignalEmitter<'_>, name: &str) -> zbus::Result<()>;
+}
+
+/// Emits `EnvironmentChanged` whenever the active environment changes, checking
+/// periodically and whenever an activation through this service is done.
+fn watch_active_environment(connection: Connection, enwiro: Arc<Enwiro>, checks: Receiver<()>) {
+    let mut active_environment: Option<String> = None;
+
+    loop {
+        let _ = checks.recv_timeout(POLL_INTERVAL);
+        let current = match enwiro.call("current", None) {
+            Ok(current) => current.as_str().unwrap_or_default().to_string(),
+            Err(error) => {
+                tracing::debug!(%error, "Could not get active environment");
+                continue;
+            }
+        };
+        if active_environment.as_ref() == Some(&current) {
+            continue;
+        }
+
+        // Nothing changed from the point of view of clients at startup
+        if active_environment.is_some() {
+            let result = connection
+                .object_server()
+                .interface::<_, Manager>(OBJECT_PATH)
+                .and_then(|manager| {
+                    zbus::block_on(Manager::environment_changed(
+                        manager.signal_emitter(),
+                        &current,
+                    ))
+                });
+            if let Err(error) = result {
+                tracing::warn!(%error, "Could not emit EnvironmentChanged");
+            }
+        }
+        active_environment = Some(current);
+    }
+}
+
+fn main() -> zbus::Result<()> {
+    let _ = enwiro_logging::init_logging("enwiro-bridge-dbus-manager");
+    let enwiro = Arc::new(Enwiro::default());
+    let (sender, receiver) = mpsc::channel();
+
+    let connection = connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(
+            OBJECT_PATH,
+            Manager {
+                enwiro: enwiro.clone(),
+                checks: Mutex::new(sender),
+            },
+        )?
+        .build()?;
+
+    watch_active_environment(connection, enwiro, receiver);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_keep_their_code() {
+        let error = ErrorCode::EnvironmentNotFound.error(io::ErrorKind::Other, "missing");
+        assert!(
+            matches!(to_fdo_error(error), fdo::Error::InvalidArgs(message) if message == "missing")
+        );
+
+        let error = ErrorCode::AdapterUnavailable.error(io::ErrorKind::Other, "no adapter");
+        assert!(matches!(to_fdo_error(error), fdo::Error::NotSupported(_)));
+
+        let error = io::Error::other("failed");
+        assert!(matches!(to_fdo_error(error), fdo::Error::Failed(_)));
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use enwiro_bridge_core::{read_entries, spawn_activate, spawn_list_all, Entry};
+use zbus::{blocking::connection, fdo, interface, zvariant::Value};
+
+const BUS_NAME: &str = "org.enwiro.SearchProvider";
+const OBJECT_PATH: &str = "/org/enwiro/SearchProvider";
+
+/// How long listed entries are reused before `list-all` is run again.
+const CACHE_LIFETIME: Duration = Duration::from_secs(30);
+
+// Match types defined by KRunner
+const KRUNNER_EXACT_MATCH: i32 = 100;
+const KRUNNER_POSSIBLE_MATCH: i32 = 30;
+
+type KRunnerMatch = (
+    String,
+    String,
+    String,
+    i32,
+    f64,
+    HashMap<String, Value<'static>>,
+);
+
+/// Entries from `list-all`, shared by both search provider interfaces.
+#[derive(Default)]
+struct EntryCache {
+    entries: Mutex<Option<(Instant, Vec<Entry>)>>,
+}
+
+impl EntryCache {
+    fn list_entries() -> io::Result<Vec<Entry>> {
+        let mut list_all = spawn_list_all()?;
+        let list_all_output = list_all
+            .stdout
+            .take()
+            .expect("Could not read list-all output");
+        let mut entries = vec![];
+        read_entries(list_all_output, |entry| {
+            entries.push(entry);
+            Ok(())
+        })?;
+        list_all.wait()?;
+
+        Ok(entries)
+    }
+
+    /// Returns entries whose name contains every search term, ignoring case.
+    /// Existing environments come before recipes.
+    fn search(&self, terms: &[String]) -> fdo::Result<Vec<Entry>> {
+        let mut cached_entries = self.entries.lock().expect("Entry cache is poisoned");
+        let is_stale = match &*cached_entries {
+            Some((listed_at, _)) => listed_at.elapsed() > CACHE_LIFETIME,
+            None => true,
+        };
+        if is_stale {
+            let entries =
+                Self::list_entries().map_err(|error| fdo::Error::Failed(error.to_string()))?;
+            *cached_entries = Some((Instant::now(), entries));
+        }
+
+        let terms: Vec<String> = terms.iter().map(|term| term.to_lowercase()).collect();
+        let mut results: Vec<Entry> = cached_entries
+            .as_ref()
+            .map(|(_, entries)| entries.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter(|entry| {
+                let name = entry.name.to_lowercase();
+                terms.iter().all(|term| name.contains(term))
+            })
+            .cloned()
+            .collect();
+        results.sort_by_key(|entry| !entry.is_environment());
+
+        Ok(results)
+    }
+
+    fn describe(&self, name: &str) -> String {
+        let cached_entries = self.entries.lock().expect("Entry cache is poisoned");
+        let cookbook = cached_entries.as_ref().and_then(|(_, entries)| {
+            entries
+                .iter()
+                .find(|entry| entry.name == name)
+                .and_then(|entry| entry.cookbook.clone())
+        });
+
+        match cookbook {
+            Some(cookbook) => format!("Recipe from {}", cookbook),
+            None => "Environment".to_string(),
+        }
+    }
+}
+
+/// Starts activating the environment without blocking the D-Bus call. The
+/// child is reaped in the background since this service is long-running.
+fn activate(name: &str) -> fdo::Result<()> {
+    let mut child = spawn_activate(name).map_err(|error| fdo::Error::Failed(error.to_string()))?;
+    thread::spawn(move || child.wait());
+
+    Ok(())
+}
+
+struct KRunner {
+    cache: Arc<EntryCache>,
+}
+
+#[interface(name = "org.kde.krunner1")]
+impl KRunner {
+    fn actions(&self) -> Vec<(String, String, String)> {
+        vec![]
+    }
+
+    #[zbus(name = "Match")]
+    fn find_matches(&self, query: String) -> fdo::Result<Vec<KRunnerMatch>> {
+        let terms: Vec<String> = query.split_whitespace().map(|x| x.to_string()).collect();
+        if terms.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(self
+            .cache
+            .search(&terms)?
+            .into_iter()
+            .map(|entry| {
+                let is_exact_match = entry.name.eq_ignore_ascii_case(query.trim());
+                let relevance = match (is_exact_match, entry.is_environment()) {
+                    (true, _) => 1.0,
+                    (false, true) => 0.7,
+                    (false, false) => 0.5,
+                };
+                let properties = HashMap::from([(
+                    "subtext".to_string(),
+                    Value::from(self.cache.describe(&entry.name)),
+                )]);
+
+                (
+                    entry.name.clone(),
+                    entry.name,
+                    "folder".to_string(),
+                    match is_exact_match {
+                        true => KRUNNER_EXACT_MATCH,
+                        false => KRUNNER_POSSIBLE_MATCH,
+                    },
+                    relevance,
+                    properties,
+                )
+            })
+            .collect())
+    }
+
+    fn run(&self, match_id: String, _action_id: String) -> fdo::Result<()> {
+        activate(&match_id)
+    }
+}
+
+struct GnomeSearchProvider {
+    cache: Arc<EntryCache>,
+}
+
+#[interface(name = "org.gnome.Shell.SearchProvider2")]
+impl GnomeSearchProvider {
+    fn get_initial_result_set(&self, terms: Vec<String>) -> fdo::Result<Vec<String>> {
+        Ok(self
+            .cache
+            .search(&terms)?
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect())
+    }
+
+    fn get_subsearch_result_set(
+        &self,
+        _previous_results: Vec<String>,
+        terms: Vec<String>,
+    ) -> fdo::Result<Vec<String>> {
+        self.get_initial_result_set(terms)
+    }
+
+    fn get_result_metas(&self, identifiers: Vec<String>) -> Vec<HashMap<String, Value<'static>>> {
+        identifiers
+            .into_iter()
+            .map(|name| {
+                HashMap::from([
+                    ("id".to_string(), Value::from(name.clone())),
+                    ("name".to_string(), Value::from(name.clone())),
+                    (
+                        "description".to_string(),
+                        Value::from(self.cache.describe(&name)),
+                    ),
+                    ("gicon".to_string(), Value::from("folder".to_string())),
+                ])
+            })
+            .collect()
+    }
+
+    fn activate_result(
+        &self,
+        identifier: String,
+        _terms: Vec<String>,
+        _timestamp: u32,
+    ) -> fdo::Result<()> {
+        activate(&identifier)
+    }
+
+    fn launch_search(&self, _terms: Vec<String>, _timestamp: u32) {}
+}
+
+fn main() -> zbus::Result<()> {
+    let _ = enwiro_logging::init_logging("enwiro-bridge-dbus-search");
+    let cache = Arc::new(EntryCache::default());
+
+    let _connection = connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(
+            OBJECT_PATH,
+            KRunner {
+                cache: cache.clone(),
+            },
+        )?
+        .serve_at(OBJECT_PATH, GnomeSearchProvider { cache })?
+        .build()?;
+
+    loop {
+        std::thread::park();
+    }
+}
@@ -0,0 +1,648 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+use clap::Parser;
+use enwiro_bridge_core::{
+    read_entries, run_enwiro, spawn_activate, spawn_list_all, take_pipe, Entry, COLUMN_SEPARATOR,
+};
+use enwiro_errors::ErrorCode;
+use path_lookup::iterate_executables;
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigurationValues {
+    /// Shell command that reads entries on stdin and prints the selected one,
+    /// for example `fuzzel --dmenu` or `wofi --dmenu`.
+    pub launcher_command: Option<String>,
+
+    /// Attach an icon to every entry. Defaults to what the detected launcher
+    /// supports.
+    pub show_icons: Option<bool>,
+
+    /// Use pango markup to dim the description column. Defaults to what the
+    /// detected launcher supports.
+    pub use_markup: Option<bool>,
+
+    /// Option of the launcher that shows a message above the entries, used to
+    /// show details, such as rofi's `-mesg`. Defaults to what the detected
+    /// launcher supports.
+    pub message_option: Option<String>,
+
+    /// Icon names per cookbook. Existing environments use the `_` key.
+    pub icons: HashMap<String, String>,
+
+    /// Columns shown for every entry, in this order.
+    pub columns: Vec<Column>,
+
+    /// Text between the columns.
+    pub column_separator: String,
+
+    /// Descriptions longer than this many characters are cut short.
+    pub max_description_length: usize,
+
+    /// Names shown in the cookbook column instead of the cookbooks' own.
+    /// Existing environments use the `_` key and show nothing by default.
+    pub cookbook_names: HashMap<String, String>,
+
+    /// Actions bound to rofi's kb-custom-1, kb-custom-2, ... key bindings.
+    pub custom_actions: Vec<CustomAction>,
+
+    /// Shell command reading the text to copy on stdin. Defaults to wl-copy
+    /// or xclip, whichever is available.
+    pub clipboard_command: Option<String>,
+
+    /// Command used to open an environment in a file manager.
+    pub file_manager_command: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Column {
+    Cookbook,
+    Name,
+    Category,
+    Description,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CustomAction {
+    Remove,
+    CopyPath,
+    OpenFileManager,
+    ShowDetails,
+}
+
+impl Default for ConfigurationValues {
+    fn default() -> Self {
+        Self {
+            launcher_command: None,
+            show_icons: None,
+            use_markup: None,
+            message_option: None,
+            icons: HashMap::from([
+                ("_".to_string(), "folder".to_string()),
+                ("git".to_string(), "git".to_string()),
+            ]),
+            columns: vec![Column::Cookbook, Column::Name],
+            column_separator: COLUMN_SEPARATOR.to_string(),
+            max_description_length: 60,
+            cookbook_names: HashMap::new(),
+            custom_actions: vec![
+                CustomAction::Remove,
+                CustomAction::CopyPath,
+                CustomAction::OpenFileManager,
+                CustomAction::ShowDetails,
+            ],
+            clipboard_command: None,
+            file_manager_command: "xdg-open".to_string(),
+        }
+    }
+}
+
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about = "Pick an environment or recipe with a dmenu-style launcher and activate it"
+)]
+struct EnwiroBridgeDmenu {
+    /// Launcher command to use instead of the configured or detected one
+    #[arg(long)]
+    launcher: Option<String>,
+}
+
+struct Launcher {
+    executable: &'static str,
+    command: &'static str,
+    supports_icons: bool,
+    supports_markup: bool,
+    message_option: Option<&'static str>,
+}
+
+const KNOWN_LAUNCHERS: [Launcher; 4] = [
+    Launcher {
+        executable: "fuzzel",
+        command: "fuzzel --dmenu",
+        supports_icons: true,
+        supports_markup: false,
+        message_option: None,
+    },
+    Launcher {
+        executable: "rofi",
+        command: "rofi -dmenu -show-icons -markup-rows",
+        supports_icons: true,
+        supports_markup: true,
+        message_option: Some("-mesg"),
+    },
+    Launcher {
+        executable: "wofi",
+        command: "wofi --dmenu --allow-markup",
+        supports_icons: false,
+        supports_markup: true,
+        message_option: None,
+    },
+    Launcher {
+        executable: "bemenu",
+        command: "bemenu",
+        supports_icons: false,
+        supports_markup: false,
+        message_option: None,
+    },
+];
+
+const KNOWN_CLIPBOARD_COMMANDS: [(&str, &str); 2] = [
+    ("wl-copy", "wl-copy"),
+    ("xclip", "xclip -selection clipboard"),
+];
+
+// rofi exits with 10 for kb-custom-1, 11 for kb-custom-2, and so on
+const FIRST_CUSTOM_KEY_EXIT_CODE: i32 = 10;
+const LAST_CUSTOM_KEY_EXIT_CODE: i32 = 28;
+
+/// The chosen entry, and the custom key used to choose it if any.
+enum Selection {
+    Default(Entry),
+    Custom(usize, Entry),
+}
+
+struct FormatOptions<'a> {
+    show_icons: bool,
+    use_markup: bool,
+    icons: &'a HashMap<String, String>,
+    columns: &'a [Column],
+    column_separator: &'a str,
+    max_description_length: usize,
+    cookbook_names: &'a HashMap<String, String>,
+}
+
+fn is_in_path(executable: &str) -> bool {
+    iterate_executables().any(|x| x == executable)
+}
+
+/// Finds the first known dmenu-compatible launcher available in PATH.
+fn detect_launcher() -> Option<&'static Launcher> {
+    KNOWN_LAUNCHERS
+        .iter()
+        .find(|launcher| is_in_path(launcher.executable))
+}
+
+fn detect_clipboard_command() -> Option<String> {
+    KNOWN_CLIPBOARD_COMMANDS
+        .iter()
+        .find(|(executable, _)| is_in_path(executable))
+        .map(|(_, command)| command.to_string())
+}
+
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Cuts text longer than `max_length` characters short, ending it with an
+/// ellipsis.
+fn truncate(text: &str, max_length: usize) -> String {
+    match text.char_indices().nth(max_length) {
+        Some(_) => {
+            let kept: String = text.chars().take(max_length.saturating_sub(1)).collect();
+            format!("{}…", kept.trim_end())
+        }
+        None => text.to_string(),
+    }
+}
+
+/// Formats the configured columns of an entry, decorated with markup if
+/// enabled. This is the text that the launcher prints when it is chosen.
+fn format_columns(entry: &Entry, options: &FormatOptions) -> String {
+    let cookbook_key = entry.cookbook.as_deref().unwrap_or("_");
+    let cookbook = match (options.cookbook_names.get(cookbook_key), &entry.cookbook) {
+        (Some(display_name), _) => display_name.as_str(),
+        (None, Some(cookbook)) => cookbook.as_str(),
+        (None, None) => "",
+    };
+
+    options
+        .columns
+        .iter()
+        .map(|column| match (column, options.use_markup) {
+            (Column::Cookbook, true) => escape_markup(cookbook),
+            (Column::Cookbook, false) => cookbook.to_string(),
+            (Column::Name, true) => escape_markup(&entry.name),
+            (Column::Name, false) => entry.name.to_string(),
+            (Column::Category, true) => match &entry.category {
+                Some(category) => format!("<i>{}</i>", escape_markup(category)),
+                None => "".to_string(),
+            },
+            (Column::Category, false) => entry.category.clone().unwrap_or_default(),
+            (Column::Description, use_markup) => {
+                let description = truncate(
+                    entry.description.as_deref().unwrap_or_default(),
+                    options.max_description_length,
+                );
+                match use_markup {
+                    true if !description.is_empty() => {
+                        format!("<span alpha=\"50%\">{}</span>", escape_markup(&description))
+                    }
+                    _ => description,
+                }
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(options.column_separator)
+}
+
+/// Chosen entries are found again by the text of their columns, which is
+/// only unique if the name is one of them.
+fn validate_columns(columns: &[Column]) -> io::Result<()> {
+    match columns.iter().any(|column| matches!(column, Column::Name)) {
+        true => Ok(()),
+        false => Err(ErrorCode::ConfigInvalid.error(
+            io::ErrorKind::InvalidInput,
+            "The configured columns must include \"name\"",
+        )),
+    }
+}
+
+/// The configured launcher command, or else the one of the detected launcher.
+fn get_launcher_command(
+    configured_command: Option<String>,
+    detected_launcher: Option<&Launcher>,
+) -> io::Result<String> {
+    configured_command
+        .or_else(|| detected_launcher.map(|launcher| launcher.command.to_string()))
+        .ok_or_else(|| {
+            ErrorCode::ConfigInvalid.error(
+                io::ErrorKind::NotFound,
+                "No launcher found. Install fuzzel, rofi, wofi or bemenu, or configure launcher_command.",
+            )
+        })
+}
+
+/// Appends the icon of an entry to its columns, if icons are enabled.
+fn format_entry(columns: &str, entry: &Entry, options: &FormatOptions) -> String {
+    let icon = match options.icons.get(entry.cookbook.as_deref().unwrap_or("_")) {
+        Some(icon) if options.show_icons => format!("\0icon\x1f{}", icon),
+        _ => "".to_string(),
+    };
+
+    format!("{}{}\n", columns, icon)
+}
+
+/// Lets the user choose an entry in the launcher, started with extra
+/// arguments such as a message. Returns `None` when the selection was
+/// aborted.
+fn pick_entry(
+    launcher_command: &str,
+    launcher_args: &[String],
+    options: &FormatOptions,
+) -> io::Result<Option<Selection>> {
+    let mut list_all = spawn_list_all()?;
+    let mut launcher = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", launcher_command))
+        .arg("sh")
+        .args(launcher_args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let list_all_output = take_pipe(list_all.stdout.take(), "list-all output")?;
+    let mut launcher_input = take_pipe(launcher.stdin.take(), "launcher input")?;
+    let (output, names) = thread::scope(|scope| {
+        // Entries are passed on as soon as list-all prints them, so launchers
+        // reading their input asynchronously (such as rofi) show environments
+        // while recipes are still being collected. Writing fails once the
+        // launcher exits, which just means the user made a choice early.
+        let writer = scope.spawn(move || {
+            // The columns of each entry, to find out which one was chosen
+            // whatever the layout
+            let mut names = HashMap::new();
+            let _ = read_entries(list_all_output, |entry| {
+                let columns = format_columns(&entry, options);
+                launcher_input.write_all(format_entry(&columns, &entry, options).as_bytes())?;
+                names.entry(columns).or_insert(entry);
+                launcher_input.flush()
+            });
+            names
+        });
+        let output = launcher.wait_with_output();
+        let _ = list_all.kill();
+        let names = writer.join().map_err(|_| {
+            ErrorCode::Other.error(io::ErrorKind::Other, "Could not read list-all output")
+        })?;
+        output.map(|output| (output, names))
+    })?;
+    list_all.wait()?;
+
+    let selected_entry = String::from_utf8_lossy(&output.stdout);
+    let Some(entry) = names.get(selected_entry.trim_end_matches('\n')).cloned() else {
+        return Ok(None);
+    };
+
+    match output.status.code() {
+        Some(0) => Ok(Some(Selection::Default(entry))),
+        Some(code @ FIRST_CUSTOM_KEY_EXIT_CODE..=LAST_CUSTOM_KEY_EXIT_CODE) => Ok(Some(
+            Selection::Custom((code - FIRST_CUSTOM_KEY_EXIT_CODE) as usize, entry),
+        )),
+        _ => Ok(None),
+    }
+}
+
+/// Details of an entry provided by its cookbook, as a message for the
+/// launcher.
+fn get_details_message(entry: &Entry, options: &FormatOptions) -> io::Result<String> {
+    let details = match &entry.cookbook {
+        Some(cookbook) => run_enwiro(&["describe", "--cookbook", cookbook, &entry.name])?,
+        None => run_enwiro(&["describe", &entry.name])?,
+    };
+    let message = match details.is_empty() {
+        true => format!("No details about {}", entry.name),
+        false => details,
+    };
+
+    Ok(match options.use_markup {
+        true => escape_markup(&message),
+        false => message,
+    })
+}
+
+fn run_custom_action(
+    action: CustomAction,
+    name: &str,
+    config: &ConfigurationValues,
+) -> io::Result<()> {
+    match action {
+        // Shown by reopening the launcher instead, see `main`
+        CustomAction::ShowDetails => {}
+        CustomAction::Remove => {
+            run_enwiro(&["remove", name])?;
+        }
+        CustomAction::CopyPath => {
+            let path = run_enwiro(&["show-path", "--cook", name])?;
+            let clipboard_command = config
+                .clipboard_command
+                .clone()
+                .or_else(detect_clipboard_command)
+                .ok_or_else(|| {
+                    io::Error::other(
+                        "No clipboard tool found. Install wl-copy or xclip, or configure clipboard_command.",
+                    )
+                })?;
+            let mut clipboard = Command::new("sh")
+                .arg("-c")
+                .arg(clipboard_command)
+                .stdin(Stdio::piped())
+                .spawn()?;
+            take_pipe(clipboard.stdin.take(), "clipboard input")?.write_all(path.as_bytes())?;
+            clipboard.wait()?;
+        }
+        CustomAction::OpenFileManager => {
+            let path = run_enwiro(&["show-path", "--cook", name])?;
+            Command::new("sh")
+                .arg("-c")
+                .arg(format!("{} \"$1\"", config.file_manager_command))
+                .arg("sh")
+                .arg(path)
+                .spawn()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let args = EnwiroBridgeDmenu::parse();
+    let _ = enwiro_logging::init_logging("enwiro-bridge-dmenu");
+    let config: ConfigurationValues = confy::load("enwiro", "bridge-dmenu").map_err(|error| {
+        ErrorCode::ConfigInvalid.error(
+            io::ErrorKind::InvalidData,
+            format!("Could not load configuration: {}", error),
+        )
+    })?;
+
+    validate_columns(&config.columns)?;
+    let configured_command = args.launcher.or(config.launcher_command.clone());
+    let detected_launcher = match configured_command {
+        Some(_) => None,
+        None => detect_launcher(),
+    };
+    let launcher_command = get_launcher_command(configured_command, detected_launcher)?;
+    let options = FormatOptions {
+        show_icons: config
+            .show_icons
+            .unwrap_or(detected_launcher.is_some_and(|launcher| launcher.supports_icons)),
+        use_markup: config
+            .use_markup
+            .unwrap_or(detected_launcher.is_some_and(|launcher| launcher.supports_markup)),
+        icons: &config.icons,
+        columns: &config.columns,
+        column_separator: &config.column_separator,
+        max_description_length: config.max_description_length,
+        cookbook_names: &config.cookbook_names,
+    };
+
+    let message_option = config.message_option.clone().or_else(|| {
+        detected_launcher.and_then(|launcher| launcher.message_option.map(str::to_string))
+    });
+
+    // The launcher is opened again with the details of an entry when they
+    // are asked for, until an entry is chosen
+    let mut launcher_args = vec![];
+    let (custom_key, entry) = loop {
+        let (custom_key, entry) = match pick_entry(&launcher_command, &launcher_args, &options)? {
+            Some(Selection::Default(entry)) => (None, entry),
+            Some(Selection::Custom(key, entry)) => (Some(key), entry),
+            None => return Ok(()),
+        };
+        let action = custom_key.and_then(|key| config.custom_actions.get(key));
+        match (action, &message_option) {
+            (Some(CustomAction::ShowDetails), Some(message_option)) => {
+                launcher_args = vec![
+                    message_option.clone(),
+                    get_details_message(&entry, &options)?,
+                ];
+            }
+            (Some(CustomAction::ShowDetails), None) => {
+                return Err(io::Error::other(
+                    "The launcher cannot show details. Configure message_option to show them.",
+                ))
+            }
+            _ => break (custom_key, entry),
+        }
+    };
+
+    match custom_key {
+        None => {
+            spawn_activate(&entry.name)?;
+        }
+        Some(key) => {
+            if let Some(action) = config.custom_actions.get(key) {
+                run_custom_action(*action, &entry.name, &config)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use enwiro_bridge_core::Entry;
+    use enwiro_errors::ErrorCode;
+
+    use crate::{
+        format_columns, format_entry, get_launcher_command, truncate, validate_columns, Column,
+        FormatOptions, KNOWN_LAUNCHERS,
+    };
+
+    fn get_entry(cookbook: Option<&str>, name: &str, category: Option<&str>) -> Entry {
+        Entry {
+            cookbook: cookbook.map(str::to_string),
+            name: name.to_string(),
+            category: category.map(str::to_string),
+            description: None,
+        }
+    }
+
+    fn get_options<'a>(
+        columns: &'a [Column],
+        cookbook_names: &'a HashMap<String, String>,
+        icons: &'a HashMap<String, String>,
+        use_markup: bool,
+    ) -> FormatOptions<'a> {
+        FormatOptions {
+            show_icons: true,
+            use_markup,
+            icons,
+            columns,
+            column_separator: "\t",
+            max_description_length: 10,
+            cookbook_names,
+        }
+    }
+
+    #[test]
+    fn test_format_columns_in_configured_order() {
+        let columns = [Column::Name, Column::Cookbook, Column::Category];
+        let cookbook_names = HashMap::from([("_".to_string(), "env".to_string())]);
+        let icons = HashMap::new();
+        let options = get_options(&columns, &cookbook_names, &icons, false);
+
+        assert_eq!(
+            format_columns(&get_entry(Some("github"), "pr-1", Some("Mine")), &options),
+            "pr-1\tgithub\tMine"
+        );
+        assert_eq!(
+            format_columns(&get_entry(None, "enwiro", None), &options),
+            "enwiro\tenv\t"
+        );
+    }
+
+    #[test]
+    fn test_format_columns_with_markup() {
+        let columns = [
+            Column::Cookbook,
+            Column::Name,
+            Column::Category,
+            Column::Description,
+        ];
+        let cookbook_names = HashMap::new();
+        let icons = HashMap::new();
+        let options = get_options(&columns, &cookbook_names, &icons, true);
+        let entry = Entry {
+            description: Some("x > y".to_string()),
+            ..get_entry(Some("git&co"), "a<b>&c", Some("R&D"))
+        };
+
+        assert_eq!(
+            format_columns(&entry, &options),
+            "git&amp;co\ta&lt;b&gt;&amp;c\t<i>R&amp;D</i>\t<span alpha=\"50%\">x &gt; y</span>"
+        );
+        assert_eq!(
+            format_columns(&get_entry(None, "enwiro", None), &options),
+            "\tenwiro\t\t"
+        );
+    }
+
+    #[test]
+    fn test_format_description_column() {
+        let columns = [Column::Name, Column::Description];
+        let cookbook_names = HashMap::new();
+        let icons = HashMap::new();
+        let options = get_options(&columns, &cookbook_names, &icons, false);
+        let described_entry = |description: &str| Entry {
+            description: Some(description.to_string()),
+            ..get_entry(Some("github"), "pr-1", None)
+        };
+
+        assert_eq!(
+            format_columns(&described_entry("Fix names"), &options),
+            "pr-1\tFix names"
+        );
+        assert_eq!(
+            format_columns(&described_entry("Fix the names of workspaces"), &options),
+            "pr-1\tFix the n…"
+        );
+        assert_eq!(
+            format_columns(&get_entry(None, "enwiro", None), &options),
+            "enwiro\t"
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("enwiro", 6), "enwiro");
+        assert_eq!(truncate("enwiro", 5), "enwi…");
+        assert_eq!(truncate("ünïcödé", 3), "ün…");
+        assert_eq!(truncate("", 0), "");
+    }
+
+    #[test]
+    fn test_format_entry_appends_icon_of_cookbook() {
+        let columns = [Column::Name];
+        let cookbook_names = HashMap::new();
+        let icons = HashMap::from([("git".to_string(), "git-icon".to_string())]);
+        let options = get_options(&columns, &cookbook_names, &icons, false);
+
+        assert_eq!(
+            format_entry("foo", &get_entry(Some("git"), "foo", None), &options),
+            "foo\0icon\x1fgit-icon\n"
+        );
+        assert_eq!(
+            format_entry("foo", &get_entry(None, "foo", None), &options),
+            "foo\n"
+        );
+    }
+
+    #[test]
+    fn test_columns_must_include_name() {
+        assert!(validate_columns(&[Column::Cookbook, Column::Name]).is_ok());
+        assert!(validate_columns(&[Column::Cookbook, Column::Category]).is_err());
+        assert!(validate_columns(&[]).is_err());
+    }
+
+    #[test]
+    fn test_launcher_command() {
+        let fuzzel = &KNOWN_LAUNCHERS[0];
+        assert_eq!(
+            get_launcher_command(Some("wofi --dmenu".to_string()), Some(fuzzel)).unwrap(),
+            "wofi --dmenu"
+        );
+        assert_eq!(
+            get_launcher_command(None, Some(fuzzel)).unwrap(),
+            "fuzzel --dmenu"
+        );
+
+        let error = get_launcher_command(None, None).unwrap_err();
+        assert_eq!(ErrorCode::of(&error), ErrorCode::ConfigInvalid);
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{io, process::Command};
+
+use clap::Parser;
+use enwiro_bridge_core::{read_entries, run_enwiro, spawn_list_all};
+use serde_derive::{Deserialize, Serialize};
+
+/// Opens the environment in a tab-bar tab named after it, reusing the tab if
+/// it already exists.
+const DEFAULT_OPEN_TEMPLATE: &str = r#"(if (member {name} (mapcar (lambda (tab) (alist-get 'name tab)) (tab-bar-tabs)))
+    (tab-bar-select-tab-by-name {name})
+  (tab-bar-new-tab)
+  (tab-bar-rename-tab {name})
+  (find-file {path}))"#;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigurationValues {
+    /// Emacs Lisp evaluated by emacsclient to open an environment. `{name}`
+    /// and `{path}` are replaced with the quoted environment name and path.
+    pub open_template: String,
+}
+
+impl Default for ConfigurationValues {
+    fn default() -> Self {
+        Self {
+            open_template: DEFAULT_OPEN_TEMPLATE.to_string(),
+        }
+    }
+}
+
+#[derive(Parser)]
+enum EnwiroBridgeEmacsclient {
+    List(ListArgs),
+    Open(OpenArgs),
+}
+
+/// Print environment and recipe names, one per line, for completing-read
+#[derive(clap::Args)]
+pub struct ListArgs {}
+
+/// Open an environment in Emacs, cooking it first if necessary
+#[derive(clap::Args)]
+pub struct OpenArgs {
+    environment_name: String,
+}
+
+fn quote_elisp_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn list() -> io::Result<()> {
+    let mut list_all = spawn_list_all()?;
+    let list_all_output = list_all
+        .stdout
+        .take()
+        .expect("Could not read list-all output");
+
+    read_entries(list_all_output, |entry| {
+        println!("{}", entry.name);
+        Ok(())
+    })?;
+    list_all.wait()?;
+
+    Ok(())
+}
+
+fn open(config: &ConfigurationValues, args: OpenArgs) -> io::Result<()> {
+    let path = run_enwiro(&["show-path", "--cook", &args.environment_name])?;
+    let expression = config
+        .open_template
+        .replace("{name}", &quote_elisp_string(&args.environment_name))
+        .replace("{path}", &quote_elisp_string(&path));
+
+    let status = Command::new("emacsclient")
+        .arg("--no-wait")
+        .arg("--eval")
+        .arg(expression)
+        .status()?;
+
+    if !status.success() {
+        return Err(io::Error::other(
+            "emacsclient failed. Is the Emacs server running?",
+        ));
+    }
+
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let args = EnwiroBridgeEmacsclient::parse();
+    let _ = enwiro_logging::init_logging("enwiro-bridge-emacsclient");
+    let config: ConfigurationValues = match confy::load("enwiro", "bridge-emacsclient") {
+        Ok(x) => x,
+        Err(x) => {
+            panic!("Could not load configuration: {:?}", x);
+        }
+    };
+
+    match args {
+        EnwiroBridgeEmacsclient::List(_) => list(),
+        EnwiroBridgeEmacsclient::Open(args) => open(&config, args),
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    env,
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+use clap::Parser;
+use enwiro_bridge_core::{
+    extract_name, read_entries, run_enwiro, spawn_list_all, take_pipe, Entry,
+};
+use enwiro_errors::ErrorCode;
+
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about = "Pick an environment or recipe with fzf and activate it"
+)]
+struct EnwiroBridgeFzf {
+    /// Print the path of the selected environment instead of activating it
+    #[arg(long)]
+    print_path: bool,
+
+    #[command(subcommand)]
+    command: Option<EnwiroBridgeFzfCommand>,
+}
+
+#[derive(clap::Subcommand)]
+enum EnwiroBridgeFzfCommand {
+    /// Render the preview pane for an entry. Used internally by fzf.
+    #[command(hide = true)]
+    Preview(PreviewArgs),
+}
+
+#[derive(clap::Args)]
+pub struct PreviewArgs {
+    entry: String,
+}
+
+/// Renders the preview of an entry, using `run_enwiro` to ask enwiro about
+/// it. Environments show `enwiro info`, with their description and recent
+/// activity, and recipes the details given by their cookbook.
+fn render_preview(
+    entry: &str,
+    run_enwiro: impl Fn(&[&str]) -> io::Result<String>,
+) -> io::Result<String> {
+    let (cookbook, name) = match entry.split_once('\t') {
+        Some(columns) => columns,
+        None => return Ok(String::new()),
+    };
+
+    if cookbook.is_empty() {
+        return run_enwiro(&["info", name]);
+    }
+
+    let mut lines = vec![
+        format!("Recipe: {}", name),
+        format!("Cookbook: {}", cookbook),
+    ];
+    // Details are only nice to have, and not every cookbook has them
+    match run_enwiro(&["describe", name, "--cookbook", cookbook]) {
+        Ok(details) if !details.is_empty() => lines.extend([String::new(), details]),
+        _ => {}
+    }
+    lines.push(String::new());
+    lines.push("Not cooked yet. Selecting it will create the environment.".to_string());
+
+    Ok(lines.join("\n"))
+}
+
+fn preview(args: PreviewArgs) -> io::Result<()> {
+    println!("{}", render_preview(&args.entry, run_enwiro)?);
+
+    Ok(())
+}
+
+/// Lets the user choose an entry in fzf. Returns `None` when the selection
+/// was aborted.
+fn pick_entry() -> io::Result<Option<String>> {
+    let preview_command = format!("'{}' preview {{}}", env::current_exe()?.display());
+    let mut list_all = spawn_list_all()?;
+    let mut fzf = Command::new("fzf")
+        .arg("--delimiter=\t")
+        .arg("--preview")
+        .arg(preview_command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let list_all_output = take_pipe(list_all.stdout.take(), "list-all output")?;
+    let mut fzf_input = take_pipe(fzf.stdin.take(), "fzf input")?;
+    let output = thread::scope(|scope| {
+        scope.spawn(move || {
+            read_entries(list_all_output, |entry: Entry| {
+                writeln!(fzf_input, "{}", entry.to_columns())
+            })
+        });
+        let output = fzf.wait_with_output();
+        let _ = list_all.kill();
+        output
+    })?;
+    list_all.wait()?;
+
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+}
+
+fn main() -> io::Result<()> {
+    let args = EnwiroBridgeFzf::parse();
+    let _ = enwiro_logging::init_logging("enwiro-bridge-fzf");
+
+    if let Some(EnwiroBridgeFzfCommand::Preview(preview_args)) = args.command {
+        return preview(preview_args);
+    }
+
+    let selected_entry = match pick_entry()? {
+        Some(entry) => entry,
+        None => return Ok(()),
+    };
+    let name = extract_name(&selected_entry).ok_or_else(|| {
+        ErrorCode::InvalidInput.error(
+            io::ErrorKind::InvalidData,
+            format!("Could not parse selected entry {:?}", selected_entry),
+        )
+    })?;
+
+    if args.print_path {
+        println!("{}", run_enwiro(&["show-path", "--cook", name])?);
+    } else {
+        run_enwiro(&["activate", name])?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_enwiro(args: &[&str]) -> io::Result<String> {
+        match args {
+            ["info", "enwiro"] => Ok(
+                "name: enwiro\ndescription: Workspace manager\nlast activated: 2 hours ago"
+                    .to_string(),
+            ),
+            ["describe", "kantord/enwiro#12", "--cookbook", "github"] => {
+                Ok("Fix workspace names\nOpen, 2 comments".to_string())
+            }
+            _ => Err(io::Error::other("failed")),
+        }
+    }
+
+    #[test]
+    fn test_environment_preview_shows_info() {
+        let preview = render_preview("\tenwiro", fake_enwiro).unwrap();
+
+        assert!(preview.contains("description: Workspace manager"));
+        assert!(preview.contains("last activated: 2 hours ago"));
+    }
+
+    #[test]
+    fn test_recipe_preview_shows_details() {
+        let preview = render_preview("github\tkantord/enwiro#12", fake_enwiro).unwrap();
+
+        assert!(preview.starts_with("Recipe: kantord/enwiro#12\nCookbook: github\n"));
+        assert!(preview.contains("Fix workspace names"));
+        assert!(preview.ends_with("Not cooked yet. Selecting it will create the environment."));
+    }
+
+    #[test]
+    fn test_recipe_preview_without_details() {
+        let preview = render_preview("git\tdotfiles", fake_enwiro).unwrap();
+
+        assert_eq!(
+            preview,
+            "Recipe: dotfiles\nCookbook: git\n\nNot cooked yet. Selecting it will create the environment."
+        );
+    }
+}
@@ -0,0 +1,724 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use enwiro_errors::ErrorCode;
+use git2::{BranchType, StatusOptions};
+// Re-exported for the in-process version of this cookbook in enwiro
+pub use git2::Repository;
+use serde_derive::{Deserialize, Serialize};
+
+/// Environment variable through which enwiro passes this cookbook's section of
+/// its own configuration, encoded as JSON.
+const CONFIGURATION_VARIABLE: &str = "ENWIRO_COOKBOOK_CONFIG";
+
+const STATUS_CACHE_PATH: &str = "${XDG_CACHE_HOME}/enwiro/cookbook-git-status.json";
+
+/// Statuses of repositories are read again after this many seconds.
+const STATUS_CACHE_SECONDS: u64 = 5 * 60;
+
+/// File whose modification time is when repositories were last fetched.
+const FETCH_STAMP_PATH: &str = "${XDG_CACHE_HOME}/enwiro/cookbook-git-fetch";
+
+const DEFAULT_FETCH_CONCURRENCY: usize = 4;
+
+/// Lines of the README shown when describing a repository.
+const README_EXCERPT_LINES: usize = 15;
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ConfigurationValues {
+    pub repo_globs: Vec<String>,
+
+    // Whether descriptions mention uncommitted changes and commits not pushed
+    // or not pulled yet
+    pub show_status: bool,
+
+    // Minutes between fetches of all repositories in the background, which
+    // are disabled if not set
+    pub fetch_interval: Option<u64>,
+
+    // How many repositories are fetched at the same time
+    pub fetch_concurrency: Option<usize>,
+}
+
+fn get_home_directory() -> Option<String> {
+    Some(home::home_dir()?.to_str()?.to_string())
+}
+
+/// Default values of the XDG base directory variables, used when they are not set.
+fn get_xdg_default(name: &str) -> Option<&'static str> {
+    match name {
+        "XDG_CONFIG_HOME" => Some("~/.config"),
+        "XDG_DATA_HOME" => Some("~/.local/share"),
+        "XDG_STATE_HOME" => Some("~/.local/state"),
+        "XDG_CACHE_HOME" => Some("~/.cache"),
+        _ => None,
+    }
+}
+
+/// Expands `~` and environment variables such as `$HOME` or `${XDG_DATA_HOME}`
+/// in a configured path.
+fn expand_path(path: &str) -> Result<String, io::Error> {
+    let expanded =
+        shellexpand::full_with_context(path, get_home_directory, |name| match env::var(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => match get_xdg_default(name) {
+                Some(default) => Ok(Some(shellexpand::tilde(default).to_string())),
+                None => Err(error),
+            },
+        })
+        .map_err(io::Error::other)?;
+
+    Ok(expanded.to_string())
+}
+
+impl ConfigurationValues {
+    /// Expands `~` and environment variables in the configured globs.
+    pub fn expand_paths(mut self) -> Result<Self, io::Error> {
+        self.repo_globs = self
+            .repo_globs
+            .iter()
+            .map(|glob| {
+                expand_path(glob).map_err(|error| {
+                    ErrorCode::ConfigInvalid.error(
+                        io::ErrorKind::InvalidInput,
+                        format!("Could not expand repo glob {}: {}", glob, error),
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(self)
+    }
+}
+
+/// Loads the configuration from the cookbook's own configuration file.
+pub fn load_configuration_file() -> Result<ConfigurationValues, io::Error> {
+    confy::load::<ConfigurationValues>("enwiro", "cookbook-git")
+        .map_err(|error| {
+            ErrorCode::ConfigInvalid.error(
+                io::ErrorKind::InvalidData,
+                format!("Could not load configuration: {}", error),
+            )
+        })?
+        .expand_paths()
+}
+
+/// Loads the configuration passed by enwiro, falling back to the cookbook's
+/// own configuration file.
+pub fn load_configuration() -> Result<ConfigurationValues, io::Error> {
+    match env::var(CONFIGURATION_VARIABLE) {
+        Ok(configuration) => serde_json::from_str::<ConfigurationValues>(&configuration)
+            .map_err(|error| {
+                ErrorCode::ConfigInvalid.error(
+                    io::ErrorKind::InvalidData,
+                    format!("Could not parse configuration passed by enwiro: {}", error),
+                )
+            })?
+            .expand_paths(),
+        Err(_) => load_configuration_file(),
+    }
+}
+
+/// Name of the directory of a repository, given the path of its `.git`
+/// directory, or of the repository itself if it is bare.
+fn get_repository_name(git_path: &Path) -> Option<String> {
+    let repo_path = match git_path.file_name()? == ".git" {
+        true => git_path.parent()?,
+        false => git_path,
+    };
+
+    Some(repo_path.file_name()?.to_string_lossy().to_string())
+}
+
+/// Finds the repositories matching the configured globs, keyed by the name of
+/// their directory.
+pub fn build_repository_hashmap(config: &ConfigurationValues) -> HashMap<String, Repository> {
+    let mut results: HashMap<String, Repository> = HashMap::new();
+    for glob_from_config in config.repo_globs.iter() {
+        glob::glob(glob_from_config)
+            .expect("Could not parse glob")
+            .for_each(|entry| {
+                if let Ok(path) = entry {
+                    if let Ok(repo) = Repository::open(path) {
+                        // Names that are not valid UTF-8 get replacement
+                        // characters, the same ones when listing and cooking
+                        if let Some(repo_name) = get_repository_name(repo.path()) {
+                            results.insert(repo_name, repo);
+                        }
+                    }
+                }
+            });
+    }
+
+    results
+}
+
+/// State of the working tree and the current branch of a repository.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepositoryStatus {
+    pub dirty: bool,
+
+    // Commits of the current branch missing from its upstream, and the other
+    // way around, as of the last fetch
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+impl RepositoryStatus {
+    pub fn read(repo: &Repository) -> Result<Self, git2::Error> {
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(false)
+            .include_ignored(false);
+        let dirty = !repo.statuses(Some(&mut options))?.is_empty();
+
+        let (ahead, behind) = match get_upstream_commits(repo) {
+            Some((local, upstream)) => repo.graph_ahead_behind(local, upstream)?,
+            None => (0, 0),
+        };
+
+        Ok(Self {
+            dirty,
+            ahead,
+            behind,
+        })
+    }
+
+    /// Describes the status briefly, or returns `None` if there is nothing
+    /// to report.
+    pub fn summary(&self) -> Option<String> {
+        let mut parts = vec![];
+        if self.dirty {
+            parts.push("dirty".to_string());
+        }
+        if self.ahead > 0 {
+            parts.push(format!("{} ahead", self.ahead));
+        }
+        if self.behind > 0 {
+            parts.push(format!("{} behind", self.behind));
+        }
+
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// Commits of the current branch and of its upstream, if it has one.
+fn get_upstream_commits(repo: &Repository) -> Option<(git2::Oid, git2::Oid)> {
+    let head = repo.head().ok()?;
+    let branch_name = head.shorthand()?;
+    let branch = repo.find_branch(branch_name, BranchType::Local).ok()?;
+    let upstream = branch.upstream().ok()?;
+
+    Some((head.target()?, upstream.get().target()?))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedStatus {
+    // Unix timestamp of when the status was read, in seconds
+    read_at: u64,
+    status: RepositoryStatus,
+}
+
+impl CachedStatus {
+    fn is_fresh(&self) -> bool {
+        now().saturating_sub(self.read_at) < STATUS_CACHE_SECONDS
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Statuses read during previous listings, so that listing many repositories
+/// stays fast.
+struct StatusCache {
+    path: Option<PathBuf>,
+    statuses: HashMap<PathBuf, CachedStatus>,
+}
+
+impl StatusCache {
+    fn load() -> Self {
+        Self::load_from(expand_path(STATUS_CACHE_PATH).ok().map(PathBuf::from))
+    }
+
+    fn load_from(path: Option<PathBuf>) -> Self {
+        let statuses = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Self { path, statuses }
+    }
+
+    fn get(&mut self, repo: &Repository) -> Option<RepositoryStatus> {
+        let key = repo.path().to_path_buf();
+        if let Some(cached) = self.statuses.get(&key) {
+            if cached.is_fresh() {
+                return Some(cached.status.clone());
+            }
+        }
+
+        let status = RepositoryStatus::read(repo).ok()?;
+        self.statuses.insert(
+            key,
+            CachedStatus {
+                read_at: now(),
+                status: status.clone(),
+            },
+        );
+
+        Some(status)
+    }
+
+    /// Statuses that would be read again anyway, and those of repositories
+    /// that no longer exist, are left out so that the cache does not grow
+    /// forever. Failing to save the cache only makes the next listing slower.
+    fn save(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        self.statuses
+            .retain(|repo_path, cached| cached.is_fresh() && repo_path.exists());
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(content) = serde_json::to_string(&self.statuses) {
+            let _ = fs::write(path, content);
+        }
+    }
+}
+
+/// Describes the recipes of repositories, keyed by their name: the path of
+/// the repository, followed by a summary of its status if `show_status` is
+/// enabled.
+pub fn get_descriptions(
+    config: &ConfigurationValues,
+    repositories: &HashMap<String, Repository>,
+) -> HashMap<String, String> {
+    let mut cache = config.show_status.then(StatusCache::load);
+    let descriptions = describe_repositories(repositories, cache.as_mut());
+    if let Some(mut cache) = cache {
+        cache.save();
+    }
+
+    descriptions
+}
+
+fn describe_repositories(
+    repositories: &HashMap<String, Repository>,
+    mut cache: Option<&mut StatusCache>,
+) -> HashMap<String, String> {
+    repositories
+        .iter()
+        .filter_map(|(name, repo)| {
+            let path = repo.path().parent()?.to_string_lossy().to_string();
+            let summary = cache
+                .as_mut()
+                .and_then(|cache| cache.get(repo))
+                .and_then(|status| status.summary());
+            let description = match summary {
+                Some(summary) => format!("{} ({})", path, summary),
+                None => path,
+            };
+
+            Some((name.clone(), description))
+        })
+        .collect()
+}
+
+/// The first lines of the README in a directory, if there is one.
+fn get_readme_excerpt(directory: &Path) -> Option<String> {
+    let readme_path = fs::read_dir(directory)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_stem()
+                .is_some_and(|stem| stem.eq_ignore_ascii_case("readme"))
+                && path.is_file()
+        })
+        .min()?;
+    let content = fs::read_to_string(readme_path).ok()?;
+    let mut lines: Vec<&str> = content.lines().take(README_EXCERPT_LINES + 1).collect();
+    if lines.len() > README_EXCERPT_LINES {
+        lines.truncate(README_EXCERPT_LINES);
+        lines.push("…");
+    }
+    let excerpt = lines.join("\n").trim().to_string();
+
+    (!excerpt.is_empty()).then_some(excerpt)
+}
+
+/// Details of a repository for `describe`: its current branch with a summary
+/// of its status, and the beginning of its README.
+pub fn describe_repository(repo: &Repository) -> String {
+    let mut sections = vec![];
+    if let Some(branch) = repo.head().ok().as_ref().and_then(|head| head.shorthand()) {
+        let summary = RepositoryStatus::read(repo)
+            .ok()
+            .and_then(|status| status.summary());
+        sections.push(match summary {
+            Some(summary) => format!("Branch: {} ({})", branch, summary),
+            None => format!("Branch: {}", branch),
+        });
+    }
+    if let Some(excerpt) = repo.workdir().and_then(get_readme_excerpt) {
+        sections.push(excerpt);
+    }
+
+    sections.join("\n\n")
+}
+
+/// Whether `fetch_interval` is set and repositories were last fetched longer
+/// ago than that.
+pub fn is_fetch_due(config: &ConfigurationValues) -> bool {
+    match expand_path(FETCH_STAMP_PATH) {
+        Ok(stamp_path) => is_fetch_due_at(config, Path::new(&stamp_path)),
+        Err(_) => config.fetch_interval.is_some(),
+    }
+}
+
+fn is_fetch_due_at(config: &ConfigurationValues, stamp_path: &Path) -> bool {
+    let Some(interval) = config.fetch_interval else {
+        return false;
+    };
+    let last_fetched = fs::metadata(stamp_path)
+        .ok()
+        .and_then(|metadata| metadata.modified().ok());
+
+    match last_fetched {
+        Some(last_fetched) => last_fetched
+            .elapsed()
+            .is_ok_and(|elapsed| elapsed >= Duration::from_secs(interval.saturating_mul(60))),
+        None => true,
+    }
+}
+
+/// Records that repositories are being fetched, so that listings happening
+/// in the meantime do not start fetching them again.
+fn stamp_fetch(stamp_path: &Path) -> Result<(), io::Error> {
+    if let Some(parent) = stamp_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(stamp_path, "")
+}
+
+/// Runs `git fetch --all` in a repository. Fetches happen in the background,
+/// where nobody could answer a prompt for a password or a host key, so git
+/// and ssh fail instead of waiting for one.
+fn fetch_repository(path: &Path) -> Result<Output, io::Error> {
+    let ssh_command = match env::var("GIT_SSH_COMMAND") {
+        Ok(ssh_command) => format!("{} -o BatchMode=yes", ssh_command),
+        Err(_) => "ssh -o BatchMode=yes".to_string(),
+    };
+
+    Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["fetch", "--all", "--quiet"])
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_SSH_COMMAND", ssh_command)
+        .stdin(Stdio::null())
+        .output()
+}
+
+/// Fetches all remotes of the repositories using `git fetch`, so that it uses
+/// the same credentials as when fetching by hand. At most
+/// `fetch_concurrency` repositories are fetched at the same time, and
+/// repositories that fail to fetch do not stop the others.
+pub fn fetch_repositories(
+    config: &ConfigurationValues,
+    repositories: &HashMap<String, Repository>,
+) -> Result<(), io::Error> {
+    stamp_fetch(Path::new(&expand_path(FETCH_STAMP_PATH)?))?;
+    let paths: Vec<PathBuf> = repositories
+        .values()
+        .map(|repo| repo.workdir().unwrap_or(repo.path()).to_path_buf())
+        .collect();
+    let next_index = AtomicUsize::new(0);
+    let concurrency = config
+        .fetch_concurrency
+        .unwrap_or(DEFAULT_FETCH_CONCURRENCY)
+        .clamp(1, paths.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency {
+            scope.spawn(|| {
+                while let Some(path) = paths.get(next_index.fetch_add(1, Ordering::Relaxed)) {
+                    match fetch_repository(path) {
+                        Ok(output) if output.status.success() => {}
+                        Ok(output) => tracing::warn!(
+                            path = %path.display(),
+                            error = %String::from_utf8_lossy(&output.stderr).trim(),
+                            "Could not fetch repository"
+                        ),
+                        Err(error) => tracing::warn!(
+                            path = %path.display(),
+                            %error,
+                            "Could not run git fetch"
+                        ),
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, process, time::SystemTime};
+
+    use super::*;
+
+    fn get_temporary_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!(
+            "enwiro-cookbook-git-{}-{}-{}",
+            name,
+            process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&directory).unwrap();
+
+        directory
+    }
+
+    fn commit(repo: &Repository, reference: &str, parents: &[git2::Oid]) -> git2::Oid {
+        let signature = git2::Signature::now("enwiro", "enwiro@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let parents: Vec<git2::Commit> = parents
+            .iter()
+            .map(|parent| repo.find_commit(*parent).unwrap())
+            .collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+
+        // Distinct messages keep commits with the same parent and tree apart
+        repo.commit(
+            Some(reference),
+            &signature,
+            &signature,
+            &format!("Commit {}", reference),
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    /// Repository whose current branch is one commit ahead of its upstream,
+    /// a local branch, and one commit behind it.
+    fn create_diverged_repository(directory: &Path) -> Repository {
+        let repo = Repository::init(directory).unwrap();
+        let base = commit(&repo, "HEAD", &[]);
+        commit(&repo, "HEAD", &[base]);
+        commit(&repo, "refs/heads/upstream", &[base]);
+        let branch_name = repo.head().unwrap().shorthand().unwrap().to_string();
+        repo.find_branch(&branch_name, BranchType::Local)
+            .unwrap()
+            .set_upstream(Some("upstream"))
+            .unwrap();
+
+        repo
+    }
+
+    fn get_config(fetch_interval: Option<u64>) -> ConfigurationValues {
+        ConfigurationValues {
+            fetch_interval,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_globs_that_cannot_be_expanded_are_invalid() {
+        let config = ConfigurationValues {
+            repo_globs: vec!["${ENWIRO_UNDEFINED_VARIABLE}/*".to_string()],
+            ..Default::default()
+        };
+
+        let error = config.expand_paths().unwrap_err();
+
+        assert_eq!(ErrorCode::of(&error), ErrorCode::ConfigInvalid);
+    }
+
+    #[test]
+    fn test_fetch_is_due_until_stamped() {
+        let directory = get_temporary_directory("stamp");
+        let stamp_path = directory.join("cache/cookbook-git-fetch");
+
+        assert!(is_fetch_due_at(&get_config(Some(60)), &stamp_path));
+        stamp_fetch(&stamp_path).unwrap();
+        assert!(!is_fetch_due_at(&get_config(Some(60)), &stamp_path));
+        assert!(is_fetch_due_at(&get_config(Some(0)), &stamp_path));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_is_never_due_without_interval() {
+        let directory = get_temporary_directory("no-interval");
+        let stamp_path = directory.join("cookbook-git-fetch");
+
+        assert!(!is_fetch_due_at(&get_config(None), &stamp_path));
+        stamp_fetch(&stamp_path).unwrap();
+        assert!(!is_fetch_due_at(&get_config(None), &stamp_path));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_fetch_is_due_again_once_stamp_is_old() {
+        let directory = get_temporary_directory("old-stamp");
+        let stamp_path = directory.join("cookbook-git-fetch");
+        stamp_fetch(&stamp_path).unwrap();
+        let two_hours_ago = SystemTime::now() - Duration::from_secs(2 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(&stamp_path)
+            .unwrap()
+            .set_modified(two_hours_ago)
+            .unwrap();
+
+        assert!(is_fetch_due_at(&get_config(Some(60)), &stamp_path));
+        assert!(!is_fetch_due_at(&get_config(Some(180)), &stamp_path));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_huge_intervals_do_not_overflow() {
+        let directory = get_temporary_directory("huge-interval");
+        let stamp_path = directory.join("cookbook-git-fetch");
+        stamp_fetch(&stamp_path).unwrap();
+
+        assert!(!is_fetch_due_at(&get_config(Some(u64::MAX)), &stamp_path));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_summary_lists_what_there_is_to_report() {
+        let status = RepositoryStatus {
+            dirty: true,
+            ahead: 2,
+            behind: 0,
+        };
+
+        assert_eq!(status.summary(), Some("dirty, 2 ahead".to_string()));
+        assert_eq!(RepositoryStatus::default().summary(), None);
+    }
+
+    #[test]
+    fn test_status_of_committed_repository_is_clean() {
+        let directory = get_temporary_directory("clean");
+        let repo = Repository::init(&directory).unwrap();
+        commit(&repo, "HEAD", &[]);
+
+        assert_eq!(
+            RepositoryStatus::read(&repo).unwrap(),
+            RepositoryStatus::default()
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_status_reports_changes_and_upstream_commits() {
+        let directory = get_temporary_directory("diverged");
+        let repo = create_diverged_repository(&directory);
+        fs::write(directory.join("new-file"), "content").unwrap();
+
+        assert_eq!(
+            RepositoryStatus::read(&repo).unwrap(),
+            RepositoryStatus {
+                dirty: true,
+                ahead: 1,
+                behind: 1,
+            }
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_descriptions_include_status_summary() {
+        let directory = get_temporary_directory("descriptions");
+        let repo_directory = directory.join("project");
+        let repo = create_diverged_repository(&repo_directory);
+        let repositories = HashMap::from([("project".to_string(), repo)]);
+        let mut cache = StatusCache::load_from(None);
+
+        let descriptions = describe_repositories(&repositories, Some(&mut cache));
+
+        assert_eq!(
+            descriptions["project"],
+            format!("{} (1 ahead, 1 behind)", repo_directory.display())
+        );
+        assert_eq!(
+            describe_repositories(&repositories, None)["project"],
+            repo_directory.display().to_string()
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_status_cache_reuses_recent_statuses() {
+        let directory = get_temporary_directory("cache");
+        let repo_directory = directory.join("project");
+        let cache_path = directory.join("cache/cookbook-git-status.json");
+        let repo = Repository::init(&repo_directory).unwrap();
+        commit(&repo, "HEAD", &[]);
+        let mut cache = StatusCache::load_from(Some(cache_path.clone()));
+        assert_eq!(cache.get(&repo), Some(RepositoryStatus::default()));
+        cache.save();
+        fs::write(repo_directory.join("new-file"), "content").unwrap();
+
+        let mut cache = StatusCache::load_from(Some(cache_path));
+
+        assert_eq!(cache.get(&repo), Some(RepositoryStatus::default()));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_status_cache_forgets_missing_repositories_and_old_statuses() {
+        let directory = get_temporary_directory("prune");
+        let cache_path = directory.join("cookbook-git-status.json");
+        let existing_path = directory.join("existing/.git");
+        fs::create_dir_all(&existing_path).unwrap();
+        let cached = |read_at| CachedStatus {
+            read_at,
+            status: RepositoryStatus::default(),
+        };
+        let mut cache = StatusCache {
+            path: Some(cache_path.clone()),
+            statuses: HashMap::from([
+                (existing_path.clone(), cached(now())),
+                (directory.join("removed/.git"), cached(now())),
+                (existing_path.join("old"), cached(0)),
+            ]),
+        };
+
+        cache.save();
+
+        assert_eq!(
+            StatusCache::load_from(Some(cache_path))
+                .statuses
+                .into_keys()
+                .collect::<Vec<_>>(),
+            vec![existing_path]
+        );
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
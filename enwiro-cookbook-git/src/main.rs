@@ -1,18 +1,23 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    env,
+    io::{self, Write},
+    process::{Command, Stdio},
+};
 
 use clap::Parser;
-use git2::Repository;
-use serde_derive::{Deserialize, Serialize};
-
-#[derive(Debug, Serialize, Deserialize, Default)]
-pub struct ConfigurationValues {
-    pub repo_globs: Vec<String>,
-}
+use enwiro_cookbook_git::{
+    build_repository_hashmap, describe_repository, fetch_repositories, get_descriptions,
+    is_fetch_due, load_configuration, ConfigurationValues,
+};
+use enwiro_errors::ErrorCode;
 
 #[derive(Parser)]
 enum EnwiroCookbookGit {
     ListRecipes(ListRecipesArgs),
     Cook(CookArgs),
+    Metadata(MetadataArgs),
+    Fetch(FetchArgs),
+    Describe(DescribeArgs),
 }
 
 #[derive(clap::Args)]
@@ -24,65 +29,114 @@ pub struct CookArgs {
     recipe_name: String,
 }
 
-fn build_repository_hashmap(config: &ConfigurationValues) -> HashMap<String, Repository> {
-    let mut results: HashMap<String, Repository> = HashMap::new();
-    for glob_from_config in config.repo_globs.iter() {
-        glob::glob(glob_from_config)
-            .expect("Could not parse glob")
-            .for_each(|entry| {
-                if let Ok(path) = entry {
-                    if let Ok(repo) = Repository::open(path) {
-                        let repo_path_string =
-                            repo.path().to_str().unwrap().replace("/.git", "").clone();
-                        let repo_name = Path::new(&repo_path_string.to_string())
-                            .file_name()
-                            .unwrap()
-                            .to_str()
-                            .unwrap()
-                            .to_string();
-
-                        results.insert(repo_name, repo);
-                    }
-                }
-            });
-    }
+#[derive(clap::Args)]
+pub struct MetadataArgs {}
 
-    results
+/// Fetch all repositories, which listing recipes does in the background when
+/// `fetch_interval` is set
+#[derive(clap::Args)]
+pub struct FetchArgs {}
+
+#[derive(clap::Args)]
+pub struct DescribeArgs {
+    recipe_name: String,
+}
+
+const METADATA: &str =
+    r#"{"protocolVersion": 2, "description": "Environments for local Git repositories", "describe": true}"#;
+
+/// Starts fetching repositories in a separate process, which keeps running
+/// after the recipes are listed.
+fn start_background_fetch() -> Result<(), io::Error> {
+    Command::new(env::current_exe()?)
+        .arg("fetch")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    Ok(())
 }
 
 fn list_recipes(config: &ConfigurationValues) {
-    for key in build_repository_hashmap(config).keys() {
-        println!("{}", key);
+    if is_fetch_due(config) {
+        if let Err(error) = start_background_fetch() {
+            tracing::warn!(%error, "Could not start fetching repositories");
+        }
+    }
+    let repositories = build_repository_hashmap(config);
+    for (name, description) in get_descriptions(config, &repositories) {
+        let recipe = serde_json::json!({
+            "name": name,
+            "description": description,
+        });
+        println!("{}", recipe);
     }
 }
 
+fn recipe_not_found(recipe_name: &str) -> io::Error {
+    ErrorCode::RecipeNotFound.error(
+        io::ErrorKind::NotFound,
+        format!("Could not find recipe {}", recipe_name),
+    )
+}
+
 /// Cooks a recipe. It returns the path to the already existing local
 /// clone of the repository.
-fn cook(config: &ConfigurationValues, args: CookArgs) {
+fn cook(config: &ConfigurationValues, args: CookArgs) -> Result<(), io::Error> {
     let repositories = build_repository_hashmap(config);
-    let selected_repo = repositories.get(&args.recipe_name);
-    if let Some(repo) = selected_repo {
-        println!("{}", repo.path().parent().unwrap().to_str().unwrap())
-    } else {
-        panic!("Could not find recipe {}", args.recipe_name);
-    }
+    let repo = repositories
+        .get(&args.recipe_name)
+        .ok_or_else(|| recipe_not_found(&args.recipe_name))?;
+    let path = repo.path().parent().ok_or_else(|| {
+        ErrorCode::CookFailed.error(
+            io::ErrorKind::NotFound,
+            format!("Could not find the directory of {}", args.recipe_name),
+        )
+    })?;
+    // Printed as bytes on Unix, since the path may not be valid UTF-8
+    #[cfg(unix)]
+    let path = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+    #[cfg(not(unix))]
+    let path = path.to_string_lossy().as_bytes().to_vec();
+
+    io::stdout().write_all(&[&path[..], b"\n"].concat())
 }
 
-fn main() -> Result<(), ()> {
+/// Prints the current branch and the beginning of the README of a
+/// repository.
+fn describe(config: &ConfigurationValues, args: DescribeArgs) -> Result<(), io::Error> {
+    let repositories = build_repository_hashmap(config);
+    let repo = repositories
+        .get(&args.recipe_name)
+        .ok_or_else(|| recipe_not_found(&args.recipe_name))?;
+    println!("{}", describe_repository(repo));
+
+    Ok(())
+}
+
+fn main() -> Result<(), io::Error> {
     let args = EnwiroCookbookGit::parse();
-    let config: ConfigurationValues = match confy::load("enwiro", "cookbook-git") {
-        Ok(x) => x,
-        Err(x) => {
-            panic!("Could not load configuration: {:?}", x);
-        }
-    };
+    let _ = enwiro_logging::init_logging("enwiro-cookbook-git");
+    let config = load_configuration()?;
 
     match args {
         EnwiroCookbookGit::ListRecipes(_) => {
             list_recipes(&config);
         }
         EnwiroCookbookGit::Cook(args) => {
-            cook(&config, args);
+            cook(&config, args)?;
+        }
+        EnwiroCookbookGit::Metadata(_) => {
+            println!("{}", METADATA);
+        }
+        EnwiroCookbookGit::Describe(args) => {
+            describe(&config, args)?;
+        }
+        EnwiroCookbookGit::Fetch(_) => {
+            let repositories = build_repository_hashmap(&config);
+            fetch_repositories(&config, &repositories)
+                .map_err(|error| ErrorCode::PluginFailed.tag(error))?;
         }
     };
 
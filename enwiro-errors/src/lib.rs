@@ -0,0 +1,219 @@
+use std::{error::Error, fmt, io};
+
+use serde_derive::{Deserialize, Serialize};
+
+/// Kinds of failures that callers such as bridges can tell apart without
+/// reading error messages. Each one exits with its own status.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorCode {
+    Other,
+    InvalidInput,
+    EnvironmentNotFound,
+    RecipeNotFound,
+    RecipeAmbiguous,
+    CookFailed,
+    AdapterFailed,
+    /// No adapter is configured, or it is too old for what was asked
+    AdapterUnavailable,
+    /// The command run by `enwiro wrap` failed, which exits with its status
+    CommandFailed,
+    /// The configuration could not be loaded
+    ConfigInvalid,
+    /// A plugin failed at something other than cooking or switching
+    /// workspaces, such as describing a recipe or being installed
+    PluginFailed,
+}
+
+/// Returned when the command run by `enwiro wrap` fails, so that enwiro can
+/// exit with the same status.
+#[derive(Debug)]
+pub struct ChildFailed {
+    pub code: i32,
+}
+
+impl fmt::Display for ChildFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Command exited with status {}", self.code)
+    }
+}
+
+impl Error for ChildFailed {}
+
+/// An error tagged with its `ErrorCode`, carried inside an `io::Error`.
+#[derive(Debug)]
+struct CodedError {
+    code: ErrorCode,
+    message: String,
+
+    // Set for recipes that failed to cook
+    cookbook: Option<String>,
+    recipe: Option<String>,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for CodedError {}
+
+impl ErrorCode {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorCode::Other | ErrorCode::CommandFailed => 1,
+            // Like the usage errors reported by clap
+            ErrorCode::InvalidInput => 2,
+            ErrorCode::EnvironmentNotFound => 3,
+            ErrorCode::RecipeNotFound => 4,
+            ErrorCode::RecipeAmbiguous => 5,
+            ErrorCode::CookFailed => 6,
+            ErrorCode::AdapterFailed => 7,
+            ErrorCode::AdapterUnavailable => 8,
+            ErrorCode::ConfigInvalid => 9,
+            ErrorCode::PluginFailed => 10,
+        }
+    }
+
+    /// Creates an error with this code.
+    pub fn error(self, kind: io::ErrorKind, message: impl Into<String>) -> io::Error {
+        io::Error::new(
+            kind,
+            CodedError {
+                code: self,
+                message: message.into(),
+                cookbook: None,
+                recipe: None,
+            },
+        )
+    }
+
+    /// Tags an error with this code, keeping its kind and message. Errors
+    /// that already have a code keep it.
+    pub fn tag(self, error: io::Error) -> io::Error {
+        match ErrorCode::of(&error) {
+            ErrorCode::Other => self.error(error.kind(), error.to_string()),
+            _ => error,
+        }
+    }
+
+    /// Finds the code of an error. Untagged errors are classified by kind.
+    pub fn of(error: &io::Error) -> ErrorCode {
+        if let Some(inner) = error.get_ref() {
+            if let Some(coded_error) = inner.downcast_ref::<CodedError>() {
+                return coded_error.code;
+            }
+            if inner.is::<ChildFailed>() {
+                return ErrorCode::CommandFailed;
+            }
+        }
+
+        match error.kind() {
+            io::ErrorKind::InvalidInput => ErrorCode::InvalidInput,
+            _ => ErrorCode::Other,
+        }
+    }
+}
+
+/// Tags the error of a cookbook that failed to cook a recipe, remembering
+/// both so that they can be reported separately. Errors that already have a
+/// code keep it.
+pub fn cook_error(cookbook: &str, recipe: &str, error: io::Error) -> io::Error {
+    let code = match ErrorCode::of(&error) {
+        ErrorCode::Other => ErrorCode::CookFailed,
+        code => code,
+    };
+
+    io::Error::new(
+        error.kind(),
+        CodedError {
+            code,
+            message: error.to_string(),
+            cookbook: Some(cookbook.to_string()),
+            recipe: Some(recipe.to_string()),
+        },
+    )
+}
+
+/// The exit status for a failed command.
+pub fn get_exit_code(error: &io::Error) -> i32 {
+    match error
+        .get_ref()
+        .and_then(|error| error.downcast_ref::<ChildFailed>())
+    {
+        Some(ChildFailed { code }) => *code,
+        None => ErrorCode::of(error).exit_code(),
+    }
+}
+
+/// Describes an error as a JSON object, for `--error-format json` and `enwiro rpc`.
+pub fn to_json(error: &io::Error) -> serde_json::Value {
+    let mut json = serde_json::json!({
+        "code": ErrorCode::of(error),
+        "exitCode": get_exit_code(error),
+        "message": error.to_string(),
+    });
+    if let Some(coded_error) = error
+        .get_ref()
+        .and_then(|error| error.downcast_ref::<CodedError>())
+    {
+        if let Some(cookbook) = &coded_error.cookbook {
+            json["cookbook"] = cookbook.as_str().into();
+        }
+        if let Some(recipe) = &coded_error.recipe {
+            json["recipe"] = recipe.as_str().into();
+        }
+    }
+
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_round_trip_through_json() {
+        let json = serde_json::to_value(ErrorCode::EnvironmentNotFound).unwrap();
+
+        assert_eq!(json, "environment-not-found");
+        assert_eq!(
+            serde_json::from_value::<ErrorCode>(json).unwrap(),
+            ErrorCode::EnvironmentNotFound
+        );
+    }
+
+    #[test]
+    fn test_tag_keeps_existing_code() {
+        let error = ErrorCode::AdapterFailed.tag(io::Error::other("failed"));
+        assert_eq!(ErrorCode::of(&error), ErrorCode::AdapterFailed);
+        assert_eq!(error.to_string(), "failed");
+
+        let error = ErrorCode::PluginFailed.tag(error);
+        assert_eq!(ErrorCode::of(&error), ErrorCode::AdapterFailed);
+    }
+
+    #[test]
+    fn test_child_failures_exit_with_their_status() {
+        let error = io::Error::other(ChildFailed { code: 42 });
+
+        assert_eq!(ErrorCode::of(&error), ErrorCode::CommandFailed);
+        assert_eq!(get_exit_code(&error), 42);
+        assert_eq!(to_json(&error)["exitCode"], 42);
+    }
+
+    #[test]
+    fn test_cook_errors_keep_existing_code() {
+        let error = cook_error(
+            "git",
+            "enwiro",
+            ErrorCode::RecipeNotFound.error(io::ErrorKind::NotFound, "missing"),
+        );
+
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(ErrorCode::of(&error), ErrorCode::RecipeNotFound);
+        assert_eq!(to_json(&error)["cookbook"], "git");
+        assert_eq!(to_json(&error)["recipe"], "enwiro");
+    }
+}
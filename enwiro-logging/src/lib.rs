@@ -0,0 +1,294 @@
+use std::{
+    env, fs,
+    io::{self},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tracing::{
+    span::{Attributes, Id},
+    Subscriber,
+};
+use tracing_subscriber::{
+    fmt::format::FmtSpan,
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+/// Environment variable holding the log filter, for example `debug` or
+/// `warn,enwiro=debug`.
+pub const LOG_FILTER_VARIABLE: &str = "ENWIRO_LOG";
+
+/// Environment variable selecting the log format, `text` or `json`.
+pub const LOG_FORMAT_VARIABLE: &str = "ENWIRO_LOG_FORMAT";
+
+/// Environment variable selecting where logs are written, `file` or `journald`.
+pub const LOG_OUTPUT_VARIABLE: &str = "ENWIRO_LOG_OUTPUT";
+
+const DEFAULT_FILE_LEVEL: &str = "info";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Output {
+    /// A log file per component in the log directory
+    #[default]
+    File,
+
+    /// The systemd journal, with each field of an event as a journal field
+    Journald,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Name of the binary, used as the name of the log file
+    pub component: String,
+
+    /// Level of messages written to the log file unless `filter` says otherwise
+    pub file_level: String,
+
+    /// Writes one JSON object per line instead of plain text
+    pub json: bool,
+
+    /// Per-component filter directives such as `enwiro_cookbook_git=debug`
+    pub filter: Option<String>,
+
+    /// Directory containing the log files, defaults to `$XDG_STATE_HOME/enwiro`
+    pub directory: Option<PathBuf>,
+
+    pub output: Output,
+}
+
+impl Options {
+    pub fn new(component: &str) -> Self {
+        Self {
+            component: component.to_string(),
+            file_level: DEFAULT_FILE_LEVEL.to_string(),
+            json: false,
+            filter: None,
+            directory: None,
+            output: Output::default(),
+        }
+    }
+
+    /// Options for a component configured using `ENWIRO_LOG`,
+    /// `ENWIRO_LOG_FORMAT` and `ENWIRO_LOG_OUTPUT`, so that every binary is
+    /// configured the same way.
+    pub fn from_variables(component: &str, get_variable: impl Fn(&str) -> Option<String>) -> Self {
+        Self {
+            json: get_variable(LOG_FORMAT_VARIABLE)
+                .is_some_and(|format| format.eq_ignore_ascii_case("json")),
+            filter: get_variable(LOG_FILTER_VARIABLE).filter(|filter| !filter.trim().is_empty()),
+            output: match get_variable(LOG_OUTPUT_VARIABLE) {
+                Some(output) if output.eq_ignore_ascii_case("journald") => Output::Journald,
+                _ => Output::File,
+            },
+            ..Self::new(component)
+        }
+    }
+
+    fn get_directory(&self) -> Option<PathBuf> {
+        if let Some(directory) = &self.directory {
+            return Some(directory.clone());
+        }
+
+        let state_directory = match env::var_os("XDG_STATE_HOME") {
+            Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+            _ => home::home_dir()?.join(".local/state"),
+        };
+
+        Some(state_directory.join("enwiro"))
+    }
+
+    fn get_filter(&self) -> Result<EnvFilter, io::Error> {
+        let directives = match &self.filter {
+            Some(filter) => format!("{},{}", self.file_level, filter),
+            None => self.file_level.clone(),
+        };
+
+        EnvFilter::try_new(directives).map_err(io::Error::other)
+    }
+}
+
+/// A span that was entered while timings were recorded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanTiming {
+    pub name: String,
+
+    /// Number of spans this one is nested in
+    pub depth: usize,
+
+    /// How long the span was open, or `None` if it has not been closed yet
+    pub duration: Option<Duration>,
+}
+
+/// Durations of the spans created while logging is set up with timings, in
+/// the order they started. They are recorded regardless of the log filter.
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    spans: Arc<Mutex<Vec<SpanTiming>>>,
+}
+
+impl Timings {
+    pub fn get(&self) -> Vec<SpanTiming> {
+        self.spans.lock().unwrap().clone()
+    }
+}
+
+/// Where a span is recorded in `Timings`, kept in the extensions of the span.
+struct SpanStart {
+    index: usize,
+    instant: Instant,
+}
+
+struct TimingsLayer {
+    timings: Timings,
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TimingsLayer {
+    fn on_new_span(&self, _attributes: &Attributes<'_>, id: &Id, context: Context<'_, S>) {
+        let Some(span) = context.span(id) else {
+            return;
+        };
+        let mut spans = self.timings.spans.lock().unwrap();
+        spans.push(SpanTiming {
+            name: span.name().to_string(),
+            depth: span.scope().skip(1).count(),
+            duration: None,
+        });
+        span.extensions_mut().insert(SpanStart {
+            index: spans.len() - 1,
+            instant: Instant::now(),
+        });
+    }
+
+    fn on_close(&self, id: Id, context: Context<'_, S>) {
+        let Some(span) = context.span(&id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        if let Some(start) = extensions.get::<SpanStart>() {
+            self.timings.spans.lock().unwrap()[start.index].duration =
+                Some(start.instant.elapsed());
+        }
+    }
+}
+
+/// Sets up logging to `<component>.log` using the options found in the
+/// environment.
+pub fn init_logging(component: &str) -> Result<(), io::Error> {
+    init_logging_with(Options::from_variables(component, |name| {
+        env::var(name).ok()
+    }))
+}
+
+/// Like `init_logging`, also recording how long each span took.
+pub fn init_logging_with_timings(component: &str) -> Result<Timings, io::Error> {
+    let timings = Timings::default();
+    init(
+        Options::from_variables(component, |name| env::var(name).ok()),
+        Some(timings.clone()),
+    )?;
+
+    Ok(timings)
+}
+
+pub fn init_logging_with(options: Options) -> Result<(), io::Error> {
+    init(options, None)
+}
+
+fn try_init<L: Layer<Registry> + Send + Sync>(
+    layer: L,
+    timings: Option<Timings>,
+) -> Result<(), io::Error> {
+    let registry = tracing_subscriber::registry().with(layer);
+    let result = match timings {
+        Some(timings) => registry.with(TimingsLayer { timings }).try_init(),
+        None => registry.try_init(),
+    };
+
+    result.map_err(io::Error::other)
+}
+
+fn init(options: Options, timings: Option<Timings>) -> Result<(), io::Error> {
+    let filter = options.get_filter()?;
+
+    #[cfg(unix)]
+    if options.output == Output::Journald {
+        let layer = tracing_journald::layer()?.with_syslog_identifier(options.component);
+
+        return try_init(layer.with_filter(filter), timings);
+    }
+
+    let directory = options
+        .get_directory()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "User home directory not found"))?;
+    fs::create_dir_all(&directory)?;
+    let log_file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(directory.join(format!("{}.log", options.component)))?;
+
+    // Closed spans are logged with how long they took
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(Mutex::new(log_file))
+        .with_ansi(false)
+        .with_span_events(FmtSpan::CLOSE);
+    match options.json {
+        true => try_init(layer.json().with_filter(filter), timings),
+        false => try_init(layer.with_filter(filter), timings),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use crate::{Options, Output, Timings, TimingsLayer};
+
+    #[test]
+    fn test_options_are_read_from_environment_variables() {
+        let options = Options::from_variables("enwiro", |name| match name {
+            "ENWIRO_LOG" => Some("enwiro_cookbook_git=debug".to_string()),
+            "ENWIRO_LOG_FORMAT" => Some("JSON".to_string()),
+            "ENWIRO_LOG_OUTPUT" => Some("journald".to_string()),
+            _ => None,
+        });
+
+        assert!(options.json);
+        assert_eq!(options.output, Output::Journald);
+        assert_eq!(
+            options.filter,
+            Some("enwiro_cookbook_git=debug".to_string())
+        );
+        assert!(options.get_filter().is_ok());
+        assert_eq!(
+            Options::from_variables("enwiro", |_| None),
+            Options::new("enwiro")
+        );
+    }
+
+    #[test]
+    fn test_timings_record_nested_spans() {
+        let timings = Timings::default();
+        let subscriber = tracing_subscriber::registry().with(TimingsLayer {
+            timings: timings.clone(),
+        });
+
+        tracing::subscriber::with_default(subscriber, || {
+            let _outer = tracing::debug_span!("outer").entered();
+            tracing::trace_span!("inner").in_scope(|| {});
+        });
+
+        let spans = timings.get();
+        assert_eq!(
+            spans
+                .iter()
+                .map(|span| (span.name.as_str(), span.depth))
+                .collect::<Vec<_>>(),
+            vec![("outer", 0), ("inner", 1)]
+        );
+        assert!(spans.iter().all(|span| span.duration.is_some()));
+    }
+}
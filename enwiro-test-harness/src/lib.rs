@@ -0,0 +1,285 @@
+// The fake plugins are shell scripts
+#![cfg(unix)]
+
+use std::{
+    env::{self, temp_dir},
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    time::Duration,
+};
+
+use rand::Rng;
+
+/// What a fake plugin does when it is invoked with a command, and optionally
+/// an argument such as the recipe to cook.
+#[derive(Clone, Debug)]
+struct Response {
+    command: String,
+    argument: Option<String>,
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+    delay: Option<Duration>,
+}
+
+/// A plugin executable generated from a declarative description. Every
+/// invocation is recorded, so that tests can check how enwiro called it.
+/// When several responses match an invocation, the one added first is used.
+#[derive(Clone, Debug)]
+pub struct FakePlugin {
+    executable_name: String,
+    metadata: serde_json::Value,
+    responses: Vec<Response>,
+}
+
+impl FakePlugin {
+    fn new(prefix: &str, name: &str) -> Self {
+        Self {
+            executable_name: format!("{}{}", prefix, name),
+            metadata: serde_json::json!({"protocolVersion": 2}),
+            responses: vec![],
+        }
+    }
+
+    pub fn adapter(name: &str) -> Self {
+        Self::new("enwiro-adapter-", name)
+    }
+
+    pub fn cookbook(name: &str) -> Self {
+        Self::new("enwiro-cookbook-", name)
+    }
+
+    /// Replaces what the plugin prints for `metadata`, which is protocol
+    /// version 2 without any optional capabilities by default.
+    pub fn metadata(mut self, metadata: serde_json::Value) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Prints `stdout` and succeeds when invoked with `command`, whatever
+    /// its arguments.
+    pub fn respond(self, command: &str, stdout: &str) -> Self {
+        self.respond_with(command, None, stdout, "", 0)
+    }
+
+    /// Prints `stdout` and succeeds when invoked with `command` and
+    /// `argument`.
+    pub fn respond_to(self, command: &str, argument: &str, stdout: &str) -> Self {
+        self.respond_with(command, Some(argument), stdout, "", 0)
+    }
+
+    /// Prints `stderr` and exits with status 1 when invoked with `command`.
+    pub fn fail(self, command: &str, stderr: &str) -> Self {
+        self.respond_with(command, None, "", stderr, 1)
+    }
+
+    /// Waits before responding to `command`, for example to run into
+    /// timeouts. Applies to the response added last.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        if let Some(response) = self.responses.last_mut() {
+            response.delay = Some(delay);
+        }
+        self
+    }
+
+    fn respond_with(
+        mut self,
+        command: &str,
+        argument: Option<&str>,
+        stdout: &str,
+        stderr: &str,
+        exit_code: i32,
+    ) -> Self {
+        self.responses.push(Response {
+            command: command.to_string(),
+            argument: argument.map(str::to_string),
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+            exit_code,
+            delay: None,
+        });
+        self
+    }
+
+    fn is_adapter(&self) -> bool {
+        self.executable_name.starts_with("enwiro-adapter-")
+    }
+
+    /// The plugin as a shell script, recording its invocations in `log_path`.
+    fn to_script(&self, log_path: &Path) -> String {
+        let mut script = format!(
+            "#!/bin/sh\necho \"$*\" >> {}\n",
+            quote(&log_path.to_string_lossy())
+        );
+        let metadata = Response {
+            command: "metadata".to_string(),
+            argument: None,
+            stdout: format!("{}\n", self.metadata),
+            stderr: String::new(),
+            exit_code: 0,
+            delay: None,
+        };
+        for response in self.responses.iter().chain([&metadata]) {
+            let mut condition = format!("[ \"$1\" = {} ]", quote(&response.command));
+            if let Some(argument) = &response.argument {
+                condition.push_str(&format!(" && [ \"$2\" = {} ]", quote(argument)));
+            }
+            script.push_str(&format!("if {}; then\n", condition));
+            if let Some(delay) = response.delay {
+                script.push_str(&format!("    sleep {}\n", delay.as_secs_f64()));
+            }
+            script.push_str(&format!(
+                "    printf '%s' {}\n    printf '%s' {} >&2\n    exit {}\nfi\n",
+                quote(&response.stdout),
+                quote(&response.stderr),
+                response.exit_code
+            ));
+        }
+        script.push_str("echo \"Unexpected call: $*\" >&2\nexit 1\n");
+
+        script
+    }
+}
+
+/// Quotes a value for a shell script.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// A temporary home for running enwiro with fake plugins and its own
+/// configuration, workspaces and caches, isolated from the user's.
+pub struct Harness {
+    enwiro_executable: PathBuf,
+    directory: PathBuf,
+    plugins: Vec<FakePlugin>,
+    extra_configuration: String,
+}
+
+impl Harness {
+    /// Uses the given enwiro binary, such as `env!("CARGO_BIN_EXE_enwiro")`
+    /// in the tests of the enwiro crate.
+    pub fn new(enwiro_executable: impl Into<PathBuf>) -> Self {
+        let directory = temp_dir().join(format!(
+            "enwiro-harness-{}",
+            rand::thread_rng().gen_range(100000000..999999999)
+        ));
+        for subdirectory in ["bin", "home", "workspaces", "logs"] {
+            fs::create_dir_all(directory.join(subdirectory))
+                .expect("Could not create harness directory");
+        }
+
+        Self {
+            enwiro_executable: enwiro_executable.into(),
+            directory,
+            plugins: vec![],
+            extra_configuration: String::new(),
+        }
+    }
+
+    /// Installs a fake plugin. The first adapter becomes the configured one.
+    pub fn plugin(mut self, plugin: FakePlugin) -> Self {
+        let executable = self.directory.join("bin").join(&plugin.executable_name);
+        fs::write(
+            &executable,
+            plugin.to_script(&self.log_path(&plugin.executable_name)),
+        )
+        .expect("Could not write fake plugin");
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755))
+            .expect("Could not make fake plugin executable");
+        self.plugins.push(plugin);
+        self
+    }
+
+    /// Appends to the generated configuration file, for example tables such
+    /// as `[adapter_calls]`.
+    pub fn configuration(mut self, configuration: &str) -> Self {
+        self.extra_configuration.push_str(configuration);
+        self.extra_configuration.push('\n');
+        self
+    }
+
+    pub fn workspaces_directory(&self) -> PathBuf {
+        self.directory.join("workspaces")
+    }
+
+    fn log_path(&self, executable_name: &str) -> PathBuf {
+        self.directory.join("logs").join(executable_name)
+    }
+
+    fn write_configuration(&self) {
+        let adapter = self
+            .plugins
+            .iter()
+            .find(|plugin| plugin.is_adapter())
+            .map(|plugin| plugin.executable_name.trim_start_matches("enwiro-adapter-"));
+        let allowed_plugins: Vec<&str> = self
+            .plugins
+            .iter()
+            .map(|plugin| plugin.executable_name.as_str())
+            .collect();
+        let mut configuration = format!(
+            "workspaces_directory = {}\nallowed_plugins = {}\n",
+            serde_json::json!(self.workspaces_directory()),
+            serde_json::json!(allowed_plugins)
+        );
+        if let Some(adapter) = adapter {
+            configuration.push_str(&format!("adapter = {}\n", serde_json::json!(adapter)));
+        }
+        configuration.push_str("\n[notifications]\nbackend = \"none\"\n\n");
+        configuration.push_str(&self.extra_configuration);
+
+        let configuration_directory = self.directory.join("home/.config/enwiro");
+        fs::create_dir_all(&configuration_directory)
+            .expect("Could not create configuration directory");
+        fs::write(configuration_directory.join("enwiro.toml"), configuration)
+            .expect("Could not write configuration");
+    }
+
+    /// Runs enwiro with the given arguments, finding only the fake plugins.
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.write_configuration();
+        let home = self.directory.join("home");
+        let path = env::join_paths(
+            [self.directory.join("bin")].into_iter().chain(
+                env::var_os("PATH")
+                    .iter()
+                    .flat_map(|path| env::split_paths(path)),
+            ),
+        )
+        .expect("Could not build PATH");
+        let mut command = Command::new(&self.enwiro_executable);
+        for (name, _) in env::vars_os() {
+            let name = name.to_string_lossy();
+            if name.starts_with("ENWIRO_") || name.starts_with("XDG_") {
+                command.env_remove(&*name);
+            }
+        }
+
+        command
+            .args(args)
+            .env("HOME", &home)
+            .env("XDG_CONFIG_HOME", home.join(".config"))
+            .env("PATH", path)
+            .output()
+            .expect("Could not run enwiro")
+    }
+
+    /// The arguments of each invocation of a fake plugin so far, except for
+    /// `metadata`.
+    pub fn calls(&self, plugin: &FakePlugin) -> Vec<String> {
+        fs::read_to_string(self.log_path(&plugin.executable_name))
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| *line != "metadata")
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.directory);
+    }
+}
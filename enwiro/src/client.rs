@@ -1,37 +1,522 @@
-use std::process::Command;
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
 
-use crate::plugin::Plugin;
+use serde_derive::Deserialize;
 
+use crate::{
+    cookbook::CookbookTrait,
+    environment_metadata::EnvironmentMetadata,
+    error_codes::ErrorCode,
+    platform::path_from_bytes,
+    plugin::{Plugin, PluginMetadata},
+    secrets::resolve_secrets,
+};
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+/// Environment variable passing a cookbook its section of the configuration,
+/// encoded as JSON.
+const CONFIGURATION_VARIABLE: &str = "ENWIRO_COOKBOOK_CONFIG";
+
+/// Cookbooks speaking this protocol version or newer list their recipes as
+/// JSON objects, one per line.
+const JSON_LINES_PROTOCOL_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Recipe {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+
+    // Section of the cookbook's recipes this one belongs to, such as "review
+    // requests"
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+/// Parses a line printed by `list-recipes`. Older cookbooks print one recipe
+/// per line, optionally followed by a tab and a description.
+fn parse_recipe(line: &str, protocol_version: u32) -> Option<Recipe> {
+    if line.trim().is_empty() {
+        return None;
+    }
+
+    if protocol_version >= JSON_LINES_PROTOCOL_VERSION {
+        return serde_json::from_str(line).ok();
+    }
+
+    match line.split_once('\t') {
+        Some((name, description)) => Some(Recipe {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            category: None,
+        }),
+        None => Some(Recipe {
+            name: line.to_string(),
+            description: None,
+            category: None,
+        }),
+    }
+}
+
+/// Result of cooking a recipe. Cookbooks either print just the path of the
+/// environment or, since protocol version 2, a JSON object with the path and
+/// metadata about the environment. Only the plain path may contain bytes that
+/// are not valid UTF-8.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct CookResponse {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub metadata: EnvironmentMetadata,
+}
+
+fn parse_cook_output(output: &[u8], protocol_version: u32) -> CookResponse {
+    let output = output.trim_ascii();
+
+    if protocol_version >= JSON_LINES_PROTOCOL_VERSION && output.starts_with(b"{") {
+        if let Ok(response) = serde_json::from_slice(output) {
+            return response;
+        }
+    }
+
+    CookResponse {
+        path: path_from_bytes(output),
+        metadata: EnvironmentMetadata::default(),
+    }
+}
+
+#[derive(Debug)]
 pub struct CookbookClient {
-    pub plugin: Plugin
+    pub plugin: Plugin,
+    metadata: OnceCell<PluginMetadata>,
+    settings: Option<serde_json::Value>,
+
+    // The settings with their secrets resolved, which runs commands, so it is
+    // only done once the cookbook is actually invoked
+    resolved_settings: OnceCell<Option<serde_json::Value>>,
 }
 
 impl CookbookClient {
-    pub fn new(plugin: Plugin) -> Self {
+    pub fn new(plugin: Plugin, settings: Option<serde_json::Value>) -> Self {
         Self {
-            plugin
+            plugin,
+            metadata: OnceCell::new(),
+            settings,
+            resolved_settings: OnceCell::new(),
+        }
+    }
+
+    fn command(&self) -> Command {
+        let mut command = Command::new(&self.plugin.executable);
+        let settings = self
+            .resolved_settings
+            .get_or_init(|| self.settings.clone().map(resolve_secrets));
+        if let Some(settings) = settings {
+            command.env(CONFIGURATION_VARIABLE, settings.to_string());
+        }
+
+        command
+    }
+
+    /// Metadata of the cookbook, queried the first time it is needed.
+    pub fn metadata(&self) -> &PluginMetadata {
+        self.metadata.get_or_init(|| self.plugin.get_metadata())
+    }
+}
+
+impl CookbookTrait for CookbookClient {
+    fn name(&self) -> &str {
+        &self.plugin.name
+    }
+
+    fn priority(&self, configured_priorities: &HashMap<String, i32>) -> i32 {
+        match configured_priorities.get(&self.plugin.name) {
+            Some(priority) => *priority,
+            None => self.metadata().default_priority.unwrap_or(0),
         }
     }
 
-    pub fn list_recipes(&self) -> Vec<String> {
-        let output = Command::new(&self.plugin.executable)
+    fn stream_recipes(
+        &self,
+        on_recipe: &mut dyn FnMut(Recipe) -> Result<(), std::io::Error>,
+    ) -> Result<(), std::io::Error> {
+        let _span = tracing::debug_span!("list_recipes", cookbook = %self.plugin.name).entered();
+        let protocol_version = self.metadata().protocol_version;
+        let mut child = self
+            .command()
             .arg("list-recipes")
-            .output()
-            .expect("Adapter failed to determine active environment name");
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child.stdout.take().expect("Could not read cookbook output");
+
+        // Recipes are often named after paths, which are not always valid UTF-8
+        for line in BufReader::new(stdout).split(b'\n') {
+            let result = line.and_then(|line| {
+                match parse_recipe(
+                    String::from_utf8_lossy(&line).trim_end_matches('\r'),
+                    protocol_version,
+                ) {
+                    Some(recipe) => on_recipe(recipe),
+                    None => Ok(()),
+                }
+            });
+            if let Err(error) = result {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(error);
+            }
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(ErrorCode::PluginFailed.error(
+                std::io::ErrorKind::Other,
+                format!("Cookbook failed to list recipes: {}", status),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn cook(&self, recipe: &str) -> Result<CookResponse, std::io::Error> {
+        let _span =
+            tracing::debug_span!("cook_recipe", cookbook = %self.plugin.name, recipe).entered();
+        let protocol_version = self.metadata().protocol_version;
+        tracing::debug!(cookbook = %self.plugin.name, recipe, "Cooking recipe");
+        let output = self.command().arg("cook").arg(recipe).output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::warn!(cookbook = %self.plugin.name, recipe, stderr = %stderr.trim(), "Cookbook failed to cook recipe");
+            return Err(std::io::Error::other(format!(
+                "Cookbook failed to cook recipe: {}",
+                stderr.trim()
+            )));
+        }
+
+        Ok(parse_cook_output(&output.stdout, protocol_version))
+    }
+
+    fn prefetch(&self, recipe: &str) -> Result<(), std::io::Error> {
+        if !self.metadata().prefetch {
+            return Ok(());
+        }
+
+        tracing::debug!(cookbook = %self.plugin.name, recipe, "Prefetching recipe");
+        let output = self.command().arg("prefetch").arg(recipe).output()?;
+        if !output.status.success() {
+            return Err(ErrorCode::PluginFailed.error(
+                std::io::ErrorKind::Other,
+                format!(
+                    "Cookbook failed to prefetch recipe: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        stdout.lines().map(|x| x.to_string()).collect()
+        Ok(())
     }
 
-    pub fn cook(&self, recipe: &str) -> String {
-        let output = Command::new(&self.plugin.executable)
-            .arg("cook")
-            .arg(recipe)
-            .output()
-            .expect("Failed to cook recipe");
+    fn describe(&self, recipe: &str) -> Result<Option<String>, std::io::Error> {
+        if !self.metadata().describe {
+            return Ok(None);
+        }
+
+        let output = self.command().arg("describe").arg(recipe).output()?;
+        if !output.status.success() {
+            return Err(ErrorCode::PluginFailed.error(
+                std::io::ErrorKind::Other,
+                format!(
+                    "Cookbook failed to describe recipe: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        let details = String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string();
+
+        Ok(Some(details).filter(|details| !details.is_empty()))
+    }
+
+    fn uncook(&self, recipe: &str) -> Result<(), std::io::Error> {
+        if !self.metadata().uncook {
+            return Ok(());
+        }
+
+        tracing::debug!(cookbook = %self.plugin.name, recipe, "Uncooking recipe");
+        let output = self.command().arg("uncook").arg(recipe).output()?;
+        if !output.status.success() {
+            return Err(ErrorCode::PluginFailed.error(
+                std::io::ErrorKind::Other,
+                format!(
+                    "Cookbook failed to uncook recipe: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        env::temp_dir,
+        ffi::OsStr,
+        fs,
+        os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+        path::{Path, PathBuf},
+    };
+
+    use rand::Rng;
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::{
+        client::{parse_cook_output, parse_recipe, CookResponse, CookbookClient, Recipe},
+        cookbook::CookbookTrait,
+        environment_metadata::EnvironmentMetadata,
+        error_codes::ErrorCode,
+        plugin::{Plugin, PluginKind, PluginMetadata},
+    };
+
+    fn parse_recipes(output: &str, protocol_version: u32) -> Vec<Recipe> {
+        output
+            .lines()
+            .filter_map(|line| parse_recipe(line, protocol_version))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_recipes_from_json_lines() {
+        let output = "{\"name\": \"with\\ttab\", \"description\": \"Foo\", \"category\": \"Mine\", \"icon\": \"git\"}\n\
+                      {\"name\": \"bar\"}\n";
+
+        assert_eq!(
+            parse_recipes(output, 2),
+            vec![
+                Recipe {
+                    name: "with\ttab".to_string(),
+                    description: Some("Foo".to_string()),
+                    category: Some("Mine".to_string()),
+                },
+                Recipe {
+                    name: "bar".to_string(),
+                    description: None,
+                    category: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_recipes_from_legacy_lines() {
+        let output = "foo\tFoo project\nbar\n";
+
+        assert_eq!(
+            parse_recipes(output, 1),
+            vec![
+                Recipe {
+                    name: "foo".to_string(),
+                    description: Some("Foo project".to_string()),
+                    category: None,
+                },
+                Recipe {
+                    name: "bar".to_string(),
+                    description: None,
+                    category: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_cook_output_with_metadata() {
+        let output = b"{\"path\": \"/foo\", \"description\": \"Foo\", \"gear\": [\"nvim\"]}\n";
+
+        assert_eq!(
+            parse_cook_output(output, 2),
+            CookResponse {
+                path: PathBuf::from("/foo"),
+                metadata: EnvironmentMetadata {
+                    description: Some("Foo".to_string()),
+                    gear: vec!["nvim".to_string()],
+                    ..Default::default()
+                },
+            }
+        );
+        assert_eq!(parse_cook_output(b"/foo\n", 2).path, Path::new("/foo"));
+        assert_eq!(
+            parse_cook_output(b"{weird}/path\n", 1).path,
+            Path::new("{weird}/path")
+        );
+    }
+
+    #[test]
+    fn test_parse_cook_output_keeps_bytes_of_path() {
+        assert_eq!(
+            parse_cook_output(b"/foo/caf\xe9\n", 2).path.as_os_str(),
+            OsStr::from_bytes(b"/foo/caf\xe9")
+        );
+    }
+
+    #[test]
+    fn test_configured_priority_overrides_default_priority() {
+        let cookbook = CookbookClient::new(
+            Plugin {
+                name: "git".to_string(),
+                kind: PluginKind::Cookbook,
+                executable: "enwiro-cookbook-git".to_string(),
+            },
+            None,
+        );
+        cookbook
+            .metadata
+            .set(PluginMetadata {
+                default_priority: Some(10),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(cookbook.priority(&HashMap::new()), 10);
+        assert_eq!(
+            cookbook.priority(&HashMap::from([("git".to_string(), -5)])),
+            -5
+        );
+    }
+
+    #[test]
+    fn test_failing_to_list_recipes_is_an_error() {
+        let executable = temp_dir().join(format!(
+            "enwiro-cookbook-{}",
+            rand::thread_rng().gen_range(100000000..999999999)
+        ));
+        fs::write(&executable, "#!/bin/sh\necho foo\nexit 3\n").unwrap();
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755)).unwrap();
+        let cookbook = CookbookClient::new(
+            Plugin {
+                name: "broken".to_string(),
+                kind: PluginKind::Cookbook,
+                executable: executable.to_string_lossy().to_string(),
+            },
+            None,
+        );
+
+        let error = cookbook.list_recipes().unwrap_err();
+
+        assert_eq!(ErrorCode::of(&error), ErrorCode::PluginFailed);
+    }
+
+    #[test]
+    fn test_recipes_that_are_not_utf8_are_listed() {
+        let executable = temp_dir().join(format!(
+            "enwiro-cookbook-{}",
+            rand::thread_rng().gen_range(100000000..999999999)
+        ));
+        fs::write(&executable, "#!/bin/sh\nprintf 'caf\\351\\nfoo\\n'\n").unwrap();
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755)).unwrap();
+        let cookbook = CookbookClient::new(
+            Plugin {
+                name: "files".to_string(),
+                kind: PluginKind::Cookbook,
+                executable: executable.to_string_lossy().to_string(),
+            },
+            None,
+        );
+
+        let names: Vec<String> = cookbook
+            .list_recipes()
+            .unwrap()
+            .into_iter()
+            .map(|recipe| recipe.name)
+            .collect();
+
+        assert_eq!(names, vec!["caf\u{FFFD}", "foo"]);
+    }
+
+    #[test]
+    fn test_settings_are_passed_to_the_cookbook() {
+        let executable = temp_dir().join(format!(
+            "enwiro-cookbook-{}",
+            rand::thread_rng().gen_range(100000000..999999999)
+        ));
+        fs::write(&executable, "#!/bin/sh\necho \"$ENWIRO_COOKBOOK_CONFIG\"\n").unwrap();
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755)).unwrap();
+        let cookbook = CookbookClient::new(
+            Plugin {
+                name: "echo".to_string(),
+                kind: PluginKind::Cookbook,
+                executable: executable.to_string_lossy().to_string(),
+            },
+            Some(json!({"repo_globs": ["~/projects/*"]})),
+        );
+
+        let recipes = cookbook.list_recipes().unwrap();
+
+        assert_eq!(recipes.len(), 1);
+        assert_eq!(recipes[0].name, "{\"repo_globs\":[\"~/projects/*\"]}");
+    }
+
+    #[test]
+    fn test_secrets_are_resolved_when_the_cookbook_is_invoked() {
+        let id = rand::thread_rng().gen_range(100000000..999999999);
+        let executable = temp_dir().join(format!("enwiro-cookbook-{}", id));
+        let marker = temp_dir().join(format!("enwiro-secret-{}", id));
+        fs::write(&executable, "#!/bin/sh\necho \"$ENWIRO_COOKBOOK_CONFIG\"\n").unwrap();
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755)).unwrap();
+        let cookbook = CookbookClient::new(
+            Plugin {
+                name: "echo".to_string(),
+                kind: PluginKind::Cookbook,
+                executable: executable.to_string_lossy().to_string(),
+            },
+            Some(json!({"token_cmd": format!("touch '{}' && echo hunter2", marker.display())})),
+        );
+
+        assert!(!marker.exists());
+        let recipes = cookbook.list_recipes().unwrap();
+
+        assert!(marker.exists());
+        assert_eq!(recipes[0].name, "{\"token\":\"hunter2\"}");
+        fs::remove_file(marker).unwrap();
+    }
+    #[rstest]
+    #[case::supported(true, Some("Details of foo".to_string()))]
+    #[case::unsupported(false, None)]
+    fn test_describe_recipe(#[case] describe: bool, #[case] expected: Option<String>) {
+        let executable = temp_dir().join(format!(
+            "enwiro-cookbook-{}",
+            rand::thread_rng().gen_range(100000000..999999999)
+        ));
+        fs::write(
+            &executable,
+            "#!/bin/sh\nprintf 'Details of %s\\n\\n' \"$2\"\n",
+        )
+        .unwrap();
+        fs::set_permissions(&executable, fs::Permissions::from_mode(0o755)).unwrap();
+        let cookbook = CookbookClient::new(
+            Plugin {
+                name: "details".to_string(),
+                kind: PluginKind::Cookbook,
+                executable: executable.to_string_lossy().to_string(),
+            },
+            None,
+        );
+        cookbook
+            .metadata
+            .set(PluginMetadata {
+                protocol_version: 2,
+                describe,
+                ..Default::default()
+            })
+            .unwrap();
 
-        String::from_utf8_lossy(&output.stdout).to_string().trim().to_string()
+        assert_eq!(cookbook.describe("foo").unwrap(), expected);
     }
 }
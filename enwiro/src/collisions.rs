@@ -0,0 +1,178 @@
+use std::{collections::HashMap, io};
+
+use crate::config::CollisionPolicy;
+
+/// Separates a recipe name from its cookbook in names made unique with the
+/// `suffix` collision policy, for example `enwiro@github`.
+const COOKBOOK_SEPARATOR: char = '@';
+
+pub fn suffix_with_cookbook(name: &str, cookbook: &str) -> String {
+    format!("{}{}{}", name, COOKBOOK_SEPARATOR, cookbook)
+}
+
+/// Splits a name made unique with the `suffix` collision policy into the
+/// recipe name and the cookbook name.
+pub fn split_cookbook_suffix(name: &str) -> Option<(&str, &str)> {
+    name.rsplit_once(COOKBOOK_SEPARATOR)
+        .filter(|(recipe_name, cookbook)| !recipe_name.is_empty() && !cookbook.is_empty())
+}
+
+/// Finds the recipe an environment was cooked from by the given cookbook.
+/// Environments cooked under a name made unique with the `suffix` policy have
+/// the cookbook appended to the recipe name.
+pub fn strip_cookbook_suffix<'a>(name: &'a str, cookbook: &str) -> &'a str {
+    match split_cookbook_suffix(name) {
+        Some((recipe_name, suffix)) if suffix == cookbook => recipe_name,
+        _ => name,
+    }
+}
+
+/// Names listed so far, with the cookbook each of them comes from, used to
+/// decide what to do with recipes whose name is already taken.
+pub struct ClaimedNames {
+    policy: CollisionPolicy,
+
+    // None for environments not known to be cooked by any cookbook
+    names: HashMap<String, Option<String>>,
+}
+
+impl ClaimedNames {
+    pub fn new(policy: CollisionPolicy) -> Self {
+        Self {
+            policy,
+            names: HashMap::new(),
+        }
+    }
+
+    pub fn policy(&self) -> CollisionPolicy {
+        self.policy
+    }
+
+    pub fn claim_environment(&mut self, name: &str, cookbook: Option<&str>) {
+        self.names
+            .insert(name.to_string(), cookbook.map(str::to_string));
+    }
+
+    /// Returns the name a recipe should be listed as, or `None` if it should
+    /// not be listed. A recipe whose environment was already cooked from the
+    /// same cookbook is never listed, this is not a collision.
+    pub fn claim_recipe(
+        &mut self,
+        cookbook: &str,
+        recipe_name: &str,
+    ) -> Result<Option<String>, io::Error> {
+        let claimed_by = match self.names.get(recipe_name) {
+            None => {
+                self.claim_environment(recipe_name, Some(cookbook));
+                return Ok(Some(recipe_name.to_string()));
+            }
+            Some(claimed_by) if claimed_by.as_deref() == Some(cookbook) => return Ok(None),
+            Some(claimed_by) => claimed_by.as_deref().unwrap_or("an existing environment"),
+        };
+
+        tracing::warn!(
+            name = recipe_name,
+            cookbook,
+            claimed_by,
+            "Recipe name collides with another entry"
+        );
+        match self.policy {
+            CollisionPolicy::Hide => Ok(None),
+            CollisionPolicy::Suffix => {
+                let name = suffix_with_cookbook(recipe_name, cookbook);
+                match self.names.contains_key(&name) {
+                    true => Ok(None),
+                    false => {
+                        self.claim_environment(&name, Some(cookbook));
+                        Ok(Some(name))
+                    }
+                }
+            }
+            CollisionPolicy::Error => Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "Recipe \"{}\" of cookbook {} collides with {}",
+                    recipe_name, cookbook, claimed_by
+                ),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        collisions::{split_cookbook_suffix, strip_cookbook_suffix, ClaimedNames},
+        config::CollisionPolicy,
+    };
+
+    fn claim_all(policy: CollisionPolicy) -> Vec<Option<String>> {
+        let mut claimed_names = ClaimedNames::new(policy);
+        claimed_names.claim_environment("cooked", Some("git"));
+        claimed_names.claim_environment("manual", None);
+
+        [
+            ("git", "cooked"),
+            ("git", "manual"),
+            ("git", "new"),
+            ("github", "new"),
+        ]
+        .into_iter()
+        .map(|(cookbook, name)| claimed_names.claim_recipe(cookbook, name).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn test_collisions_are_hidden() {
+        assert_eq!(
+            claim_all(CollisionPolicy::Hide),
+            vec![None, None, Some("new".to_string()), None]
+        );
+    }
+
+    #[test]
+    fn test_collisions_are_suffixed() {
+        assert_eq!(
+            claim_all(CollisionPolicy::Suffix),
+            vec![
+                None,
+                Some("manual@git".to_string()),
+                Some("new".to_string()),
+                Some("new@github".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collisions_are_errors() {
+        let mut claimed_names = ClaimedNames::new(CollisionPolicy::Error);
+        claimed_names.claim_environment("cooked", Some("git"));
+
+        assert_eq!(claimed_names.claim_recipe("git", "cooked").unwrap(), None);
+        assert!(claimed_names.claim_recipe("github", "cooked").is_err());
+    }
+
+    #[rstest]
+    #[case("enwiro@github", Some(("enwiro", "github")))]
+    #[case("me@example.com@git", Some(("me@example.com", "git")))]
+    #[case("enwiro", None)]
+    #[case("enwiro@", None)]
+    fn test_split_cookbook_suffix(#[case] name: &str, #[case] expected: Option<(&str, &str)>) {
+        assert_eq!(split_cookbook_suffix(name), expected);
+    }
+
+    #[rstest]
+    #[case("enwiro@github", "github", "enwiro")]
+    #[case("enwiro@github", "git", "enwiro@github")]
+    #[case("me@example.com", "git", "me@example.com")]
+    #[case("@github", "github", "@github")]
+    fn test_strip_cookbook_suffix(
+        #[case] name: &str,
+        #[case] cookbook: &str,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(strip_cookbook_suffix(name, cookbook), expected);
+    }
+}
@@ -0,0 +1,525 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use crate::{
+    environment_config::EnvironmentConfig,
+    environments::{normalize_environment_name, Environment},
+    groups::get_group_members,
+    hooks::{HookEvent, Hooks},
+    notifier::NotificationLevel,
+    usage_stats::UsageStats,
+    CommandContext,
+};
+
+#[derive(clap::Args, Default)]
+#[command(
+    author,
+    version,
+    about = "Switch to the workspace of an environment, cooking it first if necessary"
+)]
+pub struct ActivateArgs {
+    #[arg(required_unless_present_any = ["all_pinned", "group", "stdin"])]
+    pub environment_name: Option<String>,
+
+    /// Only switch to environments that already exist, without cooking
+    #[arg(long, conflicts_with = "cook_only")]
+    pub no_cook: bool,
+
+    /// Cook the environment if necessary, without switching workspaces
+    #[arg(long)]
+    pub cook_only: bool,
+
+    /// Activate all pinned environments, the most used first
+    #[arg(long, conflicts_with_all = ["environment_name", "group", "stdin"])]
+    pub all_pinned: bool,
+
+    /// Activate the environments of a group, see `enwiro group`
+    #[arg(long, conflicts_with_all = ["environment_name", "stdin"])]
+    pub group: Option<String>,
+
+    /// Activate the environments named on standard input, one per line
+    #[arg(long, conflicts_with = "environment_name")]
+    pub stdin: bool,
+}
+
+/// Cooks the environment if necessary before switching to its workspace, so
+/// that a failed cook never leaves an empty workspace behind.
+fn activate_environment<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    environment_name: &str,
+    args: &ActivateArgs,
+) -> Result<(), io::Error> {
+    let _span = tracing::debug_span!("activate", environment_name).entered();
+    let environment = match args.no_cook {
+        true => Environment::get_one(
+            &context.config.workspaces_directory,
+            &normalize_environment_name(environment_name)?,
+        )?,
+        false => context.get_or_cook_environment(&Some(environment_name.to_string()))?,
+    };
+    if args.cook_only {
+        return Ok(());
+    }
+    let hooks = Hooks::new(&context.config)?;
+    hooks.run(HookEvent::PreActivate, &environment)?;
+    let environment_config = EnvironmentConfig::load(&environment)?;
+    let mut usage_stats = UsageStats::load(&context.config.workspaces_directory);
+    // Ranking needs every environment, which is slow with many of them
+    let rank = match context.adapter.supports_ranks() {
+        true => usage_stats
+            .rank_environments(context.get_all_environments()?.keys().map(String::as_str))
+            .iter()
+            .position(|name| *name == environment.name)
+            .map(|position| position + 1),
+        false => None,
+    };
+    tracing::debug_span!("adapter").in_scope(|| {
+        context
+            .adapter
+            .activate(&environment.name, &environment_config.outputs, rank)
+    })?;
+    hooks.run(HookEvent::PostActivate, &environment)?;
+
+    usage_stats.record_activation(&environment.name);
+    usage_stats.save(&context.config.workspaces_directory)
+}
+
+/// Activates an environment and notifies about the outcome, which is also
+/// returned as the message of the notification.
+fn activate_and_notify<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    environment_name: &str,
+    args: &ActivateArgs,
+) -> (Result<(), io::Error>, String) {
+    let result = activate_environment(context, environment_name, args);
+    let (level, message) = match &result {
+        Ok(_) if args.cook_only => (
+            NotificationLevel::Success,
+            format!("Environment {} is ready", environment_name),
+        ),
+        Ok(_) => (
+            NotificationLevel::Success,
+            format!("Activated {}", environment_name),
+        ),
+        Err(error) => (
+            NotificationLevel::Error,
+            format!("Could not activate {}: {}", environment_name, error),
+        ),
+    };
+    context.notify(level, &message);
+
+    (result, message)
+}
+
+/// Names of the environments to activate together, in the order in which
+/// they are activated.
+fn get_batch_environment_names<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: &ActivateArgs,
+) -> Result<Vec<String>, io::Error> {
+    if let Some(group) = &args.group {
+        return get_group_members(&context.config, group);
+    }
+    if args.stdin {
+        return BufReader::new(&mut context.reader)
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .map(|line| line.map(|line| line.trim().to_string()))
+            .collect();
+    }
+
+    let environments = context.get_all_environments()?;
+    let usage_stats = UsageStats::load(&context.config.workspaces_directory);
+
+    Ok(usage_stats
+        .rank_environments(environments.keys().map(String::as_str))
+        .into_iter()
+        .filter(|name| usage_stats.is_pinned(name))
+        .collect())
+}
+
+/// Activates several environments one after the other, so that the last
+/// one ends up focused, printing the outcome for each. Failing to activate
+/// one does not stop the others from being activated.
+fn activate_batch<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: &ActivateArgs,
+) -> Result<(), io::Error> {
+    let environment_names = get_batch_environment_names(context, args)?;
+    if environment_names.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "There are no environments to activate",
+        ));
+    }
+
+    let mut failures = 0;
+    for environment_name in &environment_names {
+        let (result, message) = activate_and_notify(context, environment_name, args);
+        if result.is_err() {
+            failures += 1;
+        }
+        context
+            .writer
+            .write_all(format!("{}\n", message).as_bytes())?;
+    }
+
+    match failures {
+        0 => Ok(()),
+        failures => Err(io::Error::other(format!(
+            "Could not activate {} of {} environments",
+            failures,
+            environment_names.len()
+        ))),
+    }
+}
+
+pub fn activate<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: ActivateArgs,
+) -> Result<(), io::Error> {
+    match &args.environment_name {
+        Some(environment_name) => activate_and_notify(context, environment_name, &args).0,
+        None => activate_batch(context, &args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs, io::Cursor, path::Path};
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::activate::{activate, ActivateArgs},
+        notifier::NotificationLevel,
+        test_utils::test_utilities::{
+            context_object, EnwiroAdapterMock, FakeContext, NotifierMock,
+        },
+        usage_stats::UsageStats,
+    };
+
+    #[rstest]
+    fn test_activate_existing_environment(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+
+        activate(
+            &mut context_object,
+            ActivateArgs {
+                environment_name: Some("foobar".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let usage_stats = UsageStats::load(&context_object.config.workspaces_directory);
+        assert_eq!(usage_stats.environments["foobar"].activation_count, 1);
+    }
+
+    #[rstest]
+    fn test_activate_passes_outputs_to_adapter(mut context_object: FakeContext) {
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.create_mock_environment("foobar");
+        fs::write(
+            Path::new(&context_object.config.workspaces_directory).join("foobar/.enwiro.toml"),
+            "[outputs]\nHDMI-1 = \"chat\"\n",
+        )
+        .unwrap();
+
+        activate(
+            &mut context_object,
+            ActivateArgs {
+                environment_name: Some("foobar".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            *adapter.activated_outputs.borrow(),
+            vec![BTreeMap::from([("HDMI-1".to_string(), "chat".to_string())])]
+        );
+    }
+
+    #[rstest]
+    fn test_activate_passes_frecency_rank_to_adapter(mut context_object: FakeContext) {
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.create_mock_environment("foobar");
+        context_object.create_mock_environment("baz");
+
+        for environment_name in ["foobar", "foobar", "baz", "baz"] {
+            activate(
+                &mut context_object,
+                ActivateArgs {
+                    environment_name: Some(environment_name.to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            *adapter.activated_ranks.borrow(),
+            vec![None, Some(1), None, Some(2)]
+        );
+    }
+
+    #[rstest]
+    fn test_activate_does_not_rank_for_adapters_without_ranks(mut context_object: FakeContext) {
+        let mut adapter = EnwiroAdapterMock::new("foobaz");
+        adapter.supports_ranks = false;
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.create_mock_environment("foobar");
+
+        for _ in 0..2 {
+            activate(
+                &mut context_object,
+                ActivateArgs {
+                    environment_name: Some("foobar".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        assert_eq!(*adapter.activated_ranks.borrow(), vec![None, None]);
+    }
+
+    #[rstest]
+    fn test_activate_notifies_about_the_outcome(mut context_object: FakeContext) {
+        let notifier = NotifierMock::default();
+        context_object.notifier = Box::new(notifier.clone());
+        context_object.create_mock_environment("foobar");
+
+        activate(
+            &mut context_object,
+            ActivateArgs {
+                environment_name: Some("foobar".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        context_object.config.notifications.quiet = true;
+        activate(
+            &mut context_object,
+            ActivateArgs {
+                environment_name: Some("foobar".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let _ = activate(
+            &mut context_object,
+            ActivateArgs {
+                environment_name: Some("non_existing_env".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let notifications = notifier.notifications.borrow();
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(
+            notifications[0],
+            (NotificationLevel::Success, "Activated foobar".to_string())
+        );
+        assert_eq!(notifications[1].0, NotificationLevel::Error);
+    }
+
+    #[rstest]
+    fn test_activate_shows_progress_while_cooking(mut context_object: FakeContext) {
+        let notifier = NotifierMock::default();
+        context_object.notifier = Box::new(notifier.clone());
+
+        let _ = activate(
+            &mut context_object,
+            ActivateArgs {
+                environment_name: Some("non_existing_env".to_string()),
+                ..Default::default()
+            },
+        );
+
+        let notifications = notifier.notifications.borrow();
+        assert_eq!(
+            notifications[0],
+            (
+                NotificationLevel::Success,
+                "Cooking non_existing_env…".to_string()
+            )
+        );
+        assert_eq!(notifications[1].0, NotificationLevel::Error);
+    }
+
+    #[rstest]
+    #[should_panic]
+    fn test_activate_panics_when_env_cannot_be_cooked(mut context_object: FakeContext) {
+        activate(
+            &mut context_object,
+            ActivateArgs {
+                environment_name: Some("non_existing_env".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    }
+
+    #[rstest]
+    fn test_activate_does_not_switch_workspace_when_cooking_fails(mut context_object: FakeContext) {
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.adapter = Box::new(adapter.clone());
+
+        let result = activate(
+            &mut context_object,
+            ActivateArgs {
+                environment_name: Some("non_existing_env".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert!(result.is_err());
+        assert!(adapter.activated_environments.borrow().is_empty());
+    }
+
+    #[rstest]
+    fn test_activate_without_cooking(mut context_object: FakeContext) {
+        let notifier = NotifierMock::default();
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.notifier = Box::new(notifier.clone());
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.create_mock_environment("foobar");
+
+        let args = |environment_name: &str| ActivateArgs {
+            environment_name: Some(environment_name.to_string()),
+            no_cook: true,
+            ..Default::default()
+        };
+        activate(&mut context_object, args("foobar")).unwrap();
+        assert!(activate(&mut context_object, args("non_existing_env")).is_err());
+
+        assert_eq!(*adapter.activated_environments.borrow(), vec!["foobar"]);
+        let notifications = notifier.notifications.borrow();
+        assert_eq!(
+            notifications[0],
+            (NotificationLevel::Success, "Activated foobar".to_string())
+        );
+        // Nothing is cooked, so there is no progress notification
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(notifications[1].0, NotificationLevel::Error);
+    }
+
+    #[rstest]
+    fn test_activate_names_from_stdin(mut context_object: FakeContext) {
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.create_mock_environment("foobar");
+        context_object.create_mock_environment("baz");
+        context_object.reader = Cursor::new(b"foobar\n\nnon_existing_env\nbaz\n".to_vec());
+
+        let result = activate(
+            &mut context_object,
+            ActivateArgs {
+                stdin: true,
+                no_cook: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Could not activate 1 of 3 environments"
+        );
+        assert_eq!(
+            *adapter.activated_environments.borrow(),
+            vec!["foobar", "baz"]
+        );
+        let output = context_object.get_output();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "Activated foobar");
+        assert!(lines[1].starts_with("Could not activate non_existing_env: "));
+        assert_eq!(lines[2], "Activated baz");
+    }
+
+    #[rstest]
+    fn test_activate_group(mut context_object: FakeContext) {
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.create_mock_environment("foobar");
+        context_object.create_mock_environment("baz");
+        context_object.config.groups.insert(
+            "morning".to_string(),
+            vec!["baz".to_string(), "foobar".to_string()],
+        );
+        let args = |group: &str| ActivateArgs {
+            group: Some(group.to_string()),
+            ..Default::default()
+        };
+
+        activate(&mut context_object, args("morning")).unwrap();
+        assert!(activate(&mut context_object, args("evening")).is_err());
+
+        assert_eq!(
+            *adapter.activated_environments.borrow(),
+            vec!["baz", "foobar"]
+        );
+    }
+
+    #[rstest]
+    fn test_activate_all_pinned(mut context_object: FakeContext) {
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.adapter = Box::new(adapter.clone());
+        for name in ["foobar", "baz", "unpinned"] {
+            context_object.create_mock_environment(name);
+        }
+        let mut usage_stats = UsageStats::default();
+        usage_stats.toggle_pin("foobar");
+        usage_stats.toggle_pin("baz");
+        usage_stats.record_activation("foobar");
+        usage_stats
+            .save(&context_object.config.workspaces_directory)
+            .unwrap();
+
+        activate(
+            &mut context_object,
+            ActivateArgs {
+                all_pinned: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            *adapter.activated_environments.borrow(),
+            vec!["foobar", "baz"]
+        );
+    }
+
+    #[rstest]
+    fn test_activate_cook_only(mut context_object: FakeContext) {
+        let notifier = NotifierMock::default();
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.notifier = Box::new(notifier.clone());
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.create_mock_environment("foobar");
+
+        activate(
+            &mut context_object,
+            ActivateArgs {
+                environment_name: Some("foobar".to_string()),
+                cook_only: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert!(adapter.activated_environments.borrow().is_empty());
+        assert_eq!(
+            *notifier.notifications.borrow(),
+            vec![(
+                NotificationLevel::Success,
+                "Environment foobar is ready".to_string()
+            )]
+        );
+        let usage_stats = UsageStats::load(&context_object.config.workspaces_directory);
+        assert!(!usage_stats.environments.contains_key("foobar"));
+    }
+}
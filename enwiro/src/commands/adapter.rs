@@ -1,53 +1,443 @@
-use std::process::Command;
+use std::{
+    cell::OnceCell,
+    collections::BTreeMap,
+    io::{BufRead, BufReader, Read},
+    process::{Command, Output, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde_derive::Deserialize;
+
+use crate::{
+    config::AdapterCallsConfiguration,
+    environments::{decode_environment_name, encode_environment_name},
+    error_codes::ErrorCode,
+    plugin::{get_plugins, Plugin, PluginKind, PluginMetadata},
+};
+
+/// How often a running adapter is checked for having finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What an adapter knows about the focused window, printed as JSON by
+/// `get-focused-window`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct FocusedWindow {
+    pub title: Option<String>,
+
+    // Working directory of the program shown in the window, such as the
+    // shell running in a terminal
+    pub cwd: Option<String>,
+}
 
 pub trait EnwiroAdapterTrait {
     fn get_active_environment_name(&self) -> Result<String, std::io::Error>;
+    #[allow(dead_code)]
     fn get_active_lens_name(&self) -> Result<String, std::io::Error>;
+    /// Switches to the workspace of an environment, and to the workspaces
+    /// `outputs` assigns to each output, if any. `rank` is the position of
+    /// the environment among the most used ones, starting at 1, so that
+    /// adapters can give them their own slots.
+    fn activate(
+        &self,
+        environment_name: &str,
+        outputs: &BTreeMap<String, String>,
+        rank: Option<usize>,
+    ) -> Result<(), std::io::Error>;
+
+    /// Names of all workspaces, including workspaces not created by enwiro,
+    /// without the text the adapter adds around environment names. They are
+    /// not decoded: workspaces of environments carry encoded names, but
+    /// workspaces created by hand may contain `%` as it is.
+    fn list_workspaces(&self) -> Result<Vec<String>, std::io::Error>;
+
+    /// Renames a workspace, named as `list_workspaces` lists it, to the name
+    /// the adapter would give to the workspace of an environment.
+    fn rename_workspace(
+        &self,
+        workspace_name: &str,
+        new_environment_name: &str,
+    ) -> Result<(), std::io::Error>;
+
+    fn get_focused_window(&self) -> Result<FocusedWindow, std::io::Error>;
+
+    /// Whether the adapter uses the rank passed to `activate`, which is only
+    /// worth computing if it does.
+    fn supports_ranks(&self) -> bool;
+
+    /// Moves the workspaces of the given environments, the most used first,
+    /// to the slots the adapter gives to environments of their rank.
+    fn renumber_workspaces(
+        &self,
+        ranked_environment_names: &[String],
+    ) -> Result<(), std::io::Error>;
+
+    /// Whether the adapter can hide workspaces and show them again.
+    fn supports_hiding_workspaces(&self) -> bool;
+
+    /// Hides the workspaces of the given environments, or shows them again,
+    /// without closing anything on them.
+    fn set_workspaces_hidden(
+        &self,
+        environment_names: &[String],
+        hidden: bool,
+    ) -> Result<(), std::io::Error>;
+
+    /// Keeps running, calling `on_focus` with the environment of every
+    /// workspace that gains focus, or an empty name for workspaces without
+    /// one, until the adapter stops or `on_focus` fails.
+    fn watch_focus(
+        &self,
+        on_focus: &mut dyn FnMut(&str) -> Result<(), std::io::Error>,
+    ) -> Result<(), std::io::Error>;
 }
 
 pub struct EnwiroAdapterExternal {
-    adapter_command: String,
+    plugin: Plugin,
+    calls: AdapterCallsConfiguration,
+
+    // Metadata of the adapter, asked for the first time it is needed
+    metadata: OnceCell<PluginMetadata>,
+}
+
+/// Reads a pipe of a child process to the end without blocking the caller,
+/// so that the child never waits for room in the pipe.
+fn read_in_background(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut content = vec![];
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut content);
+        }
+
+        content
+    })
+}
+
+/// Runs a command like `Command::output`, but kills it if it does not finish
+/// in time.
+fn output_with_timeout(command: &mut Command, timeout: Duration) -> Result<Output, std::io::Error> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("Adapter did not finish within {:?}", timeout),
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
 }
 
 impl EnwiroAdapterTrait for EnwiroAdapterExternal {
     fn get_active_environment_name(&self) -> Result<String, std::io::Error> {
-        let output = Command::new(&self.adapter_command)
-            .arg("get-active-workspace-id")
-            .output()
-            .expect("Adapter failed to determine active environment name");
+        let workspace_id = self.query(&["get-active-workspace-id"])?;
 
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            return Ok(stdout.to_string().split(':').nth(0).unwrap().to_string());
-        } else {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            panic!("Error: {}", stderr);
-        }
+        Ok(decode_environment_name(
+            workspace_id.split(':').next().unwrap_or_default(),
+        ))
     }
 
     fn get_active_lens_name(&self) -> Result<String, std::io::Error> {
-        let output = Command::new(&self.adapter_command)
-            .arg("get-active-workspace-id")
-            .output()
-            .expect("Adapter failed to determine active lens name");
+        let workspace_id = self.query(&["get-active-workspace-id"])?;
 
-        if output.status.success() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            match stdout.to_string().split(':').nth(1) {
-                Some(value) => Ok(value.to_string()),
-                None => Ok("".to_string()),
+        Ok(workspace_id
+            .split(':')
+            .nth(1)
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    fn activate(
+        &self,
+        environment_name: &str,
+        outputs: &BTreeMap<String, String>,
+        rank: Option<usize>,
+    ) -> Result<(), std::io::Error> {
+        let metadata = self.metadata();
+        // Adapters predating protocol versioning do not know how to activate
+        if metadata.protocol_version < 1 {
+            return Err(ErrorCode::AdapterUnavailable.error(
+                std::io::ErrorKind::Unsupported,
+                format!(
+                    "{} does not support activating environments. Please upgrade it.",
+                    self.plugin.executable
+                ),
+            ));
+        }
+
+        tracing::debug!(adapter = %self.plugin.name, environment_name, "Activating environment");
+        let mut args = vec![
+            "activate".to_string(),
+            encode_environment_name(environment_name),
+        ];
+        if metadata.outputs {
+            for (output, workspace) in outputs {
+                args.push("--output".to_string());
+                args.push(format!("{}={}", output, workspace));
             }
+        } else if !outputs.is_empty() {
+            tracing::warn!(
+                adapter = %self.plugin.name,
+                "Adapter does not support outputs, activating the environment only"
+            );
+        }
+        if let Some(rank) = rank.filter(|_| metadata.ranks) {
+            args.push("--rank".to_string());
+            args.push(rank.to_string());
+        }
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let retries = match self.calls.retry_activation {
+            true => self.calls.retries,
+            false => 0,
+        };
+        let output = self.output(&args, retries)?;
+
+        if output.status.success() {
+            Ok(())
         } else {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            panic!("Error: {}", stderr);
+            Err(ErrorCode::AdapterFailed.error(
+                std::io::ErrorKind::Other,
+                format!("Adapter failed to activate environment: {}", stderr.trim()),
+            ))
+        }
+    }
+
+    fn list_workspaces(&self) -> Result<Vec<String>, std::io::Error> {
+        self.require_capability(|metadata| metadata.workspaces, "listing workspaces")?;
+        let output = self.query(&["list-workspaces"])?;
+
+        Ok(output
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn rename_workspace(
+        &self,
+        workspace_name: &str,
+        new_environment_name: &str,
+    ) -> Result<(), std::io::Error> {
+        self.require_capability(|metadata| metadata.workspaces, "renaming workspaces")?;
+        self.run(&[
+            "rename-workspace",
+            workspace_name,
+            &encode_environment_name(new_environment_name),
+        ])?;
+
+        Ok(())
+    }
+
+    fn get_focused_window(&self) -> Result<FocusedWindow, std::io::Error> {
+        self.require_capability(
+            |metadata| metadata.focused_window,
+            "describing the focused window",
+        )?;
+        let output = self.query(&["get-focused-window"])?;
+
+        serde_json::from_str(&output).map_err(|error| {
+            ErrorCode::AdapterFailed.error(std::io::ErrorKind::InvalidData, error.to_string())
+        })
+    }
+
+    fn supports_ranks(&self) -> bool {
+        self.metadata().ranks
+    }
+
+    fn supports_hiding_workspaces(&self) -> bool {
+        self.metadata().hide_workspaces
+    }
+
+    fn renumber_workspaces(
+        &self,
+        ranked_environment_names: &[String],
+    ) -> Result<(), std::io::Error> {
+        self.require_capability(|metadata| metadata.ranks, "renumbering workspaces")?;
+        let encoded_names: Vec<String> = ranked_environment_names
+            .iter()
+            .map(|name| encode_environment_name(name))
+            .collect();
+        let mut args = vec!["renumber-workspaces"];
+        args.extend(encoded_names.iter().map(String::as_str));
+        self.run(&args)?;
+
+        Ok(())
+    }
+
+    fn set_workspaces_hidden(
+        &self,
+        environment_names: &[String],
+        hidden: bool,
+    ) -> Result<(), std::io::Error> {
+        self.require_capability(|metadata| metadata.hide_workspaces, "hiding workspaces")?;
+        let encoded_names: Vec<String> = environment_names
+            .iter()
+            .map(|name| encode_environment_name(name))
+            .collect();
+        let mut args = vec![match hidden {
+            true => "hide-workspaces",
+            false => "show-workspaces",
+        }];
+        args.extend(encoded_names.iter().map(String::as_str));
+        self.run(&args)?;
+
+        Ok(())
+    }
+
+    fn watch_focus(
+        &self,
+        on_focus: &mut dyn FnMut(&str) -> Result<(), std::io::Error>,
+    ) -> Result<(), std::io::Error> {
+        self.require_capability(|metadata| metadata.watch_focus, "watching focus")?;
+        let mut child = Command::new(&self.plugin.executable)
+            .arg("watch-focus")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|error| ErrorCode::AdapterFailed.tag(error))?;
+
+        // Workspaces are named like `get-active-workspace-id` prints them
+        let result = match child.stdout.take() {
+            Some(stdout) => BufReader::new(stdout).lines().try_for_each(|line| {
+                let line = line?;
+                on_focus(&decode_environment_name(
+                    line.split(':').next().unwrap_or_default(),
+                ))
+            }),
+            None => Ok(()),
+        };
+        if result.is_err() {
+            let _ = child.kill();
+        }
+        let status = child.wait()?;
+        result?;
+
+        match status.success() {
+            true => Ok(()),
+            false => Err(ErrorCode::AdapterFailed.error(
+                std::io::ErrorKind::Other,
+                format!("Adapter stopped watching focus: {}", status),
+            )),
         }
     }
 }
 impl EnwiroAdapterExternal {
-    pub fn new(adapter_name: &str) -> Self {
+    pub fn new(
+        adapter_name: &str,
+        allowed_plugins: Option<&[String]>,
+        calls: AdapterCallsConfiguration,
+    ) -> Self {
+        let plugin = get_plugins(PluginKind::Adapter, allowed_plugins)
+            .into_iter()
+            .find(|plugin| plugin.name == adapter_name)
+            .unwrap_or_else(|| Plugin {
+                name: adapter_name.to_string(),
+                kind: PluginKind::Adapter,
+                executable: format!("enwiro-adapter-{}", adapter_name),
+            });
+
         Self {
-            adapter_command: format!("enwiro-adapter-{}", adapter_name),
+            plugin,
+            calls,
+            metadata: OnceCell::new(),
+        }
+    }
+
+    fn metadata(&self) -> &PluginMetadata {
+        self.metadata.get_or_init(|| self.plugin.get_metadata())
+    }
+
+    /// Fails unless the metadata of the adapter announces the capability
+    /// needed for `feature`.
+    fn require_capability(
+        &self,
+        is_supported: impl Fn(&PluginMetadata) -> bool,
+        feature: &str,
+    ) -> Result<(), std::io::Error> {
+        if !is_supported(self.metadata()) {
+            return Err(ErrorCode::AdapterUnavailable.error(
+                std::io::ErrorKind::Unsupported,
+                format!(
+                    "{} does not support {}. Please upgrade it.",
+                    self.plugin.executable, feature
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Runs the adapter, retrying up to `retries` times if it fails or times
+    /// out, since window managers such as i3 occasionally drop connections
+    /// while busy. Only the last attempt is reported.
+    fn output(&self, args: &[&str], retries: u32) -> Result<Output, std::io::Error> {
+        let timeout = Duration::from_secs(self.calls.timeout_seconds);
+        let mut delay = Duration::from_millis(self.calls.retry_delay_ms);
+        let mut attempt = 0;
+        loop {
+            let result =
+                output_with_timeout(Command::new(&self.plugin.executable).args(args), timeout);
+            let failed = match &result {
+                Ok(output) => !output.status.success(),
+                Err(error) => error.kind() == std::io::ErrorKind::TimedOut,
+            };
+            if !failed || attempt >= retries {
+                return result.map_err(|error| ErrorCode::AdapterFailed.tag(error));
+            }
+            attempt += 1;
+            tracing::warn!(adapter = %self.plugin.name, command = args[0], attempt, "Adapter call failed, retrying");
+            thread::sleep(delay);
+            delay *= 2;
+        }
+    }
+
+    /// Asks the adapter something without changing anything, so that failed
+    /// calls can safely be retried. Returns what it printed.
+    fn query(&self, args: &[&str]) -> Result<String, std::io::Error> {
+        self.run_with_retries(args, self.calls.retries)
+    }
+
+    /// Runs the adapter once, returning what it printed. Used for calls that
+    /// change workspaces, which might do their work twice if retried after
+    /// failing halfway.
+    fn run(&self, args: &[&str]) -> Result<String, std::io::Error> {
+        self.run_with_retries(args, 0)
+    }
+
+    fn run_with_retries(&self, args: &[&str], retries: u32) -> Result<String, std::io::Error> {
+        let output = self.output(args, retries)?;
+        if !output.status.success() {
+            return Err(ErrorCode::AdapterFailed.error(
+                std::io::ErrorKind::Other,
+                format!(
+                    "Adapter failed to run {}: {}",
+                    args[0],
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
         }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
     }
 }
 
@@ -55,16 +445,238 @@ pub struct EnwiroAdapterNone {}
 
 impl EnwiroAdapterTrait for EnwiroAdapterNone {
     fn get_active_environment_name(&self) -> Result<String, std::io::Error> {
-        Err(std::io::Error::new(
+        Err(ErrorCode::AdapterUnavailable.error(
             std::io::ErrorKind::NotFound,
             "Could not determine active environment because no adapter is configured.",
         ))
     }
 
     fn get_active_lens_name(&self) -> Result<String, std::io::Error> {
-        Err(std::io::Error::new(
+        Err(ErrorCode::AdapterUnavailable.error(
             std::io::ErrorKind::NotFound,
             "Could not determine active lens because no adapter is configured.",
         ))
     }
+
+    fn activate(
+        &self,
+        _environment_name: &str,
+        _outputs: &BTreeMap<String, String>,
+        _rank: Option<usize>,
+    ) -> Result<(), std::io::Error> {
+        Err(ErrorCode::AdapterUnavailable.error(
+            std::io::ErrorKind::NotFound,
+            "Could not activate environment because no adapter is configured.",
+        ))
+    }
+
+    fn list_workspaces(&self) -> Result<Vec<String>, std::io::Error> {
+        Err(ErrorCode::AdapterUnavailable.error(
+            std::io::ErrorKind::NotFound,
+            "Could not list workspaces because no adapter is configured.",
+        ))
+    }
+
+    fn rename_workspace(
+        &self,
+        _workspace_name: &str,
+        _new_environment_name: &str,
+    ) -> Result<(), std::io::Error> {
+        Err(ErrorCode::AdapterUnavailable.error(
+            std::io::ErrorKind::NotFound,
+            "Could not rename workspace because no adapter is configured.",
+        ))
+    }
+
+    fn get_focused_window(&self) -> Result<FocusedWindow, std::io::Error> {
+        Err(ErrorCode::AdapterUnavailable.error(
+            std::io::ErrorKind::NotFound,
+            "Could not describe the focused window because no adapter is configured.",
+        ))
+    }
+
+    fn supports_ranks(&self) -> bool {
+        false
+    }
+
+    fn supports_hiding_workspaces(&self) -> bool {
+        false
+    }
+
+    fn renumber_workspaces(
+        &self,
+        _ranked_environment_names: &[String],
+    ) -> Result<(), std::io::Error> {
+        Err(ErrorCode::AdapterUnavailable.error(
+            std::io::ErrorKind::NotFound,
+            "Could not renumber workspaces because no adapter is configured.",
+        ))
+    }
+
+    fn set_workspaces_hidden(
+        &self,
+        _environment_names: &[String],
+        _hidden: bool,
+    ) -> Result<(), std::io::Error> {
+        Err(ErrorCode::AdapterUnavailable.error(
+            std::io::ErrorKind::NotFound,
+            "Could not hide workspaces because no adapter is configured.",
+        ))
+    }
+
+    fn watch_focus(
+        &self,
+        _on_focus: &mut dyn FnMut(&str) -> Result<(), std::io::Error>,
+    ) -> Result<(), std::io::Error> {
+        Err(ErrorCode::AdapterUnavailable.error(
+            std::io::ErrorKind::NotFound,
+            "Could not watch focus because no adapter is configured.",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::OnceCell, env::temp_dir, fs, path::PathBuf};
+
+    use rand::Rng;
+
+    use crate::{
+        commands::adapter::{EnwiroAdapterExternal, EnwiroAdapterTrait},
+        config::AdapterCallsConfiguration,
+        error_codes::ErrorCode,
+        platform::make_executable,
+        plugin::{Plugin, PluginKind, PluginMetadata},
+    };
+
+    /// An adapter running the given shell script, retrying quickly.
+    fn get_adapter(script: &str, timeout_seconds: u64) -> (EnwiroAdapterExternal, PathBuf) {
+        let directory = temp_dir().join(format!(
+            "enwiro-adapter-{}",
+            rand::thread_rng().gen_range(100000000..999999999)
+        ));
+        fs::create_dir_all(&directory).unwrap();
+        let executable = directory.join("enwiro-adapter-mock");
+        fs::write(&executable, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        make_executable(&executable).unwrap();
+        let adapter = EnwiroAdapterExternal {
+            plugin: Plugin {
+                name: "mock".to_string(),
+                kind: PluginKind::Adapter,
+                executable: executable.to_string_lossy().to_string(),
+            },
+            calls: AdapterCallsConfiguration {
+                retries: 2,
+                retry_delay_ms: 1,
+                timeout_seconds,
+                retry_activation: false,
+            },
+            metadata: OnceCell::new(),
+        };
+
+        (adapter, directory)
+    }
+
+    #[test]
+    fn test_failing_adapter_returns_errors() {
+        let (adapter, directory) = get_adapter("echo 'no IPC connection' >&2; exit 1", 10);
+
+        let error = adapter.get_active_environment_name().unwrap_err();
+
+        assert_eq!(ErrorCode::of(&error), ErrorCode::AdapterFailed);
+        assert!(error.to_string().contains("no IPC connection"));
+        assert!(adapter.get_active_lens_name().is_err());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_failed_calls_are_retried() {
+        let (adapter, directory) = get_adapter(
+            "echo x >> \"$0.calls\"; [ $(wc -l < \"$0.calls\") -ge 2 ] && echo done",
+            10,
+        );
+
+        let output = adapter.query(&["list-workspaces"]).unwrap();
+
+        assert_eq!(output, "done\n");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_activation_is_retried_by_default() {
+        let (mut adapter, directory) = get_adapter(
+            "echo x >> \"$0.calls\"; [ $(wc -l < \"$0.calls\") -ge 2 ]",
+            10,
+        );
+        adapter.calls = AdapterCallsConfiguration::default();
+        adapter
+            .metadata
+            .set(PluginMetadata {
+                protocol_version: 1,
+                ..Default::default()
+            })
+            .unwrap();
+
+        adapter.activate("foo", &Default::default(), None).unwrap();
+
+        let calls = fs::read_to_string(directory.join("enwiro-adapter-mock.calls")).unwrap();
+        assert_eq!(calls.lines().count(), 2);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_watch_focus_reads_names_until_adapter_stops() {
+        let (adapter, directory) = get_adapter("printf 'foo\\n\\nbar:notes\\n'", 10);
+        adapter
+            .metadata
+            .set(PluginMetadata {
+                protocol_version: 2,
+                watch_focus: true,
+                ..Default::default()
+            })
+            .unwrap();
+        let mut focused_names = vec![];
+
+        adapter
+            .watch_focus(&mut |name| {
+                focused_names.push(name.to_string());
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(focused_names, vec!["foo", "", "bar"]);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_calls_changing_workspaces_are_not_retried() {
+        let (adapter, directory) = get_adapter("echo x >> \"$0.calls\"; exit 1", 10);
+
+        assert!(adapter.run(&["renumber-workspaces", "foo"]).is_err());
+
+        let calls = fs::read_to_string(directory.join("enwiro-adapter-mock.calls")).unwrap();
+        assert_eq!(calls.lines().count(), 1);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_calls_that_keep_failing_or_hang_fail() {
+        let (failing_adapter, failing_directory) = get_adapter("echo broken >&2; exit 1", 10);
+        let (hanging_adapter, hanging_directory) = get_adapter("sleep 5", 0);
+
+        assert!(failing_adapter
+            .query(&["list-workspaces"])
+            .unwrap_err()
+            .to_string()
+            .contains("broken"));
+        assert_eq!(
+            hanging_adapter
+                .query(&["list-workspaces"])
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::TimedOut
+        );
+        fs::remove_dir_all(failing_directory).unwrap();
+        fs::remove_dir_all(hanging_directory).unwrap();
+    }
 }
@@ -0,0 +1,196 @@
+use std::{
+    collections::HashSet,
+    io::{self, BufReader, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    commands::init::prompt,
+    config::expand_path,
+    cookbook::list_recipes_or_skip,
+    environments::{
+        decode_environment_name, encode_environment_name, normalize_environment_name, Environment,
+    },
+    platform::link_directory,
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Turn existing workspaces into environments and give them canonical names"
+)]
+pub struct AdoptArgs {
+    /// Only adopt workspaces named after an environment or recipe, without
+    /// asking about the others
+    #[arg(long)]
+    pub no_prompt: bool,
+}
+
+/// What a workspace becomes when it is adopted.
+enum Adoption {
+    /// An existing environment, or one to cook from a recipe
+    Environment(String),
+
+    /// A new environment named after the workspace, linking to a directory
+    Directory(String),
+}
+
+/// Reads an answer to the question about a workspace. Answers starting with
+/// `/` or `~` are directories, other ones environments or recipes.
+fn parse_answer(answer: &str) -> Option<Adoption> {
+    match answer {
+        "" => None,
+        answer if answer.starts_with('/') || answer.starts_with('~') => {
+            Some(Adoption::Directory(answer.to_string()))
+        }
+        answer => Some(Adoption::Environment(answer.to_string())),
+    }
+}
+
+/// The environment or recipe a workspace is named after, if any. Workspaces
+/// of environments carry encoded names, unlike workspaces created by hand, so
+/// both the name as it is and decoded are tried.
+fn find_known_name(
+    workspace_name: &str,
+    workspaces_directory: &str,
+    recipe_names: &HashSet<String>,
+) -> Option<String> {
+    [
+        workspace_name.to_string(),
+        decode_environment_name(workspace_name),
+    ]
+    .into_iter()
+    .find(|name| {
+        Environment::get_one(workspaces_directory, name).is_ok() || recipe_names.contains(name)
+    })
+}
+
+pub fn adopt<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: AdoptArgs,
+) -> Result<(), io::Error> {
+    let workspaces_directory = context.config.workspaces_directory.clone();
+    let recipe_names: HashSet<String> = context
+        .get_cookbooks()
+        .iter()
+        .flat_map(|cookbook| list_recipes_or_skip(cookbook.as_ref()))
+        .map(|recipe| recipe.name)
+        .collect();
+
+    // Everything is asked first, so that answers can be read in one go
+    let mut adoptions = vec![];
+    let mut reader = BufReader::new(&mut context.reader);
+    for workspace_name in context.adapter.list_workspaces()? {
+        // Window managers name their default workspaces after their number
+        if workspace_name
+            .chars()
+            .all(|character| character.is_ascii_digit())
+        {
+            continue;
+        }
+        let known_name = find_known_name(&workspace_name, &workspaces_directory, &recipe_names);
+        let adoption = match known_name {
+            Some(name) => Some(Adoption::Environment(name)),
+            None if args.no_prompt => None,
+            None => parse_answer(&prompt(
+                &mut reader,
+                &mut context.writer,
+                &format!(
+                    "Environment, recipe or directory for workspace \"{}\" (empty to skip)",
+                    workspace_name
+                ),
+                "",
+            )?),
+        };
+        if let Some(adoption) = adoption {
+            adoptions.push((workspace_name, adoption));
+        }
+    }
+
+    for (workspace_name, adoption) in adoptions {
+        let environment = match adoption {
+            Adoption::Environment(name) => context.get_or_cook_environment(&Some(name))?,
+            Adoption::Directory(directory) => {
+                let name = normalize_environment_name(&workspace_name)?;
+                link_directory(
+                    Path::new(&expand_path(&directory)?),
+                    &Path::new(&workspaces_directory).join(encode_environment_name(&name)),
+                )?;
+                Environment::get_one(&workspaces_directory, &name)?
+            }
+        };
+        context
+            .adapter
+            .rename_workspace(&workspace_name, &environment.name)?;
+        context.writer.write_all(
+            format!("Adopted {} as {}\n", workspace_name, environment.name).as_bytes(),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, io::Cursor};
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::adopt::{adopt, AdoptArgs},
+        test_utils::test_utilities::{context_object, EnwiroAdapterMock, FakeContext},
+    };
+
+    #[rstest]
+    fn test_adopt_existing_and_new_workspaces(mut context_object: FakeContext) {
+        context_object.create_mock_environment("known");
+        let mut adapter = EnwiroAdapterMock::new("known");
+        adapter.workspaces = vec![
+            "known".to_string(),
+            "notes".to_string(),
+            "scratch".to_string(),
+        ];
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.reader = Cursor::new(
+            format!("{}\n\n", temp_dir().to_str().unwrap())
+                .as_bytes()
+                .to_vec(),
+        );
+
+        adopt(&mut context_object, AdoptArgs { no_prompt: false }).unwrap();
+
+        assert_eq!(
+            *adapter.renamed_workspaces.borrow(),
+            vec![
+                ("known".to_string(), "known".to_string()),
+                ("notes".to_string(), "notes".to_string()),
+            ]
+        );
+        assert!(context_object
+            .get_environment(&Some("notes".to_string()))
+            .is_ok());
+        assert!(context_object
+            .get_output()
+            .ends_with("Adopted notes as notes\n"));
+    }
+
+    #[rstest]
+    fn test_adopt_skips_numbered_workspaces_and_knows_encoded_names(
+        mut context_object: FakeContext,
+    ) {
+        context_object.create_mock_environment("a%3Ab");
+        let mut adapter = EnwiroAdapterMock::new("a:b");
+        adapter.workspaces = vec!["1".to_string(), "a%3Ab".to_string(), "100%AB".to_string()];
+        context_object.adapter = Box::new(adapter.clone());
+
+        adopt(&mut context_object, AdoptArgs { no_prompt: false }).unwrap();
+
+        assert_eq!(
+            *adapter.renamed_workspaces.borrow(),
+            vec![("a%3Ab".to_string(), "a:b".to_string())]
+        );
+        assert!(!context_object.get_output().contains("\"1\""));
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use enwiro_logging::SpanTiming;
+
+use crate::{
+    plugin::{get_plugins, get_plugins_uncached, PluginKind},
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Measure how long listing and activating environments takes"
+)]
+pub struct BenchArgs {
+    /// How many times each step is measured
+    #[arg(long, default_value_t = 3)]
+    pub runs: usize,
+
+    /// Also measure switching to this environment's workspace and back
+    #[arg(long)]
+    pub activate: Option<String>,
+}
+
+/// Timings of a step, the first run separately because nothing is cached yet.
+struct Measurement {
+    label: String,
+    first: Duration,
+    rest: Vec<Duration>,
+    details: Option<String>,
+}
+
+impl Measurement {
+    fn median(&self) -> Option<Duration> {
+        let mut rest = self.rest.clone();
+        rest.sort();
+
+        rest.get(rest.len() / 2).copied()
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let milliseconds = duration.as_secs_f64() * 1000.0;
+    if milliseconds >= 1000.0 {
+        return format!("{:.2} s", milliseconds / 1000.0);
+    }
+
+    format!("{:.1} ms", milliseconds)
+}
+
+/// Summarizes the spans recorded for `--timings`, indenting nested steps.
+pub fn format_timings(spans: &[SpanTiming]) -> String {
+    spans
+        .iter()
+        .map(|span| {
+            let label = format!("{}{}", "  ".repeat(span.depth), span.name);
+            let duration = match span.duration {
+                Some(duration) => format_duration(duration),
+                None => "unfinished".to_string(),
+            };
+
+            format!("{:<28} {:>10}\n", label, duration)
+        })
+        .collect()
+}
+
+fn format_measurement(measurement: &Measurement) -> String {
+    let mut line = format!(
+        "{:<28} {:>10} first",
+        measurement.label,
+        format_duration(measurement.first)
+    );
+    if let Some(median) = measurement.median() {
+        line.push_str(&format!(", {:>10} after", format_duration(median)));
+    }
+    if let Some(details) = &measurement.details {
+        line.push_str(&format!("  ({})", details));
+    }
+
+    line
+}
+
+/// Runs a step `runs` times, or at least once, returning its timings and the
+/// result of its last run.
+fn measure<T>(
+    label: &str,
+    runs: usize,
+    mut step: impl FnMut() -> Result<T, io::Error>,
+) -> Result<(Measurement, T), io::Error> {
+    let mut durations = vec![];
+    let mut result = None;
+    for _ in 0..runs.max(1) {
+        let start = Instant::now();
+        result = Some(step()?);
+        durations.push(start.elapsed());
+    }
+
+    let measurement = Measurement {
+        label: label.to_string(),
+        first: durations.remove(0),
+        rest: durations,
+        details: None,
+    };
+    Ok((measurement, result.expect("Step ran at least once")))
+}
+
+pub fn bench<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: BenchArgs,
+) -> Result<(), io::Error> {
+    let allowed_plugins = context.config.allowed_plugins.clone();
+    let mut measurements = vec![];
+
+    let (measurement, _) = measure("plugin discovery (uncached)", args.runs, || {
+        Ok(get_plugins_uncached(
+            PluginKind::Cookbook,
+            allowed_plugins.as_deref(),
+        ))
+    })?;
+    measurements.push(measurement);
+    let (measurement, _) = measure("plugin discovery", args.runs, || {
+        Ok(get_plugins(
+            PluginKind::Cookbook,
+            allowed_plugins.as_deref(),
+        ))
+    })?;
+    measurements.push(measurement);
+
+    for cookbook in context.get_cookbooks() {
+        let label = format!("cookbook {}", cookbook.name());
+        let (mut measurement, recipe_count) = measure(&label, args.runs, || {
+            let mut recipe_count = 0;
+            cookbook.stream_recipes(&mut |_| {
+                recipe_count += 1;
+                Ok(())
+            })?;
+            Ok(recipe_count)
+        })?;
+        measurement.details = Some(format!("{} recipes", recipe_count));
+        measurements.push(measurement);
+    }
+
+    // The whole command, as run by bridges, including starting enwiro itself
+    let executable = env::current_exe()?;
+    let (measurement, _) = measure("enwiro list-all", args.runs, || {
+        let status = Command::new(&executable)
+            .arg("list-all")
+            .stdout(Stdio::null())
+            .status()?;
+        match status.success() {
+            true => Ok(()),
+            false => Err(io::Error::other(format!("list-all failed with {}", status))),
+        }
+    })?;
+    measurements.push(measurement);
+
+    let (mut measurement, active_environment_name) = measure("adapter query", args.runs, || {
+        context.adapter.get_active_environment_name()
+    })?;
+    measurement.details = Some(format!("active: {}", active_environment_name));
+    measurements.push(measurement);
+
+    if let Some(environment_name) = &args.activate {
+        let (measurement, _) = measure("activation round trip", args.runs, || {
+            context
+                .adapter
+                .activate(environment_name, &BTreeMap::new(), None)?;
+            context
+                .adapter
+                .activate(&active_environment_name, &BTreeMap::new(), None)
+        })?;
+        measurements.push(measurement);
+    }
+
+    for measurement in &measurements {
+        context
+            .writer
+            .write_all(format!("{}\n", format_measurement(measurement)).as_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use enwiro_logging::SpanTiming;
+    use rstest::rstest;
+
+    use crate::{
+        commands::bench::{
+            format_duration, format_measurement, format_timings, measure, Measurement,
+        },
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    #[case(Duration::from_micros(1500), "1.5 ms")]
+    #[case(Duration::from_millis(2346), "2.35 s")]
+    fn test_format_duration(#[case] duration: Duration, #[case] expected: &str) {
+        assert_eq!(format_duration(duration), expected);
+    }
+
+    #[test]
+    fn test_format_timings() {
+        let spans = [
+            SpanTiming {
+                name: "activate".to_string(),
+                depth: 0,
+                duration: Some(Duration::from_millis(412)),
+            },
+            SpanTiming {
+                name: "cook".to_string(),
+                depth: 1,
+                duration: Some(Duration::from_millis(380)),
+            },
+        ];
+
+        assert_eq!(
+            format_timings(&spans),
+            concat!(
+                "activate                       412.0 ms\n",
+                "  cook                         380.0 ms\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_format_measurement() {
+        let measurement = Measurement {
+            label: "cookbook git".to_string(),
+            first: Duration::from_millis(300),
+            rest: vec![
+                Duration::from_millis(30),
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+            ],
+            details: Some("42 recipes".to_string()),
+        };
+
+        assert_eq!(
+            format_measurement(&measurement),
+            "cookbook git                   300.0 ms first,    20.0 ms after  (42 recipes)"
+        );
+    }
+
+    #[rstest]
+    fn test_measure_runs_step_repeatedly(context_object: FakeContext) {
+        let (measurement, active_environment_name) = measure("adapter query", 3, || {
+            context_object.adapter.get_active_environment_name()
+        })
+        .unwrap();
+
+        assert_eq!(active_environment_name, "foobaz");
+        assert_eq!(measurement.rest.len(), 2);
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+    process::Command,
+};
+
+use crate::{
+    environment_metadata::EnvironmentMetadata,
+    environments::{encode_environment_name, normalize_environment_name, Environment},
+    platform::link_directory,
+    CommandContext,
+};
+
+/// Directory inside the workspaces directory holding the worktrees of cloned
+/// environments.
+const WORKTREES_DIRECTORY: &str = ".worktrees";
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Create a second checkout of a Git environment as a new environment"
+)]
+pub struct CloneArgs {
+    pub environment_name: String,
+
+    /// Name of the new environment, for example `proj-2` when cloning `proj`
+    pub new_name: Option<String>,
+
+    /// Branch to create for the new checkout, named after the new environment
+    /// by default
+    #[arg(long)]
+    pub branch: Option<String>,
+}
+
+fn run_git(directory: &Path, args: &[&str]) -> Result<String, io::Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The first of `name-2`, `name-3`, ... that is not used by an environment.
+fn get_sibling_name(workspaces_directory: &str, name: &str) -> String {
+    (2..)
+        .map(|number| format!("{}-{}", name, number))
+        .find(|sibling_name| {
+            fs::symlink_metadata(
+                Path::new(workspaces_directory).join(encode_environment_name(sibling_name)),
+            )
+            .is_err()
+        })
+        .expect("Ran out of numbers")
+}
+
+pub fn clone<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: CloneArgs,
+) -> Result<(), io::Error> {
+    let workspaces_directory = &context.config.workspaces_directory;
+    let environment = context.get_environment(&Some(args.environment_name))?;
+    let repository = run_git(
+        &fs::canonicalize(&environment.path)?,
+        &["rev-parse", "--show-toplevel"],
+    )
+    .map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "Environment \"{}\" is not a Git repository and cannot be cloned",
+                environment.name
+            ),
+        )
+    })?;
+
+    let new_name = match args.new_name {
+        Some(new_name) => normalize_environment_name(&new_name)?,
+        None => get_sibling_name(workspaces_directory, &environment.name),
+    };
+    let environment_path = Path::new(workspaces_directory).join(encode_environment_name(&new_name));
+    if fs::symlink_metadata(&environment_path).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Environment \"{}\" already exists", new_name),
+        ));
+    }
+
+    let worktree_path = Path::new(workspaces_directory)
+        .join(WORKTREES_DIRECTORY)
+        .join(encode_environment_name(&new_name));
+    fs::create_dir_all(Path::new(workspaces_directory).join(WORKTREES_DIRECTORY))?;
+    let branch = args.branch.unwrap_or_else(|| new_name.clone());
+    let worktree_path_string = worktree_path.to_string_lossy();
+    run_git(
+        Path::new(&repository),
+        &["worktree", "add", "-b", &branch, &worktree_path_string],
+    )?;
+    link_directory(&worktree_path, &environment_path)?;
+
+    // The clone is used like the original, but is cleaned up as a worktree.
+    // Locking the original does not protect its clones
+    let metadata = EnvironmentMetadata {
+        cleanup_command: vec![
+            "git".to_string(),
+            "-C".to_string(),
+            repository,
+            "worktree".to_string(),
+            "remove".to_string(),
+            worktree_path_string.to_string(),
+        ],
+        disk_usage: None,
+        locked: false,
+        ..EnvironmentMetadata::load_at(&environment.path)
+    };
+    metadata.save(workspaces_directory, &new_name)?;
+
+    let cloned_environment = Environment::get_one(workspaces_directory, &new_name)?;
+    context
+        .writer
+        .write_all(format!("{}\n", cloned_environment.name).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, process::Command};
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::clone::{clone, CloneArgs},
+        environment_metadata::EnvironmentMetadata,
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    fn git(directory: &str, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(directory)
+            .args([
+                "-c",
+                "user.name=enwiro",
+                "-c",
+                "user.email=enwiro@example.com",
+            ])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+    }
+
+    #[rstest]
+    fn test_clone_creates_sibling_worktree(mut context_object: FakeContext) {
+        context_object.create_mock_environment("proj");
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let repository = format!("{}/proj", workspaces_directory);
+        git(&repository, &["init", "--quiet"]);
+        git(
+            &repository,
+            &["commit", "--quiet", "--allow-empty", "-m", "Initial commit"],
+        );
+        EnvironmentMetadata {
+            tags: vec!["work".to_string()],
+            locked: true,
+            ..Default::default()
+        }
+        .save(&workspaces_directory, "proj")
+        .unwrap();
+
+        clone(
+            &mut context_object,
+            CloneArgs {
+                environment_name: "proj".to_string(),
+                new_name: None,
+                branch: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(context_object.get_output(), "proj-2\n");
+        let clone_path = Path::new(&workspaces_directory).join("proj-2");
+        assert!(clone_path.is_symlink());
+        assert!(fs::canonicalize(&clone_path)
+            .unwrap()
+            .join(".git")
+            .is_file());
+        let metadata = EnvironmentMetadata::load(&workspaces_directory, "proj-2");
+        assert_eq!(metadata.tags, vec!["work"]);
+        assert!(!metadata.locked);
+        assert_eq!(metadata.cleanup_command[0], "git");
+        assert_eq!(metadata.cleanup_command[3..5], ["worktree", "remove"]);
+    }
+
+    #[rstest]
+    fn test_clone_requires_git_repository(mut context_object: FakeContext) {
+        context_object.create_mock_environment("notes");
+
+        let result = clone(
+            &mut context_object,
+            CloneArgs {
+                environment_name: "notes".to_string(),
+                new_name: Some("notes-copy".to_string()),
+                branch: None,
+            },
+        );
+
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    }
+}
@@ -0,0 +1,357 @@
+use std::{
+    collections::HashSet,
+    io::{self, Read, Write},
+};
+
+use clap::{Arg, Command, CommandFactory};
+
+use crate::{
+    commands::list_all::{collect_entries, ListAllArgs},
+    error_codes::ErrorCode,
+    Cli, CommandContext,
+};
+
+/// Name of the hidden command that completion scripts run to complete
+/// environment and recipe names.
+const COMPLETE_COMMAND: &str = "__enwiro_complete";
+
+/// Arguments that take the name of an environment or recipe, by id.
+const NAME_ARGUMENTS: [&str; 2] = ["environment_name", "environment_names"];
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Print a completion script that completes environment and recipe names as they are now"
+)]
+pub struct CompletionsArgs {
+    pub shell: Shell,
+}
+
+#[derive(clap::Args)]
+pub struct CompleteArgs {
+    /// Only tell whether names are completed for the last word, by failing
+    /// if they are not, without listing them
+    #[arg(long)]
+    pub check: bool,
+
+    /// Words of the command line up to the one being completed, starting
+    /// with the program name
+    pub words: Vec<String>,
+}
+
+/// Runs the completion generated by clap for everything but names. Names are
+/// completed by enwiro itself, which fails where no name is expected.
+fn get_names_completion(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => format!(
+            r#"
+_enwiro_with_names() {{
+    local names
+    if names=$(enwiro {COMPLETE_COMMAND} -- "${{COMP_WORDS[@]:0:COMP_CWORD+1}}" 2>/dev/null); then
+        local IFS=$'\n'
+        COMPREPLY=($names)
+    else
+        _enwiro "$@"
+    fi
+}}
+complete -F _enwiro_with_names -o bashdefault -o default enwiro
+"#
+        ),
+        Shell::Zsh => format!(
+            r#"
+_enwiro_with_names() {{
+    local output
+    if output=$(enwiro {COMPLETE_COMMAND} -- "${{(@)words[1,CURRENT]}}" 2>/dev/null); then
+        local -a names
+        names=("${{(@f)output}}")
+        compadd -a names
+    else
+        _enwiro "$@"
+    fi
+}}
+
+if [ "$funcstack[1]" = "_enwiro" ]; then
+    _enwiro_with_names "$@"
+else
+    compdef _enwiro_with_names enwiro
+fi
+"#
+        ),
+        Shell::Fish => format!(
+            r#"complete -c enwiro -n "enwiro {COMPLETE_COMMAND} --check -- (commandline -opc) (commandline -ct) 2>/dev/null" -f -a "(enwiro {COMPLETE_COMMAND} -- (commandline -opc) (commandline -ct) 2>/dev/null)"
+"#
+        ),
+    }
+}
+
+/// The command line interface without hidden subcommands, which clap would
+/// otherwise complete as well.
+fn get_completed_command() -> Command {
+    let command = Cli::command();
+
+    Command::new("enwiro")
+        .args(command.get_arguments().cloned())
+        .subcommands(
+            command
+                .get_subcommands()
+                .filter(|subcommand| !subcommand.is_hide_set())
+                .cloned(),
+        )
+}
+
+fn get_script(shell: Shell) -> String {
+    let mut script = vec![];
+    let generator = match shell {
+        Shell::Bash => clap_complete::Shell::Bash,
+        Shell::Zsh => clap_complete::Shell::Zsh,
+        Shell::Fish => clap_complete::Shell::Fish,
+    };
+    clap_complete::generate(
+        generator,
+        &mut get_completed_command(),
+        "enwiro",
+        &mut script,
+    );
+    let mut script = String::from_utf8_lossy(&script).to_string();
+    // The zsh script registers its own function, which has to make way for
+    // the one completing names
+    if let Shell::Zsh = shell {
+        if let Some(registration) = script.rfind("if [ \"$funcstack[1]\" = \"_enwiro\" ]") {
+            script.truncate(script[..registration].trim_end().len() + 1);
+        }
+    }
+
+    script + &get_names_completion(shell)
+}
+
+pub fn completions<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: CompletionsArgs,
+) -> Result<(), io::Error> {
+    context.writer.write_all(get_script(args.shell).as_bytes())
+}
+
+/// Whether an option given as a word, such as `--group` or `-g`, is followed
+/// by its value in the next word.
+fn takes_value_in_next_word(command: &Command, word: &str) -> bool {
+    let takes_values = |arg: &Arg| arg.get_action().takes_values();
+    match word.strip_prefix("--") {
+        Some(long) => {
+            !long.contains('=')
+                && command
+                    .get_arguments()
+                    .find(|arg| arg.get_long() == Some(long))
+                    .is_some_and(takes_values)
+        }
+        // In `-abc`, the first option taking a value takes the rest of the
+        // word, or the next word if it is the last one
+        None => {
+            let shorts: Vec<char> = word.chars().skip(1).collect();
+            for (index, short) in shorts.iter().enumerate() {
+                match command
+                    .get_arguments()
+                    .find(|arg| arg.get_short() == Some(*short))
+                {
+                    Some(arg) if takes_values(arg) => return index == shorts.len() - 1,
+                    Some(_) => {}
+                    None => return false,
+                }
+            }
+
+            false
+        }
+    }
+}
+
+/// Whether the last of the words, the program name being the first, is the
+/// name of an environment or recipe, as opposed to a subcommand, an option,
+/// the value of an option or another argument.
+fn completes_name(words: &[String]) -> bool {
+    let Some((current, previous)) = words.split_last() else {
+        return false;
+    };
+    let mut root = Cli::command();
+    root.build();
+    let mut command = &root;
+    let mut positional_index = 0;
+    let mut is_option_value = false;
+    let mut only_positionals = false;
+    for word in previous.iter().skip(1) {
+        if is_option_value {
+            is_option_value = false;
+        } else if !only_positionals && word == "--" {
+            only_positionals = true;
+        } else if !only_positionals && word.starts_with('-') && word.len() > 1 {
+            is_option_value = takes_value_in_next_word(command, word);
+        } else if command.has_subcommands() {
+            match command.find_subcommand(word) {
+                Some(subcommand) => command = subcommand,
+                None => return false,
+            }
+        } else {
+            positional_index += 1;
+        }
+    }
+    if is_option_value
+        || command.has_subcommands()
+        || (!only_positionals && current.starts_with('-'))
+    {
+        return false;
+    }
+
+    // The last positional argument may take any number of values
+    let positionals: Vec<&Arg> = command.get_positionals().collect();
+    let positional = positionals.get(positional_index).or_else(|| {
+        positionals.last().filter(|arg| {
+            arg.get_num_args()
+                .is_some_and(|range| range.max_values() > 1)
+        })
+    });
+
+    positional.is_some_and(|arg| NAME_ARGUMENTS.contains(&arg.get_id().as_str()))
+}
+
+/// Prints the names of the environments and recipes starting with the last
+/// word, one per line, the environments first. Fails if the last word is not
+/// a name, so that completion scripts complete it some other way.
+pub fn complete<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: CompleteArgs,
+) -> Result<(), io::Error> {
+    if !completes_name(&args.words) {
+        return Err(ErrorCode::InvalidInput
+            .error(io::ErrorKind::InvalidInput, "Names are not completed here"));
+    }
+    if args.check {
+        return Ok(());
+    }
+    let prefix = args.words.last().map(String::as_str).unwrap_or_default();
+    let entries = collect_entries(context, &ListAllArgs::default())?;
+    let mut completed_names = HashSet::new();
+    for entry in entries {
+        if entry.name.starts_with(prefix) && completed_names.insert(entry.name.clone()) {
+            context
+                .writer
+                .write_all(format!("{}\n", entry.name).as_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        commands::completions::{
+            complete, completes_name, get_names_completion, get_script, CompleteArgs, Shell,
+            COMPLETE_COMMAND,
+        },
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    fn get_words(command_line: &str) -> Vec<String> {
+        command_line.split(' ').map(str::to_string).collect()
+    }
+
+    #[rstest]
+    #[case(Shell::Bash)]
+    #[case(Shell::Zsh)]
+    #[case(Shell::Fish)]
+    fn test_scripts_complete_subcommands_and_names(#[case] shell: Shell) {
+        let script = get_script(shell);
+
+        assert!(script.contains("activate"));
+        assert!(script.contains("no-cook"));
+        assert!(script.contains("enwiro __enwiro_complete --"));
+        assert!(!script.contains("complete -c enwiro -f\n"));
+        // Hidden commands are only run by the script, never completed
+        assert_eq!(
+            script.matches(COMPLETE_COMMAND).count(),
+            get_names_completion(shell)
+                .matches(COMPLETE_COMMAND)
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_zsh_script_registers_name_completion_only() {
+        let script = get_script(Shell::Zsh);
+
+        assert_eq!(script.matches("compdef _").count(), 1);
+        assert!(script.contains("compdef _enwiro_with_names enwiro"));
+    }
+
+    #[rstest]
+    #[case("enwiro act", false)]
+    #[case("enwiro activate ", true)]
+    #[case("enwiro activate enw", true)]
+    #[case("enwiro activate -", false)]
+    #[case("enwiro activate --group ", false)]
+    #[case("enwiro activate --group work ", true)]
+    #[case("enwiro activate --group=work ", true)]
+    #[case("enwiro --error-format json show-path ", true)]
+    #[case("enwiro completions ", false)]
+    #[case("enwiro group add ", false)]
+    #[case("enwiro group add work ", true)]
+    #[case("enwiro group add work enwiro ", true)]
+    #[case("enwiro wrap ", false)]
+    #[case("enwiro wrap vim ", true)]
+    #[case("enwiro wrap vim enwiro ", false)]
+    #[case("enwiro unknown ", false)]
+    fn test_names_are_only_completed_where_names_are_expected(
+        #[case] command_line: &str,
+        #[case] expected: bool,
+    ) {
+        assert_eq!(completes_name(&get_words(command_line)), expected);
+    }
+
+    #[rstest]
+    fn test_complete_names_with_prefix(mut context_object: FakeContext) {
+        for name in ["enwiro", "enwiro-docs", "dotfiles"] {
+            context_object.create_mock_environment(name);
+        }
+
+        complete(
+            &mut context_object,
+            CompleteArgs {
+                check: false,
+                words: get_words("enwiro activate enw"),
+            },
+        )
+        .unwrap();
+
+        let mut names: Vec<String> = context_object
+            .get_output()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["enwiro", "enwiro-docs"]);
+    }
+
+    #[rstest]
+    fn test_complete_fails_where_names_are_not_expected(mut context_object: FakeContext) {
+        context_object.create_mock_environment("enwiro");
+
+        let result = complete(
+            &mut context_object,
+            CompleteArgs {
+                check: false,
+                words: get_words("enwiro completions "),
+            },
+        );
+
+        assert!(result.is_err());
+        assert_eq!(context_object.get_output(), "");
+    }
+}
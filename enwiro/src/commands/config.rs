@@ -0,0 +1,46 @@
+use std::io::{self, Write};
+
+use crate::config_validation::validate_configuration;
+
+#[derive(clap::Args)]
+#[command(author, version, about = "Inspect the configuration")]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum ConfigCommand {
+    Validate(ValidateArgs),
+}
+
+/// Report unknown keys, invalid values and missing adapters or cookbooks
+#[derive(clap::Args)]
+pub struct ValidateArgs {}
+
+fn validate<W: Write>(writer: &mut W, profile: Option<String>) -> Result<(), io::Error> {
+    let problems = validate_configuration(profile);
+
+    if problems.is_empty() {
+        writer.write_all("Configuration is valid".as_bytes())?;
+        return Ok(());
+    }
+
+    writer.write_all(problems.join("\n").as_bytes())?;
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Found {} problem(s) in the configuration", problems.len()),
+    ))
+}
+
+/// Unlike other commands, this one runs before the configuration is loaded,
+/// so that it can report problems that would prevent loading it.
+pub fn config<W: Write>(
+    writer: &mut W,
+    profile: Option<String>,
+    args: ConfigArgs,
+) -> Result<(), io::Error> {
+    match args.command {
+        ConfigCommand::Validate(_) => validate(writer, profile),
+    }
+}
@@ -0,0 +1,36 @@
+use std::io::{self, Read, Write};
+
+use crate::{cookbook::describe_entry, CommandContext};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Print the details of an environment or recipe provided by its cookbook"
+)]
+pub struct DescribeArgs {
+    pub environment_name: String,
+
+    /// Cookbook of the recipe. Environments are described by the cookbook
+    /// that cooked them
+    #[arg(long)]
+    pub cookbook: Option<String>,
+}
+
+/// Prints nothing if the cookbook cannot describe the entry, since details are
+/// only nice to have.
+pub fn describe<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: DescribeArgs,
+) -> Result<(), io::Error> {
+    let details = describe_entry(
+        &context.get_cookbooks(),
+        &context.config.workspaces_directory,
+        &args.environment_name,
+        args.cookbook.as_deref(),
+    );
+
+    context
+        .writer
+        .write_all(details.unwrap_or_default().as_bytes())
+}
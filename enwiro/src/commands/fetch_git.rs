@@ -0,0 +1,16 @@
+use std::io::{self, Read, Write};
+
+use crate::{cookbook::fetch_git_repositories, CommandContext};
+
+/// Started in the background by the builtin git cookbook when its
+/// `fetch_interval` is due, since enwiro exits as soon as recipes are listed.
+#[derive(clap::Args)]
+#[command(about = "Fetch the repositories of the builtin git cookbook")]
+pub struct FetchGitArgs {}
+
+pub fn fetch_git<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    _args: FetchGitArgs,
+) -> Result<(), io::Error> {
+    fetch_git_repositories(&context.config.cookbooks.settings)
+}
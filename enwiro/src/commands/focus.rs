@@ -0,0 +1,375 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    commands::activate::{activate, ActivateArgs},
+    hooks::{HookEvent, Hooks},
+    notifier::NotificationLevel,
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Activate an environment for a limited time, notifying when the time is up"
+)]
+pub struct FocusArgs {
+    #[arg(required_unless_present = "stop")]
+    pub environment_name: Option<String>,
+
+    /// How long to focus, such as `50m`, `1h30m` or `90s`. Plain numbers are
+    /// minutes
+    #[arg(long = "for", value_parser = parse_duration, required_unless_present = "stop")]
+    pub duration: Option<Duration>,
+
+    /// Hide the workspaces of other environments until the time is up, if the
+    /// adapter supports it
+    #[arg(long)]
+    pub hide_others: bool,
+
+    /// Show the workspaces hidden by a focus that was stopped or killed early
+    #[arg(long, conflicts_with_all = ["environment_name", "duration", "hide_others"])]
+    pub stop: bool,
+}
+
+/// Workspaces hidden by `--hide-others` are recorded here until they are
+/// shown again, so that they can be restored if focus is killed early.
+const HIDDEN_WORKSPACES_FILE_NAME: &str = ".hidden_workspaces.json";
+
+fn save_hidden_workspaces(workspaces_directory: &str, names: &[String]) -> Result<(), io::Error> {
+    let content = serde_json::to_string(names).map_err(io::Error::other)?;
+
+    fs::write(
+        Path::new(workspaces_directory).join(HIDDEN_WORKSPACES_FILE_NAME),
+        content,
+    )
+}
+
+/// Shows the workspaces hidden by an earlier focus, if any. Returns how many
+/// were shown. Workspaces recorded while using an adapter that cannot show
+/// them are forgotten, since it cannot have hidden them either.
+fn show_hidden_workspaces<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+) -> Result<usize, io::Error> {
+    let path = Path::new(&context.config.workspaces_directory).join(HIDDEN_WORKSPACES_FILE_NAME);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(error) => return Err(error),
+    };
+    let mut names: Vec<String> = serde_json::from_str(&content).unwrap_or_default();
+    if !context.adapter.supports_hiding_workspaces() {
+        names.clear();
+    }
+    if !names.is_empty() {
+        context.adapter.set_workspaces_hidden(&names, false)?;
+    }
+    fs::remove_file(path)?;
+
+    Ok(names.len())
+}
+
+/// Parses a duration made of numbers followed by `h`, `m` or `s`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid_duration = || format!("Invalid duration \"{}\", expected e.g. 50m", value);
+    if let Ok(minutes) = value.parse::<u64>() {
+        return minutes
+            .checked_mul(60)
+            .map(Duration::from_secs)
+            .ok_or_else(invalid_duration);
+    }
+
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for character in value.chars() {
+        if character.is_ascii_digit() {
+            number.push(character);
+            continue;
+        }
+        let unit = match character {
+            'h' => 60 * 60,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid_duration()),
+        };
+        let amount: u64 = number.parse().map_err(|_| invalid_duration())?;
+        seconds = amount
+            .checked_mul(unit)
+            .and_then(|amount| seconds.checked_add(amount))
+            .ok_or_else(invalid_duration)?;
+        number.clear();
+    }
+    if !number.is_empty() || value.is_empty() {
+        return Err(invalid_duration());
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let parts = [
+        (seconds / 3600, 'h'),
+        (seconds % 3600 / 60, 'm'),
+        (seconds % 60, 's'),
+    ];
+    let formatted: String = parts
+        .iter()
+        .filter(|(amount, _)| *amount > 0)
+        .map(|(amount, unit)| format!("{}{}", amount, unit))
+        .collect();
+
+    match formatted.is_empty() {
+        true => "0s".to_string(),
+        false => formatted,
+    }
+}
+
+/// Waits until the time is up, so it is meant to run in the background, such
+/// as from a key binding. Adapters that cannot hide workspaces only get a
+/// warning, since the timer is what matters.
+pub fn focus<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: FocusArgs,
+) -> Result<(), io::Error> {
+    // Workspaces left hidden by a focus that was killed are shown again first
+    let shown_count = show_hidden_workspaces(context)?;
+    let (Some(environment_name), Some(duration)) = (args.environment_name, args.duration) else {
+        return context
+            .writer
+            .write_all(format!("Showed {} hidden workspace(s)\n", shown_count).as_bytes());
+    };
+
+    activate(
+        context,
+        ActivateArgs {
+            environment_name: Some(environment_name.clone()),
+            ..Default::default()
+        },
+    )?;
+    let environment = context.get_environment(&Some(environment_name))?;
+
+    let mut hidden_environment_names = vec![];
+    if args.hide_others && !context.adapter.supports_hiding_workspaces() {
+        tracing::warn!("The adapter cannot hide the workspaces of other environments");
+    } else if args.hide_others {
+        let other_environment_names: Vec<String> = context
+            .get_all_environments()?
+            .into_keys()
+            .filter(|name| *name != environment.name)
+            .collect();
+        match context
+            .adapter
+            .set_workspaces_hidden(&other_environment_names, true)
+        {
+            Ok(()) => hidden_environment_names = other_environment_names,
+            Err(error) => tracing::warn!(%error, "Could not hide other workspaces"),
+        }
+        // Only recorded once hidden, so that nothing is left to show if hiding
+        // failed
+        if !hidden_environment_names.is_empty() {
+            save_hidden_workspaces(
+                &context.config.workspaces_directory,
+                &hidden_environment_names,
+            )?;
+        }
+    }
+    context.notify(
+        NotificationLevel::Success,
+        &format!(
+            "Focusing on {} for {}",
+            environment.name,
+            format_duration(duration)
+        ),
+    );
+
+    thread::sleep(duration);
+
+    if !hidden_environment_names.is_empty() {
+        context
+            .adapter
+            .set_workspaces_hidden(&hidden_environment_names, false)?;
+    }
+    let _ = fs::remove_file(
+        Path::new(&context.config.workspaces_directory).join(HIDDEN_WORKSPACES_FILE_NAME),
+    );
+    context.notify(
+        NotificationLevel::Success,
+        &format!("Time is up for {}", environment.name),
+    );
+    Hooks::new(&context.config)?.run(HookEvent::FocusEnd, &environment)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, path::Path, time::Duration};
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::focus::{
+            focus, format_duration, parse_duration, save_hidden_workspaces, FocusArgs,
+            HIDDEN_WORKSPACES_FILE_NAME,
+        },
+        notifier::NotificationLevel,
+        test_utils::test_utilities::{
+            context_object, EnwiroAdapterMock, FakeContext, NotifierMock,
+        },
+    };
+
+    #[rstest]
+    #[case("50", Some(50 * 60))]
+    #[case("50m", Some(50 * 60))]
+    #[case("1h30m", Some(90 * 60))]
+    #[case("90s", Some(90))]
+    #[case("", None)]
+    #[case("1h30", None)]
+    #[case("5 minutes", None)]
+    #[case("99999999999999999999m", None)]
+    #[case("307445734561825861", None)]
+    #[case("5124095576030432h", None)]
+    fn test_parse_duration(#[case] value: &str, #[case] expected_seconds: Option<u64>) {
+        assert_eq!(
+            parse_duration(value).ok(),
+            expected_seconds.map(Duration::from_secs)
+        );
+    }
+
+    #[rstest]
+    #[case(90, "1m30s")]
+    #[case(50 * 60, "50m")]
+    #[case(90 * 60, "1h30m")]
+    fn test_format_duration(#[case] seconds: u64, #[case] expected: &str) {
+        assert_eq!(format_duration(Duration::from_secs(seconds)), expected);
+    }
+
+    #[rstest]
+    fn test_focus_hides_other_workspaces_until_time_is_up(mut context_object: FakeContext) {
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        let notifier = NotifierMock::default();
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.notifier = Box::new(notifier.clone());
+        context_object.create_mock_environment("foobar");
+        context_object.create_mock_environment("baz");
+        let hooks_directory = Path::new(context_object.config.hooks_directory.as_ref().unwrap());
+        let output_path =
+            Path::new(&context_object.config.workspaces_directory).join(".hook_output");
+        fs::create_dir_all(hooks_directory).unwrap();
+        let hook_path = hooks_directory.join("focus-end");
+        fs::write(
+            &hook_path,
+            format!(
+                "#!/bin/sh\necho \"$ENWIRO_ENV\" > {}\n",
+                output_path.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        focus(
+            &mut context_object,
+            FocusArgs {
+                environment_name: Some("foobar".to_string()),
+                duration: Some(Duration::ZERO),
+                hide_others: true,
+                stop: false,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(*adapter.activated_environments.borrow(), vec!["foobar"]);
+        assert_eq!(*adapter.hidden_workspaces.borrow(), vec![vec!["baz"]]);
+        assert_eq!(*adapter.shown_workspaces.borrow(), vec![vec!["baz"]]);
+        assert_eq!(
+            notifier.notifications.borrow().last().unwrap(),
+            &(
+                NotificationLevel::Success,
+                "Time is up for foobar".to_string()
+            )
+        );
+        assert_eq!(fs::read_to_string(output_path).unwrap(), "foobar\n");
+        assert!(!Path::new(&context_object.config.workspaces_directory)
+            .join(HIDDEN_WORKSPACES_FILE_NAME)
+            .exists());
+    }
+
+    #[rstest]
+    fn test_stop_shows_workspaces_hidden_by_a_killed_focus(mut context_object: FakeContext) {
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.adapter = Box::new(adapter.clone());
+        save_hidden_workspaces(
+            &context_object.config.workspaces_directory,
+            &["baz".to_string()],
+        )
+        .unwrap();
+        let stop_args = || FocusArgs {
+            environment_name: None,
+            duration: None,
+            hide_others: false,
+            stop: true,
+        };
+
+        focus(&mut context_object, stop_args()).unwrap();
+        focus(&mut context_object, stop_args()).unwrap();
+
+        assert_eq!(*adapter.shown_workspaces.borrow(), vec![vec!["baz"]]);
+        assert_eq!(
+            context_object.get_output(),
+            "Showed 1 hidden workspace(s)\nShowed 0 hidden workspace(s)\n"
+        );
+    }
+
+    #[rstest]
+    fn test_focus_without_hiding_support(mut context_object: FakeContext) {
+        let mut adapter = EnwiroAdapterMock::new("foobaz");
+        adapter.supports_hiding_workspaces = false;
+        context_object.adapter = Box::new(adapter.clone());
+        context_object.create_mock_environment("foobar");
+        context_object.create_mock_environment("baz");
+        let hidden_workspaces_path = Path::new(&context_object.config.workspaces_directory)
+            .join(HIDDEN_WORKSPACES_FILE_NAME);
+        // Left behind by an earlier version that recorded workspaces before
+        // trying to hide them
+        save_hidden_workspaces(
+            &context_object.config.workspaces_directory,
+            &["baz".to_string()],
+        )
+        .unwrap();
+
+        focus(
+            &mut context_object,
+            FocusArgs {
+                environment_name: Some("foobar".to_string()),
+                duration: Some(Duration::ZERO),
+                hide_others: true,
+                stop: false,
+            },
+        )
+        .unwrap();
+        assert!(!hidden_workspaces_path.exists());
+        focus(
+            &mut context_object,
+            FocusArgs {
+                environment_name: None,
+                duration: None,
+                hide_others: false,
+                stop: true,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(*adapter.activated_environments.borrow(), vec!["foobar"]);
+        assert!(adapter.hidden_workspaces.borrow().is_empty());
+        assert!(adapter.shown_workspaces.borrow().is_empty());
+        assert_eq!(
+            context_object.get_output(),
+            "Showed 0 hidden workspace(s)\n"
+        );
+    }
+}
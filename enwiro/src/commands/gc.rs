@@ -0,0 +1,238 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read, Write},
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    collisions::strip_cookbook_suffix,
+    commands::remove::{discard_removed_environments, remove_environment},
+    cookbook::{list_recipes_or_skip, CookbookTrait},
+    environment_metadata::EnvironmentMetadata,
+    environments::Environment,
+    notifier::NotificationLevel,
+    usage_stats::{now, UsageStats, DAY_IN_SECONDS},
+    CommandContext,
+};
+
+/// Environments not used for this many days are suggested for removal, unless
+/// `stale_after_days` is configured.
+const DEFAULT_STALE_AFTER_DAYS: u64 = 30;
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "List environments that look finished, such as ones for closed pull requests"
+)]
+pub struct GcArgs {
+    /// Remove the suggested environments, which `enwiro undo` can restore
+    #[arg(long)]
+    pub suggested: bool,
+
+    /// Send a notification summarizing the suggestions instead of printing
+    /// them, for example from a timer
+    #[arg(long, conflicts_with = "suggested")]
+    pub notify: bool,
+
+    /// Forget all removed environments instead, so that `enwiro undo` can no
+    /// longer restore them, and let their cookbooks clean up after them
+    #[arg(long, conflicts_with_all = ["suggested", "notify"])]
+    pub empty_trash: bool,
+}
+
+/// When the environment was last activated, or created if it never was.
+fn get_last_used(environment: &Environment, usage_stats: &UsageStats) -> u64 {
+    let created = fs::symlink_metadata(&environment.path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs());
+
+    usage_stats.last_activated(&environment.name).max(created)
+}
+
+/// Finds environments that were not used for a while and whose recipe is no
+/// longer listed by the cookbook that cooked them, which is how cookbooks
+/// tell that a pull request or an issue was closed. Environments that were
+/// not cooked from a recipe, are pinned or locked, or whose cookbook lists no
+/// recipes at all, are never suggested.
+fn find_finished_environments(
+    cookbooks: &[Box<dyn CookbookTrait>],
+    environments: &[Environment],
+    usage_stats: &UsageStats,
+    stale_after_days: u64,
+    timestamp: u64,
+) -> Vec<String> {
+    let mut recipes_by_cookbook: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut finished_names = vec![];
+    for environment in environments {
+        let name = &environment.name;
+        if usage_stats.is_pinned(name)
+            || get_last_used(environment, usage_stats) + stale_after_days * DAY_IN_SECONDS
+                > timestamp
+        {
+            continue;
+        }
+        let metadata = EnvironmentMetadata::load_at(&environment.path);
+        if metadata.locked {
+            continue;
+        }
+        let Some(cookbook) = cookbooks
+            .iter()
+            .find(|cookbook| metadata.cookbook.as_deref() == Some(cookbook.name()))
+        else {
+            continue;
+        };
+        let recipes = recipes_by_cookbook
+            .entry(cookbook.name().to_string())
+            .or_insert_with(|| {
+                list_recipes_or_skip(cookbook.as_ref())
+                    .into_iter()
+                    .map(|recipe| recipe.name)
+                    .collect()
+            });
+        // Environments cooked before the recipe was recorded are matched by
+        // their name
+        let recipe = match &metadata.recipe {
+            Some(recipe) => recipe,
+            None => strip_cookbook_suffix(name, cookbook.name()),
+        };
+        if !recipes.is_empty() && !recipes.contains(recipe) {
+            finished_names.push(name.clone());
+        }
+    }
+    finished_names.sort();
+
+    finished_names
+}
+
+pub fn gc<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: GcArgs,
+) -> Result<(), io::Error> {
+    let workspaces_directory = &context.config.workspaces_directory;
+    let environments: Vec<Environment> = context.get_all_environments()?.into_values().collect();
+    let cookbooks = context.get_cookbooks();
+    if args.empty_trash {
+        let failures = discard_removed_environments(&context.config, &cookbooks, true)?;
+        for failure in &failures {
+            context
+                .writer
+                .write_all(format!("{}\n", failure).as_bytes())?;
+        }
+        return match failures.len() {
+            0 => Ok(()),
+            count => Err(io::Error::other(format!(
+                "Could not clean up after {} environment(s)",
+                count
+            ))),
+        };
+    }
+    let finished_names = find_finished_environments(
+        &cookbooks,
+        &environments,
+        &UsageStats::load(workspaces_directory),
+        context
+            .config
+            .stale_after_days
+            .unwrap_or(DEFAULT_STALE_AFTER_DAYS),
+        now(),
+    );
+
+    if args.notify {
+        if !finished_names.is_empty() {
+            context.notify(
+                NotificationLevel::Success,
+                &format!(
+                    "{} environment(s) look finished: {}. Run `enwiro gc --suggested` to remove them",
+                    finished_names.len(),
+                    finished_names.join(", ")
+                ),
+            );
+        }
+        return Ok(());
+    }
+
+    let mut failures = 0;
+    for name in finished_names {
+        let line = match args.suggested {
+            true => match remove_environment(&context.config, &cookbooks, &name, false) {
+                Ok(()) => format!("Removed {}\n", name),
+                Err(error) => {
+                    failures += 1;
+                    format!("Could not remove {}: {}\n", name, error)
+                }
+            },
+            false => format!("{}\n", name),
+        };
+        context.writer.write_all(line.as_bytes())?;
+    }
+
+    match failures {
+        0 => Ok(()),
+        failures => Err(io::Error::other(format!(
+            "Could not remove {} environment(s)",
+            failures
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        commands::gc::find_finished_environments,
+        cookbook::CookbookTrait,
+        environment_metadata::EnvironmentMetadata,
+        test_utils::test_utilities::{context_object, CookbookMock, FakeContext},
+        usage_stats::{now, UsageStats, DAY_IN_SECONDS},
+    };
+
+    #[rstest]
+    fn test_finished_environments_are_old_and_no_longer_listed(mut context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let cooked = |recipe: Option<&str>, locked: bool| EnvironmentMetadata {
+            cookbook: Some("mock".to_string()),
+            recipe: recipe.map(str::to_string),
+            locked,
+            ..Default::default()
+        };
+        for (name, metadata) in [
+            ("closed-pr", Some(cooked(Some("closed-pr"), false))),
+            ("open-pr", Some(cooked(Some("open-pr"), false))),
+            ("open-pr-2", Some(cooked(Some("open-pr"), false))),
+            ("closed-pr-2", Some(cooked(Some("closed-pr"), false))),
+            ("old-closed-pr", Some(cooked(None, false))),
+            ("locked-pr", Some(cooked(None, true))),
+            ("pinned-pr", Some(cooked(None, false))),
+            ("by-hand", None),
+        ] {
+            context_object.create_mock_environment(name);
+            if let Some(metadata) = metadata {
+                metadata.save(&workspaces_directory, name).unwrap();
+            }
+        }
+        let mut usage_stats = UsageStats::default();
+        usage_stats.toggle_pin("pinned-pr");
+        let cookbooks: Vec<Box<dyn CookbookTrait>> =
+            vec![Box::new(CookbookMock::new("mock", &["open-pr"]))];
+        let environments: Vec<_> = context_object
+            .get_all_environments()
+            .unwrap()
+            .into_values()
+            .collect();
+
+        let find = |timestamp: u64| {
+            find_finished_environments(&cookbooks, &environments, &usage_stats, 30, timestamp)
+        };
+
+        assert!(find(now()).is_empty());
+        assert_eq!(
+            find(now() + 31 * DAY_IN_SECONDS),
+            vec!["closed-pr", "closed-pr-2", "old-closed-pr"]
+        );
+    }
+}
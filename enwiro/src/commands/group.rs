@@ -0,0 +1,148 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    groups::{get_groups, Groups},
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(author, version, about = "Manage groups of environments")]
+pub struct GroupArgs {
+    #[command(subcommand)]
+    command: GroupCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum GroupCommand {
+    List(ListArgs),
+    Add(AddArgs),
+    Remove(RemoveArgs),
+}
+
+/// List all groups and their environments
+#[derive(clap::Args)]
+pub struct ListArgs {}
+
+/// Add environments to a group, creating the group if necessary
+#[derive(clap::Args)]
+pub struct AddArgs {
+    pub group: String,
+
+    #[arg(required = true)]
+    pub environment_names: Vec<String>,
+}
+
+/// Remove environments from a group, or the whole group if no environments
+/// are given
+#[derive(clap::Args)]
+pub struct RemoveArgs {
+    pub group: String,
+
+    pub environment_names: Vec<String>,
+}
+
+fn list<R: Read, W: Write>(context: &mut CommandContext<R, W>) -> Result<(), io::Error> {
+    for (group, members) in get_groups(&context.config)? {
+        context
+            .writer
+            .write_all(format!("{}: {}\n", group, members.join(", ")).as_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn add<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: AddArgs,
+) -> Result<(), io::Error> {
+    let workspaces_directory = &context.config.workspaces_directory;
+    let mut groups = Groups::load(workspaces_directory)?;
+    groups.add(&args.group, &args.environment_names);
+
+    groups.save(workspaces_directory)
+}
+
+/// Groups from the configuration can only be changed there.
+fn remove<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: RemoveArgs,
+) -> Result<(), io::Error> {
+    let workspaces_directory = &context.config.workspaces_directory;
+    let mut groups = Groups::load(workspaces_directory)?;
+    if !groups.remove(&args.group, &args.environment_names) {
+        let message = match context.config.groups.contains_key(&args.group) {
+            true => format!(
+                "Group {} is defined in the configuration, change it there",
+                args.group
+            ),
+            false => format!("Nothing to remove from group {}", args.group),
+        };
+        return Err(io::Error::new(io::ErrorKind::NotFound, message));
+    }
+
+    groups.save(workspaces_directory)
+}
+
+pub fn group<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: GroupArgs,
+) -> Result<(), io::Error> {
+    match args.command {
+        GroupCommand::List(_) => list(context),
+        GroupCommand::Add(args) => add(context, args),
+        GroupCommand::Remove(args) => remove(context, args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        commands::group::{group, AddArgs, GroupArgs, GroupCommand, ListArgs, RemoveArgs},
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    fn run(context_object: &mut FakeContext, command: GroupCommand) -> Result<(), std::io::Error> {
+        group(context_object, GroupArgs { command })
+    }
+
+    #[rstest]
+    fn test_manage_groups(mut context_object: FakeContext) {
+        context_object
+            .config
+            .groups
+            .insert("hobby".to_string(), vec!["game".to_string()]);
+
+        run(
+            &mut context_object,
+            GroupCommand::Add(AddArgs {
+                group: "work".to_string(),
+                environment_names: vec!["backend".to_string(), "frontend".to_string()],
+            }),
+        )
+        .unwrap();
+        run(
+            &mut context_object,
+            GroupCommand::Remove(RemoveArgs {
+                group: "work".to_string(),
+                environment_names: vec!["frontend".to_string()],
+            }),
+        )
+        .unwrap();
+        let result = run(
+            &mut context_object,
+            GroupCommand::Remove(RemoveArgs {
+                group: "hobby".to_string(),
+                environment_names: vec![],
+            }),
+        );
+        run(&mut context_object, GroupCommand::List(ListArgs {})).unwrap();
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("defined in the configuration"));
+        assert_eq!(context_object.get_output(), "hobby: game\nwork: backend\n");
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    commands::{
+        activate::{activate, ActivateArgs},
+        adapter::FocusedWindow,
+    },
+    environments::Environment,
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Suggest the environment matching the focused window, such as a terminal in its directory"
+)]
+pub struct InferArgs {
+    /// Activate the matching environment instead of printing its name
+    #[arg(long)]
+    pub activate: bool,
+}
+
+/// Names shorter than this are too likely to appear in unrelated titles.
+const MIN_TITLE_MATCH_LENGTH: usize = 3;
+
+/// Whether a word appears in a text on its own, rather than as part of a
+/// longer word, like `api` in `rapid`.
+fn contains_word(text: &str, word: &str) -> bool {
+    let is_word_character = |character: char| character.is_alphanumeric() || character == '_';
+    text.match_indices(word).any(|(start, _)| {
+        !text[..start].ends_with(is_word_character)
+            && !text[start + word.len()..].starts_with(is_word_character)
+    })
+}
+
+/// Finds the environment the focused window belongs to. An environment
+/// containing the working directory of the window wins, the innermost one if
+/// they are nested. Otherwise the environment with the longest name appearing
+/// as a whole word in the window title is used.
+fn find_environment_for_window(
+    environments: &HashMap<String, Environment>,
+    window: &FocusedWindow,
+) -> Option<String> {
+    let by_directory = window.cwd.as_ref().and_then(|cwd| {
+        environments
+            .values()
+            .filter_map(|environment| {
+                let path = fs::canonicalize(&environment.path).ok()?;
+                Path::new(cwd)
+                    .starts_with(&path)
+                    .then(|| (path.components().count(), environment.name.clone()))
+            })
+            .max()
+            .map(|(_, name)| name)
+    });
+    if by_directory.is_some() {
+        return by_directory;
+    }
+
+    let title = window.title.as_ref()?;
+    environments
+        .keys()
+        .filter(|name| name.chars().count() >= MIN_TITLE_MATCH_LENGTH && contains_word(title, name))
+        .max_by(|a, b| a.len().cmp(&b.len()).then(b.cmp(a)))
+        .cloned()
+}
+
+pub fn infer<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: InferArgs,
+) -> Result<(), io::Error> {
+    let window = context.adapter.get_focused_window()?;
+    let environment_name = find_environment_for_window(&context.get_all_environments()?, &window)
+        .ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "No environment matches the focused window",
+        )
+    })?;
+
+    match args.activate {
+        true => activate(
+            context,
+            ActivateArgs {
+                environment_name: Some(environment_name),
+                ..Default::default()
+            },
+        ),
+        false => context.writer.write_all(environment_name.as_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::symlink};
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::{
+            adapter::FocusedWindow,
+            infer::{infer, InferArgs},
+        },
+        test_utils::test_utilities::{context_object, EnwiroAdapterMock, FakeContext},
+    };
+
+    #[rstest]
+    #[case::innermost_directory(Some("nested"), None, "foo-nested")]
+    #[case::directory_before_title(Some(""), Some("vim - foo-nested"), "foo")]
+    #[case::longest_name_in_title(None, Some("vim - foo-nested"), "foo-nested")]
+    #[case::whole_words_only(None, Some("vim - foobar, foo-nestedness"), "foo")]
+    fn test_infer_environment(
+        mut context_object: FakeContext,
+        #[case] cwd: Option<&str>,
+        #[case] title: Option<&str>,
+        #[case] expected_name: &str,
+    ) {
+        // foo-nested links to a directory inside foo
+        context_object.create_mock_environment("foo");
+        let foo_path = fs::canonicalize(
+            context_object
+                .get_environment(&Some("foo".to_string()))
+                .unwrap()
+                .path,
+        )
+        .unwrap();
+        fs::create_dir(foo_path.join("nested")).unwrap();
+        symlink(
+            foo_path.join("nested"),
+            foo_path.with_file_name("foo-nested"),
+        )
+        .unwrap();
+        let mut adapter = EnwiroAdapterMock::new("");
+        adapter.focused_window = FocusedWindow {
+            title: title.map(str::to_string),
+            cwd: cwd.map(|cwd| foo_path.join(cwd).join("src").to_string_lossy().to_string()),
+        };
+        context_object.adapter = Box::new(adapter);
+
+        infer(&mut context_object, InferArgs { activate: false }).unwrap();
+
+        assert_eq!(context_object.get_output(), expected_name);
+    }
+
+    #[rstest]
+    fn test_short_names_are_not_inferred_from_titles(mut context_object: FakeContext) {
+        context_object.create_mock_environment("ui");
+        let mut adapter = EnwiroAdapterMock::new("");
+        adapter.focused_window = FocusedWindow {
+            title: Some("vim - ui".to_string()),
+            cwd: None,
+        };
+        context_object.adapter = Box::new(adapter);
+
+        assert!(infer(&mut context_object, InferArgs { activate: false }).is_err());
+    }
+}
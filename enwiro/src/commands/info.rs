@@ -0,0 +1,195 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    cookbook::describe_recipe,
+    disk_usage::{format_size, get_disk_usage},
+    environment_metadata::EnvironmentMetadata,
+    usage_stats::UsageStats,
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Show everything enwiro knows about an environment"
+)]
+pub struct InfoArgs {
+    pub environment_name: Option<String>,
+}
+
+pub fn format_age(seconds: u64) -> String {
+    let (amount, unit) = match seconds {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (seconds / 60, "minute"),
+        3600..=86399 => (seconds / 3600, "hour"),
+        _ => (seconds / 86400, "day"),
+    };
+
+    match amount {
+        1 => format!("1 {} ago", unit),
+        _ => format!("{} {}s ago", amount, unit),
+    }
+}
+
+/// Summarizes `git status`, for example `main...origin/main [ahead 1], 2
+/// changed files`. Returns `None` if the environment is not a Git repository.
+fn get_git_summary(path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(["status", "--porcelain", "--branch"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let branch = lines.next()?.trim_start_matches("## ").to_string();
+    let changes = match lines.count() {
+        0 => "clean".to_string(),
+        1 => "1 changed file".to_string(),
+        count => format!("{} changed files", count),
+    };
+
+    Some(format!("{}, {}", branch, changes))
+}
+
+pub fn info<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: InfoArgs,
+) -> Result<(), io::Error> {
+    let workspaces_directory = &context.config.workspaces_directory;
+    let environment = context.get_environment(&args.environment_name)?;
+    let metadata = EnvironmentMetadata::load_at(&environment.path);
+    let usage_stats = UsageStats::load(workspaces_directory);
+
+    let mut lines = vec![
+        format!("name: {}", environment.name),
+        format!("path: {}", environment.path.display()),
+    ];
+    if let Ok(target) = fs::read_link(&environment.path) {
+        lines.push(format!("links to: {}", target.display()));
+    }
+    if let Some(cookbook) = &metadata.cookbook {
+        lines.push(format!("cookbook: {}", cookbook));
+    }
+    if let Some(description) = &metadata.description {
+        lines.push(format!("description: {}", description));
+    }
+    if !metadata.tags.is_empty() {
+        lines.push(format!("tags: {}", metadata.tags.join(", ")));
+    }
+    if metadata.locked {
+        lines.push("locked: true".to_string());
+    }
+
+    match usage_stats.environments.get(&environment.name) {
+        Some(stats) => {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+            lines.push(format!("activations: {}", stats.activation_count));
+            lines.push(format!(
+                "last activated: {}",
+                format_age(now.saturating_sub(stats.last_activated))
+            ));
+            lines.push(format!("pinned: {}", stats.pinned));
+        }
+        None => lines.push("activations: 0".to_string()),
+    }
+
+    let disk_usage = get_disk_usage(&environment)?;
+    lines.push(format!("disk usage: {}", format_size(disk_usage.bytes)));
+    if let Some(git_summary) = get_git_summary(&environment.path) {
+        lines.push(format!("git: {}", git_summary));
+    }
+
+    let is_active = match context.adapter.get_active_environment_name() {
+        Ok(active_environment_name) => (active_environment_name == environment.name).to_string(),
+        Err(_) => "unknown".to_string(),
+    };
+    lines.push(format!("active: {}", is_active));
+
+    let details = metadata.cookbook.as_deref().and_then(|cookbook| {
+        describe_recipe(&context.get_cookbooks(), cookbook, &environment.name)
+    });
+    if let Some(details) = details {
+        lines.push("details:".to_string());
+        lines.extend(details.lines().map(|line| format!("  {}", line)));
+    }
+
+    context.writer.write_all(lines.join("\n").as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::info::{format_age, info, InfoArgs},
+        environment_metadata::EnvironmentMetadata,
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    #[case(10, "just now")]
+    #[case(60, "1 minute ago")]
+    #[case(7200, "2 hours ago")]
+    #[case(3 * 86400, "3 days ago")]
+    fn test_format_age(#[case] seconds: u64, #[case] expected: &str) {
+        assert_eq!(format_age(seconds), expected);
+    }
+
+    #[rstest]
+    fn test_info(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobaz");
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        fs::write(
+            format!("{}/foobaz/README.md", workspaces_directory),
+            "hello",
+        )
+        .unwrap();
+        EnvironmentMetadata {
+            cookbook: Some("git".to_string()),
+            description: Some("Foo project".to_string()),
+            tags: vec!["rust".to_string(), "work".to_string()],
+            ..Default::default()
+        }
+        .save(&workspaces_directory, "foobaz")
+        .unwrap();
+
+        info(
+            &mut context_object,
+            InfoArgs {
+                environment_name: Some("foobaz".to_string()),
+            },
+        )
+        .unwrap();
+
+        let output = context_object.get_output();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "name: foobaz");
+        for line in [
+            "cookbook: git",
+            "description: Foo project",
+            "tags: rust, work",
+            "activations: 0",
+            "disk usage: 5 B",
+            "active: true",
+        ] {
+            assert!(lines.contains(&line), "{} not in {:?}", line, lines);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    config::ConfigurationValues,
+    plugin::{get_plugins, PluginKind},
+    CommandContext,
+};
+
+/// Directories in the home directory that commonly hold code repositories.
+const CODE_DIRECTORY_NAMES: &[&str] = &[
+    "code",
+    "dev",
+    "git",
+    "projects",
+    "repos",
+    "src",
+    "workspace",
+    "Projects",
+];
+
+#[derive(clap::Args)]
+#[command(author, version, about = "Interactively set up the configuration")]
+pub struct InitArgs {}
+
+/// Guesses the running window manager from the environment it sets up.
+fn detect_window_manager(get_variable: impl Fn(&str) -> Option<String>) -> Option<&'static str> {
+    if get_variable("SWAYSOCK").is_some() {
+        return Some("sway");
+    }
+    if get_variable("I3SOCK").is_some() {
+        return Some("i3");
+    }
+
+    let desktop = get_variable("XDG_CURRENT_DESKTOP")?.to_lowercase();
+    ["sway", "i3"]
+        .into_iter()
+        .find(|window_manager| desktop.split(':').any(|x| x == *window_manager))
+}
+
+fn suggest_adapter(window_manager: Option<&str>, adapters: &[String]) -> Option<String> {
+    let preferred = match window_manager {
+        Some("i3") => Some("i3wm"),
+        Some("sway") => Some("sway"),
+        _ => None,
+    };
+
+    match preferred.filter(|adapter| adapters.iter().any(|x| x == adapter)) {
+        Some(adapter) => Some(adapter.to_string()),
+        None if adapters.len() == 1 => Some(adapters[0].clone()),
+        None => None,
+    }
+}
+
+/// Finds directories in the home directory that contain Git repositories and
+/// returns globs matching those repositories.
+fn find_repository_globs(home_directory: &Path) -> Vec<String> {
+    CODE_DIRECTORY_NAMES
+        .iter()
+        .filter(|name| {
+            fs::read_dir(home_directory.join(name))
+                .map(|entries| {
+                    entries
+                        .filter_map(Result::ok)
+                        .any(|entry| entry.path().join(".git").exists())
+                })
+                .unwrap_or(false)
+        })
+        .map(|name| format!("~/{}/*", name))
+        .collect()
+}
+
+fn get_keybinding_snippet(window_manager: &str) -> String {
+    format!(
+        "# Add this to your {} configuration to pick environments using rofi:\n\
+         bindsym $mod+p exec enwiro-bridge-dmenu\n\
+         \n\
+         # and this to ~/.config/enwiro/bridge-dmenu.toml:\n\
+         launcher_command = \"rofi -dmenu -show-icons -markup-rows -i\"",
+        window_manager
+    )
+}
+
+/// Asks a question and returns the answer, or `default` if the answer is empty.
+pub fn prompt<B: BufRead, W: Write>(
+    reader: &mut B,
+    writer: &mut W,
+    question: &str,
+    default: &str,
+) -> Result<String, io::Error> {
+    match default.is_empty() {
+        true => write!(writer, "{}: ", question)?,
+        false => write!(writer, "{} [{}]: ", question, default)?,
+    }
+    writer.flush()?;
+
+    let mut answer = String::new();
+    reader.read_line(&mut answer)?;
+
+    Ok(match answer.trim() {
+        "" => default.to_string(),
+        answer => answer.to_string(),
+    })
+}
+
+pub fn init<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    _args: InitArgs,
+) -> Result<(), io::Error> {
+    let mut reader = BufReader::new(&mut context.reader);
+    let writer = &mut context.writer;
+
+    // Values from the local configuration, profiles and environment variables
+    // should not end up in the configuration file, so start from the file.
+    let mut config: ConfigurationValues =
+        confy::load("enwiro", "enwiro").map_err(io::Error::other)?;
+
+    config.workspaces_directory = prompt(
+        &mut reader,
+        writer,
+        "Directory for environments",
+        &config.workspaces_directory,
+    )?;
+
+    let window_manager = detect_window_manager(|name| env::var(name).ok());
+    let mut adapters: Vec<String> = get_plugins(PluginKind::Adapter, None)
+        .into_iter()
+        .map(|plugin| plugin.name)
+        .collect();
+    adapters.sort();
+    if let Some(window_manager) = window_manager {
+        writeln!(writer, "Detected window manager: {}", window_manager)?;
+    }
+    writeln!(
+        writer,
+        "Available adapters: {}",
+        match adapters.is_empty() {
+            true => "none".to_string(),
+            false => adapters.join(", "),
+        }
+    )?;
+    let default_adapter = config
+        .adapter
+        .clone()
+        .or_else(|| suggest_adapter(window_manager, &adapters))
+        .unwrap_or_default();
+    let adapter = prompt(&mut reader, writer, "Adapter to use", &default_adapter)?;
+    config.adapter = Some(adapter).filter(|adapter| !adapter.is_empty());
+
+    let home_directory = home::home_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "User home directory not found"))?;
+    let repository_globs = prompt(
+        &mut reader,
+        writer,
+        "Globs matching Git repositories, separated by commas",
+        &find_repository_globs(&home_directory).join(", "),
+    )?;
+    let repository_globs: Vec<String> = repository_globs
+        .split(',')
+        .map(|glob| glob.trim().to_string())
+        .filter(|glob| !glob.is_empty())
+        .collect();
+    if !repository_globs.is_empty() {
+        let git_settings = config
+            .cookbooks
+            .settings
+            .entry("git".to_string())
+            .or_insert_with(|| serde_json::json!({}));
+        if !git_settings.is_object() {
+            *git_settings = serde_json::json!({});
+        }
+        git_settings["repo_globs"] = serde_json::json!(repository_globs);
+    }
+
+    confy::store("enwiro", "enwiro", &config).map_err(io::Error::other)?;
+    writeln!(
+        writer,
+        "Configuration written to {}",
+        confy::get_configuration_file_path("enwiro", "enwiro")
+            .map_err(io::Error::other)?
+            .display()
+    )?;
+
+    if let Some(window_manager) = window_manager {
+        let answer = prompt(&mut reader, writer, "Show a keybinding snippet? (y/N)", "")?;
+        if answer.eq_ignore_ascii_case("y") {
+            write!(writer, "\n{}", get_keybinding_snippet(window_manager))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{create_dir, create_dir_all},
+        io::Cursor,
+    };
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::init::{detect_window_manager, find_repository_globs, prompt, suggest_adapter},
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[test]
+    fn test_window_manager_is_detected_and_adapter_suggested() {
+        let window_manager = detect_window_manager(|name| match name {
+            "XDG_CURRENT_DESKTOP" => Some("i3".to_string()),
+            _ => None,
+        });
+        assert_eq!(window_manager, Some("i3"));
+        assert_eq!(detect_window_manager(|_| None), None);
+
+        assert_eq!(
+            suggest_adapter(window_manager, &["i3wm".to_string(), "sway".to_string()]),
+            Some("i3wm".to_string())
+        );
+        assert_eq!(
+            suggest_adapter(Some("sway"), &["i3wm".to_string()]),
+            Some("i3wm".to_string())
+        );
+        assert_eq!(suggest_adapter(None, &[]), None);
+    }
+
+    #[rstest]
+    fn test_directories_with_repositories_are_found(context_object: FakeContext) {
+        let home_directory = std::path::Path::new(&context_object.config.workspaces_directory);
+        create_dir_all(home_directory.join("projects/foo/.git")).unwrap();
+        create_dir_all(home_directory.join("src/not-a-repository")).unwrap();
+        create_dir(home_directory.join("code")).unwrap();
+
+        assert_eq!(
+            find_repository_globs(home_directory),
+            vec!["~/projects/*".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_prompt_uses_default_for_empty_answer() {
+        let mut reader = Cursor::new("\n/tmp/envs\n".as_bytes());
+        let mut writer = Cursor::new(vec![]);
+
+        assert_eq!(
+            prompt(&mut reader, &mut writer, "Directory", "~/envs").unwrap(),
+            "~/envs"
+        );
+        assert_eq!(
+            prompt(&mut reader, &mut writer, "Directory", "~/envs").unwrap(),
+            "/tmp/envs"
+        );
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "Directory [~/envs]: Directory [~/envs]: "
+        );
+    }
+}
@@ -1,32 +1,835 @@
-use std::io::{self, Read, Write};
+use std::{
+    cmp::Ordering,
+    io::{self, Read, Write},
+    path::Path,
+    sync::mpsc,
+    thread,
+};
 
-use crate::context::CommandContext;
+use serde_derive::Serialize;
 
-#[derive(clap::Args)]
+use crate::{
+    client::Recipe,
+    collisions::ClaimedNames,
+    commands::pick::{rank_entries, PickerEntry},
+    config::{CollisionPolicy, ConfigurationValues, SortOrder},
+    context::CommandContext,
+    cookbook::{list_recipes_or_skip, CookbookTrait},
+    disk_usage::{format_size, get_disk_usage},
+    environment_metadata::EnvironmentMetadata,
+    environments::Environment,
+    groups::get_group_members,
+    parallel::map_in_parallel,
+    usage_stats::UsageStats,
+};
+
+#[derive(clap::Args, Default)]
 #[command(
     author,
     version,
     about = "list all existing environments as well as recipes to create environments"
 )]
-pub struct ListAllArgs {}
+pub struct ListAllArgs {
+    /// Only list recipes from these cookbooks, and environments cooked by them
+    #[arg(long)]
+    pub cookbook: Vec<String>,
 
-pub fn list_all<R: Read, W: Write>(context: &mut CommandContext<R, W>) -> Result<(), io::Error> {
-    for environment in context.get_all_environments()?.values() {
-        context
-            .writer
-            .write_all(format!("_: {}\n", environment.name).as_bytes())
-            .expect("Could not write to output");
+    /// Only list existing environments
+    #[arg(long, conflicts_with = "recipes_only")]
+    pub envs_only: bool,
+
+    /// Only list recipes
+    #[arg(long)]
+    pub recipes_only: bool,
+
+    /// Stop after listing this many entries
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// Only list entries whose name or description fuzzy-match this text, the
+    /// best matches first
+    #[arg(long)]
+    pub query: Option<String>,
+
+    /// Order of environments and of the recipes of each cookbook, `sort` from
+    /// the configuration by default
+    #[arg(long, value_enum)]
+    pub sort: Option<SortOrder>,
+
+    /// Show the disk usage of existing environments after their names,
+    /// separated by a tab. Measurements are cached for an hour
+    #[arg(long)]
+    pub du: bool,
+
+    /// Only list the environments of this group, and recipes to create them
+    #[arg(long)]
+    pub group: Option<String>,
+
+    /// Print each entry as a line of JSON, with its description and category
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// An environment or recipe as it is listed.
+#[derive(Default, Serialize)]
+struct ListedEntry<'a> {
+    // None for environments that already exist
+    cookbook: Option<&'a str>,
+
+    name: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<&'a str>,
+
+    // Measured with `--du`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk_usage: Option<u64>,
+}
+
+impl ListedEntry<'_> {
+    /// Formats the entry as a `cookbook: name` line, followed by the disk
+    /// usage if it was measured, or as a line of JSON.
+    fn format(&self, json: bool) -> Result<String, io::Error> {
+        if json {
+            return serde_json::to_string(self)
+                .map(|line| format!("{}\n", line))
+                .map_err(io::Error::other);
+        }
+
+        let cookbook = self.cookbook.unwrap_or("_");
+        Ok(match self.disk_usage {
+            Some(bytes) => format!("{}: {}\t{}\n", cookbook, self.name, format_size(bytes)),
+            None => format!("{}: {}\n", cookbook, self.name),
+        })
+    }
+}
+
+impl ListAllArgs {
+    /// Whether an entry should be listed. Existing environments are matched
+    /// against the cookbook they were cooked from, if it is known.
+    fn includes(&self, is_environment: bool, cookbook: Option<&str>) -> bool {
+        let matches_kind = match is_environment {
+            true => !self.recipes_only,
+            false => !self.envs_only,
+        };
+        let matches_cookbook = self.cookbook.is_empty()
+            || cookbook.is_some_and(|cookbook| self.cookbook.iter().any(|name| name == cookbook));
+
+        matches_kind && matches_cookbook
+    }
+
+    fn is_full(&self, listed_count: usize) -> bool {
+        self.limit.is_some_and(|limit| listed_count >= limit)
+    }
+
+    /// Names of the environments of the group to list, if any.
+    fn get_group_members(
+        &self,
+        config: &ConfigurationValues,
+    ) -> Result<Option<Vec<String>>, io::Error> {
+        self.group
+            .as_ref()
+            .map(|group| get_group_members(config, group))
+            .transpose()
+    }
+}
+
+fn is_in_group(group_members: &Option<Vec<String>>, name: &str) -> bool {
+    group_members
+        .as_ref()
+        .is_none_or(|members| members.iter().any(|member| member == name))
+}
+
+/// Compares two entries, given as their name and cookbook, in the given order.
+/// Entries that are otherwise equal are ordered by name.
+fn compare_entries(
+    sort: SortOrder,
+    usage_stats: &UsageStats,
+    (a_name, a_cookbook): (&str, Option<&str>),
+    (b_name, b_cookbook): (&str, Option<&str>),
+) -> Ordering {
+    let ordering = match sort {
+        SortOrder::Frecency => usage_stats
+            .is_pinned(b_name)
+            .cmp(&usage_stats.is_pinned(a_name))
+            .then(
+                usage_stats
+                    .frecency(b_name)
+                    .partial_cmp(&usage_stats.frecency(a_name))
+                    .unwrap_or(Ordering::Equal),
+            ),
+        SortOrder::Recent => usage_stats
+            .last_activated(b_name)
+            .cmp(&usage_stats.last_activated(a_name)),
+        // Entries without a known cookbook come last
+        SortOrder::Cookbook => a_cookbook
+            .is_none()
+            .cmp(&b_cookbook.is_none())
+            .then(a_cookbook.cmp(&b_cookbook)),
+        SortOrder::Name => Ordering::Equal,
+    };
+
+    ordering.then(a_name.cmp(b_name))
+}
+
+/// Compares two recipes, given as their name and cookbook, in the given
+/// order. Unlike environments, recipes are ordered by how often they were
+/// cooked when sorting by frecency.
+fn compare_recipes(
+    sort: SortOrder,
+    usage_stats: &UsageStats,
+    (a_name, a_cookbook): (&str, &str),
+    (b_name, b_cookbook): (&str, &str),
+) -> Ordering {
+    match sort {
+        SortOrder::Frecency => usage_stats
+            .is_pinned(b_name)
+            .cmp(&usage_stats.is_pinned(a_name))
+            .then(
+                usage_stats
+                    .recipe_frecency(b_cookbook, b_name)
+                    .partial_cmp(&usage_stats.recipe_frecency(a_cookbook, a_name))
+                    .unwrap_or(Ordering::Equal),
+            )
+            .then(a_name.cmp(b_name)),
+        _ => compare_entries(
+            sort,
+            usage_stats,
+            (a_name, Some(a_cookbook)),
+            (b_name, Some(b_cookbook)),
+        ),
+    }
+}
+
+/// Collects the entries to rank against a query, which is also what the
+/// picker shows. Unlike the plain listing, this waits for all cookbooks.
+pub fn collect_entries<R: Read, W: Write>(
+    context: &CommandContext<R, W>,
+    args: &ListAllArgs,
+) -> Result<Vec<PickerEntry>, io::Error> {
+    let mut claimed_names = ClaimedNames::new(context.config.cookbooks.collisions);
+    let mut entries: Vec<PickerEntry> = vec![];
+    let group_members = args.get_group_members(&context.config)?;
+    let environments = context.get_all_environments()?;
+    let paths: Vec<&Path> = environments.values().map(|x| x.path.as_path()).collect();
+    let all_metadata = EnvironmentMetadata::load_many(&paths);
+    let names: Vec<String> = environments.into_keys().collect();
+    for (name, metadata) in names.into_iter().zip(all_metadata) {
+        claimed_names.claim_environment(&name, metadata.cookbook.as_deref());
+        if args.includes(true, metadata.cookbook.as_deref()) && is_in_group(&group_members, &name) {
+            entries.push(PickerEntry {
+                name,
+                cookbook: None,
+                description: metadata.description,
+                category: None,
+            });
+        }
     }
 
     for cookbook in context.get_cookbooks() {
-        for line in cookbook.list_recipes() {
-            context
-                .writer
-                .write_all(format!("{}: {}\n", cookbook.plugin.name, line).as_bytes())
-                .expect("Could not write to output");
+        if !args.includes(false, Some(cookbook.name())) {
+            continue;
+        }
+        for recipe in list_recipes_or_skip(cookbook.as_ref()) {
+            if let Some(name) = claimed_names.claim_recipe(cookbook.name(), &recipe.name)? {
+                if !is_in_group(&group_members, &recipe.name) {
+                    continue;
+                }
+                entries.push(PickerEntry {
+                    name,
+                    cookbook: Some(cookbook.name().to_string()),
+                    description: recipe.description,
+                    category: recipe.category,
+                });
+            }
         }
     }
 
+    Ok(entries)
+}
+
+fn list_matching<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: &ListAllArgs,
+    query: &str,
+) -> Result<(), io::Error> {
+    let entries = collect_entries(context, args)?;
+    let usage_stats = UsageStats::load(&context.config.workspaces_directory);
+    let ranked_entries = rank_entries(&entries, query, &usage_stats);
+
+    for entry in ranked_entries
+        .into_iter()
+        .take(args.limit.unwrap_or(usize::MAX))
+    {
+        let listed_entry = ListedEntry {
+            cookbook: entry.cookbook.as_deref(),
+            name: &entry.name,
+            description: entry.description.as_deref(),
+            category: entry.category.as_deref(),
+            disk_usage: None,
+        };
+        context
+            .writer
+            .write_all(listed_entry.format(args.json)?.as_bytes())?;
+    }
+
     Ok(())
 }
 
+pub fn list_all<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: ListAllArgs,
+) -> Result<(), io::Error> {
+    if let Some(query) = &args.query {
+        return list_matching(context, &args, query);
+    }
+
+    let workspaces_directory = context.config.workspaces_directory.clone();
+    let group_members = args.get_group_members(&context.config)?;
+    let environments: Vec<Environment> = context.get_all_environments()?.into_values().collect();
+    let sort = args.sort.unwrap_or(context.config.sort);
+
+    let mut cookbooks = context.get_cookbooks();
+    cookbooks.retain(|cookbook| args.includes(false, Some(cookbook.name())));
+    if sort == SortOrder::Cookbook {
+        cookbooks.sort_by(|a, b| a.name().cmp(b.name()));
+    }
+    let mut claimed_names = ClaimedNames::new(context.config.cookbooks.collisions);
+    let paths: Vec<&Path> = environments.iter().map(|x| x.path.as_path()).collect();
+    let all_metadata = EnvironmentMetadata::load_many(&paths);
+    let mut environments: Vec<(Environment, EnvironmentMetadata)> = environments
+        .into_iter()
+        .zip(all_metadata)
+        .inspect(|(environment, metadata)| {
+            claimed_names.claim_environment(&environment.name, metadata.cookbook.as_deref());
+        })
+        .filter(|(environment, metadata)| {
+            args.includes(true, metadata.cookbook.as_deref())
+                && is_in_group(&group_members, &environment.name)
+        })
+        .collect();
+    let mut listing = Listing {
+        args: &args,
+        sort,
+        usage_stats: UsageStats::load(&workspaces_directory),
+        claimed_names,
+        interleave_recipes: context.config.cookbooks.interleave_recipes,
+        group_members,
+        listed_count: 0,
+        writer: &mut context.writer,
+    };
+    environments.sort_by(|(a, a_metadata), (b, b_metadata)| {
+        compare_entries(
+            listing.sort,
+            &listing.usage_stats,
+            (&a.name, a_metadata.cookbook.as_deref()),
+            (&b.name, b_metadata.cookbook.as_deref()),
+        )
+    });
+    let (environments, all_metadata): (Vec<Environment>, Vec<EnvironmentMetadata>) =
+        environments.into_iter().unzip();
+    let disk_usages = match args.du {
+        true => measure_environments(&environments),
+        false => vec![None; environments.len()],
+    };
+    for ((environment, metadata), disk_usage) in
+        environments.iter().zip(&all_metadata).zip(disk_usages)
+    {
+        listing.write_entry(&ListedEntry {
+            name: &environment.name,
+            description: metadata.description.as_deref(),
+            disk_usage,
+            ..Default::default()
+        })?;
+    }
+    // Environments are shown right away, without waiting for any cookbook
+    listing.writer.flush()?;
+
+    if listing.is_full() {
+        return Ok(());
+    }
+
+    write_recipes(&mut listing, cookbooks)
+}
+
+/// Gets the disk usage of environments, measuring the ones without a recent
+/// measurement a few at a time.
+fn measure_environments(environments: &[Environment]) -> Vec<Option<u64>> {
+    map_in_parallel(environments, |environment| {
+        match get_disk_usage(environment) {
+            Ok(disk_usage) => Some(disk_usage.bytes),
+            Err(error) => {
+                tracing::warn!(%error, environment = environment.name, "Could not get disk usage");
+                None
+            }
+        }
+    })
+}
+
+/// State of a listing without a query, shared by the environments and the
+/// recipes of each cookbook.
+struct Listing<'a, W: Write> {
+    writer: &'a mut W,
+    args: &'a ListAllArgs,
+    sort: SortOrder,
+    usage_stats: UsageStats,
+
+    // Names of the environments and recipes listed so far
+    claimed_names: ClaimedNames,
+
+    // Whether the recipes of all cookbooks are sorted together
+    interleave_recipes: bool,
+
+    // Environments of the group to list, if any
+    group_members: Option<Vec<String>>,
+
+    listed_count: usize,
+}
+
+impl<W: Write> Listing<'_, W> {
+    fn is_full(&self) -> bool {
+        self.args.is_full(self.listed_count)
+    }
+
+    fn write_entry(&mut self, entry: &ListedEntry) -> Result<(), io::Error> {
+        if self.is_full() {
+            return Ok(());
+        }
+        self.listed_count += 1;
+
+        self.writer
+            .write_all(entry.format(self.args.json)?.as_bytes())
+    }
+
+    /// Writes the recipes of the given cookbooks, sorted together. Names are
+    /// claimed in the order of the cookbooks. The recipes of a single
+    /// cookbook are kept together by category, in the order in which the
+    /// cookbook lists the categories.
+    fn write_recipes_of(&mut self, cookbooks: Vec<(String, Vec<Recipe>)>) -> Result<(), io::Error> {
+        let group_by_category = cookbooks.len() == 1;
+        let mut categories: Vec<Option<&str>> = vec![];
+        let mut recipe_names = vec![];
+        for (cookbook, recipes) in &cookbooks {
+            for recipe in recipes {
+                let category = recipe.category.as_deref();
+                let category_index = match categories.iter().position(|x| *x == category) {
+                    Some(index) => index,
+                    None => {
+                        categories.push(category);
+                        categories.len() - 1
+                    }
+                };
+                if let Some(name) = self.claimed_names.claim_recipe(cookbook, &recipe.name)? {
+                    if is_in_group(&self.group_members, &recipe.name) {
+                        recipe_names.push((name, cookbook.as_str(), category_index, recipe));
+                    }
+                }
+            }
+        }
+        recipe_names.sort_by(
+            |(a, a_cookbook, a_category, _), (b, b_cookbook, b_category, _)| {
+                let category_ordering = match group_by_category {
+                    true => a_category.cmp(b_category),
+                    false => Ordering::Equal,
+                };
+
+                category_ordering.then_with(|| {
+                    compare_recipes(
+                        self.sort,
+                        &self.usage_stats,
+                        (a, a_cookbook),
+                        (b, b_cookbook),
+                    )
+                })
+            },
+        );
+        for (recipe_name, cookbook, _, recipe) in recipe_names {
+            self.write_entry(&ListedEntry {
+                cookbook: Some(cookbook),
+                name: &recipe_name,
+                description: recipe.description.as_deref(),
+                category: recipe.category.as_deref(),
+                disk_usage: None,
+            })?;
+        }
+
+        self.writer.flush()
+    }
+}
+
+/// Lists the recipes of all cookbooks at the same time, and writes those of
+/// each cookbook as soon as it is done so that whoever reads the output can
+/// show them while slower cookbooks are still running. When sorting by
+/// cookbook, or when colliding recipes get a suffix so that the first cookbook
+/// keeps the plain name, each cookbook also waits for the ones before it.
+/// Interleaved recipes are only written once all cookbooks are done.
+fn write_recipes<W: Write>(
+    listing: &mut Listing<W>,
+    cookbooks: Vec<Box<dyn CookbookTrait>>,
+) -> Result<(), io::Error> {
+    let interleave = listing.interleave_recipes && listing.sort != SortOrder::Cookbook;
+    let keep_order = interleave
+        || listing.sort == SortOrder::Cookbook
+        || listing.claimed_names.policy() == CollisionPolicy::Suffix;
+    let mut finished: Vec<Option<(String, Vec<Recipe>)>> = cookbooks.iter().map(|_| None).collect();
+    let mut next_index = 0;
+
+    let (sender, receiver) = mpsc::channel();
+    for (index, cookbook) in cookbooks.into_iter().enumerate() {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let mut recipes = vec![];
+            let result = cookbook.stream_recipes(&mut |recipe| {
+                recipes.push(recipe);
+                Ok(())
+            });
+            // The recipes listed before the error are still worth showing
+            if let Err(error) = result {
+                tracing::warn!(cookbook = cookbook.name(), %error, "Could not list recipes");
+            }
+            let _ = sender.send((index, cookbook.name().to_string(), recipes));
+        });
+    }
+    drop(sender);
+
+    for (index, cookbook, recipes) in receiver {
+        if interleave {
+            finished[index] = Some((cookbook, recipes));
+            continue;
+        }
+        if keep_order {
+            finished[index] = Some((cookbook, recipes));
+            while let Some((cookbook, recipes)) =
+                finished.get_mut(next_index).and_then(Option::take)
+            {
+                listing.write_recipes_of(vec![(cookbook, recipes)])?;
+                next_index += 1;
+            }
+        } else {
+            listing.write_recipes_of(vec![(cookbook, recipes)])?;
+        }
+
+        // Cookbooks that are still running are not waited for
+        if listing.is_full() {
+            break;
+        }
+    }
+
+    match interleave {
+        true => listing.write_recipes_of(finished.into_iter().flatten().collect()),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Cursor, time::Duration};
+
+    use rstest::rstest;
+
+    use crate::{
+        client::Recipe,
+        collisions::ClaimedNames,
+        commands::list_all::{compare_entries, list_all, write_recipes, ListAllArgs, Listing},
+        config::{CollisionPolicy, SortOrder},
+        cookbook::CookbookTrait,
+        test_utils::test_utilities::{context_object, CookbookMock, FakeContext},
+        usage_stats::UsageStats,
+    };
+
+    fn list_recipes(
+        sort: SortOrder,
+        collisions: CollisionPolicy,
+        interleave_recipes: bool,
+        usage_stats: UsageStats,
+    ) -> String {
+        let cookbooks: Vec<Box<dyn CookbookTrait>> = vec![
+            Box::new(CookbookMock {
+                delay: Duration::from_millis(200),
+                ..CookbookMock::new("slow", &["foo", "bar"])
+            }),
+            Box::new(CookbookMock::new("fast", &["baz", "existing"])),
+        ];
+        let args = ListAllArgs::default();
+        let mut writer = Cursor::new(vec![]);
+        let mut claimed_names = ClaimedNames::new(collisions);
+        claimed_names.claim_environment("existing", None);
+        let mut listing = Listing {
+            writer: &mut writer,
+            args: &args,
+            sort,
+            usage_stats,
+            claimed_names,
+            interleave_recipes,
+            group_members: None,
+            listed_count: 0,
+        };
+
+        write_recipes(&mut listing, cookbooks).unwrap();
+
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_recipes_of_fast_cookbooks_are_not_held_back() {
+        assert_eq!(
+            list_recipes(
+                SortOrder::Name,
+                CollisionPolicy::Hide,
+                false,
+                UsageStats::default()
+            ),
+            "fast: baz\nslow: bar\nslow: foo\n"
+        );
+    }
+
+    #[test]
+    fn test_recipes_sorted_by_cookbook_keep_their_order() {
+        assert_eq!(
+            list_recipes(
+                SortOrder::Cookbook,
+                CollisionPolicy::Hide,
+                false,
+                UsageStats::default()
+            ),
+            "slow: bar\nslow: foo\nfast: baz\n"
+        );
+    }
+
+    #[test]
+    fn test_colliding_recipes_get_a_suffix() {
+        assert_eq!(
+            list_recipes(
+                SortOrder::Name,
+                CollisionPolicy::Suffix,
+                false,
+                UsageStats::default()
+            ),
+            "slow: bar\nslow: foo\nfast: baz\nfast: existing@fast\n"
+        );
+    }
+
+    #[rstest]
+    #[case::grouped(false, "fast: baz\nslow: foo\nslow: bar\n")]
+    #[case::interleaved(true, "slow: foo\nslow: bar\nfast: baz\n")]
+    fn test_cooked_recipes_come_first(#[case] interleave_recipes: bool, #[case] expected: &str) {
+        let mut usage_stats = UsageStats::default();
+        usage_stats.record_cook("slow", "foo");
+
+        assert_eq!(
+            list_recipes(
+                SortOrder::Frecency,
+                CollisionPolicy::Hide,
+                interleave_recipes,
+                usage_stats
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_recipes_are_grouped_by_category() {
+        let recipe = |name: &str, category: Option<&str>| Recipe {
+            name: name.to_string(),
+            description: None,
+            category: category.map(str::to_string),
+        };
+        let args = ListAllArgs::default();
+        let mut writer = Cursor::new(vec![]);
+        let mut listing = Listing {
+            writer: &mut writer,
+            args: &args,
+            sort: SortOrder::Name,
+            usage_stats: UsageStats::default(),
+            claimed_names: ClaimedNames::new(CollisionPolicy::Hide),
+            interleave_recipes: false,
+            group_members: None,
+            listed_count: 0,
+        };
+
+        listing
+            .write_recipes_of(vec![(
+                "github".to_string(),
+                vec![
+                    recipe("pr-2", Some("My pull requests")),
+                    recipe("issue-1", Some("Issues")),
+                    recipe("pr-1", Some("My pull requests")),
+                    recipe("enwiro", None),
+                ],
+            )])
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "github: pr-1\ngithub: pr-2\ngithub: issue-1\ngithub: enwiro\n"
+        );
+    }
+
+    #[test]
+    fn test_json_lines_include_category() {
+        let args = ListAllArgs {
+            json: true,
+            ..Default::default()
+        };
+        let mut writer = Cursor::new(vec![]);
+        let mut listing = Listing {
+            writer: &mut writer,
+            args: &args,
+            sort: SortOrder::Name,
+            usage_stats: UsageStats::default(),
+            claimed_names: ClaimedNames::new(CollisionPolicy::Hide),
+            interleave_recipes: false,
+            group_members: None,
+            listed_count: 0,
+        };
+
+        listing
+            .write_recipes_of(vec![(
+                "github".to_string(),
+                vec![Recipe {
+                    name: "pr-1".to_string(),
+                    description: Some("Fix names".to_string()),
+                    category: Some("My pull requests".to_string()),
+                }],
+            )])
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "{\"cookbook\":\"github\",\"name\":\"pr-1\",\"description\":\"Fix names\",\"category\":\"My pull requests\"}\n"
+        );
+    }
+
+    #[rstest]
+    #[case(ListAllArgs::default(), true, true, true)]
+    #[case(ListAllArgs { envs_only: true, ..Default::default() }, true, false, true)]
+    #[case(ListAllArgs { recipes_only: true, ..Default::default() }, false, true, false)]
+    #[case(ListAllArgs { cookbook: vec!["git".to_string()], ..Default::default() }, true, true, false)]
+    fn test_list_all_filters(
+        #[case] args: ListAllArgs,
+        #[case] includes_git_environment: bool,
+        #[case] includes_git_recipe: bool,
+        #[case] includes_unknown_environment: bool,
+    ) {
+        assert_eq!(args.includes(true, Some("git")), includes_git_environment);
+        assert_eq!(args.includes(false, Some("git")), includes_git_recipe);
+        assert_eq!(args.includes(true, None), includes_unknown_environment);
+    }
+
+    #[rstest]
+    fn test_list_all_environments_by_frecency_with_limit(mut context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        for name in ["alpha", "beta", "gamma"] {
+            context_object.create_mock_environment(name);
+        }
+        let mut usage_stats = UsageStats::default();
+        usage_stats.record_activation("gamma");
+        usage_stats.save(&workspaces_directory).unwrap();
+
+        list_all(
+            &mut context_object,
+            ListAllArgs {
+                envs_only: true,
+                limit: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(context_object.get_output(), "_: gamma\n_: alpha\n");
+    }
+
+    #[rstest]
+    fn test_list_all_environments_of_group(mut context_object: FakeContext) {
+        for name in ["alpha", "beta", "gamma"] {
+            context_object.create_mock_environment(name);
+        }
+        context_object.config.groups.insert(
+            "release-week".to_string(),
+            vec!["gamma".to_string(), "alpha".to_string()],
+        );
+
+        list_all(
+            &mut context_object,
+            ListAllArgs {
+                group: Some("release-week".to_string()),
+                sort: Some(SortOrder::Name),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(context_object.get_output(), "_: alpha\n_: gamma\n");
+    }
+
+    #[rstest]
+    fn test_list_all_with_disk_usage(mut context_object: FakeContext) {
+        context_object.create_mock_environment("alpha");
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        fs::write(format!("{}/alpha/README.md", workspaces_directory), "hello").unwrap();
+
+        list_all(
+            &mut context_object,
+            ListAllArgs {
+                envs_only: true,
+                du: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(context_object.get_output(), "_: alpha\t5 B\n");
+    }
+
+    #[rstest]
+    fn test_list_all_with_query(mut context_object: FakeContext) {
+        for name in ["enwiro", "dotfiles", "enwiro-docs"] {
+            context_object.create_mock_environment(name);
+        }
+
+        list_all(
+            &mut context_object,
+            ListAllArgs {
+                envs_only: true,
+                query: Some("enwdoc".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(context_object.get_output(), "_: enwiro-docs\n");
+    }
+
+    #[rstest]
+    #[case(SortOrder::Frecency, vec!["pinned", "frequent", "recent", "alpha", "beta"])]
+    #[case(SortOrder::Name, vec!["alpha", "beta", "frequent", "pinned", "recent"])]
+    #[case(SortOrder::Recent, vec!["recent", "frequent", "alpha", "beta", "pinned"])]
+    #[case(SortOrder::Cookbook, vec!["beta", "recent", "alpha", "frequent", "pinned"])]
+    fn test_sort_orders(#[case] sort: SortOrder, #[case] expected_names: Vec<&str>) {
+        let mut usage_stats = UsageStats::default();
+        for (name, activation_count, last_activated) in [("frequent", 5, 100), ("recent", 1, 200)] {
+            let stats = usage_stats
+                .environments
+                .entry(name.to_string())
+                .or_default();
+            stats.activation_count = activation_count;
+            stats.last_activated = last_activated;
+        }
+        usage_stats.toggle_pin("pinned");
+        let mut entries = [
+            ("alpha", Some("git")),
+            ("beta", Some("chezmoi")),
+            ("frequent", Some("git")),
+            ("pinned", None),
+            ("recent", Some("chezmoi")),
+        ];
+
+        entries.sort_by(|a, b| compare_entries(sort, &usage_stats, *a, *b));
+
+        let names: Vec<&str> = entries.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, expected_names);
+    }
+}
@@ -38,7 +38,7 @@ mod tests {
 
         let output = context_object.get_output();
         let output_lines: Vec<&str> = output.lines().collect();
-        let expected_output = vec!["foobar", "baz"];
+        let expected_output = ["foobar", "baz"];
 
         assert_set_eq!(output_lines, expected_output);
     }
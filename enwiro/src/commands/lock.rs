@@ -0,0 +1,63 @@
+use std::io::{self, Read, Write};
+
+use crate::{environment_metadata::EnvironmentMetadata, CommandContext};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Lock an environment so that it is not removed without --force"
+)]
+pub struct LockArgs {
+    pub environment_name: Option<String>,
+
+    /// Unlock the environment instead
+    #[arg(long)]
+    pub unlock: bool,
+}
+
+pub fn lock<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: LockArgs,
+) -> Result<(), io::Error> {
+    let environment = context.get_environment(&args.environment_name)?;
+    let mut metadata = EnvironmentMetadata::load_at(&environment.path);
+    metadata.locked = !args.unlock;
+    metadata.save_at(&environment.path)?;
+
+    let message = match args.unlock {
+        true => format!("Unlocked {}\n", environment.name),
+        false => format!("Locked {}\n", environment.name),
+    };
+    context.writer.write_all(message.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        commands::lock::{lock, LockArgs},
+        environment_metadata::EnvironmentMetadata,
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    fn test_lock_and_unlock(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let args = |unlock: bool| LockArgs {
+            environment_name: Some("foobar".to_string()),
+            unlock,
+        };
+
+        lock(&mut context_object, args(false)).unwrap();
+        assert!(EnvironmentMetadata::load(&workspaces_directory, "foobar").locked);
+        lock(&mut context_object, args(true)).unwrap();
+        assert!(!EnvironmentMetadata::load(&workspaces_directory, "foobar").locked);
+        assert_eq!(
+            context_object.get_output(),
+            "Locked foobar\nUnlocked foobar\n"
+        );
+    }
+}
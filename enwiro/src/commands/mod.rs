@@ -1,5 +1,34 @@
+pub mod activate;
 pub mod adapter;
+pub mod adopt;
+pub mod bench;
+pub mod clone;
+pub mod completions;
+pub mod config;
+pub mod describe;
+#[cfg(feature = "cookbook-git")]
+pub mod fetch_git;
+pub mod focus;
+pub mod gc;
+pub mod group;
+pub mod infer;
+pub mod info;
+pub mod init;
 pub mod list_all;
 pub mod list_environments;
+pub mod lock;
+pub mod open_in_code;
+pub mod pick;
+pub mod plugin;
+pub mod plugins;
+pub mod precook;
+pub mod remove;
+pub mod renumber;
+pub mod rpc;
+pub mod serve;
+pub mod shell;
 pub mod show_path;
+pub mod stats;
+pub mod undo;
+pub mod watch;
 pub mod wrap;
@@ -0,0 +1,127 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    environments::{encode_environment_name, Environment},
+    CommandContext,
+};
+
+const CODE_WORKSPACES_DIRECTORY_NAME: &str = ".code-workspaces";
+
+#[derive(clap::Args)]
+#[command(author, version, about = "Open an environment in Visual Studio Code")]
+pub struct OpenInCodeArgs {
+    pub environment_name: Option<String>,
+
+    /// Open a new window instead of reusing the last active one
+    #[arg(long)]
+    pub new_window: bool,
+}
+
+/// Creates or updates the `.code-workspace` file belonging to an environment.
+/// Settings added to the file from within VS Code are preserved.
+fn write_code_workspace(
+    workspaces_directory: &str,
+    environment: &Environment,
+) -> Result<PathBuf, io::Error> {
+    let code_workspaces_directory =
+        Path::new(workspaces_directory).join(CODE_WORKSPACES_DIRECTORY_NAME);
+    fs::create_dir_all(&code_workspaces_directory)?;
+    let code_workspace_path = code_workspaces_directory.join(format!(
+        "{}.code-workspace",
+        encode_environment_name(&environment.name)
+    ));
+
+    let mut code_workspace: Map<String, Value> = fs::read_to_string(&code_workspace_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    code_workspace.insert(
+        "folders".to_string(),
+        json!([{"name": environment.name, "path": environment.path.to_string_lossy()}]),
+    );
+
+    let content = serde_json::to_string_pretty(&code_workspace).map_err(io::Error::other)?;
+    fs::write(&code_workspace_path, content)?;
+
+    Ok(code_workspace_path)
+}
+
+pub fn open_in_code<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: OpenInCodeArgs,
+) -> Result<(), io::Error> {
+    let environment = context.get_or_cook_environment(&args.environment_name)?;
+    let code_workspace_path =
+        write_code_workspace(&context.config.workspaces_directory, &environment)?;
+
+    let status = Command::new("code")
+        .arg(match args.new_window {
+            true => "--new-window",
+            false => "--reuse-window",
+        })
+        .arg(code_workspace_path)
+        .status()?;
+
+    if !status.success() {
+        return Err(io::Error::other("Could not start Visual Studio Code"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rstest::rstest;
+    use serde_json::Value;
+
+    use crate::{
+        commands::open_in_code::write_code_workspace,
+        environments::Environment,
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    fn test_write_code_workspace_keeps_existing_settings(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let environment = Environment::get_one(&workspaces_directory, "foobar").unwrap();
+
+        let code_workspace_path =
+            write_code_workspace(&workspaces_directory, &environment).unwrap();
+        fs::write(
+            &code_workspace_path,
+            "{\"folders\": [], \"settings\": {\"editor.tabSize\": 2}}",
+        )
+        .unwrap();
+        write_code_workspace(&workspaces_directory, &environment).unwrap();
+
+        let code_workspace: Value =
+            serde_json::from_str(&fs::read_to_string(&code_workspace_path).unwrap()).unwrap();
+        assert_eq!(
+            code_workspace["folders"][0]["path"],
+            environment.path.to_str().unwrap()
+        );
+        assert_eq!(code_workspace["settings"]["editor.tabSize"], 2);
+    }
+
+    #[rstest]
+    fn test_code_workspaces_are_not_listed_as_environments(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let environment = Environment::get_one(&workspaces_directory, "foobar").unwrap();
+
+        write_code_workspace(&workspaces_directory, &environment).unwrap();
+
+        let environments = Environment::get_all(&workspaces_directory).unwrap();
+        assert_eq!(environments.len(), 1);
+    }
+}
@@ -0,0 +1,427 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    io::{self, Read, Write},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    Terminal,
+};
+
+use crate::{
+    commands::{
+        activate::{activate, ActivateArgs},
+        list_all::{collect_entries, ListAllArgs},
+        remove::remove_environment,
+        show_path::{show_path, ShowPathArgs},
+    },
+    config::ConfigurationValues,
+    cookbook::{describe_entry, CookbookTrait},
+    usage_stats::UsageStats,
+    CommandContext,
+};
+
+const HELP_TEXT: &str = "enter: activate  ctrl-p: print path  ctrl-x: remove  ctrl-t: pin/unpin  ctrl-d: details  esc: quit";
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Interactively pick an environment or recipe and act on it"
+)]
+pub struct PickArgs {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickerEntry {
+    pub name: String,
+
+    // None for environments that already exist
+    pub cookbook: Option<String>,
+
+    pub description: Option<String>,
+
+    pub category: Option<String>,
+}
+
+enum PickerAction {
+    Activate(String),
+    ShowPath(String),
+}
+
+/// Filters entries whose name or description match the query and orders them:
+/// pinned entries first, then by match quality, then by frecency.
+pub fn rank_entries<'a>(
+    entries: &'a [PickerEntry],
+    query: &str,
+    usage_stats: &UsageStats,
+) -> Vec<&'a PickerEntry> {
+    let matcher = SkimMatcherV2::default();
+    let mut scored_entries: Vec<(&PickerEntry, i64)> = entries
+        .iter()
+        .filter_map(|entry| {
+            if query.is_empty() {
+                return Some((entry, 0));
+            }
+            let name_score = matcher.fuzzy_match(&entry.name, query);
+            let description_score = entry
+                .description
+                .as_ref()
+                .and_then(|description| matcher.fuzzy_match(description, query));
+            name_score
+                .max(description_score)
+                .map(|score| (entry, score))
+        })
+        .collect();
+
+    scored_entries.sort_by(|(a, a_score), (b, b_score)| {
+        usage_stats
+            .is_pinned(&b.name)
+            .cmp(&usage_stats.is_pinned(&a.name))
+            .then(b_score.cmp(a_score))
+            .then(
+                usage_stats
+                    .entry_frecency(&b.name, b.cookbook.as_deref())
+                    .partial_cmp(&usage_stats.entry_frecency(&a.name, a.cookbook.as_deref()))
+                    .unwrap_or(Ordering::Equal),
+            )
+            .then(a.name.cmp(&b.name))
+    });
+
+    scored_entries.into_iter().map(|(entry, _)| entry).collect()
+}
+
+fn render_entry<'a>(entry: &'a PickerEntry, usage_stats: &UsageStats) -> ListItem<'a> {
+    let mut spans = vec![Span::raw(match usage_stats.is_pinned(&entry.name) {
+        true => "* ",
+        false => "  ",
+    })];
+    spans.push(Span::raw(entry.name.as_str()));
+    for detail in [&entry.cookbook, &entry.description].into_iter().flatten() {
+        spans.push(Span::styled(
+            format!("  {}", detail),
+            Style::new().add_modifier(Modifier::DIM),
+        ));
+    }
+
+    ListItem::new(Line::from(spans))
+}
+
+/// How often the picker checks for details while some are being loaded.
+const DETAILS_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Details of an entry shown next to the list, provided by the cookbook of
+/// the recipe or of the environment.
+enum Details {
+    Loading,
+    Loaded(Option<String>),
+}
+
+/// Asks cookbooks for details in the background, since describing a recipe
+/// can take a while and the picker has to keep reacting to keys meanwhile.
+/// The thread stops once the picker drops it.
+struct Describer {
+    requests: mpsc::Sender<PickerEntry>,
+    details: mpsc::Receiver<(String, Option<String>)>,
+}
+
+impl Describer {
+    fn spawn(cookbooks: Vec<Box<dyn CookbookTrait>>, workspaces_directory: String) -> Self {
+        let (requests, request_receiver) = mpsc::channel::<PickerEntry>();
+        let (details_sender, details) = mpsc::channel();
+        thread::spawn(move || {
+            for entry in request_receiver {
+                let entry_details = describe_entry(
+                    &cookbooks,
+                    &workspaces_directory,
+                    &entry.name,
+                    entry.cookbook.as_deref(),
+                );
+                if details_sender.send((entry.name, entry_details)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { requests, details }
+    }
+}
+
+fn run_picker<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    entries: &mut Vec<PickerEntry>,
+    usage_stats: &mut UsageStats,
+    config: &ConfigurationValues,
+    cookbooks: &[Box<dyn CookbookTrait>],
+    describer: &Describer,
+) -> Result<Option<PickerAction>, io::Error> {
+    let mut query = String::new();
+    let mut list_state = ListState::default();
+    let mut message: Option<String> = None;
+    let mut show_details = false;
+    // Cookbooks are only asked once for the details of each entry
+    let mut details: HashMap<String, Details> = HashMap::new();
+
+    loop {
+        for (name, entry_details) in describer.details.try_iter() {
+            details.insert(name, Details::Loaded(entry_details));
+        }
+        let visible_entries = rank_entries(entries, &query, usage_stats);
+        let selected = list_state
+            .selected()
+            .unwrap_or(0)
+            .min(visible_entries.len().saturating_sub(1));
+        list_state.select(Some(selected));
+        let described_entry = visible_entries.get(selected).filter(|_| show_details);
+        if let Some(entry) = described_entry {
+            details.entry(entry.name.clone()).or_insert_with(|| {
+                let _ = describer.requests.send((*entry).clone());
+                Details::Loading
+            });
+        }
+        let selected_details_text = match described_entry.and_then(|entry| details.get(&entry.name))
+        {
+            Some(Details::Loading) => "Loading details…",
+            Some(Details::Loaded(Some(text))) => text.as_str(),
+            _ => "No details",
+        };
+        let is_loading = details
+            .values()
+            .any(|entry_details| matches!(entry_details, Details::Loading));
+
+        terminal.draw(|frame| {
+            let [input_area, list_area, help_area] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ])
+            .areas(frame.area());
+            let list_area = match show_details {
+                true => {
+                    let [list_area, details_area] = Layout::horizontal([
+                        Constraint::Percentage(50),
+                        Constraint::Percentage(50),
+                    ])
+                    .areas(list_area);
+                    frame.render_widget(
+                        Paragraph::new(selected_details_text)
+                            .block(Block::new().borders(Borders::LEFT))
+                            .wrap(Wrap { trim: false }),
+                        details_area,
+                    );
+                    list_area
+                }
+                false => list_area,
+            };
+
+            let items: Vec<ListItem> = visible_entries
+                .iter()
+                .map(|entry| render_entry(entry, usage_stats))
+                .collect();
+            let list =
+                List::new(items).highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+            frame.render_widget(Paragraph::new(format!("> {}", query)), input_area);
+            frame.render_stateful_widget(list, list_area, &mut list_state);
+            frame.render_widget(
+                Paragraph::new(message.as_deref().unwrap_or(HELP_TEXT))
+                    .style(Style::new().add_modifier(Modifier::DIM)),
+                help_area,
+            );
+        })?;
+
+        // Redraws from time to time while details are loading, to show them
+        if is_loading && !event::poll(DETAILS_POLL_INTERVAL)? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        let selected_name = visible_entries
+            .get(selected)
+            .map(|entry| entry.name.clone());
+        let is_selected_environment = visible_entries
+            .get(selected)
+            .is_some_and(|entry| entry.cookbook.is_none());
+        message = None;
+
+        match (key.code, key.modifiers.contains(KeyModifiers::CONTROL)) {
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), true) => return Ok(None),
+            (KeyCode::Enter, _) => return Ok(selected_name.map(PickerAction::Activate)),
+            (KeyCode::Char('p'), true) => return Ok(selected_name.map(PickerAction::ShowPath)),
+            (KeyCode::Char('d'), true) => show_details = !show_details,
+            (KeyCode::Char('t'), true) => {
+                if let Some(name) = selected_name {
+                    usage_stats.toggle_pin(&name);
+                    usage_stats.save(&config.workspaces_directory)?;
+                }
+            }
+            (KeyCode::Char('x'), true) => match selected_name {
+                Some(name) if is_selected_environment => {
+                    match remove_environment(config, cookbooks, &name, false) {
+                        Ok(()) => {
+                            entries.retain(|entry| entry.name != name);
+                            message = Some(format!("Removed {}, `enwiro undo` restores it", name));
+                        }
+                        Err(error) => message = Some(error.to_string()),
+                    }
+                }
+                Some(_) => message = Some("Recipes cannot be removed".to_string()),
+                None => {}
+            },
+            (KeyCode::Up, _) => list_state.select(Some(selected.saturating_sub(1))),
+            (KeyCode::Down, _) => list_state.select(Some(selected + 1)),
+            (KeyCode::Backspace, _) => {
+                query.pop();
+                list_state.select(Some(0));
+            }
+            (KeyCode::Char(character), false) => {
+                query.push(character);
+                list_state.select(Some(0));
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn pick<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    _args: PickArgs,
+) -> Result<(), io::Error> {
+    let mut entries = collect_entries(context, &ListAllArgs::default())?;
+    let mut usage_stats = UsageStats::load(&context.config.workspaces_directory);
+    let cookbooks = context.get_cookbooks();
+    let describer = Describer::spawn(
+        context.get_cookbooks(),
+        context.config.workspaces_directory.clone(),
+    );
+
+    // The interface is drawn on stderr so that stdout stays usable, for
+    // example in `cd "$(enwiro pick)"` after choosing "print path".
+    enable_raw_mode()?;
+    execute!(io::stderr(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stderr()))?;
+    let picker_result = run_picker(
+        &mut terminal,
+        &mut entries,
+        &mut usage_stats,
+        &context.config,
+        &cookbooks,
+        &describer,
+    );
+    disable_raw_mode()?;
+    execute!(io::stderr(), LeaveAlternateScreen)?;
+
+    match picker_result? {
+        Some(PickerAction::Activate(name)) => activate(
+            context,
+            ActivateArgs {
+                environment_name: Some(name),
+                ..Default::default()
+            },
+        ),
+        Some(PickerAction::ShowPath(name)) => show_path(
+            context,
+            ShowPathArgs {
+                environment_name: Some(name),
+                cook: true,
+                ..Default::default()
+            },
+        ),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::test_utilities::CookbookMock;
+
+    fn entry(name: &str, cookbook: Option<&str>) -> PickerEntry {
+        PickerEntry {
+            name: name.to_string(),
+            cookbook: cookbook.map(|x| x.to_string()),
+            description: None,
+            category: None,
+        }
+    }
+
+    #[test]
+    fn test_rank_entries_filters_by_fuzzy_query() {
+        let entries = vec![
+            entry("enwiro", None),
+            entry("dotfiles", Some("git")),
+            entry("enwiro-docs", Some("git")),
+        ];
+
+        let ranked = rank_entries(&entries, "enw", &UsageStats::default());
+        let names: Vec<&str> = ranked.iter().map(|entry| entry.name.as_str()).collect();
+
+        assert_eq!(names, vec!["enwiro", "enwiro-docs"]);
+    }
+
+    #[test]
+    fn test_rank_entries_matches_descriptions() {
+        let mut described_entry = entry("kantord/enwiro#12", Some("github"));
+        described_entry.description = Some("Fix workspace names".to_string());
+        let entries = vec![entry("dotfiles", None), described_entry];
+
+        let ranked = rank_entries(&entries, "workspace", &UsageStats::default());
+        let names: Vec<&str> = ranked.iter().map(|entry| entry.name.as_str()).collect();
+
+        assert_eq!(names, vec!["kantord/enwiro#12"]);
+    }
+
+    #[test]
+    fn test_rank_entries_puts_pinned_and_frequent_entries_first() {
+        let entries = vec![
+            entry("alpha", None),
+            entry("beta", None),
+            entry("gamma", Some("git")),
+        ];
+        let mut usage_stats = UsageStats::default();
+        usage_stats.record_activation("beta");
+        usage_stats.toggle_pin("gamma");
+
+        let ranked = rank_entries(&entries, "", &usage_stats);
+        let names: Vec<&str> = ranked.iter().map(|entry| entry.name.as_str()).collect();
+
+        assert_eq!(names, vec!["gamma", "beta", "alpha"]);
+    }
+
+    #[test]
+    fn test_describer_describes_entries_in_the_background() {
+        let cookbook = CookbookMock {
+            describes: true,
+            ..CookbookMock::new("git", &[])
+        };
+        let describer = Describer::spawn(vec![Box::new(cookbook)], String::new());
+
+        describer
+            .requests
+            .send(entry("dotfiles@git", Some("git")))
+            .unwrap();
+
+        assert_eq!(
+            describer.details.recv().unwrap(),
+            (
+                "dotfiles@git".to_string(),
+                Some("All about dotfiles".to_string())
+            )
+        );
+    }
+}
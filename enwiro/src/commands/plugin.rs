@@ -0,0 +1,249 @@
+use std::{
+    env::consts::ARCH,
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+    process::Command,
+};
+
+use crate::{
+    error_codes::ErrorCode, platform::make_executable, plugin::get_installed_plugins_directory,
+    CommandContext,
+};
+
+const DEFAULT_REPOSITORY: &str = "kantord/enwiro";
+
+#[derive(clap::Args)]
+#[command(author, version, about = "Manage enwiro plugins")]
+pub struct PluginArgs {
+    #[command(subcommand)]
+    command: PluginCommand,
+}
+
+#[derive(clap::Subcommand)]
+enum PluginCommand {
+    Install(InstallArgs),
+}
+
+/// Download a prebuilt plugin from GitHub releases and install it
+#[derive(clap::Args)]
+pub struct InstallArgs {
+    /// Name of the plugin, for example `cookbook-git` or `adapter-i3wm`
+    pub name: String,
+
+    /// GitHub repository publishing the plugin
+    #[arg(long, default_value = DEFAULT_REPOSITORY)]
+    pub repository: String,
+
+    /// Release tag to install instead of the latest release
+    #[arg(long)]
+    pub version: Option<String>,
+}
+
+fn get_executable_name(plugin_name: &str) -> String {
+    match plugin_name.starts_with("enwiro-") {
+        true => plugin_name.to_string(),
+        false => format!("enwiro-{}", plugin_name),
+    }
+}
+
+/// Target triple of the prebuilt plugins that run on this machine, see
+/// `.github/workflows/release.yaml`. Plugins are only published for Linux
+/// and macOS.
+fn get_target() -> Result<String, io::Error> {
+    let system = if cfg!(target_os = "linux") {
+        "unknown-linux-gnu"
+    } else if cfg!(target_os = "macos") {
+        "apple-darwin"
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Prebuilt plugins are only published for Linux and macOS",
+        ));
+    };
+
+    Ok(format!("{}-{}", ARCH, system))
+}
+
+/// URL of the release asset containing the plugin binary for the given
+/// target. Its checksum is published next to it with a `.sha256` suffix.
+fn get_asset_url(
+    repository: &str,
+    version: &Option<String>,
+    executable_name: &str,
+    target: &str,
+) -> String {
+    let release = match version {
+        Some(version) => format!("download/{}", version),
+        None => "latest/download".to_string(),
+    };
+
+    format!(
+        "https://github.com/{}/releases/{}/{}-{}",
+        repository, release, executable_name, target
+    )
+}
+
+/// Extracts the hash from a checksum file in the format used by `sha256sum`.
+fn parse_checksum(checksum_file: &str) -> Option<String> {
+    let checksum = checksum_file.split_whitespace().next()?.to_lowercase();
+
+    match checksum.len() == 64 && checksum.chars().all(|x| x.is_ascii_hexdigit()) {
+        true => Some(checksum),
+        false => None,
+    }
+}
+
+fn download(url: &str, destination: &Path) -> Result<(), io::Error> {
+    let status = Command::new("curl")
+        .args([
+            "--fail",
+            "--location",
+            "--silent",
+            "--show-error",
+            "--output",
+        ])
+        .arg(destination)
+        .arg(url)
+        .status()?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!("Could not download {}", url)));
+    }
+
+    Ok(())
+}
+
+/// Uses `sha256sum`, or `shasum` where it is missing, such as on macOS.
+fn compute_checksum(path: &Path) -> Result<String, io::Error> {
+    let output = match Command::new("sha256sum").arg(path).output() {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Command::new("shasum")
+            .args(["--algorithm", "256"])
+            .arg(path)
+            .output()?,
+        output => output?,
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .filter(|_| output.status.success())
+        .map(|checksum| checksum.to_lowercase())
+        .ok_or_else(|| io::Error::other("Could not compute checksum of the downloaded plugin"))
+}
+
+fn install<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: InstallArgs,
+) -> Result<(), io::Error> {
+    let executable_name = get_executable_name(&args.name);
+    let installed_plugins_directory = get_installed_plugins_directory()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "User home directory not found"))?;
+    fs::create_dir_all(&installed_plugins_directory)?;
+
+    let asset_url = get_asset_url(
+        &args.repository,
+        &args.version,
+        &executable_name,
+        &get_target()?,
+    );
+    let download_path = installed_plugins_directory.join(format!(".{}.download", executable_name));
+    let checksum_path = installed_plugins_directory.join(format!(".{}.sha256", executable_name));
+
+    let result = download(&asset_url, &download_path)
+        .and_then(|_| download(&format!("{}.sha256", asset_url), &checksum_path))
+        .and_then(|_| {
+            let expected_checksum = parse_checksum(&fs::read_to_string(&checksum_path)?)
+                .ok_or_else(|| io::Error::other("The published checksum is not valid"))?;
+            if compute_checksum(&download_path)? != expected_checksum {
+                return Err(io::Error::other(
+                    "Checksum of the downloaded plugin does not match, refusing to install it",
+                ));
+            }
+
+            make_executable(&download_path)?;
+            fs::rename(
+                &download_path,
+                installed_plugins_directory.join(&executable_name),
+            )
+        });
+    let _ = fs::remove_file(&download_path);
+    let _ = fs::remove_file(&checksum_path);
+    result?;
+
+    context.writer.write_all(
+        format!(
+            "Installed {} to {}",
+            executable_name,
+            installed_plugins_directory.display()
+        )
+        .as_bytes(),
+    )?;
+
+    Ok(())
+}
+
+pub fn plugin<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: PluginArgs,
+) -> Result<(), io::Error> {
+    match args.command {
+        PluginCommand::Install(args) => install(context, args),
+    }
+    .map_err(|error| ErrorCode::PluginFailed.tag(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::consts::ARCH;
+
+    use crate::commands::plugin::{get_asset_url, get_executable_name, get_target, parse_checksum};
+
+    #[test]
+    fn test_asset_url_for_plugin() {
+        assert_eq!(
+            get_asset_url(
+                "kantord/enwiro",
+                &None,
+                &get_executable_name("cookbook-git"),
+                "x86_64-unknown-linux-gnu"
+            ),
+            "https://github.com/kantord/enwiro/releases/latest/download/enwiro-cookbook-git-x86_64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            get_asset_url(
+                "foo/bar",
+                &Some("v1.0.0".to_string()),
+                &get_executable_name("enwiro-adapter-yabai"),
+                "aarch64-apple-darwin"
+            ),
+            "https://github.com/foo/bar/releases/download/v1.0.0/enwiro-adapter-yabai-aarch64-apple-darwin"
+        );
+    }
+
+    #[test]
+    fn test_target_matches_this_machine() {
+        let target = get_target().unwrap();
+
+        assert!(target.starts_with(ARCH));
+        #[cfg(target_os = "linux")]
+        assert!(target.ends_with("-unknown-linux-gnu"));
+        #[cfg(target_os = "macos")]
+        assert!(target.ends_with("-apple-darwin"));
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let checksum = "a".repeat(64);
+
+        assert_eq!(
+            parse_checksum(&format!(
+                "{}  enwiro-cookbook-git\n",
+                checksum.to_uppercase()
+            )),
+            Some(checksum)
+        );
+        assert_eq!(parse_checksum("<html>Not Found</html>"), None);
+        assert_eq!(parse_checksum(""), None);
+    }
+}
@@ -0,0 +1,142 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    plugin::{get_plugins, Plugin, PluginKind, PluginMetadata, PROTOCOL_VERSION},
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "List discovered adapters, cookbooks and gear, and check that they work"
+)]
+pub struct PluginsArgs {}
+
+/// Describes a plugin and the outcome of asking it for its metadata, which
+/// doubles as a health check.
+fn format_plugin(
+    plugin: &Plugin,
+    metadata: &Result<Option<PluginMetadata>, io::Error>,
+    priority: Option<i32>,
+    is_disabled: bool,
+) -> String {
+    let mut lines = vec![format!(
+        "{} {} ({})",
+        plugin.kind.to_string().to_lowercase(),
+        plugin.name,
+        plugin.executable
+    )];
+    let legacy_metadata = PluginMetadata::default();
+    let status = match metadata {
+        Err(error) => format!("broken: {}", error),
+        Ok(None) => "ok, but does not provide metadata".to_string(),
+        Ok(Some(metadata)) if metadata.protocol_version > PROTOCOL_VERSION => format!(
+            "newer than enwiro, which supports protocol version {}",
+            PROTOCOL_VERSION
+        ),
+        Ok(Some(_)) => "ok".to_string(),
+    };
+
+    if let Ok(metadata) = metadata {
+        let metadata = metadata.as_ref().unwrap_or(&legacy_metadata);
+        if let Some(description) = &metadata.description {
+            lines.push(format!("  description: {}", description));
+        }
+        lines.push(format!("  protocol version: {}", metadata.protocol_version));
+    }
+    if let Some(priority) = priority {
+        lines.push(format!("  priority: {}", priority));
+    }
+    lines.push(format!(
+        "  status: {}",
+        match is_disabled {
+            true => "disabled".to_string(),
+            false => status,
+        }
+    ));
+
+    lines.join("\n")
+}
+
+pub fn plugins<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    _args: PluginsArgs,
+) -> Result<(), io::Error> {
+    let cookbooks_config = &context.config.cookbooks;
+    let mut descriptions: Vec<String> = vec![];
+
+    for kind in [PluginKind::Adapter, PluginKind::Cookbook, PluginKind::Gear] {
+        let mut plugins: Vec<Plugin> =
+            get_plugins(kind.clone(), context.config.allowed_plugins.as_deref())
+                .into_iter()
+                .collect();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for plugin in plugins {
+            let metadata = plugin.query_metadata();
+            let (priority, is_disabled) = match kind {
+                PluginKind::Cookbook => (
+                    cookbooks_config
+                        .priorities
+                        .get(&plugin.name)
+                        .copied()
+                        .or_else(|| {
+                            metadata
+                                .as_ref()
+                                .ok()
+                                .and_then(|metadata| metadata.as_ref()?.default_priority)
+                        })
+                        .or(Some(0)),
+                    cookbooks_config.disabled.contains(&plugin.name),
+                ),
+                _ => (None, false),
+            };
+
+            descriptions.push(format_plugin(&plugin, &metadata, priority, is_disabled));
+        }
+    }
+
+    if descriptions.is_empty() {
+        descriptions.push("No plugins found.".to_string());
+    }
+
+    context
+        .writer
+        .write_all(descriptions.join("\n\n").as_bytes())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{
+        commands::plugins::format_plugin,
+        plugin::{Plugin, PluginKind, PluginMetadata},
+    };
+
+    #[test]
+    fn test_format_plugin_reports_metadata_and_health() {
+        let plugin = Plugin {
+            name: "git".to_string(),
+            kind: PluginKind::Cookbook,
+            executable: "/bin/enwiro-cookbook-git".to_string(),
+        };
+        let metadata = Ok(Some(PluginMetadata {
+            protocol_version: 2,
+            description: Some("Git repositories".to_string()),
+            ..Default::default()
+        }));
+
+        assert_eq!(
+            format_plugin(&plugin, &metadata, Some(0), false),
+            "cookbook git (/bin/enwiro-cookbook-git)\n  description: Git repositories\n  protocol version: 2\n  priority: 0\n  status: ok"
+        );
+        assert_eq!(
+            format_plugin(&plugin, &Err(io::Error::other("exec failed")), None, false),
+            "cookbook git (/bin/enwiro-cookbook-git)\n  status: broken: exec failed"
+        );
+    }
+}
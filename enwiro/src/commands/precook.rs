@@ -0,0 +1,323 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    collisions::strip_cookbook_suffix,
+    cookbook::{list_recipes_or_skip, CookbookTrait},
+    environment_metadata::EnvironmentMetadata,
+    environments::Environment,
+    usage_stats::{now, UsageStats},
+    CommandContext,
+};
+
+const PREFETCH_LOG_FILE_NAME: &str = ".prefetch_log.json";
+
+/// Recipes are not prefetched again within this many seconds, however often
+/// `enwiro precook` runs.
+const PREFETCH_INTERVAL: u64 = 15 * 60;
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Prepare the environments you are likely to activate next, for example from a timer"
+)]
+pub struct PrecookArgs {
+    /// How many of the most likely environments to consider
+    #[arg(long, default_value_t = 3)]
+    pub count: usize,
+}
+
+/// Cooks the recipes of the given environments without linking them into the
+/// workspaces directory, so that the slow part of cooking, such as cloning a
+/// repository, is already done when they are activated. Environments that
+/// exist already are skipped. Returns the names of the prepared environments.
+///
+/// This relies on cookbooks cooking the same recipe again without harm, as
+/// activating the environment later does.
+fn precook_from(
+    workspaces_directory: &str,
+    cookbooks: &[Box<dyn CookbookTrait>],
+    names: &[String],
+) -> Vec<String> {
+    let missing_names: Vec<&String> = names
+        .iter()
+        .filter(|name| Environment::get_one(workspaces_directory, name).is_err())
+        .collect();
+    if missing_names.is_empty() {
+        return vec![];
+    }
+
+    // Each cookbook is only asked for its recipes once, since listing them
+    // can be slow
+    let recipe_names: Vec<HashSet<String>> = cookbooks
+        .iter()
+        .map(|cookbook| {
+            list_recipes_or_skip(cookbook.as_ref())
+                .into_iter()
+                .map(|recipe| recipe.name)
+                .collect()
+        })
+        .collect();
+
+    let mut prepared_names = vec![];
+    for name in missing_names {
+        let cookbook = cookbooks
+            .iter()
+            .zip(&recipe_names)
+            .find(|(_, recipe_names)| recipe_names.contains(name));
+        let Some((cookbook, _)) = cookbook else {
+            continue;
+        };
+        match cookbook.cook(name) {
+            Ok(_) => prepared_names.push(name.clone()),
+            Err(error) => tracing::warn!(%error, name, "Could not prepare environment"),
+        }
+    }
+
+    prepared_names
+}
+
+/// When each recipe was last prefetched, keyed by cookbook and recipe name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PrefetchLog {
+    last_prefetched: HashMap<String, u64>,
+}
+
+impl PrefetchLog {
+    fn load(workspaces_directory: &str) -> Self {
+        fs::read_to_string(Path::new(workspaces_directory).join(PREFETCH_LOG_FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, workspaces_directory: &str) -> Result<(), io::Error> {
+        let content = serde_json::to_string(self).map_err(io::Error::other)?;
+
+        fs::write(
+            Path::new(workspaces_directory).join(PREFETCH_LOG_FILE_NAME),
+            content,
+        )
+    }
+
+    /// Whether the recipe was not prefetched too recently to be prefetched
+    /// again at the given time.
+    fn is_due(&self, cookbook: &str, recipe: &str, timestamp: u64) -> bool {
+        self.last_prefetched
+            .get(&format!("{}/{}", cookbook, recipe))
+            .is_none_or(|last_prefetched| last_prefetched + PREFETCH_INTERVAL <= timestamp)
+    }
+
+    fn record(&mut self, cookbook: &str, recipe: &str, timestamp: u64) {
+        self.last_prefetched
+            .insert(format!("{}/{}", cookbook, recipe), timestamp);
+    }
+
+    /// Forgets prefetches that no longer hold back prefetching again, so that
+    /// the log does not keep growing.
+    fn prune(&mut self, timestamp: u64) {
+        self.last_prefetched
+            .retain(|_, last_prefetched| *last_prefetched + PREFETCH_INTERVAL > timestamp);
+    }
+}
+
+/// Asks the cookbooks of the given environments to prefetch their recipes.
+/// Environments that do not exist or whose cookbook is unknown are skipped.
+/// Returns the names of the prefetched environments.
+fn prefetch_from(
+    workspaces_directory: &str,
+    cookbooks: &[Box<dyn CookbookTrait>],
+    names: &[String],
+    prefetch_log: &mut PrefetchLog,
+) -> Vec<String> {
+    let mut prefetched_names = vec![];
+    for name in names {
+        if Environment::get_one(workspaces_directory, name).is_err() {
+            continue;
+        }
+        let metadata = EnvironmentMetadata::load(workspaces_directory, name);
+        let Some(cookbook) = cookbooks
+            .iter()
+            .find(|cookbook| metadata.cookbook.as_deref() == Some(cookbook.name()))
+        else {
+            continue;
+        };
+        let recipe = strip_cookbook_suffix(name, cookbook.name());
+        if !prefetch_log.is_due(cookbook.name(), recipe, now()) {
+            continue;
+        }
+
+        // Failed prefetches are not recorded, so that they are tried again
+        // on the next run
+        match cookbook.prefetch(recipe) {
+            Ok(()) => {
+                prefetch_log.record(cookbook.name(), recipe, now());
+                prefetched_names.push(name.clone());
+            }
+            Err(error) => tracing::warn!(%error, name, "Could not prefetch environment"),
+        }
+    }
+
+    prefetched_names
+}
+
+pub fn precook<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: PrecookArgs,
+) -> Result<(), io::Error> {
+    let workspaces_directory = &context.config.workspaces_directory;
+    let mut likely_names = UsageStats::load(workspaces_directory).get_likely_environments(now());
+    likely_names.truncate(args.count);
+
+    let cookbooks = context.get_cookbooks();
+    let prepared_names = precook_from(workspaces_directory, &cookbooks, &likely_names);
+    for name in prepared_names {
+        context
+            .writer
+            .write_all(format!("Prepared {}\n", name).as_bytes())?;
+    }
+
+    if context.config.cookbooks.prefetch {
+        let mut prefetch_log = PrefetchLog::load(workspaces_directory);
+        prefetch_log.prune(now());
+        let prefetched_names = prefetch_from(
+            workspaces_directory,
+            &cookbooks,
+            &likely_names,
+            &mut prefetch_log,
+        );
+        prefetch_log.save(workspaces_directory)?;
+        for name in prefetched_names {
+            context
+                .writer
+                .write_all(format!("Prefetched {}\n", name).as_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        commands::precook::{precook_from, prefetch_from, PrefetchLog, PREFETCH_INTERVAL},
+        cookbook::CookbookTrait,
+        environment_metadata::EnvironmentMetadata,
+        test_utils::test_utilities::{context_object, CookbookMock, FakeContext},
+    };
+
+    #[rstest]
+    fn test_precook_skips_existing_environments(mut context_object: FakeContext) {
+        context_object.create_mock_environment("cooked");
+        let cookbook = CookbookMock::new("mock", &["cooked", "removed", "also-removed"]);
+        let listings = cookbook.listings.clone();
+        let cooked_recipes = cookbook.cooked_recipes.clone();
+        let cookbooks: Vec<Box<dyn CookbookTrait>> = vec![Box::new(cookbook)];
+
+        let prepared_names = precook_from(
+            &context_object.config.workspaces_directory,
+            &cookbooks,
+            &[
+                "cooked".to_string(),
+                "removed".to_string(),
+                "unknown".to_string(),
+                "also-removed".to_string(),
+            ],
+        );
+
+        assert_eq!(prepared_names, vec!["removed", "also-removed"]);
+        assert_eq!(
+            *cooked_recipes.lock().unwrap(),
+            vec!["removed", "also-removed"]
+        );
+        assert_eq!(*listings.lock().unwrap(), 1);
+        assert!(context_object
+            .get_environment(&Some("removed".to_string()))
+            .is_err());
+    }
+
+    #[rstest]
+    fn test_prefetch_is_rate_limited(mut context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        context_object.create_mock_environment("cooked");
+        context_object.create_mock_environment("manual");
+        EnvironmentMetadata {
+            cookbook: Some("mock".to_string()),
+            ..Default::default()
+        }
+        .save(&workspaces_directory, "cooked")
+        .unwrap();
+        let cookbook = CookbookMock::new("mock", &["cooked"]);
+        let prefetched_recipes = cookbook.prefetched_recipes.clone();
+        let cookbooks: Vec<Box<dyn CookbookTrait>> = vec![Box::new(cookbook)];
+        let names = ["cooked".to_string(), "manual".to_string()];
+        let mut prefetch_log = PrefetchLog::default();
+
+        let first_names =
+            prefetch_from(&workspaces_directory, &cookbooks, &names, &mut prefetch_log);
+        let second_names =
+            prefetch_from(&workspaces_directory, &cookbooks, &names, &mut prefetch_log);
+
+        assert_eq!(first_names, vec!["cooked"]);
+        assert!(second_names.is_empty());
+        assert_eq!(*prefetched_recipes.lock().unwrap(), vec!["cooked"]);
+    }
+
+    #[rstest]
+    fn test_failed_prefetches_are_tried_again(mut context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        context_object.create_mock_environment("cooked");
+        EnvironmentMetadata {
+            cookbook: Some("mock".to_string()),
+            ..Default::default()
+        }
+        .save(&workspaces_directory, "cooked")
+        .unwrap();
+        let cookbook = CookbookMock {
+            prefetch_error: Some("rate limited".to_string()),
+            ..CookbookMock::new("mock", &["cooked"])
+        };
+        let prefetched_recipes = cookbook.prefetched_recipes.clone();
+        let cookbooks: Vec<Box<dyn CookbookTrait>> = vec![Box::new(cookbook)];
+        let names = ["cooked".to_string()];
+        let mut prefetch_log = PrefetchLog::default();
+
+        let first_names =
+            prefetch_from(&workspaces_directory, &cookbooks, &names, &mut prefetch_log);
+        let second_names =
+            prefetch_from(&workspaces_directory, &cookbooks, &names, &mut prefetch_log);
+
+        assert!(first_names.is_empty());
+        assert!(second_names.is_empty());
+        assert_eq!(
+            *prefetched_recipes.lock().unwrap(),
+            vec!["cooked", "cooked"]
+        );
+        assert!(prefetch_log.last_prefetched.is_empty());
+    }
+
+    #[test]
+    fn test_prefetch_log_forgets_old_prefetches() {
+        let mut prefetch_log = PrefetchLog::default();
+        prefetch_log.record("git", "old", 1000);
+        prefetch_log.record("git", "recent", 1000 + PREFETCH_INTERVAL);
+
+        prefetch_log.prune(1000 + PREFETCH_INTERVAL + 1);
+
+        assert!(prefetch_log.is_due("git", "old", 1000 + PREFETCH_INTERVAL + 1));
+        assert!(!prefetch_log.is_due("git", "recent", 1000 + PREFETCH_INTERVAL + 1));
+        assert_eq!(
+            prefetch_log.last_prefetched.keys().collect::<Vec<_>>(),
+            vec!["git/recent"]
+        );
+    }
+}
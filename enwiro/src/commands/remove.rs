@@ -0,0 +1,228 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    config::ConfigurationValues,
+    cookbook::{uncook_environment, CookbookTrait},
+    environment_metadata::EnvironmentMetadata,
+    environments::Environment,
+    hooks::{HookEvent, Hooks},
+    trash::Trash,
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Remove an environment that was cooked from a recipe, which `enwiro undo` can restore"
+)]
+pub struct RemoveArgs {
+    pub environment_name: String,
+
+    /// Remove the environment even if it is locked
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Forgets removals that can no longer be undone, or all of them when
+/// emptying the trash, and lets the cookbooks clean up after the environments
+/// they removed. Returns a description of each environment that could not be
+/// cleaned up.
+pub fn discard_removed_environments(
+    config: &ConfigurationValues,
+    cookbooks: &[Box<dyn CookbookTrait>],
+    everything: bool,
+) -> Result<Vec<String>, io::Error> {
+    let mut failures = vec![];
+    for environment in Trash::new(config)?.discard(everything)? {
+        if let Err(error) = uncook_environment(cookbooks, &environment.metadata, &environment.name)
+        {
+            failures.push(format!(
+                "Could not clean up after {}: {}",
+                environment.name, error
+            ));
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Removes an environment after running the `pre-remove` hooks, recording it
+/// in the trash so that it can be restored. Cookbooks only clean up after it
+/// once that is no longer possible. Locked environments are only removed when
+/// forced.
+pub fn remove_environment(
+    config: &ConfigurationValues,
+    cookbooks: &[Box<dyn CookbookTrait>],
+    name: &str,
+    force: bool,
+) -> Result<(), io::Error> {
+    let workspaces_directory = &config.workspaces_directory;
+    let environment = Environment::get_one(workspaces_directory, name)?;
+    let metadata = EnvironmentMetadata::load_at(&environment.path);
+    if !force && metadata.locked {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "Environment {} is locked, unlock it or pass --force to remove it",
+                environment.name
+            ),
+        ));
+    }
+    Hooks::new(config)?.run(HookEvent::PreRemove, &environment)?;
+
+    Trash::new(config)?.remove_environments(
+        workspaces_directory,
+        std::slice::from_ref(&environment),
+        "remove",
+    )?;
+
+    for failure in discard_removed_environments(config, cookbooks, false)? {
+        tracing::warn!("{}", failure);
+    }
+
+    Ok(())
+}
+
+pub fn remove<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: RemoveArgs,
+) -> Result<(), io::Error> {
+    let cookbooks = context.get_cookbooks();
+
+    remove_environment(
+        &context.config,
+        &cookbooks,
+        &args.environment_name,
+        args.force,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs::create_dir_all, os::unix::fs::symlink, path::Path};
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::remove::{discard_removed_environments, remove, remove_environment, RemoveArgs},
+        cookbook::CookbookTrait,
+        environment_metadata::EnvironmentMetadata,
+        test_utils::test_utilities::{context_object, CookbookMock, FakeContext},
+    };
+
+    #[rstest]
+    fn test_remove_cooked_environment(mut context_object: FakeContext) {
+        let target_directory = temp_dir().join("enwiro_remove_test_target");
+        create_dir_all(&target_directory).unwrap();
+        let environment_path =
+            Path::new(&context_object.config.workspaces_directory).join("foobar");
+        symlink(&target_directory, &environment_path).unwrap();
+
+        remove(
+            &mut context_object,
+            RemoveArgs {
+                environment_name: "foobar".to_string(),
+                force: false,
+            },
+        )
+        .unwrap();
+
+        assert!(!environment_path.exists());
+        assert!(target_directory.exists());
+    }
+
+    #[rstest]
+    fn test_remove_refuses_real_directories(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+
+        let result = remove(
+            &mut context_object,
+            RemoveArgs {
+                environment_name: "foobar".to_string(),
+                force: false,
+            },
+        );
+
+        assert!(result.is_err());
+        assert!(Path::new(&context_object.config.workspaces_directory)
+            .join("foobar")
+            .exists());
+    }
+
+    #[rstest]
+    fn test_remove_refuses_locked_environments_unless_forced(mut context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let environment_path = Path::new(&workspaces_directory).join("foobar");
+        symlink(temp_dir(), &environment_path).unwrap();
+        EnvironmentMetadata {
+            locked: true,
+            ..Default::default()
+        }
+        .save(&workspaces_directory, "foobar")
+        .unwrap();
+        let args = |force: bool| RemoveArgs {
+            environment_name: "foobar".to_string(),
+            force,
+        };
+
+        assert!(remove(&mut context_object, args(false))
+            .unwrap_err()
+            .to_string()
+            .contains("is locked"));
+        assert!(environment_path.is_symlink());
+        remove(&mut context_object, args(true)).unwrap();
+        assert!(!environment_path.is_symlink());
+    }
+
+    #[rstest]
+    fn test_cookbook_cleans_up_once_removal_cannot_be_undone(context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        for name in ["foobar@mock", "by-hand"] {
+            symlink(temp_dir(), Path::new(&workspaces_directory).join(name)).unwrap();
+        }
+        EnvironmentMetadata {
+            cookbook: Some("mock".to_string()),
+            ..Default::default()
+        }
+        .save(&workspaces_directory, "foobar@mock")
+        .unwrap();
+        let cookbook = CookbookMock::new("mock", &[]);
+        let uncooked_recipes = cookbook.uncooked_recipes.clone();
+        let cookbooks: Vec<Box<dyn CookbookTrait>> = vec![Box::new(cookbook)];
+
+        remove_environment(&context_object.config, &cookbooks, "foobar@mock", false).unwrap();
+        remove_environment(&context_object.config, &cookbooks, "by-hand", false).unwrap();
+        // Until the trash is emptied, `enwiro undo` can restore it
+        assert!(uncooked_recipes.lock().unwrap().is_empty());
+
+        let failures =
+            discard_removed_environments(&context_object.config, &cookbooks, true).unwrap();
+
+        assert!(failures.is_empty());
+        assert_eq!(*uncooked_recipes.lock().unwrap(), vec!["foobar"]);
+    }
+
+    #[rstest]
+    fn test_cleanup_command_runs_instead_of_uncooking(context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let marker = Path::new(&workspaces_directory).join("cleaned-up");
+        symlink(temp_dir(), Path::new(&workspaces_directory).join("clone")).unwrap();
+        EnvironmentMetadata {
+            cookbook: Some("mock".to_string()),
+            cleanup_command: vec!["touch".to_string(), marker.to_string_lossy().to_string()],
+            ..Default::default()
+        }
+        .save(&workspaces_directory, "clone")
+        .unwrap();
+        let cookbook = CookbookMock::new("mock", &[]);
+        let uncooked_recipes = cookbook.uncooked_recipes.clone();
+        let cookbooks: Vec<Box<dyn CookbookTrait>> = vec![Box::new(cookbook)];
+
+        remove_environment(&context_object.config, &cookbooks, "clone", false).unwrap();
+        discard_removed_environments(&context_object.config, &cookbooks, true).unwrap();
+
+        assert!(marker.exists());
+        assert!(uncooked_recipes.lock().unwrap().is_empty());
+    }
+}
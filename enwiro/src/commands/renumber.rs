@@ -0,0 +1,59 @@
+use std::io::{self, Read, Write};
+
+use crate::{usage_stats::UsageStats, CommandContext};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Move the workspaces of the most used environments to their own low-numbered slots"
+)]
+pub struct RenumberArgs {}
+
+pub fn renumber<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    _args: RenumberArgs,
+) -> Result<(), io::Error> {
+    let environments = context.get_all_environments()?;
+    let ranked_environment_names = UsageStats::load(&context.config.workspaces_directory)
+        .rank_environments(environments.keys().map(String::as_str));
+
+    context
+        .adapter
+        .renumber_workspaces(&ranked_environment_names)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        commands::renumber::{renumber, RenumberArgs},
+        test_utils::test_utilities::{context_object, EnwiroAdapterMock, FakeContext},
+        usage_stats::UsageStats,
+    };
+
+    #[rstest]
+    fn test_renumber_passes_ranked_environments_to_adapter(mut context_object: FakeContext) {
+        let adapter = EnwiroAdapterMock::new("foobaz");
+        context_object.adapter = Box::new(adapter.clone());
+        for name in ["foobar", "baz", "unused"] {
+            context_object.create_mock_environment(name);
+        }
+        let mut usage_stats = UsageStats::default();
+        usage_stats.record_activation("baz");
+        usage_stats.record_activation("foobar");
+        usage_stats.record_activation("foobar");
+        usage_stats.record_activation("removed");
+        usage_stats
+            .save(&context_object.config.workspaces_directory)
+            .unwrap();
+
+        renumber(&mut context_object, RenumberArgs {}).unwrap();
+
+        assert_eq!(
+            *adapter.renumbered_workspaces.borrow(),
+            vec![vec!["foobar".to_string(), "baz".to_string()]]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::io::{self, Read, Write};
+
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    commands::{
+        activate::{activate, ActivateArgs},
+        list_all::{collect_entries, ListAllArgs},
+    },
+    error_codes::to_json,
+    usage_stats::UsageStats,
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Serve JSON requests from stdin, one per line, for editor integrations"
+)]
+pub struct RpcArgs {}
+
+#[derive(Deserialize)]
+pub struct RpcRequest {
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default)]
+    pub params: RpcParams,
+}
+
+#[derive(Deserialize, Default)]
+pub struct RpcParams {
+    pub name: Option<String>,
+}
+
+/// Reads a single line without buffering ahead, so that the reader stays
+/// usable by the command handlers in between requests.
+fn read_line<R: Read>(reader: &mut R) -> Result<Option<String>, io::Error> {
+    let mut line: Vec<u8> = vec![];
+    let mut byte = [0u8; 1];
+
+    loop {
+        match reader.read(&mut byte)? {
+            0 if line.is_empty() => return Ok(None),
+            0 => break,
+            _ if byte[0] == b'\n' => break,
+            _ => line.push(byte[0]),
+        }
+    }
+
+    Ok(Some(String::from_utf8_lossy(&line).to_string()))
+}
+
+fn required_name(params: RpcParams) -> Result<String, io::Error> {
+    params.name.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Missing required parameter \"name\"",
+        )
+    })
+}
+
+/// Answers a request. Without `allow_changes`, requests that would cook or
+/// activate environments are refused.
+pub fn handle_request<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    request: RpcRequest,
+    allow_changes: bool,
+) -> Result<Value, io::Error> {
+    match request.method.as_str() {
+        "list_environments" => {
+            let names: Vec<String> = context.get_all_environments()?.into_keys().collect();
+            Ok(json!(names))
+        }
+        "list" => {
+            // Lists the same names as `list-all`, so that colliding recipes
+            // can be activated by the name they are shown with
+            let mut environments: Vec<String> = vec![];
+            let mut recipes: Vec<Value> = vec![];
+            for entry in collect_entries(context, &ListAllArgs::default())? {
+                match entry.cookbook {
+                    Some(cookbook) => recipes.push(json!({
+                        "cookbook": cookbook,
+                        "name": entry.name,
+                        "description": entry.description,
+                        "category": entry.category,
+                    })),
+                    None => environments.push(entry.name),
+                }
+            }
+            Ok(json!({"environments": environments, "recipes": recipes}))
+        }
+        "show_path" => {
+            let name = Some(required_name(request.params)?);
+            let environment = match allow_changes {
+                true => context.get_or_cook_environment(&name)?,
+                false => context.get_environment(&name)?,
+            };
+            // JSON cannot hold paths that are not valid UTF-8
+            Ok(json!(environment.path.to_string_lossy()))
+        }
+        "current" => Ok(json!(context.adapter.get_active_environment_name()?)),
+        "stats" => Ok(json!(UsageStats::load(
+            &context.config.workspaces_directory
+        ))),
+        "activate" if !allow_changes => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Activating environments is not allowed in read-only mode",
+        )),
+        "activate" => {
+            let name = required_name(request.params)?;
+            activate(
+                context,
+                ActivateArgs {
+                    environment_name: Some(name),
+                    ..Default::default()
+                },
+            )?;
+            Ok(Value::Null)
+        }
+        method => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unknown method \"{}\"", method),
+        )),
+    }
+}
+
+pub fn rpc<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    _args: RpcArgs,
+) -> Result<(), io::Error> {
+    while let Some(line) = read_line(&mut context.reader)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<RpcRequest>(&line) {
+            Ok(request) => {
+                let id = request.id.clone();
+                match handle_request(context, request, true) {
+                    Ok(result) => json!({"id": id, "result": result}),
+                    Err(error) => json!({"id": id, "error": to_json(&error)}),
+                }
+            }
+            Err(error) => json!({"id": null, "error": {"message": error.to_string()}}),
+        };
+
+        context
+            .writer
+            .write_all(format!("{}\n", response).as_bytes())?;
+        context.writer.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use rstest::rstest;
+    use serde_json::Value;
+
+    use crate::{
+        commands::rpc::{rpc, RpcArgs},
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    fn run_requests(context_object: &mut FakeContext, requests: &str) -> Vec<Value> {
+        context_object.reader = Cursor::new(requests.as_bytes().to_vec());
+        rpc(context_object, RpcArgs {}).unwrap();
+
+        context_object
+            .get_output()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[rstest]
+    fn test_rpc_show_path(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+
+        let responses = run_requests(
+            &mut context_object,
+            "{\"id\": 1, \"method\": \"show_path\", \"params\": {\"name\": \"foobar\"}}\n",
+        );
+
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["id"], 1);
+        assert!(responses[0]["result"].as_str().unwrap().ends_with("foobar"));
+    }
+
+    #[rstest]
+    fn test_rpc_list(mut context_object: FakeContext) {
+        context_object.config.allowed_plugins = Some(vec![]);
+        context_object.create_mock_environment("foobar");
+
+        let responses = run_requests(&mut context_object, "{\"id\": 1, \"method\": \"list\"}\n");
+
+        assert_eq!(
+            responses[0]["result"]["environments"],
+            serde_json::json!(["foobar"])
+        );
+        assert!(responses[0]["result"]["recipes"].is_array());
+    }
+
+    #[rstest]
+    fn test_rpc_answers_every_request_in_order(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+
+        let responses = run_requests(
+            &mut context_object,
+            "{\"id\": 1, \"method\": \"list_environments\"}\n\
+             not json\n\
+             {\"id\": 3, \"method\": \"frobnicate\"}\n",
+        );
+
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["result"], serde_json::json!(["foobar"]));
+        assert!(responses[1]["error"]["message"].is_string());
+        assert_eq!(responses[2]["id"], 3);
+        assert!(responses[2]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("frobnicate"));
+    }
+}
@@ -0,0 +1,424 @@
+use std::{
+    env,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::{json, Value};
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::{
+    commands::rpc::{handle_request, RpcParams, RpcRequest},
+    CommandContext,
+};
+
+const DEFAULT_PORT: u16 = 7313;
+
+/// Length of the token generated when listening on a port without one.
+const GENERATED_TOKEN_LENGTH: usize = 32;
+
+/// Largest request body read, in bytes. Parameters are only ever a few names.
+const MAX_BODY_LENGTH: u64 = 64 * 1024;
+
+#[derive(clap::Args, Default)]
+#[command(
+    author,
+    version,
+    about = "Serve a JSON API over HTTP, for browser extensions, dashboards and other tools"
+)]
+pub struct ServeArgs {
+    /// Port to listen on, only reachable from this computer
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    pub port: u16,
+
+    /// Listen on this Unix socket instead of a port
+    #[arg(long)]
+    pub socket: Option<PathBuf>,
+
+    /// Require requests to send `Authorization: Bearer <token>`. Defaults to
+    /// the `ENWIRO_SERVE_TOKEN` variable, or to a random token printed on
+    /// start when listening on a port
+    #[arg(long)]
+    pub token: Option<String>,
+
+    /// Refuse requests that would cook or activate environments
+    #[arg(long)]
+    pub read_only: bool,
+}
+
+/// Headers deciding whether a request is answered.
+#[derive(Default)]
+struct RequestHeaders {
+    authorization: Option<String>,
+    host: Option<String>,
+    origin: Option<String>,
+}
+
+impl RequestHeaders {
+    fn of(request: &Request) -> Self {
+        let get = |name: &'static str| {
+            request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv(name))
+                .map(|header| header.value.to_string())
+        };
+
+        Self {
+            authorization: get("Authorization"),
+            host: get("Host"),
+            origin: get("Origin"),
+        }
+    }
+}
+
+/// Whether a host, with or without a port, is this computer.
+fn is_local_host(host: &str) -> bool {
+    let host = match host.strip_prefix('[') {
+        Some(ipv6_host) => ipv6_host.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// Whether a request may come from a web page that is not served by this
+/// computer, including through a DNS name rebound to it. Browser extensions,
+/// whose origins are not web pages, are allowed.
+fn is_cross_site(headers: &RequestHeaders) -> bool {
+    let foreign_host = headers
+        .host
+        .as_deref()
+        .is_some_and(|host| !is_local_host(host));
+    let foreign_origin = headers.origin.as_deref().is_some_and(|origin| {
+        match origin.split_once("://") {
+            Some(("http" | "https", host)) => !is_local_host(host),
+            Some(_) => false,
+            // Sandboxed pages and local files send `null`
+            None => true,
+        }
+    });
+
+    foreign_host || foreign_origin
+}
+
+/// Compares without returning early at the first differing byte, so the
+/// response time does not tell how much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Reads the body of a request, refusing bodies over `MAX_BODY_LENGTH`
+/// instead of reading them into memory.
+fn read_body<T: Read>(reader: T) -> Result<String, (u16, Value)> {
+    let mut body = String::new();
+    match reader.take(MAX_BODY_LENGTH + 1).read_to_string(&mut body) {
+        Ok(length) if length as u64 > MAX_BODY_LENGTH => Err((
+            413,
+            json!({"error": {"message": "Request body is too large"}}),
+        )),
+        Ok(_) => Ok(body),
+        Err(error) => Err((400, json!({"error": {"message": error.to_string()}}))),
+    }
+}
+
+/// Answers a request to `/api/<method>`, using the same methods as `enwiro
+/// rpc` and the JSON body as their parameters. Returns the HTTP status code
+/// and the response body.
+fn handle_http_request<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: &ServeArgs,
+    headers: &RequestHeaders,
+    url: &str,
+    body: &str,
+) -> (u16, Value) {
+    // Web pages cannot reach Unix sockets
+    if args.socket.is_none() && is_cross_site(headers) {
+        return (
+            403,
+            json!({"error": {"message": "Requests from other sites are refused"}}),
+        );
+    }
+    if let Some(token) = &args.token {
+        let expected = format!("Bearer {}", token);
+        let authorization = headers.authorization.as_deref().unwrap_or_default();
+        if !constant_time_eq(authorization.as_bytes(), expected.as_bytes()) {
+            return (
+                401,
+                json!({"error": {"message": "Invalid or missing token"}}),
+            );
+        }
+    }
+
+    let path = url.split('?').next().unwrap_or_default();
+    let method = match path.strip_prefix("/api/") {
+        Some(method) => method.to_string(),
+        None => return (404, json!({"error": {"message": "Not found"}})),
+    };
+    let params: RpcParams = match body.trim().is_empty() {
+        true => RpcParams::default(),
+        false => match serde_json::from_str(body) {
+            Ok(params) => params,
+            Err(error) => return (400, json!({"error": {"message": error.to_string()}})),
+        },
+    };
+
+    let request = RpcRequest {
+        id: Value::Null,
+        method,
+        params,
+    };
+    match handle_request(context, request, !args.read_only) {
+        Ok(result) => (200, json!({ "result": result })),
+        Err(error) => {
+            let status = match error.kind() {
+                io::ErrorKind::PermissionDenied => 403,
+                io::ErrorKind::NotFound => 404,
+                io::ErrorKind::InvalidInput => 400,
+                _ => 500,
+            };
+            (status, json!({"error": {"message": error.to_string()}}))
+        }
+    }
+}
+
+#[cfg(unix)]
+fn start_unix_server(socket_path: &Path) -> Result<Server, io::Error> {
+    use std::{fs, os::unix::fs::FileTypeExt};
+
+    // A socket left behind by a previous run would prevent listening
+    if fs::symlink_metadata(socket_path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        fs::remove_file(socket_path)?;
+    }
+
+    Server::http_unix(socket_path)
+        .map_err(|error| io::Error::other(format!("Could not start server: {}", error)))
+}
+
+#[cfg(not(unix))]
+fn start_unix_server(_socket_path: &Path) -> Result<Server, io::Error> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix sockets are not supported on this platform",
+    ))
+}
+
+fn start_server(args: &ServeArgs) -> Result<Server, io::Error> {
+    match &args.socket {
+        Some(socket_path) => start_unix_server(socket_path),
+        None => Server::http(("127.0.0.1", args.port))
+            .map_err(|error| io::Error::other(format!("Could not start server: {}", error))),
+    }
+}
+
+pub fn serve<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    mut args: ServeArgs,
+) -> Result<(), io::Error> {
+    args.token = args
+        .token
+        .or_else(|| env::var("ENWIRO_SERVE_TOKEN").ok())
+        .filter(|token| !token.is_empty());
+    let server = start_server(&args)?;
+    match &args.socket {
+        Some(socket_path) => eprintln!("Listening on {}", socket_path.display()),
+        None => {
+            eprintln!("Listening on http://127.0.0.1:{}", args.port);
+            // Any program on this computer can connect to the port
+            if args.token.is_none() {
+                let token: String = rand::thread_rng()
+                    .sample_iter(&Alphanumeric)
+                    .take(GENERATED_TOKEN_LENGTH)
+                    .map(char::from)
+                    .collect();
+                eprintln!("Token: {}", token);
+                args.token = Some(token);
+            }
+        }
+    }
+
+    for mut request in server.incoming_requests() {
+        let (status, response) = match read_body(request.as_reader()) {
+            Ok(body) => handle_http_request(
+                context,
+                &args,
+                &RequestHeaders::of(&request),
+                request.url(),
+                &body,
+            ),
+            Err(status_and_response) => status_and_response,
+        };
+
+        let response = Response::from_string(response.to_string())
+            .with_status_code(status)
+            .with_header(
+                Header::from_bytes("Content-Type", "application/json").expect("Invalid header"),
+            );
+        if let Err(error) = request.respond(response) {
+            tracing::warn!(%error, "Could not send response");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::{
+        commands::serve::{
+            constant_time_eq, handle_http_request, read_body, RequestHeaders, ServeArgs,
+            MAX_BODY_LENGTH,
+        },
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    fn authorized(authorization: &str) -> RequestHeaders {
+        RequestHeaders {
+            authorization: Some(authorization.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    fn test_serve_answers_api_requests(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+        let args = ServeArgs::default();
+
+        let (status, response) = handle_http_request(
+            &mut context_object,
+            &args,
+            &RequestHeaders::default(),
+            "/api/show_path",
+            "{\"name\": \"foobar\"}",
+        );
+        assert_eq!(status, 200);
+        assert!(response["result"].as_str().unwrap().ends_with("foobar"));
+
+        let (status, _) = handle_http_request(
+            &mut context_object,
+            &args,
+            &RequestHeaders::default(),
+            "/other",
+            "",
+        );
+        assert_eq!(status, 404);
+        let (status, _) = handle_http_request(
+            &mut context_object,
+            &args,
+            &RequestHeaders::default(),
+            "/api/show_path",
+            "{",
+        );
+        assert_eq!(status, 400);
+    }
+
+    #[rstest]
+    fn test_serve_requires_token(mut context_object: FakeContext) {
+        let args = ServeArgs {
+            token: Some("secret".to_string()),
+            ..Default::default()
+        };
+
+        let (status, _) = handle_http_request(
+            &mut context_object,
+            &args,
+            &RequestHeaders::default(),
+            "/api/stats",
+            "",
+        );
+        assert_eq!(status, 401);
+        let (status, _) = handle_http_request(
+            &mut context_object,
+            &args,
+            &authorized("Bearer wrong"),
+            "/api/stats",
+            "",
+        );
+        assert_eq!(status, 401);
+        let (status, _) = handle_http_request(
+            &mut context_object,
+            &args,
+            &authorized("Bearer secret"),
+            "/api/stats",
+            "",
+        );
+        assert_eq!(status, 200);
+    }
+
+    #[rstest]
+    #[case(b"Bearer secret", b"Bearer secret", true)]
+    #[case(b"Bearer secreT", b"Bearer secret", false)]
+    #[case(b"Bearer secre", b"Bearer secret", false)]
+    #[case(b"", b"Bearer secret", false)]
+    fn test_constant_time_eq(#[case] a: &[u8], #[case] b: &[u8], #[case] expected: bool) {
+        assert_eq!(constant_time_eq(a, b), expected);
+    }
+
+    #[rstest]
+    fn test_serve_refuses_large_bodies() {
+        let body = "x".repeat(MAX_BODY_LENGTH as usize);
+        assert_eq!(read_body(body.as_bytes()).unwrap(), body);
+
+        let (status, _) = read_body(format!("{}x", body).as_bytes()).unwrap_err();
+        assert_eq!(status, 413);
+    }
+
+    #[rstest]
+    fn test_serve_read_only(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+        let args = ServeArgs {
+            read_only: true,
+            ..Default::default()
+        };
+
+        let (status, _) = handle_http_request(
+            &mut context_object,
+            &args,
+            &RequestHeaders::default(),
+            "/api/activate",
+            "{\"name\": \"foobar\"}",
+        );
+        assert_eq!(status, 403);
+        let (status, _) = handle_http_request(
+            &mut context_object,
+            &args,
+            &RequestHeaders::default(),
+            "/api/list_environments",
+            "",
+        );
+        assert_eq!(status, 200);
+    }
+
+    #[rstest]
+    #[case(Some("127.0.0.1:7313"), None, 200)]
+    #[case(Some("localhost:7313"), Some("http://localhost:8080"), 200)]
+    #[case(Some("127.0.0.1:7313"), Some("moz-extension://abc"), 200)]
+    #[case(Some("evil.example:7313"), None, 403)]
+    #[case(Some("127.0.0.1:7313"), Some("https://evil.example"), 403)]
+    #[case(Some("127.0.0.1:7313"), Some("null"), 403)]
+    fn test_serve_refuses_other_sites(
+        mut context_object: FakeContext,
+        #[case] host: Option<&str>,
+        #[case] origin: Option<&str>,
+        #[case] expected_status: u16,
+    ) {
+        let headers = RequestHeaders {
+            host: host.map(str::to_string),
+            origin: origin.map(str::to_string),
+            ..Default::default()
+        };
+
+        let (status, _) = handle_http_request(
+            &mut context_object,
+            &ServeArgs::default(),
+            &headers,
+            "/api/stats",
+            "",
+        );
+
+        assert_eq!(status, expected_status);
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    env,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    commands::wrap::{wrap, WrapArgs},
+    CommandContext,
+};
+
+const DEFAULT_SHELL: &str = "/bin/sh";
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Start an interactive shell inside an environment"
+)]
+pub struct ShellArgs {
+    pub environment_name: Option<String>,
+
+    /// Load variables from `.enwiro/env` or `.env` in the environment
+    #[arg(long)]
+    pub load_env_file: bool,
+}
+
+fn get_shell(get_variable: impl Fn(&str) -> Option<String>) -> String {
+    get_variable("SHELL")
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| DEFAULT_SHELL.to_string())
+}
+
+pub fn shell<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    args: ShellArgs,
+) -> Result<(), io::Error> {
+    wrap(
+        context,
+        WrapArgs {
+            command_name: get_shell(|name| env::var(name).ok()),
+            environment_name: args.environment_name,
+            load_env_file: args.load_env_file,
+            child_args: vec![],
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::commands::shell::get_shell;
+
+    #[test]
+    fn test_shell_defaults_to_sh() {
+        assert_eq!(
+            get_shell(|_| Some("/usr/bin/fish".to_string())),
+            "/usr/bin/fish"
+        );
+        assert_eq!(get_shell(|_| Some("".to_string())), "/bin/sh");
+        assert_eq!(get_shell(|_| None), "/bin/sh");
+    }
+}
@@ -1,8 +1,11 @@
-use std::io::{self, Read, Write};
+use std::{
+    fs,
+    io::{self, Read, Write},
+};
 
-use crate::CommandContext;
+use crate::{platform::path_to_bytes, CommandContext};
 
-#[derive(clap::Args)]
+#[derive(clap::Args, Default)]
 #[command(
     author,
     version,
@@ -10,29 +13,42 @@ use crate::CommandContext;
 )]
 pub struct ShowPathArgs {
     pub environment_name: Option<String>,
+
+    /// Cook the environment if it does not exist yet
+    #[arg(long)]
+    pub cook: bool,
+
+    /// Do not print a newline after the path
+    #[arg(long)]
+    pub no_newline: bool,
+
+    /// Print the directory the environment points to, resolving symbolic links
+    #[arg(long)]
+    pub canonical: bool,
 }
 
 pub fn show_path<R: Read, W: Write>(
     context: &mut CommandContext<R, W>,
     args: ShowPathArgs,
 ) -> Result<(), io::Error> {
-    let selected_environment = context.get_or_cook_environment(&args.environment_name);
-
-    context
-        .writer
-        .write_all(
-            selected_environment
-                .expect("Could not identify active environment")
-                .path
-                .as_bytes(),
-        )
-        .unwrap();
+    let environment = match args.cook {
+        true => context.get_or_cook_environment(&args.environment_name)?,
+        false => context.get_environment(&args.environment_name)?,
+    };
+    let path = match args.canonical {
+        true => fs::canonicalize(&environment.path)?,
+        false => environment.path,
+    };
 
-    Ok(())
+    // Paths that are not valid UTF-8 are printed as they are, so that shells
+    // can still use them
+    context.writer.write_all(&path_to_bytes(&path))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{fs, os::unix::fs::symlink, path::Path};
+
     use rstest::rstest;
 
     use crate::{
@@ -47,11 +63,12 @@ mod tests {
             &mut context_object,
             ShowPathArgs {
                 environment_name: Some("foobar".to_string()),
+                ..Default::default()
             },
         )
         .unwrap();
 
-        assert_eq!(context_object.get_output().ends_with("foobar"), true);
+        assert!(context_object.get_output().ends_with("foobar"));
     }
 
     #[rstest]
@@ -62,6 +79,8 @@ mod tests {
             &mut context_object,
             ShowPathArgs {
                 environment_name: Some("non_existing_env".to_string()),
+                cook: true,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -77,6 +96,7 @@ mod tests {
             &mut context_object,
             ShowPathArgs {
                 environment_name: None,
+                ..Default::default()
             },
         )
         .unwrap();
@@ -89,10 +109,50 @@ mod tests {
             &mut context_object,
             ShowPathArgs {
                 environment_name: None,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert!(context_object.get_output().ends_with("foobaz"));
+    }
+
+    #[rstest]
+    fn test_show_path_does_not_cook_by_default(mut context_object: FakeContext) {
+        let result = show_path(
+            &mut context_object,
+            ShowPathArgs {
+                environment_name: Some("non_existing_env".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[rstest]
+    fn test_show_canonical_path(mut context_object: FakeContext) {
+        let workspaces_directory = Path::new(&context_object.config.workspaces_directory);
+        let target_directory = workspaces_directory.join(".target");
+        fs::create_dir(&target_directory).unwrap();
+        symlink(&target_directory, workspaces_directory.join("foobar")).unwrap();
+
+        show_path(
+            &mut context_object,
+            ShowPathArgs {
+                environment_name: Some("foobar".to_string()),
+                canonical: true,
+                ..Default::default()
             },
         )
         .unwrap();
 
-        assert_eq!(context_object.get_output().ends_with("foobaz"), true);
+        assert_eq!(
+            context_object.get_output(),
+            fs::canonicalize(target_directory)
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
     }
 }
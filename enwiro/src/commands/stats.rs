@@ -0,0 +1,396 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    io::{self, Read, Write},
+};
+
+use ratatui::{
+    backend::CrosstermBackend,
+    crossterm::{
+        event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    },
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, List, Paragraph},
+    Terminal,
+};
+
+use crate::{
+    commands::info::format_age,
+    environment_metadata::EnvironmentMetadata,
+    usage_stats::{now, UsageStats, DAY_IN_SECONDS},
+    CommandContext,
+};
+
+const HELP_TEXT: &str = "r: reload  q: quit";
+
+const TOP_ENVIRONMENTS_COUNT: usize = 10;
+
+/// Environments not activated for this many days are listed as abandoned.
+const ABANDONED_AFTER_DAYS: u64 = 30;
+
+const HEATMAP_WEEKS: u64 = 26;
+
+const HEATMAP_SHADES: [&str; 5] = ["· ", "░░", "▒▒", "▓▓", "██"];
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Show an overview of how environments are used"
+)]
+pub struct StatsArgs {}
+
+#[derive(Debug, PartialEq)]
+struct CookbookStats {
+    name: String,
+    environment_count: usize,
+    activation_count: u64,
+}
+
+#[derive(Debug, Default)]
+struct StatsSummary {
+    // Environments with the highest frecency and their activation count
+    top_environments: Vec<(String, u64)>,
+
+    // Activations of all environments, keyed by days since the Unix epoch
+    daily_activations: BTreeMap<u64, u64>,
+
+    cookbooks: Vec<CookbookStats>,
+
+    // Existing environments not used recently and when they were last
+    // activated, or None if they never were
+    abandoned_environments: Vec<(String, Option<u64>)>,
+}
+
+/// Gathers the numbers shown by the dashboard. Only environments that still
+/// exist are taken into account, except for the daily activations.
+fn summarize(
+    workspaces_directory: &str,
+    environment_names: &[String],
+    usage_stats: &UsageStats,
+    now: u64,
+) -> StatsSummary {
+    let mut summary = StatsSummary::default();
+    for stats in usage_stats.environments.values() {
+        for (day, count) in &stats.daily_activations {
+            *summary.daily_activations.entry(*day).or_default() += count;
+        }
+    }
+
+    let activation_count = |name: &str| {
+        usage_stats
+            .environments
+            .get(name)
+            .map_or(0, |stats| stats.activation_count)
+    };
+
+    let mut top_environments: Vec<&String> = environment_names
+        .iter()
+        .filter(|name| activation_count(name) > 0)
+        .collect();
+    top_environments.sort_by(|a, b| {
+        usage_stats
+            .frecency(b)
+            .partial_cmp(&usage_stats.frecency(a))
+            .unwrap_or(Ordering::Equal)
+            .then(a.cmp(b))
+    });
+    summary.top_environments = top_environments
+        .into_iter()
+        .take(TOP_ENVIRONMENTS_COUNT)
+        .map(|name| (name.clone(), activation_count(name)))
+        .collect();
+
+    let mut cookbooks: HashMap<String, CookbookStats> = HashMap::new();
+    for name in environment_names {
+        let cookbook_name = EnvironmentMetadata::load(workspaces_directory, name)
+            .cookbook
+            .unwrap_or_else(|| "unknown".to_string());
+        let cookbook = cookbooks
+            .entry(cookbook_name.clone())
+            .or_insert(CookbookStats {
+                name: cookbook_name,
+                environment_count: 0,
+                activation_count: 0,
+            });
+        cookbook.environment_count += 1;
+        cookbook.activation_count += activation_count(name);
+    }
+    summary.cookbooks = cookbooks.into_values().collect();
+    summary.cookbooks.sort_by(|a, b| {
+        b.activation_count
+            .cmp(&a.activation_count)
+            .then(a.name.cmp(&b.name))
+    });
+
+    let mut abandoned_environments: Vec<(String, Option<u64>)> = environment_names
+        .iter()
+        .map(|name| {
+            let last_activated = Some(usage_stats.last_activated(name)).filter(|time| *time > 0);
+            (name.clone(), last_activated)
+        })
+        .filter(|(_, last_activated)| {
+            last_activated.is_none_or(|time| {
+                now.saturating_sub(time) >= ABANDONED_AFTER_DAYS * DAY_IN_SECONDS
+            })
+        })
+        .collect();
+    abandoned_environments
+        .sort_by(|(a_name, a_time), (b_name, b_time)| a_time.cmp(b_time).then(a_name.cmp(b_name)));
+    summary.abandoned_environments = abandoned_environments;
+
+    summary
+}
+
+/// Draws activations per day like a calendar, with a row per weekday and a
+/// column per week, the current week being the last one. Darker cells mean
+/// more activations compared to the busiest day.
+fn render_heatmap(daily_activations: &BTreeMap<u64, u64>, today: u64, weeks: u64) -> Vec<String> {
+    // The Unix epoch was a Thursday
+    let weekday = (today + 3) % 7;
+    let first_day = (today - weekday).saturating_sub((weeks - 1) * 7);
+    let busiest_day = daily_activations
+        .range(first_day..=today)
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0);
+
+    WEEKDAYS
+        .iter()
+        .enumerate()
+        .map(|(row, weekday_name)| {
+            let cells: String = (0..weeks)
+                .map(|week| first_day + week * 7 + row as u64)
+                .take_while(|day| *day <= today)
+                .map(|day| {
+                    let count = daily_activations.get(&day).copied().unwrap_or(0);
+                    let shade = match count {
+                        0 => 0,
+                        _ => (count * 4).div_ceil(busiest_day).clamp(1, 4),
+                    };
+                    HEATMAP_SHADES[shade as usize]
+                })
+                .collect();
+            format!("{} {}", weekday_name, cells)
+        })
+        .collect()
+}
+
+fn draw_dashboard<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    summary: &StatsSummary,
+    now: u64,
+) -> Result<(), io::Error> {
+    let top_environments: Vec<String> = summary
+        .top_environments
+        .iter()
+        .map(|(name, count)| format!("{:>5}  {}", count, name))
+        .collect();
+    let cookbooks: Vec<String> = summary
+        .cookbooks
+        .iter()
+        .map(|cookbook| {
+            format!(
+                "{:>5}  {} ({} environments)",
+                cookbook.activation_count, cookbook.name, cookbook.environment_count
+            )
+        })
+        .collect();
+    let abandoned_environments: Vec<String> = summary
+        .abandoned_environments
+        .iter()
+        .map(|(name, last_activated)| {
+            let age = match last_activated {
+                Some(time) => format_age(now.saturating_sub(*time)),
+                None => "never activated".to_string(),
+            };
+            format!("{:<16}  {}", age, name)
+        })
+        .collect();
+    let heatmap = render_heatmap(
+        &summary.daily_activations,
+        now / DAY_IN_SECONDS,
+        HEATMAP_WEEKS,
+    )
+    .join("\n");
+
+    terminal.draw(|frame| {
+        let [top_area, heatmap_area, abandoned_area, help_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(WEEKDAYS.len() as u16 + 2),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [frecency_area, cookbooks_area] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(top_area);
+
+        frame.render_widget(
+            List::new(top_environments)
+                .block(Block::bordered().title("Top environments (activations)")),
+            frecency_area,
+        );
+        frame.render_widget(
+            List::new(cookbooks).block(Block::bordered().title("Activations per cookbook")),
+            cookbooks_area,
+        );
+        frame.render_widget(
+            Paragraph::new(heatmap).block(Block::bordered().title("Activations per day")),
+            heatmap_area,
+        );
+        frame.render_widget(
+            List::new(abandoned_environments).block(
+                Block::bordered().title(format!("Not activated in {} days", ABANDONED_AFTER_DAYS)),
+            ),
+            abandoned_area,
+        );
+        frame.render_widget(
+            Paragraph::new(HELP_TEXT).style(Style::new().add_modifier(Modifier::DIM)),
+            help_area,
+        );
+    })?;
+
+    Ok(())
+}
+
+pub fn stats<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    _args: StatsArgs,
+) -> Result<(), io::Error> {
+    let workspaces_directory = context.config.workspaces_directory.clone();
+    let load_summary = || -> Result<StatsSummary, io::Error> {
+        let environment_names: Vec<String> = context.get_all_environments()?.into_keys().collect();
+        let usage_stats = UsageStats::load(&workspaces_directory);
+
+        Ok(summarize(
+            &workspaces_directory,
+            &environment_names,
+            &usage_stats,
+            now(),
+        ))
+    };
+    let mut summary = load_summary()?;
+
+    // Drawn on stderr like the picker, leaving stdout alone
+    enable_raw_mode()?;
+    execute!(io::stderr(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stderr()))?;
+    let dashboard_result = (|| -> Result<(), io::Error> {
+        loop {
+            draw_dashboard(&mut terminal, &summary, now())?;
+
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match (key.code, key.modifiers.contains(KeyModifiers::CONTROL)) {
+                (KeyCode::Esc, _) | (KeyCode::Char('q'), _) | (KeyCode::Char('c'), true) => {
+                    return Ok(())
+                }
+                (KeyCode::Char('r'), _) => summary = load_summary()?,
+                _ => {}
+            }
+        }
+    })();
+    disable_raw_mode()?;
+    execute!(io::stderr(), LeaveAlternateScreen)?;
+
+    dashboard_result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        commands::stats::{render_heatmap, summarize, CookbookStats},
+        environment_metadata::EnvironmentMetadata,
+        test_utils::test_utilities::{context_object, FakeContext},
+        usage_stats::{now, EnvironmentStats, UsageStats, DAY_IN_SECONDS},
+    };
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_summarize(context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let now = now();
+        EnvironmentMetadata {
+            cookbook: Some("git".to_string()),
+            ..Default::default()
+        }
+        .save(&workspaces_directory, "daily")
+        .unwrap();
+
+        let mut usage_stats = UsageStats::default();
+        usage_stats.record_activation("daily");
+        usage_stats.record_activation("daily");
+        usage_stats.environments.insert(
+            "old".to_string(),
+            EnvironmentStats {
+                activation_count: 5,
+                last_activated: now - 60 * DAY_IN_SECONDS,
+                ..Default::default()
+            },
+        );
+        usage_stats.record_activation("removed");
+        let environment_names = ["daily", "old", "never"].map(String::from);
+
+        let summary = summarize(&workspaces_directory, &environment_names, &usage_stats, now);
+
+        assert_eq!(
+            summary.top_environments,
+            vec![("daily".to_string(), 2), ("old".to_string(), 5)]
+        );
+        assert_eq!(summary.daily_activations[&(now / DAY_IN_SECONDS)], 3);
+        assert_eq!(
+            summary.cookbooks,
+            vec![
+                CookbookStats {
+                    name: "unknown".to_string(),
+                    environment_count: 2,
+                    activation_count: 5,
+                },
+                CookbookStats {
+                    name: "git".to_string(),
+                    environment_count: 1,
+                    activation_count: 2,
+                },
+            ]
+        );
+        assert_eq!(
+            summary.abandoned_environments,
+            vec![
+                ("never".to_string(), None),
+                ("old".to_string(), Some(now - 60 * DAY_IN_SECONDS)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_heatmap() {
+        // 2024-01-03 was a Wednesday
+        let today = 19725;
+        let daily_activations = BTreeMap::from([(today, 4), (today - 1, 1), (today - 7, 2)]);
+
+        let heatmap = render_heatmap(&daily_activations, today, 2);
+
+        assert_eq!(
+            heatmap,
+            vec![
+                "Mon · · ",
+                "Tue · ░░",
+                "Wed ▒▒██",
+                "Thu · ",
+                "Fri · ",
+                "Sat · ",
+                "Sun · ",
+            ]
+        );
+    }
+}
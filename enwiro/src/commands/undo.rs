@@ -0,0 +1,61 @@
+use std::io::{self, Read, Write};
+
+use crate::{trash::Trash, CommandContext};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Restore the environments removed by the last remove operation"
+)]
+pub struct UndoArgs {}
+
+pub fn undo<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    _args: UndoArgs,
+) -> Result<(), io::Error> {
+    let restored_names = Trash::new(&context.config)?.undo()?;
+
+    for name in restored_names {
+        context
+            .writer
+            .write_all(format!("Restored {}\n", name).as_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, os::unix::fs::symlink, path::Path};
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::{
+            remove::{remove, RemoveArgs},
+            undo::{undo, UndoArgs},
+        },
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    fn test_undo_restores_removed_environment(mut context_object: FakeContext) {
+        let environment_path =
+            Path::new(&context_object.config.workspaces_directory).join("foobar");
+        symlink(temp_dir(), &environment_path).unwrap();
+        remove(
+            &mut context_object,
+            RemoveArgs {
+                environment_name: "foobar".to_string(),
+                force: false,
+            },
+        )
+        .unwrap();
+
+        undo(&mut context_object, UndoArgs {}).unwrap();
+
+        assert!(environment_path.is_symlink());
+        assert_eq!(context_object.get_output(), "Restored foobar\n");
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    collections::HashSet,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    cookbook::{list_recipes_or_skip, CookbookTrait},
+    notifier::NotificationLevel,
+    CommandContext,
+};
+
+#[derive(clap::Args)]
+#[command(
+    author,
+    version,
+    about = "Keep running and cook environments for workspaces created by hand once they are focused"
+)]
+pub struct WatchArgs {}
+
+/// Cooks the environment of a focused workspace that has none yet, if
+/// exactly one cookbook offers a recipe of that name. If several do, the
+/// user is told once per name, since there is no telling which one was meant.
+fn cook_focused_workspace<R: Read, W: Write>(
+    context: &CommandContext<R, W>,
+    cookbooks: Vec<Box<dyn CookbookTrait>>,
+    environment_name: &str,
+    offered_names: &mut HashSet<String>,
+) -> Result<(), io::Error> {
+    if environment_name.is_empty()
+        || context
+            .get_all_environments()?
+            .contains_key(environment_name)
+    {
+        return Ok(());
+    }
+
+    let mut matching_cookbooks: Vec<Box<dyn CookbookTrait>> = cookbooks
+        .into_iter()
+        .filter(|cookbook| {
+            list_recipes_or_skip(cookbook.as_ref())
+                .iter()
+                .any(|recipe| recipe.name == environment_name)
+        })
+        .collect();
+    match matching_cookbooks.len() {
+        0 => tracing::debug!(environment_name, "No recipe for focused workspace"),
+        1 => match context.cook_environment_from(matching_cookbooks, environment_name) {
+            Ok(environment) => context.notify(
+                NotificationLevel::Success,
+                &format!("Cooked {} for the focused workspace", environment.name),
+            ),
+            Err(error) => context.notify(
+                NotificationLevel::Error,
+                &format!("Could not cook {}: {}", environment_name, error),
+            ),
+        },
+        _ if offered_names.insert(environment_name.to_string()) => {
+            matching_cookbooks.sort_by(|a, b| a.name().cmp(b.name()));
+            let cookbook_names: Vec<&str> = matching_cookbooks
+                .iter()
+                .map(|cookbook| cookbook.name())
+                .collect();
+            context.notify(
+                NotificationLevel::Success,
+                &format!(
+                    "The focused workspace {} has no environment yet. It can be cooked by {}, using `enwiro activate`",
+                    environment_name,
+                    cookbook_names.join(", ")
+                ),
+            );
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub fn watch<R: Read, W: Write>(
+    context: &mut CommandContext<R, W>,
+    _args: WatchArgs,
+) -> Result<(), io::Error> {
+    let context = &*context;
+    let mut offered_names = HashSet::new();
+
+    context.adapter.watch_focus(&mut |environment_name| {
+        cook_focused_workspace(
+            context,
+            context.get_cookbooks(),
+            environment_name,
+            &mut offered_names,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::watch::{cook_focused_workspace, watch, WatchArgs},
+        cookbook::CookbookTrait,
+        notifier::NotificationLevel,
+        test_utils::test_utilities::{
+            context_object, CookbookMock, EnwiroAdapterMock, FakeContext, NotifierMock,
+        },
+    };
+
+    #[rstest]
+    fn test_focused_workspace_is_cooked_by_the_only_matching_recipe(context_object: FakeContext) {
+        let cookbook = CookbookMock::new("git", &["newproject", "other"]);
+        let cookbooks: Vec<Box<dyn CookbookTrait>> = vec![
+            Box::new(cookbook.clone()),
+            Box::new(CookbookMock::new("github", &["other"])),
+        ];
+
+        cook_focused_workspace(
+            &context_object,
+            cookbooks,
+            "newproject",
+            &mut HashSet::new(),
+        )
+        .unwrap();
+
+        assert_eq!(*cookbook.cooked_recipes.lock().unwrap(), vec!["newproject"]);
+        assert!(context_object
+            .get_all_environments()
+            .unwrap()
+            .contains_key("newproject"));
+    }
+
+    #[rstest]
+    fn test_ambiguous_workspaces_are_offered_once(mut context_object: FakeContext) {
+        let notifier = NotifierMock::default();
+        context_object.notifier = Box::new(notifier.clone());
+        let git = CookbookMock::new("git", &["newproject"]);
+        let github = CookbookMock::new("github", &["newproject"]);
+        let mut offered_names = HashSet::new();
+
+        for _ in 0..2 {
+            let cookbooks: Vec<Box<dyn CookbookTrait>> =
+                vec![Box::new(github.clone()), Box::new(git.clone())];
+            cook_focused_workspace(&context_object, cookbooks, "newproject", &mut offered_names)
+                .unwrap();
+        }
+
+        assert!(git.cooked_recipes.lock().unwrap().is_empty());
+        assert!(github.cooked_recipes.lock().unwrap().is_empty());
+        let notifications = notifier.notifications.borrow();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].0, NotificationLevel::Success);
+        assert!(notifications[0].1.contains("git, github"));
+    }
+
+    #[rstest]
+    fn test_watch_ignores_existing_environments_and_plain_workspaces(
+        mut context_object: FakeContext,
+    ) {
+        let notifier = NotifierMock::default();
+        context_object.notifier = Box::new(notifier.clone());
+        context_object.config.allowed_plugins = Some(vec![]);
+        context_object.create_mock_environment("enwiro");
+        context_object.adapter = Box::new(EnwiroAdapterMock {
+            focused_workspaces: vec!["enwiro".to_string(), "".to_string()],
+            ..EnwiroAdapterMock::new("enwiro")
+        });
+
+        watch(&mut context_object, WatchArgs {}).unwrap();
+
+        assert!(notifier.notifications.borrow().is_empty());
+    }
+}
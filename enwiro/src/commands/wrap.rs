@@ -1,10 +1,17 @@
-use crate::CommandContext;
+use crate::{environment_metadata::EnvironmentMetadata, environments::Environment, error_codes::ChildFailed, platform::get_exit_code, CommandContext};
 
 use std::{
-    env,
+    ffi::OsString,
+    fs,
     io::{self, Read, Write},
+    path::PathBuf,
     process::Command,
 };
+
+/// Files holding environment variables for commands run in an environment,
+/// relative to the environment. Only the first one found is loaded.
+const ENV_FILE_PATHS: &[&str] = &[".enwiro/env", ".env"];
+
 #[derive(clap::Args)]
 #[command(
     author,
@@ -15,8 +22,71 @@ pub struct WrapArgs {
     pub command_name: String,
     pub environment_name: Option<String>,
 
+    /// Load variables from `.enwiro/env` or `.env` in the environment
+    #[arg(long)]
+    pub load_env_file: bool,
+
+    /// Arguments passed to the command verbatim, given after `--`
     #[clap(allow_hyphen_values = true, num_args = 0.., last=true)]
-    child_args: Option<Vec<String>>,
+    pub child_args: Vec<String>,
+}
+
+/// Parses `KEY=value` lines, ignoring comments and an `export` prefix, and
+/// removing quotes around values.
+fn parse_env_file(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = [('"', '"'), ('\'', '\'')]
+                .iter()
+                .find_map(|(start, end)| value.strip_prefix(*start)?.strip_suffix(*end))
+                .unwrap_or(value);
+
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+/// Variables telling the command which environment it runs in, followed by
+/// the ones from the environment's env file if requested. Values are not
+/// necessarily valid UTF-8, like the path of the environment.
+pub fn get_environment_variables(
+    environment: &Environment,
+    load_env_file: bool,
+) -> Vec<(String, OsString)> {
+    let metadata = EnvironmentMetadata::load_at(&environment.path);
+    let mut variables = vec![
+        ("ENWIRO_ENV".to_string(), environment.name.clone().into()),
+        ("ENWIRO_ENV_PATH".to_string(), environment.path.clone().into()),
+    ];
+    if let Some(cookbook) = metadata.cookbook {
+        variables.push(("ENWIRO_COOKBOOK".to_string(), cookbook.into()));
+    }
+
+    if load_env_file {
+        let env_file_content = ENV_FILE_PATHS
+            .iter()
+            .find_map(|path| fs::read_to_string(environment.path.join(path)).ok());
+        if let Some(content) = env_file_content {
+            variables.extend(parse_env_file(&content).into_iter().map(|(key, value)| (key, value.into())));
+        }
+    }
+
+    variables
+}
+
+fn get_child_command(args: &WrapArgs, variables: &[(String, OsString)]) -> Command {
+    let mut command = Command::new(&args.command_name);
+    command.args(&args.child_args);
+    command.envs(variables.iter().map(|(key, value)| (key, value)));
+
+    command
 }
 
 pub fn wrap<R: Read, W: Write>(
@@ -24,42 +94,147 @@ pub fn wrap<R: Read, W: Write>(
     args: WrapArgs,
 ) -> Result<(), io::Error> {
     let selected_environment = context.get_or_cook_environment(&args.environment_name);
-    let environment_path: String = match selected_environment {
-        Ok(environment) => environment.path,
-        Err(error) => match error.kind() {
-            std::io::ErrorKind::NotFound => {
-                // shoudl be stderr write
-                context
-                    .writer
-                    .write_all(
-                        "No matching environment found. Falling back to home directory.\n"
-                            .as_bytes(),
-                    )
-                    .unwrap();
-
-                env::home_dir()
-                    .expect("Could not determine user home directory")
-                    .into_os_string()
-                    .into_string()
-                    .unwrap()
-            }
-            _ => panic!("Could not determine environment path: {}", error),
-        },
+    let mut variables = vec![];
+    let environment_path: PathBuf = match selected_environment {
+        Ok(environment) => {
+            variables = get_environment_variables(&environment, args.load_env_file);
+            environment.path
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            eprintln!("No matching environment found. Falling back to home directory.");
+
+            home::home_dir().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "Could not determine user home directory")
+            })?
+        }
+        Err(error) => {
+            return Err(io::Error::new(
+                error.kind(),
+                format!("Could not determine environment path: {}", error),
+            ))
+        }
     };
-    env::set_current_dir(environment_path).expect("Failed to change directory");
 
-    let mut child = Command::new(args.command_name)
-        .args(match args.child_args {
-            Some(x) => x.into_iter().map(|x| x.to_string()).collect(),
-            None => vec![]
-        })
+    let status = get_child_command(&args, &variables)
+        .current_dir(environment_path)
         .stdin(std::process::Stdio::inherit())
         .stdout(std::process::Stdio::inherit())
         .stderr(std::process::Stdio::inherit())
-        .spawn()
-        .expect("Failed to execute command");
+        .status()
+        .map_err(|error| {
+            io::Error::new(
+                error.kind(),
+                format!("Could not run {}: {}", args.command_name, error),
+            )
+        })?;
 
-    let _ = child.wait().expect("Command wasn't running");
+    if !status.success() {
+        return Err(io::Error::other(ChildFailed {
+            code: get_exit_code(status),
+        }));
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use rstest::rstest;
+
+    use std::fs;
+
+    use crate::{
+        commands::wrap::{
+            get_child_command, get_environment_variables, parse_env_file, wrap, WrapArgs,
+        },
+        error_codes::ChildFailed,
+        environment_metadata::EnvironmentMetadata,
+        environments::Environment,
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[derive(Parser)]
+    struct WrapCli {
+        #[command(flatten)]
+        args: WrapArgs,
+    }
+
+    #[rstest]
+    #[case(&["cargo", "--", "build", "--release"], &["build", "--release"])]
+    #[case(&["cargo", "foo", "--", "run", "--", "--name", "with space"], &["run", "--", "--name", "with space"])]
+    #[case(&["ls"], &[])]
+    fn test_child_arguments_are_forwarded_verbatim(
+        #[case] command_line: &[&str],
+        #[case] expected_args: &[&str],
+    ) {
+        let args = WrapCli::parse_from(["wrap"].iter().chain(command_line)).args;
+        let command = get_child_command(&args, &[]);
+
+        assert_eq!(command.get_program(), command_line[0]);
+        assert_eq!(command.get_args().collect::<Vec<_>>(), expected_args);
+    }
+
+    #[test]
+    fn test_parse_env_file() {
+        assert_eq!(
+            parse_env_file("# comment\nexport FOO=bar\nQUOTED=\"with space\"\n\nEMPTY=\nnot a variable\n"),
+            vec![
+                ("FOO".to_string(), "bar".to_string()),
+                ("QUOTED".to_string(), "with space".to_string()),
+                ("EMPTY".to_string(), "".to_string()),
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_environment_variables_are_passed_to_command(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foo");
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let environment = Environment::get_one(&workspaces_directory, "foo").unwrap();
+        fs::write(environment.path.join(".env"), "TOKEN=secret\n").unwrap();
+        EnvironmentMetadata {
+            cookbook: Some("git".to_string()),
+            ..Default::default()
+        }
+        .save(&workspaces_directory, "foo")
+        .unwrap();
+
+        let variables = get_environment_variables(&environment, true);
+        assert_eq!(
+            variables,
+            vec![
+                ("ENWIRO_ENV".to_string(), "foo".into()),
+                ("ENWIRO_ENV_PATH".to_string(), environment.path.clone().into()),
+                ("ENWIRO_COOKBOOK".to_string(), "git".into()),
+                ("TOKEN".to_string(), "secret".into()),
+            ]
+        );
+        assert_eq!(
+            get_environment_variables(&environment, false).len(),
+            3
+        );
+    }
+
+    #[rstest]
+    fn test_wrap_returns_exit_status_of_command(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foo");
+        let wrap_args = |command_name: &str, child_args: &[&str]| WrapArgs {
+            command_name: command_name.to_string(),
+            environment_name: Some("foo".to_string()),
+            load_env_file: false,
+            child_args: child_args.iter().map(|x| x.to_string()).collect(),
+        };
+
+        assert!(wrap(&mut context_object, wrap_args("sh", &["-c", "exit 0"])).is_ok());
+
+        let error = wrap(&mut context_object, wrap_args("sh", &["-c", "exit 3"])).unwrap_err();
+        let child_failed = error.get_ref().unwrap().downcast_ref::<ChildFailed>();
+        assert_eq!(child_failed.unwrap().code, 3);
+
+        let error = wrap(&mut context_object, wrap_args("enwiro-no-such-command", &[])).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Could not run enwiro-no-such-command"));
+    }
+}
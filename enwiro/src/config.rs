@@ -1,12 +1,182 @@
 use serde_derive::{Deserialize, Serialize};
-use std::env;
+use std::{collections::HashMap, env, fs, io, path::PathBuf};
 
 use crate::plugin::{get_plugins, PluginKind};
 
+const LOCAL_CONFIGURATION_PATH: &str = ".enwiro/config.toml";
+
+/// Keys that `.enwiro/config.toml` may set. Anything that makes enwiro run
+/// commands, such as plugins, hooks, notification commands or cookbook
+/// settings, is left out, so that a cloned repository cannot run code as soon
+/// as enwiro is used inside it.
+const LOCAL_CONFIGURATION_KEYS: [&str; 9] = [
+    "workspaces_directory",
+    "sort",
+    "groups",
+    "stale_after_days",
+    "cookbooks.disabled",
+    "cookbooks.priorities",
+    "cookbooks.collisions",
+    "cookbooks.prefetch",
+    "cookbooks.interleave_recipes",
+];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConfigurationValues {
     pub workspaces_directory: String,
     pub adapter: Option<String>,
+
+    /// How calls to the adapter are retried and timed out, so that a window
+    /// manager that is briefly unresponsive does not make activation fail
+    #[serde(default)]
+    pub adapter_calls: AdapterCallsConfiguration,
+
+    /// Plugin executables to use, for example `enwiro-cookbook-git`. All
+    /// discovered plugins are used if this is not set.
+    pub allowed_plugins: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub cookbooks: CookbooksConfiguration,
+
+    #[serde(default)]
+    pub notifications: NotificationsConfiguration,
+
+    /// Order of `enwiro list-all`, unless `--sort` is given
+    #[serde(default)]
+    pub sort: SortOrder,
+
+    /// Where removed environments are recorded so that `enwiro undo` can
+    /// restore them, `${XDG_DATA_HOME}/enwiro/trash` by default
+    pub trash_directory: Option<String>,
+
+    /// Where scripts run on lifecycle events such as `post-activate` are
+    /// looked up, `${XDG_CONFIG_HOME}/enwiro/hooks` by default
+    pub hooks_directory: Option<String>,
+
+    /// Named lists of environments, which `enwiro activate --group` activates
+    /// together
+    #[serde(default)]
+    pub groups: HashMap<String, Vec<String>>,
+
+    /// Days after which unused environments whose recipe is gone are
+    /// suggested for removal by `enwiro gc`, 30 by default
+    pub stale_after_days: Option<u64>,
+
+    /// Named sets of values overriding the ones above, selected using
+    /// `--profile` or `ENWIRO_PROFILE`.
+    #[serde(default)]
+    pub profiles: HashMap<String, toml::Table>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CookbooksConfiguration {
+    /// Names of cookbooks that should not be used, for example `git`.
+    pub disabled: Vec<String>,
+
+    /// Priority per cookbook name, overriding the priority suggested by the
+    /// cookbook itself. Cookbooks with a higher priority are used first.
+    pub priorities: HashMap<String, i32>,
+
+    /// Configuration per cookbook name, passed to the cookbook instead of it
+    /// reading its own configuration file.
+    pub settings: HashMap<String, serde_json::Value>,
+
+    /// What to do with recipes whose name is already used by an existing
+    /// environment or by a recipe of another cookbook.
+    pub collisions: CollisionPolicy,
+
+    /// Whether `enwiro precook` asks cookbooks that support it to prefetch
+    /// the recipes of environments that are likely to be activated next.
+    pub prefetch: bool,
+
+    /// Whether `enwiro list-all` lists the recipes of all cookbooks together
+    /// in the order given by `sort`, instead of one cookbook after the other.
+    pub interleave_recipes: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdapterCallsConfiguration {
+    /// How many times a failed or timed out query is retried
+    pub retries: u32,
+
+    /// Milliseconds to wait before the first retry, doubled for every
+    /// further one
+    pub retry_delay_ms: u64,
+
+    /// Seconds after which a call that did not finish is stopped and counts
+    /// as failed
+    pub timeout_seconds: u64,
+
+    /// Whether activating an environment is retried like queries are. Activating
+    /// an environment that is already active changes nothing, so this is on by
+    /// default
+    pub retry_activation: bool,
+}
+
+impl Default for AdapterCallsConfiguration {
+    fn default() -> Self {
+        Self {
+            retries: 2,
+            retry_delay_ms: 200,
+            timeout_seconds: 10,
+            retry_activation: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    /// Only list the entry that came first
+    #[default]
+    Hide,
+
+    /// List the recipe with its cookbook appended, for example `enwiro@github`
+    Suffix,
+
+    /// Fail instead of choosing between them
+    Error,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierBackend {
+    #[default]
+    Desktop,
+    Command,
+    Stderr,
+    None,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Pinned entries first, then the most used ones
+    #[default]
+    Frecency,
+
+    /// Alphabetically
+    Name,
+
+    /// The most recently activated ones first
+    Recent,
+
+    /// Grouped by cookbook, then alphabetically
+    Cookbook,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationsConfiguration {
+    pub backend: NotifierBackend,
+
+    /// Shell command run for each notification when using the `command` backend
+    pub command: Option<String>,
+
+    /// Only notify about errors
+    pub quiet: bool,
 }
 
 impl ::std::default::Default for ConfigurationValues {
@@ -14,14 +184,324 @@ impl ::std::default::Default for ConfigurationValues {
         let home_dir = env::home_dir().expect("User home directory not found");
         let default_workspaces_directory = home_dir.join(".enwiro_envs");
         let mut adapter: Option<String> = None;
-        let mut available_adapters = get_plugins(PluginKind::Adapter);
+        let mut available_adapters = get_plugins(PluginKind::Adapter, None);
         if available_adapters.len() == 1 {
             adapter = Some(available_adapters.drain().next().unwrap().name);
         }
 
         Self {
-            workspaces_directory: default_workspaces_directory.to_str().unwrap().to_string(),
+            // The configuration file cannot hold paths that are not valid UTF-8
+            workspaces_directory: default_workspaces_directory.to_string_lossy().to_string(),
             adapter,
+            adapter_calls: AdapterCallsConfiguration::default(),
+            allowed_plugins: None,
+            cookbooks: CookbooksConfiguration::default(),
+            notifications: NotificationsConfiguration::default(),
+            sort: SortOrder::default(),
+            trash_directory: None,
+            hooks_directory: None,
+            groups: HashMap::new(),
+            stale_after_days: None,
+            profiles: HashMap::new(),
         }
     }
 }
+
+fn get_home_directory() -> Option<String> {
+    Some(home::home_dir()?.to_str()?.to_string())
+}
+
+/// Default values of the XDG base directory variables, used when they are not set.
+fn get_xdg_default(name: &str) -> Option<&'static str> {
+    match name {
+        "XDG_CONFIG_HOME" => Some("~/.config"),
+        "XDG_DATA_HOME" => Some("~/.local/share"),
+        "XDG_STATE_HOME" => Some("~/.local/state"),
+        "XDG_CACHE_HOME" => Some("~/.cache"),
+        _ => None,
+    }
+}
+
+/// Expands `~` and environment variables such as `$HOME` or `${XDG_DATA_HOME}`
+/// in a configured path.
+pub fn expand_path(path: &str) -> Result<String, io::Error> {
+    let expanded =
+        shellexpand::full_with_context(path, get_home_directory, |name| match env::var(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => match get_xdg_default(name) {
+                Some(default) => Ok(Some(shellexpand::tilde(default).to_string())),
+                None => Err(error),
+            },
+        })
+        .map_err(io::Error::other)?;
+
+    Ok(expanded.to_string())
+}
+
+/// Recursively merges `overlay` into `base`. Tables present in both are merged,
+/// any other value in `overlay` replaces the one in `base`.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Finds `.enwiro/config.toml` in the current directory or its closest parent
+/// containing one.
+pub fn find_local_configuration() -> Option<PathBuf> {
+    let current_directory = env::current_dir().ok()?;
+
+    current_directory
+        .ancestors()
+        .map(|directory| directory.join(LOCAL_CONFIGURATION_PATH))
+        .find(|path| path.is_file())
+}
+
+pub fn merge_configuration(
+    config: ConfigurationValues,
+    overlay: toml::Table,
+) -> Result<ConfigurationValues, io::Error> {
+    let mut merged = toml::Table::try_from(config).map_err(io::Error::other)?;
+    merge_tables(&mut merged, overlay);
+
+    merged.try_into().map_err(io::Error::other)
+}
+
+/// Finds a key that is not allowed in a local configuration, such as
+/// `adapter` or `cookbooks.settings`.
+fn find_disallowed_local_key(table: &toml::Table, prefix: &str) -> Option<String> {
+    table.iter().find_map(|(key, value)| {
+        let path = format!("{}{}", prefix, key);
+        if LOCAL_CONFIGURATION_KEYS.contains(&path.as_str()) {
+            return None;
+        }
+        let nested_prefix = format!("{}.", path);
+        match value {
+            toml::Value::Table(nested)
+                if LOCAL_CONFIGURATION_KEYS
+                    .iter()
+                    .any(|allowed| allowed.starts_with(&nested_prefix)) =>
+            {
+                find_disallowed_local_key(nested, &nested_prefix)
+            }
+            _ => Some(path),
+        }
+    })
+}
+
+fn merge_local_configuration(
+    config: ConfigurationValues,
+    local_configuration: &str,
+) -> Result<ConfigurationValues, io::Error> {
+    let overlay: toml::Table = toml::from_str(local_configuration).map_err(io::Error::other)?;
+    if let Some(key) = find_disallowed_local_key(&overlay, "") {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} can only be set in the user configuration file. Allowed keys: {}",
+                key,
+                LOCAL_CONFIGURATION_KEYS.join(", ")
+            ),
+        ));
+    }
+
+    merge_configuration(config, overlay)
+}
+
+fn apply_profile(
+    mut config: ConfigurationValues,
+    profile_name: &str,
+) -> Result<ConfigurationValues, io::Error> {
+    let profile = config.profiles.remove(profile_name).ok_or_else(|| {
+        let mut available_profiles: Vec<&String> = config.profiles.keys().collect();
+        available_profiles.sort();
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Profile \"{}\" is not defined. Available profiles: {:?}",
+                profile_name, available_profiles
+            ),
+        )
+    })?;
+
+    merge_configuration(config, profile)
+}
+
+/// Overrides configuration values with `ENWIRO_*` environment variables.
+fn apply_environment_overrides(
+    config: &mut ConfigurationValues,
+    get_variable: impl Fn(&str) -> Option<String>,
+) {
+    if let Some(workspaces_directory) = get_variable("ENWIRO_WORKSPACES_DIRECTORY") {
+        config.workspaces_directory = workspaces_directory;
+    }
+    if let Some(adapter) = get_variable("ENWIRO_ADAPTER") {
+        config.adapter = Some(adapter).filter(|adapter| !adapter.is_empty());
+    }
+    if let Some(allowed_plugins) = get_variable("ENWIRO_ALLOWED_PLUGINS") {
+        config.allowed_plugins = Some(
+            allowed_plugins
+                .split(',')
+                .map(|plugin| plugin.trim().to_string())
+                .filter(|plugin| !plugin.is_empty())
+                .collect(),
+        );
+    }
+}
+
+/// Loads the configuration file, merges the closest `.enwiro/config.toml` and
+/// the selected profile on top of it and finally applies overrides from
+/// environment variables.
+pub fn try_load_configuration(profile: Option<String>) -> Result<ConfigurationValues, io::Error> {
+    let mut config: ConfigurationValues = confy::load("enwiro", "enwiro")
+        .map_err(|x| io::Error::other(format!("Could not load configuration: {:?}", x)))?;
+
+    if let Some(local_configuration_path) = find_local_configuration() {
+        config = fs::read_to_string(&local_configuration_path)
+            .and_then(|content| merge_local_configuration(config, &content))
+            .map_err(|x| {
+                io::Error::other(format!(
+                    "Could not load configuration from {}: {}",
+                    local_configuration_path.display(),
+                    x
+                ))
+            })?;
+    }
+
+    let profile = profile.or_else(|| env::var("ENWIRO_PROFILE").ok());
+    if let Some(profile) = profile.filter(|profile| !profile.is_empty()) {
+        config = apply_profile(config, &profile)
+            .map_err(|x| io::Error::other(format!("Could not load configuration: {}", x)))?;
+    }
+    apply_environment_overrides(&mut config, |name| env::var(name).ok());
+
+    config.workspaces_directory = expand_path(&config.workspaces_directory)
+        .map_err(|x| io::Error::other(format!("Could not expand workspaces_directory: {}", x)))?;
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, env};
+
+    use crate::config::{
+        apply_environment_overrides, apply_profile, expand_path, merge_configuration,
+        merge_local_configuration, ConfigurationValues,
+    };
+
+    fn base_configuration() -> ConfigurationValues {
+        let mut config = ConfigurationValues {
+            workspaces_directory: "/home/user/.enwiro_envs".to_string(),
+            adapter: Some("i3wm".to_string()),
+            ..Default::default()
+        };
+        config.cookbooks.disabled = vec!["chezmoi".to_string()];
+        config.cookbooks.priorities = HashMap::from([("git".to_string(), 10)]);
+
+        config
+    }
+
+    #[test]
+    fn test_local_configuration_is_merged_on_top() {
+        let config = merge_local_configuration(
+            base_configuration(),
+            "workspaces_directory = \"/work/envs\"\n[cookbooks.priorities]\ngithub = 20\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.workspaces_directory, "/work/envs");
+        assert_eq!(config.adapter, Some("i3wm".to_string()));
+        assert_eq!(config.cookbooks.disabled, vec!["chezmoi".to_string()]);
+        assert_eq!(
+            config.cookbooks.priorities,
+            HashMap::from([("git".to_string(), 10), ("github".to_string(), 20)])
+        );
+    }
+
+    #[test]
+    fn test_local_configuration_cannot_run_commands() {
+        for local_configuration in [
+            "adapter = \"evil\"\n",
+            "allowed_plugins = [\"evil\"]\n",
+            "hooks_directory = \"./hooks\"\n",
+            "[notifications]\nbackend = \"command\"\ncommand = \"evil\"\n",
+            "[cookbooks.settings.git]\ntoken_cmd = \"evil\"\n",
+            "[profiles.work]\nadapter = \"evil\"\n",
+        ] {
+            let error =
+                merge_local_configuration(base_configuration(), local_configuration).unwrap_err();
+
+            assert_eq!(error.kind(), std::io::ErrorKind::PermissionDenied);
+        }
+    }
+
+    #[test]
+    fn test_environment_variables_override_configuration() {
+        let mut config = base_configuration();
+
+        apply_environment_overrides(&mut config, |name| match name {
+            "ENWIRO_WORKSPACES_DIRECTORY" => Some("/tmp/envs".to_string()),
+            "ENWIRO_ADAPTER" => Some("".to_string()),
+            "ENWIRO_ALLOWED_PLUGINS" => {
+                Some("enwiro-cookbook-git, enwiro-adapter-sway".to_string())
+            }
+            _ => None,
+        });
+
+        assert_eq!(config.workspaces_directory, "/tmp/envs");
+        assert_eq!(config.adapter, None);
+        assert_eq!(
+            config.allowed_plugins,
+            Some(vec![
+                "enwiro-cookbook-git".to_string(),
+                "enwiro-adapter-sway".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_profile_overrides_configuration() {
+        let config = merge_configuration(
+            base_configuration(),
+            toml::from_str(
+                "[profiles.work]\nworkspaces_directory = \"/work/envs\"\n\
+                 [profiles.work.cookbooks]\ndisabled = [\"git\"]\n",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let work_config = apply_profile(config, "work").unwrap();
+        assert_eq!(work_config.workspaces_directory, "/work/envs");
+        assert_eq!(work_config.adapter, Some("i3wm".to_string()));
+        assert_eq!(work_config.cookbooks.disabled, vec!["git".to_string()]);
+
+        assert!(apply_profile(base_configuration(), "personal").is_err());
+    }
+
+    #[test]
+    fn test_expand_path() {
+        let home = home::home_dir().unwrap();
+        let home = home.to_str().unwrap();
+
+        assert_eq!(expand_path("~/envs").unwrap(), format!("{}/envs", home));
+        assert_eq!(expand_path("$HOME/envs").unwrap(), format!("{}/envs", home));
+        assert_eq!(
+            expand_path("${XDG_DATA_HOME}/enwiro").unwrap(),
+            match env::var("XDG_DATA_HOME") {
+                Ok(data_home) => format!("{}/enwiro", data_home),
+                Err(_) => format!("{}/.local/share/enwiro", home),
+            }
+        );
+        assert_eq!(expand_path("/absolute/path").unwrap(), "/absolute/path");
+        assert!(expand_path("$ENWIRO_SURELY_UNDEFINED_VARIABLE/envs").is_err());
+    }
+}
@@ -0,0 +1,366 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{
+    config::{
+        expand_path, find_local_configuration, merge_configuration, try_load_configuration,
+        ConfigurationValues,
+    },
+    cookbook::get_builtin_cookbooks,
+    plugin::{get_plugins, PluginKind},
+};
+
+const CONFIGURATION_KEYS: &[&str] = &[
+    "workspaces_directory",
+    "adapter",
+    "adapter_calls",
+    "allowed_plugins",
+    "cookbooks",
+    "notifications",
+    "sort",
+    "trash_directory",
+    "hooks_directory",
+    "groups",
+    "stale_after_days",
+    "profiles",
+];
+const NESTED_CONFIGURATION_KEYS: &[(&str, &[&str])] = &[
+    (
+        "adapter_calls",
+        &[
+            "retries",
+            "retry_delay_ms",
+            "timeout_seconds",
+            "retry_activation",
+        ],
+    ),
+    (
+        "cookbooks",
+        &[
+            "disabled",
+            "priorities",
+            "settings",
+            "collisions",
+            "prefetch",
+            "interleave_recipes",
+        ],
+    ),
+    ("notifications", &["backend", "command", "quiet"]),
+];
+
+/// Finds the candidate closest to a misspelled name, if any is close enough.
+fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = name.len().div_ceil(3).max(2);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (strsim::damerau_levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+fn format_unknown(description: &str, name: &str, candidates: &[&str]) -> String {
+    match suggest(name, candidates.iter().copied()) {
+        Some(suggestion) => format!(
+            "{} \"{}\", did you mean \"{}\"?",
+            description, name, suggestion
+        ),
+        None => format!("{} \"{}\"", description, name),
+    }
+}
+
+fn find_unknown_keys(table: &toml::Table, prefix: &str, is_profile: bool) -> Vec<String> {
+    let mut problems = vec![];
+    let known_keys: Vec<&str> = CONFIGURATION_KEYS
+        .iter()
+        .copied()
+        .filter(|key| !is_profile || *key != "profiles")
+        .collect();
+
+    for (key, value) in table {
+        if !known_keys.contains(&key.as_str()) {
+            problems.push(match suggest(key, known_keys.iter().copied()) {
+                Some(suggestion) => format!(
+                    "Unknown key \"{}{}\", did you mean \"{}{}\"?",
+                    prefix, key, prefix, suggestion
+                ),
+                None => format!("Unknown key \"{}{}\"", prefix, key),
+            });
+            continue;
+        }
+
+        let nested_keys = NESTED_CONFIGURATION_KEYS
+            .iter()
+            .find(|(section, _)| section == key)
+            .map(|(_, keys)| *keys);
+        match (key.as_str(), value) {
+            (section, toml::Value::Table(nested_table)) if nested_keys.is_some() => {
+                let nested_keys = nested_keys.unwrap_or_default();
+                for key in nested_table.keys() {
+                    if !nested_keys.contains(&key.as_str()) {
+                        problems.push(format_unknown(
+                            &format!("Unknown key in {}{}:", prefix, section),
+                            key,
+                            nested_keys,
+                        ));
+                    }
+                }
+            }
+            ("profiles", toml::Value::Table(profiles)) => {
+                for (name, profile) in profiles {
+                    if let toml::Value::Table(profile) = profile {
+                        problems.extend(find_unknown_keys(
+                            profile,
+                            &format!("profiles.{}.", name),
+                            true,
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    problems
+}
+
+/// Checks that the values in a configuration file have the expected types,
+/// including the values of each profile.
+fn find_invalid_values(table: &toml::Table) -> Vec<String> {
+    let mut problems = vec![];
+
+    if let Err(error) = merge_configuration(ConfigurationValues::default(), table.clone()) {
+        problems.push(format!(
+            "Invalid value: {}",
+            error.to_string().trim().replace('\n', " ")
+        ));
+    }
+    if let Some(toml::Value::Table(profiles)) = table.get("profiles") {
+        for (name, profile) in profiles {
+            let result = match profile {
+                toml::Value::Table(profile) => {
+                    merge_configuration(ConfigurationValues::default(), profile.clone())
+                        .map(|_| ())
+                        .map_err(|x| x.to_string())
+                }
+                _ => Err("expected a table".to_string()),
+            };
+            if let Err(error) = result {
+                problems.push(format!(
+                    "Invalid value in profile \"{}\": {}",
+                    name,
+                    error.trim().replace('\n', " ")
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+fn validate_file(path: &Path) -> Vec<String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return vec![],
+    };
+
+    let problems = match toml::from_str::<toml::Table>(&content) {
+        Ok(table) => {
+            let mut problems = find_unknown_keys(&table, "", false);
+            problems.extend(find_invalid_values(&table));
+            problems
+        }
+        Err(error) => vec![format!("Invalid TOML: {}", error.message())],
+    };
+
+    problems
+        .into_iter()
+        .map(|problem| format!("{}: {}", path.display(), problem))
+        .collect()
+}
+
+fn validate_paths(config: &ConfigurationValues) -> Vec<String> {
+    let workspaces_directory = match expand_path(&config.workspaces_directory) {
+        Ok(path) => path,
+        Err(error) => {
+            return vec![format!(
+                "workspaces_directory \"{}\" cannot be expanded: {}",
+                config.workspaces_directory, error
+            )]
+        }
+    };
+    let path = Path::new(&workspaces_directory);
+
+    if !path.is_absolute() {
+        return vec![format!(
+            "workspaces_directory \"{}\" is not an absolute path",
+            workspaces_directory
+        )];
+    }
+    if path.exists() && !path.is_dir() {
+        return vec![format!(
+            "workspaces_directory \"{}\" is not a directory",
+            workspaces_directory
+        )];
+    }
+    if !path.exists() && !path.parent().is_some_and(|parent| parent.is_dir()) {
+        return vec![format!(
+            "workspaces_directory \"{}\" does not exist and cannot be created because its parent directory is missing",
+            workspaces_directory
+        )];
+    }
+
+    vec![]
+}
+
+/// Checks that the adapter, cookbooks and plugins named in the configuration
+/// are actually available.
+fn validate_references(
+    config: &ConfigurationValues,
+    adapters: &[&str],
+    cookbooks: &[&str],
+    executables: &[&str],
+) -> Vec<String> {
+    let mut problems = vec![];
+
+    if let Some(adapter) = &config.adapter {
+        if !adapters.contains(&adapter.as_str()) {
+            problems.push(format_unknown("Adapter not found:", adapter, adapters));
+        }
+    }
+
+    let cookbooks_config = &config.cookbooks;
+    let mut referenced_cookbooks: Vec<&String> = cookbooks_config
+        .disabled
+        .iter()
+        .chain(cookbooks_config.priorities.keys())
+        .chain(cookbooks_config.settings.keys())
+        .collect();
+    referenced_cookbooks.sort();
+    referenced_cookbooks.dedup();
+    for cookbook in referenced_cookbooks {
+        if !cookbooks.contains(&cookbook.as_str()) {
+            problems.push(format_unknown("Cookbook not found:", cookbook, cookbooks));
+        }
+    }
+
+    for plugin in config.allowed_plugins.iter().flatten() {
+        if !executables.contains(&plugin.as_str()) {
+            problems.push(format_unknown(
+                "Plugin in allowed_plugins not found:",
+                plugin,
+                executables,
+            ));
+        }
+    }
+
+    problems
+}
+
+/// Validates the configuration file, the local configuration and the
+/// resulting configuration, returning a description of each problem found.
+pub fn validate_configuration(profile: Option<String>) -> Vec<String> {
+    let mut problems: Vec<String> = confy::get_configuration_file_path("enwiro", "enwiro")
+        .ok()
+        .into_iter()
+        .chain(find_local_configuration())
+        .flat_map(|path| validate_file(&path))
+        .collect();
+
+    let config = match try_load_configuration(profile) {
+        Ok(config) => config,
+        Err(error) => {
+            if problems.is_empty() {
+                problems.push(error.to_string());
+            }
+            return problems;
+        }
+    };
+
+    let adapter_plugins = get_plugins(PluginKind::Adapter, config.allowed_plugins.as_deref());
+    let cookbook_plugins = get_plugins(PluginKind::Cookbook, config.allowed_plugins.as_deref());
+    let builtin_cookbooks = get_builtin_cookbooks(&HashMap::new());
+    let all_plugins: Vec<_> = [PluginKind::Adapter, PluginKind::Cookbook, PluginKind::Gear]
+        .into_iter()
+        .flat_map(|kind| get_plugins(kind, None))
+        .collect();
+
+    let adapters: Vec<&str> = adapter_plugins.iter().map(|x| x.name.as_str()).collect();
+    let cookbooks: Vec<&str> = cookbook_plugins
+        .iter()
+        .map(|x| x.name.as_str())
+        .chain(builtin_cookbooks.iter().map(|x| x.name()))
+        .collect();
+    let executables: Vec<&str> = all_plugins
+        .iter()
+        .filter_map(|x| Path::new(&x.executable).file_name()?.to_str())
+        .collect();
+
+    problems.extend(validate_paths(&config));
+    problems.extend(validate_references(
+        &config,
+        &adapters,
+        &cookbooks,
+        &executables,
+    ));
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ConfigurationValues;
+    use crate::config_validation::{find_invalid_values, find_unknown_keys, validate_references};
+
+    #[test]
+    fn test_unknown_keys_are_reported_with_suggestions() {
+        let table: toml::Table = toml::from_str(
+            "workspace_directory = \"~/envs\"\n\
+             [cookbooks]\npriority = { git = 1 }\n\
+             [profiles.work]\nadaptor = \"sway\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            find_unknown_keys(&table, "", false),
+            vec![
+                "Unknown key in cookbooks: \"priority\", did you mean \"priorities\"?",
+                "Unknown key \"profiles.work.adaptor\", did you mean \"profiles.work.adapter\"?",
+                "Unknown key \"workspace_directory\", did you mean \"workspaces_directory\"?",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_values_with_wrong_types_are_reported() {
+        let table: toml::Table =
+            toml::from_str("[cookbooks.priorities]\ngit = \"high\"\n").unwrap();
+
+        assert_eq!(find_invalid_values(&table).len(), 1);
+        assert!(find_invalid_values(&toml::Table::new()).is_empty());
+    }
+
+    #[test]
+    fn test_missing_adapters_and_cookbooks_are_reported() {
+        let mut config = ConfigurationValues {
+            workspaces_directory: "/tmp".to_string(),
+            adapter: Some("i3".to_string()),
+            allowed_plugins: Some(vec!["enwiro-cookbook-git".to_string()]),
+            ..Default::default()
+        };
+        config.cookbooks.disabled = vec!["gti".to_string(), "chezmoi".to_string()];
+
+        assert_eq!(
+            validate_references(
+                &config,
+                &["i3wm"],
+                &["git", "chezmoi"],
+                &["enwiro-cookbook-git"]
+            ),
+            vec![
+                "Adapter not found: \"i3\", did you mean \"i3wm\"?",
+                "Cookbook not found: \"gti\", did you mean \"git\"?"
+            ]
+        );
+    }
+}
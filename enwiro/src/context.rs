@@ -1,36 +1,76 @@
 use crate::{
     commands::adapter::{EnwiroAdapterExternal, EnwiroAdapterNone, EnwiroAdapterTrait},
-    config::ConfigurationValues,
-    environments::Environment, plugin::{get_plugins, PluginKind}, client::CookbookClient,
+    collisions::split_cookbook_suffix,
+    config::{CollisionPolicy, ConfigurationValues},
+    environments::{encode_environment_name, normalize_environment_name, Environment},
+    hooks::{HookEvent, Hooks}, plugin::{get_plugins, PluginKind}, client::CookbookClient,
+    cookbook::{get_builtin_cookbooks, CookbookTrait},
+    notifier::{get_notifier, NotificationLevel, Notifier},
+    error_codes::{cook_error, ErrorCode},
+    platform::replace_link,
+    usage_stats::UsageStats,
 };
-use std::{io::{Read, Write}, collections::{HashMap, HashSet}, os::unix::fs::symlink, path::Path};
+use std::{io::{Read, Write}, collections::HashMap, fs::{self, File}, path::Path};
+
+/// Directory inside the workspaces directory holding the lock files of
+/// environments being cooked.
+const LOCKS_DIRECTORY: &str = ".locks";
 
 pub struct CommandContext<R: Read, W: Write> {
     pub config: ConfigurationValues,
     pub reader: R,
     pub writer: W,
     pub adapter: Box<dyn EnwiroAdapterTrait>,
+    pub notifier: Box<dyn Notifier>,
 }
 
 impl<R: Read, W: Write> CommandContext<R, W> {
     pub fn new(config: ConfigurationValues, reader: R, writer: W) -> Self {
         let adapter: Box<dyn EnwiroAdapterTrait> = match &config.adapter {
             None => Box::new(EnwiroAdapterNone {}),
-            Some(adapter_name) => Box::new(EnwiroAdapterExternal::new(adapter_name)),
+            Some(adapter_name) => Box::new(EnwiroAdapterExternal::new(
+                adapter_name,
+                config.allowed_plugins.as_deref(),
+                config.adapter_calls,
+            )),
         };
+        let notifier = get_notifier(&config.notifications);
 
         Self {
             config,
             reader,
             writer,
             adapter,
+            notifier,
         }
     }
 
-    fn get_environment(&self, name: &Option<String>) -> Result<Environment, std::io::Error> {
+    /// Notifies the user, unless it is about a success and quiet mode is on.
+    pub fn notify(&self, level: NotificationLevel, message: &str) {
+        if level == NotificationLevel::Success && self.config.notifications.quiet {
+            return;
+        }
+
+        self.notifier.notify(level, message);
+    }
+
+    /// Finds an existing environment, the active one if no name is given.
+    /// Failing to ask the adapter for the active environment is notified
+    /// about, since commands doing so are usually run from key bindings.
+    pub fn get_environment(&self, name: &Option<String>) -> Result<Environment, std::io::Error> {
         let selected_environment_name = match name {
-            Some(x) => x.clone(),
-            None => self.adapter.get_active_environment_name().unwrap(),
+            Some(x) => normalize_environment_name(x)?,
+            None => self
+                .adapter
+                .get_active_environment_name()
+                .inspect_err(|error| {
+                    if ErrorCode::of(error) == ErrorCode::AdapterFailed {
+                        self.notify(
+                            NotificationLevel::Error,
+                            &format!("Could not determine the active environment: {}", error),
+                        );
+                    }
+                })?,
         };
 
         Environment::get_one(
@@ -40,45 +80,361 @@ impl<R: Read, W: Write> CommandContext<R, W> {
     }
 
     pub fn cook_environment(&self, name: &str) -> Result<Environment, std::io::Error> {
-        for cookbook in self.get_cookbooks() {
-            let recipes = cookbook.list_recipes();
-            for recipe in recipes.into_iter() {
-                if recipe != name {
+        self.cook_environment_from(self.get_cookbooks(), name)
+    }
+
+    /// Cooks the first recipe with the given name and records the cookbook
+    /// and description of the new environment in its metadata right away.
+    /// With the `suffix` collision policy, `name@cookbook` cooks the recipe
+    /// `name` of that cookbook, and with `error` a recipe offered by several
+    /// cookbooks is not cooked at all.
+    pub fn cook_environment_from(&self, cookbooks: Vec<Box<dyn CookbookTrait>>, name: &str) -> Result<Environment, std::io::Error> {
+        let name = normalize_environment_name(name)?;
+        let name = name.as_str();
+        let policy = self.config.cookbooks.collisions;
+        let (recipe_name, cookbook_name) = match split_cookbook_suffix(name) {
+            Some((recipe_name, cookbook_name))
+                if policy == CollisionPolicy::Suffix && cookbooks.iter().any(|cookbook| cookbook.name() == cookbook_name) =>
+            {
+                (recipe_name, Some(cookbook_name))
+            }
+            _ => (name, None),
+        };
+
+        let mut candidates = vec![];
+        let mut listing_error = None;
+        for cookbook in cookbooks {
+            if cookbook_name.is_some_and(|cookbook_name| cookbook_name != cookbook.name()) {
+                continue;
+            }
+            // A broken cookbook is skipped, but reported if no other one has the recipe
+            let recipes = match cookbook.list_recipes() {
+                Ok(recipes) => recipes,
+                Err(error) => {
+                    tracing::warn!(cookbook = cookbook.name(), %error, "Could not list recipes");
+                    listing_error = Some(ErrorCode::PluginFailed.tag(error));
                     continue;
                 }
-                let env_path = cookbook.cook(&recipe);
-                let target_path = Path::new(&self.config.workspaces_directory).join(name);
-                symlink(Path::new(&env_path), target_path)?;
-                return Environment::get_one(&self.config.workspaces_directory, name);
+            };
+            if let Some(recipe) = recipes.into_iter().find(|recipe| recipe.name == recipe_name) {
+                candidates.push((cookbook, recipe));
+                if policy != CollisionPolicy::Error {
+                    break;
+                }
             }
         }
+        if candidates.len() > 1 {
+            let cookbook_names: Vec<&str> = candidates.iter().map(|(cookbook, _)| cookbook.name()).collect();
+            return Err(ErrorCode::RecipeAmbiguous.error(
+                std::io::ErrorKind::AlreadyExists,
+                format!("Recipe \"{}\" is offered by several cookbooks: {}", recipe_name, cookbook_names.join(", ")),
+            ));
+        }
 
-        Err(std::io::Error::new(std::io::ErrorKind::Other, "No recipe available to cook this environment.)"))
+        let (cookbook, recipe) = match (candidates.pop(), listing_error) {
+            (Some(candidate), _) => candidate,
+            (None, Some(error)) => return Err(error),
+            (None, None) => return Err(ErrorCode::RecipeNotFound.error(std::io::ErrorKind::NotFound, "No recipe available to cook this environment.")),
+        };
+        let mut cooked = cookbook.cook(&recipe.name).map_err(|error| cook_error(cookbook.name(), &recipe.name, error))?;
+        let symlink_span = tracing::debug_span!("symlink").entered();
+        let target_path = Path::new(&self.config.workspaces_directory).join(encode_environment_name(name));
+        // A link left behind by an earlier, interrupted cook is replaced
+        replace_link(&cooked.path, &target_path)?;
+
+        cooked.metadata.cookbook = Some(cookbook.name().to_string());
+        cooked.metadata.recipe = Some(recipe.name.clone());
+        if cooked.metadata.description.is_none() {
+            cooked.metadata.description = recipe.description;
+        }
+        cooked.metadata.save(&self.config.workspaces_directory, name)?;
+        drop(symlink_span);
+        let mut usage_stats = UsageStats::load(&self.config.workspaces_directory);
+        usage_stats.record_cook(cookbook.name(), &recipe.name);
+        // Losing the stats only affects the order of recipes
+        if let Err(error) = usage_stats.save(&self.config.workspaces_directory) {
+            tracing::warn!(%error, "Could not save usage stats");
+        }
+        let environment = Environment::get_one(&self.config.workspaces_directory, name)?;
+        Hooks::new(&self.config)?.run(HookEvent::PostCook, &environment)?;
+
+        Ok(environment)
     }
 
     pub fn get_or_cook_environment(&self, name: &Option<String>) -> Result<Environment, std::io::Error> {
-        match self.get_environment(name) {
+        let existing_environment = tracing::debug_span!("resolve").in_scope(|| self.get_environment(name));
+        match existing_environment {
             Ok(env) => Ok(env),
-            Err(_) => {
+            Err(error) => {
                 if name.is_none() {
-                    return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "No environment could be found or cooked."));
+                    return Err(match ErrorCode::of(&error) {
+                        ErrorCode::AdapterFailed => error,
+                        _ => ErrorCode::EnvironmentNotFound.error(std::io::ErrorKind::NotFound, "No environment could be found or cooked."),
+                    });
+                }
+                let recipe_name = normalize_environment_name(name.as_ref().unwrap())?;
+
+                // Another enwiro process may be cooking the same environment,
+                // in which case its result is reused once it is done
+                let _lock = self.lock_environment(&recipe_name)?;
+                if let Ok(environment) = self.get_environment(name) {
+                    return Ok(environment);
+                }
+
+                let progress_id = match self.config.notifications.quiet {
+                    true => None,
+                    false => Some(self.notifier.create_progress(&format!("Cooking {}…", recipe_name))),
+                };
+                let result = tracing::debug_span!("cook", recipe_name).in_scope(|| self.cook_environment(&recipe_name));
+                if let Some(progress_id) = progress_id {
+                    match &result {
+                        Ok(environment) => self
+                            .notifier
+                            .update_progress(progress_id, &format!("Environment {} is ready", environment.name)),
+                        Err(_) => self.notifier.close_progress(progress_id),
+                    }
                 }
-                let recipe_name = name.clone().unwrap();
 
-                let environment = self.cook_environment(&recipe_name).expect("Could not cook environment");
-                Ok(environment)
+                result
             }
         }
     }
 
+    /// Takes an exclusive lock for the given environment, waiting while
+    /// another process holds it. The lock is released when the file is dropped.
+    fn lock_environment(&self, name: &str) -> Result<File, std::io::Error> {
+        let locks_directory = Path::new(&self.config.workspaces_directory).join(LOCKS_DIRECTORY);
+        fs::create_dir_all(&locks_directory)?;
+        let lock_file = File::create(locks_directory.join(format!("{}.lock", encode_environment_name(name))))?;
+        lock_file.lock()?;
+
+        Ok(lock_file)
+    }
+
     pub fn get_all_environments(&self) -> Result<HashMap<String, Environment>, std::io::Error> {
         Environment::get_all(&self.config.workspaces_directory)
     }
 
-    pub fn get_cookbooks(&self) -> HashSet<CookbookClient> {
-        let plugins = get_plugins(PluginKind::Cookbook);
-        let clients = plugins.into_iter().map(CookbookClient::new);
+    /// Returns the enabled cookbooks, the ones with the highest priority first.
+    /// Plugins take precedence over built-in cookbooks with the same name.
+    pub fn get_cookbooks(&self) -> Vec<Box<dyn CookbookTrait>> {
+        let cookbooks_config = &self.config.cookbooks;
+        let is_disabled = |name: &str| cookbooks_config.disabled.iter().any(|disabled| disabled == name);
+        let plugins = get_plugins(PluginKind::Cookbook, self.config.allowed_plugins.as_deref());
+        let mut cookbooks: Vec<Box<dyn CookbookTrait>> = plugins
+            .into_iter()
+            .map(|plugin| {
+                let settings = cookbooks_config.settings.get(&plugin.name).cloned();
+                Box::new(CookbookClient::new(plugin, settings)) as Box<dyn CookbookTrait>
+            })
+            .collect();
+        // Built-in cookbooks resolve their secrets when they are created, so
+        // the ones that are disabled or replaced by a plugin get no settings
+        let builtin_settings: HashMap<String, serde_json::Value> = cookbooks_config
+            .settings
+            .iter()
+            .filter(|(name, _)| !is_disabled(name) && !cookbooks.iter().any(|cookbook| cookbook.name() == name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        for builtin_cookbook in get_builtin_cookbooks(&builtin_settings) {
+            if !cookbooks.iter().any(|cookbook| cookbook.name() == builtin_cookbook.name()) {
+                cookbooks.push(builtin_cookbook);
+            }
+        }
+        cookbooks.retain(|cookbook| !is_disabled(cookbook.name()));
+
+        cookbooks.sort_by_cached_key(|cookbook| {
+            (
+                std::cmp::Reverse(cookbook.priority(&cookbooks_config.priorities)),
+                cookbook.name().to_string(),
+            )
+        });
+
+        cookbooks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, thread, time::Duration};
+
+    use rstest::rstest;
+
+    use crate::{
+        config::CollisionPolicy,
+        cookbook::CookbookTrait,
+        environment_metadata::EnvironmentMetadata,
+        error_codes::ErrorCode,
+        notifier::NotificationLevel,
+        test_utils::test_utilities::{context_object, CookbookMock, EnwiroAdapterMock, FakeContext, NotifierMock},
+        usage_stats::UsageStats,
+    };
+
+    fn broken_cookbook() -> CookbookMock {
+        CookbookMock {
+            priority: 10,
+            list_error: Some("Cookbook failed to list recipes".to_string()),
+            ..CookbookMock::new("broken", &[])
+        }
+    }
+
+    #[rstest]
+    fn test_broken_cookbooks_are_skipped(context_object: FakeContext) {
+        let recipe_path = format!("{}/.recipe", context_object.config.workspaces_directory);
+        fs::create_dir(&recipe_path).unwrap();
+        let cookbooks: Vec<Box<dyn CookbookTrait>> = vec![
+            Box::new(broken_cookbook()),
+            Box::new(CookbookMock {
+                path: recipe_path,
+                ..CookbookMock::new("mock", &["foo"])
+            }),
+        ];
+
+        assert!(context_object.cook_environment_from(cookbooks, "foo").is_ok());
+        let error = context_object
+            .cook_environment_from(vec![Box::new(broken_cookbook())], "bar")
+            .unwrap_err();
+        assert_eq!(ErrorCode::of(&error), ErrorCode::PluginFailed);
+    }
+
+    #[rstest]
+    fn test_cooking_records_cookbook_recipe_and_description(context_object: FakeContext) {
+        let workspaces_directory = &context_object.config.workspaces_directory;
+        let recipe_path = format!("{}/.recipe", workspaces_directory);
+        fs::create_dir(&recipe_path).unwrap();
+
+        let cookbook = CookbookMock {
+            recipe_description: Some("Foo project".to_string()),
+            path: recipe_path,
+            ..CookbookMock::new("mock", &["foo"])
+        };
+        let environment = context_object
+            .cook_environment_from(vec![Box::new(cookbook)], "foo")
+            .unwrap();
+
+        assert_eq!(environment.name, "foo");
+        let metadata = EnvironmentMetadata::load(workspaces_directory, "foo");
+        assert_eq!(metadata.cookbook, Some("mock".to_string()));
+        assert_eq!(metadata.recipe, Some("foo".to_string()));
+        assert_eq!(metadata.description, Some("Foo project".to_string()));
+        assert!(UsageStats::load(workspaces_directory).recipe_frecency("mock", "foo") > 0.0);
+    }
+
+    #[rstest]
+    fn test_cooked_names_are_encoded_on_disk(context_object: FakeContext) {
+        let workspaces_directory = &context_object.config.workspaces_directory;
+        let recipe_path = format!("{}/.recipe", workspaces_directory);
+        fs::create_dir(&recipe_path).unwrap();
+        let cookbook = CookbookMock {
+            path: recipe_path,
+            ..CookbookMock::new("mock", &["kantord/enwiro#12"])
+        };
+
+        let environment = context_object
+            .cook_environment_from(vec![Box::new(cookbook)], " kantord/enwiro#12")
+            .unwrap();
+
+        assert_eq!(environment.name, "kantord/enwiro#12");
+        assert!(Path::new(workspaces_directory)
+            .join("kantord%2Fenwiro#12")
+            .is_symlink());
+        assert!(context_object
+            .get_or_cook_environment(&Some("kantord/enwiro#12".to_string()))
+            .is_ok());
+    }
+
+    #[rstest]
+    fn test_failing_adapter_is_notified_about(mut context_object: FakeContext) {
+        let notifier = NotifierMock::default();
+        context_object.notifier = Box::new(notifier.clone());
+        let mut adapter = EnwiroAdapterMock::new("foo");
+        adapter.active_environment_error = Some("no IPC connection".to_string());
+        context_object.adapter = Box::new(adapter);
+
+        let error = context_object.get_or_cook_environment(&None).unwrap_err();
+
+        assert_eq!(ErrorCode::of(&error), ErrorCode::AdapterFailed);
+        assert_eq!(
+            *notifier.notifications.borrow(),
+            vec![(
+                NotificationLevel::Error,
+                "Could not determine the active environment: no IPC connection".to_string()
+            )]
+        );
+    }
+
+    #[rstest]
+    fn test_cooking_replaces_stale_link(context_object: FakeContext) {
+        let workspaces_directory = &context_object.config.workspaces_directory;
+        let recipe_path = format!("{}/.recipe", workspaces_directory);
+        fs::create_dir(&recipe_path).unwrap();
+        let environment_path = Path::new(workspaces_directory).join("foo");
+        std::os::unix::fs::symlink(recipe_path.replace(".recipe", ".gone"), &environment_path)
+            .unwrap();
+        let cookbook = CookbookMock {
+            path: recipe_path.clone(),
+            ..CookbookMock::new("mock", &["foo"])
+        };
+
+        let environment = context_object
+            .cook_environment_from(vec![Box::new(cookbook)], "foo")
+            .unwrap();
+
+        assert_eq!(environment.name, "foo");
+        assert_eq!(
+            fs::read_link(environment_path).unwrap(),
+            Path::new(&recipe_path)
+        );
+    }
+
+    #[rstest]
+    #[case(CollisionPolicy::Hide, "foo", Some("first"))]
+    #[case(CollisionPolicy::Hide, "foo@second", None)]
+    #[case(CollisionPolicy::Suffix, "foo@second", Some("second"))]
+    #[case(CollisionPolicy::Error, "foo", None)]
+    fn test_cooking_colliding_recipes(
+        mut context_object: FakeContext,
+        #[case] policy: CollisionPolicy,
+        #[case] name: &str,
+        #[case] expected_cookbook: Option<&str>,
+    ) {
+        context_object.config.cookbooks.collisions = policy;
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let recipe_path = format!("{}/.recipe", workspaces_directory);
+        fs::create_dir(&recipe_path).unwrap();
+        let cookbooks: Vec<Box<dyn CookbookTrait>> = ["first", "second"]
+            .into_iter()
+            .map(|cookbook_name| {
+                Box::new(CookbookMock {
+                    path: recipe_path.clone(),
+                    ..CookbookMock::new(cookbook_name, &["foo"])
+                }) as Box<dyn CookbookTrait>
+            })
+            .collect();
+
+        let cooked_cookbook = context_object
+            .cook_environment_from(cookbooks, name)
+            .ok()
+            .and_then(|_| EnvironmentMetadata::load(&workspaces_directory, name).cookbook);
+
+        assert_eq!(cooked_cookbook.as_deref(), expected_cookbook);
+    }
+
+    #[rstest]
+    fn test_waits_for_environment_cooked_by_another_process(context_object: FakeContext) {
+        let lock = context_object.lock_environment("foo").unwrap();
+        let environment_path = Path::new(&context_object.config.workspaces_directory).join("foo");
+        let cooking = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            fs::create_dir(environment_path).unwrap();
+            drop(lock);
+        });
+
+        let environment = context_object
+            .get_or_cook_environment(&Some("foo".to_string()))
+            .unwrap();
 
-        HashSet::from_iter(clients)
+        assert_eq!(environment.name, "foo");
+        cooking.join().unwrap();
     }
 }
@@ -0,0 +1,169 @@
+#[cfg(feature = "cookbook-git")]
+mod git;
+
+use std::{collections::HashMap, process::Command};
+
+use crate::{
+    client::{CookResponse, Recipe},
+    collisions::strip_cookbook_suffix,
+    environment_metadata::EnvironmentMetadata,
+};
+
+/// Cookbooks are `Send` so that recipes can be listed from all of them at the
+/// same time.
+pub trait CookbookTrait: Send {
+    fn name(&self) -> &str;
+
+    /// Priority of the cookbook: the configured one if any, otherwise the
+    /// default suggested by the cookbook.
+    fn priority(&self, configured_priorities: &HashMap<String, i32>) -> i32;
+
+    /// Passes recipes to `on_recipe` as soon as the cookbook provides them,
+    /// so that slow cookbooks do not hold back the ones already listed.
+    fn stream_recipes(
+        &self,
+        on_recipe: &mut dyn FnMut(Recipe) -> Result<(), std::io::Error>,
+    ) -> Result<(), std::io::Error>;
+
+    fn list_recipes(&self) -> Result<Vec<Recipe>, std::io::Error> {
+        let mut recipes = vec![];
+        self.stream_recipes(&mut |recipe| {
+            recipes.push(recipe);
+            Ok(())
+        })?;
+
+        Ok(recipes)
+    }
+
+    fn cook(&self, recipe: &str) -> Result<CookResponse, std::io::Error>;
+
+    /// Does the slow work of cooking a recipe ahead of time, such as fetching
+    /// the latest changes, without changing what cooking it returns. Cookbooks
+    /// that cannot prefetch do nothing.
+    fn prefetch(&self, _recipe: &str) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+
+    /// Describes a recipe in more detail than its one-line description, for
+    /// example with an excerpt of its README. Cookbooks that cannot describe
+    /// recipes return `None`.
+    fn describe(&self, _recipe: &str) -> Result<Option<String>, std::io::Error> {
+        Ok(None)
+    }
+
+    /// Reverses the side effects of cooking a recipe once its environment is
+    /// removed, such as removing a worktree or stopping a container.
+    /// Cookbooks whose recipes leave nothing behind do nothing.
+    fn uncook(&self, _recipe: &str) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+}
+
+/// Lists the recipes of a cookbook, or none if it fails, so that one broken
+/// cookbook does not stop the others. The failure is logged.
+pub fn list_recipes_or_skip(cookbook: &dyn CookbookTrait) -> Vec<Recipe> {
+    cookbook.list_recipes().unwrap_or_else(|error| {
+        tracing::warn!(cookbook = cookbook.name(), %error, "Could not list recipes");
+        vec![]
+    })
+}
+
+/// Describes a recipe using the cookbook of the given name. Details are only
+/// nice to have, so failing to get them is logged instead of reported.
+pub fn describe_recipe(
+    cookbooks: &[Box<dyn CookbookTrait>],
+    cookbook_name: &str,
+    recipe: &str,
+) -> Option<String> {
+    let cookbook = cookbooks
+        .iter()
+        .find(|cookbook| cookbook.name() == cookbook_name)?;
+
+    match cookbook.describe(recipe) {
+        Ok(details) => details,
+        Err(error) => {
+            tracing::warn!(cookbook = cookbook_name, recipe, %error, "Could not describe recipe");
+            None
+        }
+    }
+}
+
+/// Describes an environment or a recipe listed by `list-all`. Environments
+/// are described by the cookbook that cooked them, if any.
+pub fn describe_entry(
+    cookbooks: &[Box<dyn CookbookTrait>],
+    workspaces_directory: &str,
+    name: &str,
+    cookbook_name: Option<&str>,
+) -> Option<String> {
+    let cookbook_name = match cookbook_name {
+        Some(cookbook_name) => cookbook_name.to_string(),
+        None => EnvironmentMetadata::load(workspaces_directory, name).cookbook?,
+    };
+    let recipe = strip_cookbook_suffix(name, &cookbook_name);
+
+    describe_recipe(cookbooks, &cookbook_name, recipe)
+}
+
+/// Cleans up after an environment that was removed for good, by running its
+/// cleanup command or else by asking the cookbook that cooked it to uncook
+/// it. Environments that were not cooked from a recipe, or whose cookbook is
+/// not available, are left alone.
+pub fn uncook_environment(
+    cookbooks: &[Box<dyn CookbookTrait>],
+    metadata: &EnvironmentMetadata,
+    environment_name: &str,
+) -> Result<(), std::io::Error> {
+    if let Some((program, args)) = metadata.cleanup_command.split_first() {
+        let output = Command::new(program).args(args).output()?;
+        if !output.status.success() {
+            return Err(std::io::Error::other(format!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        return Ok(());
+    }
+
+    let Some(cookbook) = cookbooks
+        .iter()
+        .find(|cookbook| metadata.cookbook.as_deref() == Some(cookbook.name()))
+    else {
+        return Ok(());
+    };
+    cookbook.uncook(strip_cookbook_suffix(environment_name, cookbook.name()))
+}
+
+/// Cookbooks compiled into enwiro itself, which avoids starting a process for
+/// each of them. They are only used if no plugin with the same name exists.
+/// Secrets in their settings are resolved right away.
+#[allow(unused_variables)]
+pub fn get_builtin_cookbooks(
+    settings: &HashMap<String, serde_json::Value>,
+) -> Vec<Box<dyn CookbookTrait>> {
+    vec![
+        #[cfg(feature = "cookbook-git")]
+        Box::new(get_git_cookbook(settings)),
+    ]
+}
+
+#[cfg(feature = "cookbook-git")]
+fn get_git_cookbook(settings: &HashMap<String, serde_json::Value>) -> git::GitCookbook {
+    git::GitCookbook::new(
+        settings
+            .get("git")
+            .cloned()
+            .map(crate::secrets::resolve_secrets)
+            .as_ref(),
+    )
+}
+
+/// Fetches the repositories of the builtin git cookbook, in the process it
+/// starts when listing recipes once `fetch_interval` is due.
+#[cfg(feature = "cookbook-git")]
+pub fn fetch_git_repositories(
+    settings: &HashMap<String, serde_json::Value>,
+) -> Result<(), std::io::Error> {
+    get_git_cookbook(settings).fetch()
+}
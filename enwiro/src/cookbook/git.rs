@@ -0,0 +1,127 @@
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+    env,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use enwiro_cookbook_git::{
+    build_repository_hashmap, describe_repository, fetch_repositories, get_descriptions,
+    is_fetch_due, load_configuration_file, ConfigurationValues, Repository,
+};
+
+use crate::{
+    client::{CookResponse, Recipe},
+    cookbook::CookbookTrait,
+    environment_metadata::EnvironmentMetadata,
+};
+
+/// In-process version of `enwiro-cookbook-git`, sharing its configuration.
+pub struct GitCookbook {
+    config: ConfigurationValues,
+
+    // Repositories found when describing the first recipe, since finding them
+    // again for each one is slow
+    described_repositories: OnceCell<HashMap<String, Repository>>,
+}
+
+impl GitCookbook {
+    /// Uses the settings from enwiro's configuration if there are any, and the
+    /// configuration file of `enwiro-cookbook-git` otherwise.
+    pub fn new(settings: Option<&serde_json::Value>) -> Self {
+        let config = match settings {
+            Some(settings) => serde_json::from_value::<ConfigurationValues>(settings.clone())
+                .map_err(std::io::Error::other)
+                .and_then(ConfigurationValues::expand_paths),
+            None => load_configuration_file(),
+        }
+        .unwrap_or_else(|x| panic!("Invalid settings for the git cookbook: {}", x));
+
+        Self {
+            config,
+            described_repositories: OnceCell::new(),
+        }
+    }
+
+    pub fn fetch(&self) -> Result<(), std::io::Error> {
+        fetch_repositories(&self.config, &build_repository_hashmap(&self.config))
+    }
+
+    fn get_repository_paths(&self) -> HashMap<String, PathBuf> {
+        build_repository_hashmap(&self.config)
+            .into_iter()
+            .filter_map(|(name, repo)| Some((name, repo.path().parent()?.to_path_buf())))
+            .collect()
+    }
+}
+
+/// Starts fetching repositories in a separate enwiro process, which keeps
+/// running after the recipes are listed.
+fn start_background_fetch() -> Result<(), std::io::Error> {
+    Command::new(env::current_exe()?)
+        .arg("__enwiro_fetch_git")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    Ok(())
+}
+
+impl CookbookTrait for GitCookbook {
+    fn name(&self) -> &str {
+        "git"
+    }
+
+    fn priority(&self, configured_priorities: &HashMap<String, i32>) -> i32 {
+        configured_priorities.get("git").copied().unwrap_or(0)
+    }
+
+    fn stream_recipes(
+        &self,
+        on_recipe: &mut dyn FnMut(Recipe) -> Result<(), std::io::Error>,
+    ) -> Result<(), std::io::Error> {
+        if is_fetch_due(&self.config) {
+            if let Err(error) = start_background_fetch() {
+                tracing::warn!(%error, "Could not start fetching repositories");
+            }
+        }
+        let repositories = build_repository_hashmap(&self.config);
+        for (name, description) in get_descriptions(&self.config, &repositories) {
+            on_recipe(Recipe {
+                name,
+                description: Some(description),
+                category: None,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    fn cook(&self, recipe: &str) -> Result<CookResponse, std::io::Error> {
+        match self.get_repository_paths().remove(recipe) {
+            Some(path) => Ok(CookResponse {
+                path,
+                metadata: EnvironmentMetadata::default(),
+            }),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Could not find recipe {}", recipe),
+            )),
+        }
+    }
+
+    fn describe(&self, recipe: &str) -> Result<Option<String>, std::io::Error> {
+        let repositories = self
+            .described_repositories
+            .get_or_init(|| build_repository_hashmap(&self.config));
+        match repositories.get(recipe) {
+            Some(repo) => Ok(Some(describe_repository(repo))),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Could not find recipe {}", recipe),
+            )),
+        }
+    }
+}
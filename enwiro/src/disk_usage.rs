@@ -0,0 +1,118 @@
+use std::{fs, io, path::Path};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    environment_metadata::EnvironmentMetadata, environments::Environment, usage_stats::now,
+};
+
+/// How long a measurement is used before measuring the environment again.
+const MAX_AGE_SECONDS: u64 = 60 * 60;
+
+/// Disk usage of an environment, cached in its metadata because measuring
+/// large repositories can take a while.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiskUsage {
+    pub bytes: u64,
+
+    // Unix timestamp of the measurement, in seconds
+    pub measured_at: u64,
+}
+
+/// Adds up the size of the files in a directory. Symbolic links inside it
+/// are not followed, so that other environments are not counted.
+fn measure(path: &Path) -> u64 {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return 0,
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|entry| measure(&entry.path())).sum())
+        .unwrap_or(0)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit_index = 0;
+    while size >= 1024.0 && unit_index < units.len() - 1 {
+        size /= 1024.0;
+        unit_index += 1;
+    }
+
+    match unit_index {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, units[unit_index]),
+    }
+}
+
+/// Returns the disk usage of an environment, measuring it again if the cached
+/// measurement is missing or outdated.
+pub fn get_disk_usage(environment: &Environment) -> Result<DiskUsage, io::Error> {
+    let mut metadata = EnvironmentMetadata::load_at(&environment.path);
+    let now = now();
+    if let Some(disk_usage) = metadata.disk_usage {
+        if now.saturating_sub(disk_usage.measured_at) < MAX_AGE_SECONDS {
+            return Ok(disk_usage);
+        }
+    }
+
+    // The environment itself is usually a link, so its target is measured
+    let target_path = fs::canonicalize(&environment.path)?;
+    let disk_usage = DiskUsage {
+        bytes: measure(&target_path),
+        measured_at: now,
+    };
+    metadata.disk_usage = Some(disk_usage);
+    if let Err(error) = metadata.save_at(&environment.path) {
+        tracing::warn!(%error, environment = environment.name, "Could not cache disk usage");
+    }
+
+    Ok(disk_usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use rstest::rstest;
+
+    use crate::{
+        disk_usage::{format_size, get_disk_usage},
+        environment_metadata::EnvironmentMetadata,
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    #[case(0, "0 B")]
+    #[case(1023, "1023 B")]
+    #[case(1536, "1.5 KiB")]
+    #[case(5 * 1024 * 1024 * 1024, "5.0 GiB")]
+    fn test_format_size(#[case] bytes: u64, #[case] expected: &str) {
+        assert_eq!(format_size(bytes), expected);
+    }
+
+    #[rstest]
+    fn test_disk_usage_is_cached(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foobar");
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let environment = context_object
+            .get_environment(&Some("foobar".to_string()))
+            .unwrap();
+        fs::write(environment.path.join("a.txt"), "hello").unwrap();
+
+        assert_eq!(get_disk_usage(&environment).unwrap().bytes, 5);
+
+        fs::write(environment.path.join("b.txt"), "world").unwrap();
+        assert_eq!(get_disk_usage(&environment).unwrap().bytes, 5);
+
+        let mut metadata = EnvironmentMetadata::load(&workspaces_directory, "foobar");
+        metadata.disk_usage.as_mut().unwrap().measured_at = 0;
+        metadata.save(&workspaces_directory, "foobar").unwrap();
+        assert_eq!(get_disk_usage(&environment).unwrap().bytes, 10);
+    }
+}
@@ -0,0 +1,84 @@
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use serde_derive::Deserialize;
+
+use crate::environments::Environment;
+
+/// Name of the file in the root of an environment holding its settings.
+const ENVIRONMENT_CONFIG_FILE_NAME: &str = ".enwiro.toml";
+
+/// Settings kept together with the files of an environment, for example in
+/// its Git repository.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct EnvironmentConfig {
+    // Workspaces the environment shows on each output when it is activated,
+    // keyed by output. An empty name is the workspace of the environment itself.
+    pub outputs: BTreeMap<String, String>,
+}
+
+impl EnvironmentConfig {
+    /// Loads the settings of an environment. Environments without a settings
+    /// file get the default settings.
+    pub fn load(environment: &Environment) -> Result<Self, io::Error> {
+        let path = Path::new(&environment.path).join(ENVIRONMENT_CONFIG_FILE_NAME);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(error) => return Err(error),
+        };
+
+        toml::from_str(&content).map_err(|error| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Could not parse {}: {}", path.display(), error),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs, path::Path};
+
+    use rstest::rstest;
+
+    use crate::{
+        environment_config::EnvironmentConfig,
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    fn test_load_outputs(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foo");
+        let environment = context_object
+            .get_environment(&Some("foo".to_string()))
+            .unwrap();
+        fs::write(
+            Path::new(&environment.path).join(".enwiro.toml"),
+            "[outputs]\nDP-1 = \"\"\nHDMI-1 = \"chat\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            EnvironmentConfig::load(&environment).unwrap().outputs,
+            BTreeMap::from([
+                ("DP-1".to_string(), "".to_string()),
+                ("HDMI-1".to_string(), "chat".to_string()),
+            ])
+        );
+    }
+
+    #[rstest]
+    fn test_load_defaults_without_file(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foo");
+        let environment = context_object
+            .get_environment(&Some("foo".to_string()))
+            .unwrap();
+
+        assert_eq!(
+            EnvironmentConfig::load(&environment).unwrap(),
+            EnvironmentConfig::default()
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    disk_usage::DiskUsage, environments::encode_environment_name, parallel::map_in_parallel,
+};
+
+const METADATA_DIRECTORY_NAME: &str = ".meta";
+const METADATA_FILE_NAME: &str = "meta.json";
+
+/// Information about an environment provided by the cookbook that cooked it.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvironmentMetadata {
+    // Name of the cookbook that cooked the environment
+    pub cookbook: Option<String>,
+
+    // Name of the recipe it was cooked from, which differs from the name of
+    // the environment for clones and recipes with a cookbook suffix
+    pub recipe: Option<String>,
+
+    pub description: Option<String>,
+
+    // Gear suggested by the cookbook for working in this environment
+    pub gear: Vec<String>,
+
+    pub tags: Vec<String>,
+
+    // Program and arguments cleaning up after the environment once it is
+    // removed, such as removing the worktree of a clone
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cleanup_command: Vec<String>,
+
+    // Whether the environment is protected from being removed, see `enwiro lock`
+    pub locked: bool,
+
+    // Last measured disk usage, see `disk_usage::get_disk_usage`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_usage: Option<DiskUsage>,
+}
+
+/// Metadata is kept next to the environments, in a directory named like the
+/// environment's own directory so that names which are not valid UTF-8 work.
+fn get_metadata_directory(environment_path: &Path) -> PathBuf {
+    let workspaces_directory = environment_path.parent().unwrap_or(Path::new(""));
+    let directory_name = environment_path.file_name().unwrap_or_default();
+
+    workspaces_directory
+        .join(METADATA_DIRECTORY_NAME)
+        .join(directory_name)
+}
+
+fn get_environment_path(workspaces_directory: &str, environment_name: &str) -> PathBuf {
+    Path::new(workspaces_directory).join(encode_environment_name(environment_name))
+}
+
+impl EnvironmentMetadata {
+    /// Loads the metadata of an environment. Environments without metadata,
+    /// such as ones created by hand, get empty metadata.
+    pub fn load(workspaces_directory: &str, environment_name: &str) -> Self {
+        Self::load_at(&get_environment_path(
+            workspaces_directory,
+            environment_name,
+        ))
+    }
+
+    /// Loads the metadata of the environment at a path, which unlike its name
+    /// always leads back to the right directory.
+    pub fn load_at(environment_path: &Path) -> Self {
+        let metadata_path = get_metadata_directory(environment_path).join(METADATA_FILE_NAME);
+
+        fs::read_to_string(metadata_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Loads the metadata of the environments at several paths, in the same
+    /// order. The files are read by a few threads at once, since reading them
+    /// one by one is slow with many environments on a slow disk.
+    pub fn load_many(environment_paths: &[&Path]) -> Vec<Self> {
+        map_in_parallel(environment_paths, |path| Self::load_at(path))
+    }
+
+    pub fn save(
+        &self,
+        workspaces_directory: &str,
+        environment_name: &str,
+    ) -> Result<(), io::Error> {
+        self.save_at(&get_environment_path(
+            workspaces_directory,
+            environment_name,
+        ))
+    }
+
+    pub fn save_at(&self, environment_path: &Path) -> Result<(), io::Error> {
+        let metadata_directory = get_metadata_directory(environment_path);
+        let content = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+
+        fs::create_dir_all(&metadata_directory)?;
+        fs::write(metadata_directory.join(METADATA_FILE_NAME), content)
+    }
+
+    pub fn remove_at(environment_path: &Path) -> Result<(), io::Error> {
+        match fs::remove_dir_all(get_metadata_directory(environment_path)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use rstest::rstest;
+
+    use crate::{
+        environment_metadata::EnvironmentMetadata,
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    fn test_metadata_is_saved_loaded_and_removed(context_object: FakeContext) {
+        let workspaces_directory = &context_object.config.workspaces_directory;
+        let metadata = EnvironmentMetadata {
+            description: Some("Foo project".to_string()),
+            tags: vec!["work".to_string()],
+            ..Default::default()
+        };
+
+        metadata.save(workspaces_directory, "foo").unwrap();
+        assert_eq!(
+            EnvironmentMetadata::load(workspaces_directory, "foo"),
+            metadata
+        );
+
+        let environment_path = Path::new(workspaces_directory).join("foo");
+        EnvironmentMetadata::remove_at(&environment_path).unwrap();
+        assert_eq!(
+            EnvironmentMetadata::load(workspaces_directory, "foo"),
+            EnvironmentMetadata::default()
+        );
+        assert!(EnvironmentMetadata::remove_at(&environment_path).is_ok());
+    }
+
+    #[rstest]
+    fn test_load_many_keeps_order(context_object: FakeContext) {
+        let workspaces_directory = &context_object.config.workspaces_directory;
+        let names: Vec<String> = (0..20).map(|i| format!("env-{}", i)).collect();
+        for name in names.iter().step_by(2) {
+            EnvironmentMetadata {
+                description: Some(name.clone()),
+                ..Default::default()
+            }
+            .save(workspaces_directory, name)
+            .unwrap();
+        }
+
+        let paths: Vec<PathBuf> = names
+            .iter()
+            .map(|name| Path::new(workspaces_directory).join(name))
+            .collect();
+        let paths: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+        let descriptions: Vec<Option<String>> = EnvironmentMetadata::load_many(&paths)
+            .into_iter()
+            .map(|metadata| metadata.description)
+            .collect();
+
+        let expected: Vec<Option<String>> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (i % 2 == 0).then(|| name.to_string()))
+            .collect();
+        assert_eq!(descriptions, expected);
+    }
+}
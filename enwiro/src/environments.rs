@@ -1,10 +1,84 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::{fs, io};
 
+use crate::environment_metadata::EnvironmentMetadata;
+use crate::error_codes::ErrorCode;
+use crate::platform::remove_link;
+
+/// Whether a character of an environment name has to be escaped. `/` cannot
+/// appear in directory names, `:` separates fields in the adapter protocol and
+/// a leading `.` would hide the environment.
+fn needs_escaping(index: usize, character: char) -> bool {
+    matches!(character, '%' | '/' | ':')
+        || character.is_control()
+        || (index == 0 && character == '.')
+}
+
+/// Encodes an environment name for use as its directory name and when talking
+/// to adapters, by percent-encoding characters that are unsafe there.
+pub fn encode_environment_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for (index, character) in name.chars().enumerate() {
+        if needs_escaping(index, character) {
+            let mut buffer = [0; 4];
+            for byte in character.encode_utf8(&mut buffer).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            encoded.push(character);
+        }
+    }
+
+    encoded
+}
+
+/// Reverses `encode_environment_name`. Invalid escape sequences, for example
+/// in directories created by hand, are kept as they are.
+pub fn decode_environment_name(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped_byte = match bytes[index] {
+            b'%' => encoded
+                .get(index + 1..index + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped_byte {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Normalizes a name given by the user or a cookbook before it is looked up or
+/// cooked. Surrounding whitespace is removed and empty names are rejected.
+pub fn normalize_environment_name(name: &str) -> Result<String, io::Error> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Environment name cannot be empty",
+        ));
+    }
+
+    Ok(name.to_string())
+}
+
 #[derive(Debug)]
 pub struct Environment {
-    // Actual path to the environment
-    pub path: String,
+    // Actual path to the environment, which may not be valid UTF-8
+    pub path: PathBuf,
 
     // Name should be short enough to be displayed
     pub name: String,
@@ -16,16 +90,21 @@ impl Environment {
         let directory_entries = fs::read_dir(source_directory)?;
 
         for directory_entry in directory_entries {
-            let path = directory_entry.unwrap().path();
-            let id = path.file_name().unwrap().to_str().unwrap().to_string();
+            let directory_entry = directory_entry?;
+            let path = directory_entry.path();
+            // Names that are not valid UTF-8 are shown with replacement
+            // characters, while the path keeps the original bytes
+            let id = directory_entry.file_name().to_string_lossy().to_string();
 
-            if path.is_dir() {
+            // Hidden entries hold enwiro's own data, not environments
+            if path.is_dir() && !id.starts_with('.') {
+                let name = decode_environment_name(&id);
                 let new_environment = Environment {
-                    path: path.to_str().unwrap().to_string(),
-                    name: id.clone(),
+                    path,
+                    name: name.clone(),
                 };
 
-                results.insert(id.clone(), new_environment);
+                results.insert(name, new_environment);
             }
         }
 
@@ -37,10 +116,74 @@ impl Environment {
 
         match environments.remove(name) {
             Some(x) => Ok(x),
-            None => Err(io::Error::new(
+            None => Err(ErrorCode::EnvironmentNotFound.error(
                 io::ErrorKind::NotFound,
                 format!("Environment \"{}\" does not exist", name),
             ))?,
         }
     }
+
+    /// Removes an environment that was cooked from a recipe. Only symbolic
+    /// links are removed so that environments which are real directories can
+    /// never lose data this way.
+    pub fn remove(&self) -> Result<(), io::Error> {
+        if !self.path.is_symlink() {
+            return Err(io::Error::other(format!(
+                "Environment \"{}\" was not created from a recipe and cannot be removed",
+                self.name
+            )));
+        }
+
+        remove_link(&self.path)?;
+        EnvironmentMetadata::remove_at(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, fs, os::unix::ffi::OsStrExt, path::Path};
+
+    use rstest::rstest;
+
+    use crate::{
+        environments::{
+            decode_environment_name, encode_environment_name, normalize_environment_name,
+            Environment,
+        },
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    #[case("foo", "foo")]
+    #[case("kantord/enwiro#12", "kantord%2Fenwiro#12")]
+    #[case("with space: 100%", "with space%3A 100%25")]
+    #[case(".hidden.project", "%2Ehidden.project")]
+    #[case("new\nline", "new%0Aline")]
+    fn test_environment_names_round_trip(#[case] name: &str, #[case] encoded: &str) {
+        assert_eq!(encode_environment_name(name), encoded);
+        assert_eq!(decode_environment_name(encoded), name);
+    }
+
+    #[test]
+    fn test_invalid_escapes_are_kept() {
+        assert_eq!(decode_environment_name("50%off"), "50%off");
+        assert_eq!(decode_environment_name("100%"), "100%");
+    }
+
+    #[test]
+    fn test_normalize_environment_name() {
+        assert_eq!(normalize_environment_name("  foo ").unwrap(), "foo");
+        assert!(normalize_environment_name(" ").is_err());
+    }
+
+    #[rstest]
+    fn test_environment_with_non_utf8_name(context_object: FakeContext) {
+        let workspaces_directory = &context_object.config.workspaces_directory;
+        let path = Path::new(workspaces_directory).join(OsStr::from_bytes(b"caf\xe9"));
+        fs::create_dir(&path).unwrap();
+
+        let environment = Environment::get_one(workspaces_directory, "caf\u{FFFD}").unwrap();
+
+        assert_eq!(environment.path, path);
+    }
 }
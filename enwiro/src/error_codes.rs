@@ -0,0 +1,92 @@
+pub use enwiro_errors::{cook_error, get_exit_code, to_json, ChildFailed, ErrorCode};
+
+/// How errors of failed commands are printed.
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+pub enum ErrorFormat {
+    #[default]
+    Text,
+    /// A JSON object with the code, exit status and message of the error
+    Json,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use rstest::rstest;
+
+    use crate::{
+        commands::show_path::{show_path, ShowPathArgs},
+        error_codes::{cook_error, get_exit_code, to_json, ChildFailed, ErrorCode},
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    #[rstest]
+    fn test_missing_environment_has_code(mut context_object: FakeContext) {
+        let error = show_path(
+            &mut context_object,
+            ShowPathArgs {
+                environment_name: Some("missing".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            to_json(&error),
+            serde_json::json!({
+                "code": "environment-not-found",
+                "exitCode": 3,
+                "message": "Environment \"missing\" does not exist",
+            })
+        );
+    }
+
+    #[test]
+    fn test_cook_errors_name_cookbook_and_recipe() {
+        let error = ErrorCode::PluginFailed.tag(cook_error(
+            "github",
+            "kantord/enwiro#12",
+            io::Error::other("rate limited"),
+        ));
+
+        assert_eq!(
+            to_json(&error),
+            serde_json::json!({
+                "code": "cook-failed",
+                "exitCode": 6,
+                "message": "rate limited",
+                "cookbook": "github",
+                "recipe": "kantord/enwiro#12",
+            })
+        );
+    }
+
+    #[rstest]
+    #[case::tagged(ErrorCode::CookFailed.error(io::ErrorKind::Other, "failed"), ErrorCode::CookFailed, 6)]
+    #[case::tagged_twice(
+        ErrorCode::AdapterFailed.tag(ErrorCode::RecipeNotFound.error(io::ErrorKind::NotFound, "missing")),
+        ErrorCode::RecipeNotFound,
+        4
+    )]
+    #[case::invalid_input(
+        io::Error::new(io::ErrorKind::InvalidInput, "empty"),
+        ErrorCode::InvalidInput,
+        2
+    )]
+    #[case::untagged(
+        io::Error::new(io::ErrorKind::NotFound, "missing"),
+        ErrorCode::Other,
+        1
+    )]
+    #[case::child_failed(io::Error::other(ChildFailed { code: 42 }), ErrorCode::CommandFailed, 42)]
+    fn test_error_code(
+        #[case] error: io::Error,
+        #[case] expected_code: ErrorCode,
+        #[case] expected_exit_code: i32,
+    ) {
+        assert_eq!(ErrorCode::of(&error), expected_code);
+        assert_eq!(get_exit_code(&error), expected_exit_code);
+    }
+}
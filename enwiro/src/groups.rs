@@ -0,0 +1,162 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::config::ConfigurationValues;
+
+const GROUPS_FILE_NAME: &str = ".groups.json";
+
+/// Groups of environments managed using `enwiro group`, stored next to the
+/// environments themselves.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Groups {
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+fn get_groups_path(workspaces_directory: &str) -> PathBuf {
+    Path::new(workspaces_directory).join(GROUPS_FILE_NAME)
+}
+
+impl Groups {
+    /// Loads the groups. Unlike usage stats, unreadable groups are an error,
+    /// since saving them again would lose them.
+    pub fn load(workspaces_directory: &str) -> Result<Self, io::Error> {
+        match fs::read_to_string(get_groups_path(workspaces_directory)) {
+            Ok(content) => serde_json::from_str(&content).map_err(|error| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Could not read groups: {}", error),
+                )
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self, workspaces_directory: &str) -> Result<(), io::Error> {
+        let content = serde_json::to_string(self).map_err(io::Error::other)?;
+
+        fs::write(get_groups_path(workspaces_directory), content)
+    }
+
+    /// Adds environments to the end of a group, creating it if necessary.
+    pub fn add(&mut self, group: &str, environment_names: &[String]) {
+        let members = self.groups.entry(group.to_string()).or_default();
+        for environment_name in environment_names {
+            if !members.contains(environment_name) {
+                members.push(environment_name.clone());
+            }
+        }
+    }
+
+    /// Removes environments from a group, or the whole group if no
+    /// environments are given. Returns whether anything was removed.
+    pub fn remove(&mut self, group: &str, environment_names: &[String]) -> bool {
+        if environment_names.is_empty() {
+            return self.groups.remove(group).is_some();
+        }
+        let Some(members) = self.groups.get_mut(group) else {
+            return false;
+        };
+        let member_count = members.len();
+        members.retain(|member| !environment_names.contains(member));
+        let removed = members.len() < member_count;
+        if members.is_empty() {
+            self.groups.remove(group);
+        }
+
+        removed
+    }
+}
+
+/// All groups, both the ones from the configuration and the ones managed
+/// using `enwiro group`. The members of groups with the same name are
+/// combined, those from the configuration first.
+pub fn get_groups(
+    config: &ConfigurationValues,
+) -> Result<BTreeMap<String, Vec<String>>, io::Error> {
+    let mut groups: BTreeMap<String, Vec<String>> = config
+        .groups
+        .iter()
+        .map(|(group, members)| (group.clone(), members.clone()))
+        .collect();
+    for (group, members) in Groups::load(&config.workspaces_directory)?.groups {
+        let all_members = groups.entry(group).or_default();
+        for member in members {
+            if !all_members.contains(&member) {
+                all_members.push(member);
+            }
+        }
+    }
+
+    Ok(groups)
+}
+
+pub fn get_group_members(
+    config: &ConfigurationValues,
+    group: &str,
+) -> Result<Vec<String>, io::Error> {
+    get_groups(config)?.remove(group).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("There is no group named {}", group),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::test_utils::test_utilities::{context_object, FakeContext};
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_add_and_remove_members() {
+        let mut groups = Groups::default();
+        groups.add("work", &names(&["backend", "frontend"]));
+        groups.add("work", &names(&["frontend", "infra"]));
+
+        assert_eq!(
+            groups.groups["work"],
+            names(&["backend", "frontend", "infra"])
+        );
+        assert!(groups.remove("work", &names(&["frontend"])));
+        assert!(!groups.remove("work", &names(&["frontend"])));
+        assert_eq!(groups.groups["work"], names(&["backend", "infra"]));
+        assert!(groups.remove("work", &[]));
+        assert!(groups.groups.is_empty());
+    }
+
+    #[rstest]
+    fn test_groups_from_configuration_and_file_are_combined(context_object: FakeContext) {
+        let config = &context_object.config;
+        let mut groups = Groups::default();
+        groups.add("work", &names(&["frontend", "infra"]));
+        groups.add("hobby", &names(&["game"]));
+        groups.save(&config.workspaces_directory).unwrap();
+        let config = ConfigurationValues {
+            workspaces_directory: config.workspaces_directory.clone(),
+            groups: [("work".to_string(), names(&["backend", "frontend"]))].into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_group_members(&config, "work").unwrap(),
+            names(&["backend", "frontend", "infra"])
+        );
+        assert_eq!(
+            get_group_members(&config, "hobby").unwrap(),
+            names(&["game"])
+        );
+        assert!(get_group_members(&config, "chores").is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use std::{fs, io, path::PathBuf, process::Command};
+
+use crate::{
+    commands::wrap::get_environment_variables,
+    config::{expand_path, ConfigurationValues},
+    environments::Environment,
+    platform::is_executable,
+};
+
+const DEFAULT_HOOKS_DIRECTORY: &str = "${XDG_CONFIG_HOME}/enwiro/hooks";
+
+#[derive(strum_macros::Display, Clone, Copy, Debug, PartialEq, Eq)]
+#[strum(serialize_all = "kebab-case")]
+pub enum HookEvent {
+    PreActivate,
+    PostActivate,
+    PostCook,
+    PreRemove,
+    FocusEnd,
+}
+
+impl HookEvent {
+    /// Hooks run before an operation can prevent it by failing.
+    fn can_cancel(&self) -> bool {
+        matches!(self, HookEvent::PreActivate | HookEvent::PreRemove)
+    }
+}
+
+/// Runs the user's scripts for lifecycle events. The scripts for an event are
+/// the executable named after it in the hooks directory, followed by the
+/// executables in the directory of the same name with a `.d` suffix, in
+/// alphabetical order.
+pub struct Hooks {
+    directory: PathBuf,
+}
+
+impl Hooks {
+    pub fn new(config: &ConfigurationValues) -> Result<Self, io::Error> {
+        let directory = config
+            .hooks_directory
+            .as_deref()
+            .unwrap_or(DEFAULT_HOOKS_DIRECTORY);
+
+        Ok(Self {
+            directory: PathBuf::from(expand_path(directory)?),
+        })
+    }
+
+    fn get_scripts(&self, event: HookEvent) -> Vec<PathBuf> {
+        let mut scripts = vec![self.directory.join(event.to_string())];
+        if let Ok(entries) = fs::read_dir(self.directory.join(format!("{}.d", event))) {
+            let mut directory_scripts: Vec<PathBuf> =
+                entries.flatten().map(|entry| entry.path()).collect();
+            directory_scripts.sort();
+            scripts.extend(directory_scripts);
+        }
+
+        scripts
+            .into_iter()
+            .filter(|path| is_executable(path))
+            .collect()
+    }
+
+    /// Runs the scripts of an event with the details of the environment in
+    /// the same variables as `enwiro wrap`, and the event in `ENWIRO_HOOK`.
+    /// A failing script cancels the operation if the event allows it, and is
+    /// only logged otherwise.
+    pub fn run(&self, event: HookEvent, environment: &Environment) -> Result<(), io::Error> {
+        let _span = tracing::debug_span!("hooks", %event).entered();
+        for script in self.get_scripts(event) {
+            let result = Command::new(&script)
+                .current_dir(&environment.path)
+                .envs(get_environment_variables(environment, false))
+                .env("ENWIRO_HOOK", event.to_string())
+                .status()
+                .and_then(|status| match status.success() {
+                    true => Ok(()),
+                    false => Err(io::Error::other(format!(
+                        "{} hook {} failed with {}",
+                        event,
+                        script.display(),
+                        status
+                    ))),
+                });
+
+            match result {
+                Err(error) if event.can_cancel() => return Err(error),
+                Err(error) => tracing::warn!(%error, "Hook failed"),
+                Ok(()) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+    use rstest::rstest;
+
+    use crate::{
+        hooks::{HookEvent, Hooks},
+        test_utils::test_utilities::{context_object, FakeContext},
+    };
+
+    fn create_hook(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("#!/bin/sh\n{}\n", content)).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[rstest]
+    fn test_hooks_receive_environment_details(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foo");
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let hooks = Hooks::new(&context_object.config).unwrap();
+        let hooks_directory = Path::new(context_object.config.hooks_directory.as_ref().unwrap());
+        let output_path = Path::new(&workspaces_directory).join(".hook_output");
+        create_hook(
+            &hooks_directory.join("post-activate"),
+            &format!(
+                "echo \"$ENWIRO_HOOK $ENWIRO_ENV\" >> {}",
+                output_path.display()
+            ),
+        );
+        create_hook(
+            &hooks_directory.join("post-activate.d/slack"),
+            &format!("echo slack >> {}", output_path.display()),
+        );
+        let environment = context_object
+            .get_environment(&Some("foo".to_string()))
+            .unwrap();
+
+        hooks.run(HookEvent::PostActivate, &environment).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(output_path).unwrap(),
+            "post-activate foo\nslack\n"
+        );
+    }
+
+    #[rstest]
+    fn test_failing_hooks_cancel_pre_events_only(mut context_object: FakeContext) {
+        context_object.create_mock_environment("foo");
+        let hooks = Hooks::new(&context_object.config).unwrap();
+        let hooks_directory = Path::new(context_object.config.hooks_directory.as_ref().unwrap());
+        create_hook(&hooks_directory.join("pre-activate"), "exit 1");
+        create_hook(&hooks_directory.join("post-activate"), "exit 1");
+        let environment = context_object
+            .get_environment(&Some("foo".to_string()))
+            .unwrap();
+
+        assert!(hooks.run(HookEvent::PreActivate, &environment).is_err());
+        assert!(hooks.run(HookEvent::PostActivate, &environment).is_ok());
+    }
+}
@@ -1,60 +1,247 @@
+mod client;
+mod collisions;
 mod commands;
 mod config;
+mod config_validation;
 mod context;
+mod cookbook;
+mod disk_usage;
+mod environment_config;
+mod environment_metadata;
 mod environments;
+mod error_codes;
+mod groups;
+mod hooks;
+mod notifier;
+mod parallel;
+mod platform;
 mod plugin;
+mod secrets;
 mod test_utils;
-mod client;
+mod trash;
+mod usage_stats;
 
 use clap::Parser;
 
+use commands::activate::{activate, ActivateArgs};
+use commands::adopt::{adopt, AdoptArgs};
+use commands::bench::{bench, format_timings, BenchArgs};
+use commands::clone::{clone, CloneArgs};
+use commands::completions::{complete, completions, CompleteArgs, CompletionsArgs};
+use commands::config::{config, ConfigArgs};
+use commands::describe::{describe, DescribeArgs};
+#[cfg(feature = "cookbook-git")]
+use commands::fetch_git::{fetch_git, FetchGitArgs};
+use commands::focus::{focus, FocusArgs};
+use commands::gc::{gc, GcArgs};
+use commands::group::{group, GroupArgs};
+use commands::infer::{infer, InferArgs};
+use commands::info::{info, InfoArgs};
+use commands::init::{init, InitArgs};
 use commands::list_all::{list_all, ListAllArgs};
 use commands::list_environments::{list_environments, ListEnvironmentsArgs};
+use commands::lock::{lock, LockArgs};
+use commands::open_in_code::{open_in_code, OpenInCodeArgs};
+use commands::pick::{pick, PickArgs};
+use commands::plugin::{plugin, PluginArgs};
+use commands::plugins::{plugins, PluginsArgs};
+use commands::precook::{precook, PrecookArgs};
+use commands::remove::{remove, RemoveArgs};
+use commands::renumber::{renumber, RenumberArgs};
+use commands::rpc::{rpc, RpcArgs};
+use commands::serve::{serve, ServeArgs};
+use commands::shell::{shell, ShellArgs};
 use commands::show_path::{show_path, ShowPathArgs};
+use commands::stats::{stats, StatsArgs};
+use commands::undo::{undo, UndoArgs};
+use commands::watch::{watch, WatchArgs};
 use commands::wrap::{wrap, WrapArgs};
-use config::ConfigurationValues;
+use config::try_load_configuration;
+use config_validation::validate_configuration;
 use context::CommandContext;
-use std::fs::create_dir;
+use error_codes::{get_exit_code, to_json, ErrorCode, ErrorFormat};
+use std::fs::create_dir_all;
 use std::io::{Read, Write};
 use std::path::Path;
 
 #[derive(Parser)]
+#[command(author, version, about)]
+struct Cli {
+    /// Use the values of a profile defined in the configuration
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Report problems found in the configuration before running the command
+    #[arg(long, short, global = true)]
+    verbose: bool,
+
+    /// Only show notifications about errors
+    #[arg(long, short, global = true)]
+    quiet: bool,
+
+    /// How to print the error when a command fails
+    #[arg(long, global = true, value_enum, default_value_t)]
+    error_format: ErrorFormat,
+
+    /// Print how long each step of the command took, such as cooking and
+    /// switching workspaces
+    #[arg(long, global = true)]
+    timings: bool,
+
+    #[command(subcommand)]
+    command: EnwiroCli,
+}
+
+#[derive(clap::Subcommand)]
 enum EnwiroCli {
     ListEnvironments(ListEnvironmentsArgs),
     ListAll(ListAllArgs),
     ShowPath(ShowPathArgs),
+    Info(InfoArgs),
+    Describe(DescribeArgs),
     Wrap(WrapArgs),
+    Shell(ShellArgs),
+    Activate(ActivateArgs),
+    Pick(PickArgs),
+    Infer(InferArgs),
+    Stats(StatsArgs),
+    Bench(BenchArgs),
+    Precook(PrecookArgs),
+    Clone(CloneArgs),
+    Adopt(AdoptArgs),
+    Remove(RemoveArgs),
+    Group(GroupArgs),
+    Focus(FocusArgs),
+    Lock(LockArgs),
+    Gc(GcArgs),
+    Completions(CompletionsArgs),
+    #[command(name = "__enwiro_complete", hide = true)]
+    Complete(CompleteArgs),
+    #[cfg(feature = "cookbook-git")]
+    #[command(name = "__enwiro_fetch_git", hide = true)]
+    FetchGit(FetchGitArgs),
+    Renumber(RenumberArgs),
+    Watch(WatchArgs),
+    Undo(UndoArgs),
+    Rpc(RpcArgs),
+    Serve(ServeArgs),
+    OpenInCode(OpenInCodeArgs),
+    Plugins(PluginsArgs),
+    Plugin(PluginArgs),
+    Config(ConfigArgs),
+    Init(InitArgs),
 }
 
 fn ensure_can_run<R: Read, W: Write>(config: &CommandContext<R, W>) {
     let environments_directory = Path::new(&config.config.workspaces_directory);
     if !environments_directory.exists() {
-        create_dir(environments_directory)
+        create_dir_all(environments_directory)
             .expect("Workspace directory does not exist and could not be automatically created.");
     }
 }
 
+/// Prints the error of a failed command and exits with the status of its
+/// error code. When a wrapped command fails, its own exit status is used
+/// instead.
+fn exit_on_error(
+    result: Result<(), std::io::Error>,
+    format: ErrorFormat,
+) -> Result<(), std::io::Error> {
+    if let Err(error) = result {
+        match format {
+            // The wrapped command has already shown its own error
+            ErrorFormat::Text if ErrorCode::of(&error) == ErrorCode::CommandFailed => {}
+            ErrorFormat::Text => eprintln!("Error: {}", error),
+            ErrorFormat::Json => eprintln!("{}", to_json(&error)),
+        }
+        std::process::exit(get_exit_code(&error));
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<(), std::io::Error> {
-    let args = EnwiroCli::parse();
-    let config: ConfigurationValues = match confy::load("enwiro", "enwiro") {
-        Ok(x) => x,
-        Err(x) => {
-            panic!("Could not load configuration: {:?}", x);
+    let cli = Cli::parse();
+    let timings = match cli.timings {
+        true => enwiro_logging::init_logging_with_timings("enwiro").ok(),
+        false => {
+            let _ = enwiro_logging::init_logging("enwiro");
+            None
         }
     };
     let mut writer = std::io::stdout();
+
+    if let EnwiroCli::Config(args) = cli.command {
+        let result = config(&mut writer, cli.profile, args);
+        writer.write_all("\n".as_bytes()).unwrap();
+        return exit_on_error(result, cli.error_format);
+    }
+    if cli.verbose {
+        for problem in validate_configuration(cli.profile.clone()) {
+            eprintln!("Warning: {}", problem);
+        }
+    }
+
+    let mut config = match try_load_configuration(cli.profile) {
+        Ok(config) => config,
+        Err(error) => {
+            return exit_on_error(Err(ErrorCode::ConfigInvalid.tag(error)), cli.error_format)
+        }
+    };
+    if cli.quiet {
+        config.notifications.quiet = true;
+    }
     let mut reader = std::io::stdin();
     let mut context_object = CommandContext::new(config, &mut reader, &mut writer);
     ensure_can_run(&context_object);
 
-    let result = match args {
+    // Completions are read line by line, where an empty line would be a name
+    let prints_newline = !matches!(&cli.command, EnwiroCli::ShowPath(args) if args.no_newline)
+        && !matches!(&cli.command, EnwiroCli::Complete(_));
+    let result = match cli.command {
         EnwiroCli::ListEnvironments(_) => list_environments(&mut context_object),
-        EnwiroCli::ListAll(_) => list_all(&mut context_object),
+        EnwiroCli::ListAll(args) => list_all(&mut context_object, args),
         EnwiroCli::ShowPath(args) => show_path(&mut context_object, args),
+        EnwiroCli::Info(args) => info(&mut context_object, args),
+        EnwiroCli::Describe(args) => describe(&mut context_object, args),
         EnwiroCli::Wrap(args) => wrap(&mut context_object, args),
+        EnwiroCli::Shell(args) => shell(&mut context_object, args),
+        EnwiroCli::Activate(args) => activate(&mut context_object, args),
+        EnwiroCli::Pick(args) => pick(&mut context_object, args),
+        EnwiroCli::Infer(args) => infer(&mut context_object, args),
+        EnwiroCli::Stats(args) => stats(&mut context_object, args),
+        EnwiroCli::Bench(args) => bench(&mut context_object, args),
+        EnwiroCli::Precook(args) => precook(&mut context_object, args),
+        EnwiroCli::Clone(args) => clone(&mut context_object, args),
+        EnwiroCli::Adopt(args) => adopt(&mut context_object, args),
+        EnwiroCli::Remove(args) => remove(&mut context_object, args),
+        EnwiroCli::Group(args) => group(&mut context_object, args),
+        EnwiroCli::Focus(args) => focus(&mut context_object, args),
+        EnwiroCli::Lock(args) => lock(&mut context_object, args),
+        EnwiroCli::Gc(args) => gc(&mut context_object, args),
+        EnwiroCli::Completions(args) => completions(&mut context_object, args),
+        EnwiroCli::Complete(args) => complete(&mut context_object, args),
+        #[cfg(feature = "cookbook-git")]
+        EnwiroCli::FetchGit(args) => fetch_git(&mut context_object, args),
+        EnwiroCli::Renumber(args) => renumber(&mut context_object, args),
+        EnwiroCli::Watch(args) => watch(&mut context_object, args),
+        EnwiroCli::Undo(args) => undo(&mut context_object, args),
+        EnwiroCli::Rpc(args) => rpc(&mut context_object, args),
+        EnwiroCli::Serve(args) => serve(&mut context_object, args),
+        EnwiroCli::OpenInCode(args) => open_in_code(&mut context_object, args),
+        EnwiroCli::Plugins(args) => plugins(&mut context_object, args),
+        EnwiroCli::Plugin(args) => plugin(&mut context_object, args),
+        EnwiroCli::Config(_) => unreachable!(),
+        EnwiroCli::Init(args) => init(&mut context_object, args),
     };
 
-    context_object.writer.write_all("\n".as_bytes()).unwrap();
+    if prints_newline {
+        context_object.writer.write_all("\n".as_bytes()).unwrap();
+    }
+    // Printed to stderr so that output such as paths stays usable
+    if let Some(timings) = timings {
+        eprint!("{}", format_timings(&timings.get()));
+    }
 
-    return result;
+    exit_on_error(result, cli.error_format)
 }
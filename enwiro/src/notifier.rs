@@ -0,0 +1,204 @@
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::{
+    config::{NotificationsConfiguration, NotifierBackend},
+    platform::shell_command,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Success,
+    Error,
+}
+
+impl NotificationLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            NotificationLevel::Success => "success",
+            NotificationLevel::Error => "error",
+        }
+    }
+}
+
+/// Tells the user about the outcome of commands that usually run without a
+/// terminal, such as `activate` started from a launcher.
+pub trait Notifier {
+    fn notify(&self, level: NotificationLevel, message: &str);
+
+    /// Shows a notification about a long operation, returning an ID used to
+    /// update or close it. Backends that cannot update notifications show
+    /// each message as a separate notification.
+    fn create_progress(&self, message: &str) -> u32 {
+        self.notify(NotificationLevel::Success, message);
+
+        0
+    }
+
+    fn update_progress(&self, _id: u32, message: &str) {
+        self.notify(NotificationLevel::Success, message);
+    }
+
+    fn close_progress(&self, _id: u32) {}
+}
+
+#[derive(Default)]
+pub struct DesktopNotifier {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    progress_notifications: RefCell<HashMap<u32, notify_rust::NotificationHandle>>,
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl Notifier for DesktopNotifier {
+    fn notify(&self, level: NotificationLevel, message: &str) {
+        let urgency = match level {
+            NotificationLevel::Success => notify_rust::Urgency::Normal,
+            NotificationLevel::Error => notify_rust::Urgency::Critical,
+        };
+        let result = notify_rust::Notification::new()
+            .summary("enwiro")
+            .body(message)
+            .urgency(urgency)
+            .show();
+
+        // There is no notification daemon on headless machines
+        if let Err(error) = result {
+            tracing::warn!(%error, "Could not show desktop notification");
+            StderrNotifier {}.notify(level, message);
+        }
+    }
+
+    fn create_progress(&self, message: &str) -> u32 {
+        match notify_rust::Notification::new()
+            .summary("enwiro")
+            .body(message)
+            .show()
+        {
+            Ok(handle) => {
+                let id = handle.id();
+                self.progress_notifications.borrow_mut().insert(id, handle);
+
+                id
+            }
+            Err(error) => {
+                tracing::warn!(%error, "Could not show desktop notification");
+                StderrNotifier {}.create_progress(message)
+            }
+        }
+    }
+
+    /// Replaces the notification in place, using its ID.
+    fn update_progress(&self, id: u32, message: &str) {
+        match self.progress_notifications.borrow_mut().get_mut(&id) {
+            Some(handle) => {
+                handle.body(message);
+                if let Err(error) = handle.update() {
+                    tracing::warn!(%error, "Could not update desktop notification");
+                }
+            }
+            None => self.notify(NotificationLevel::Success, message),
+        }
+    }
+
+    fn close_progress(&self, id: u32) {
+        if let Some(handle) = self.progress_notifications.borrow_mut().remove(&id) {
+            handle.close();
+        }
+    }
+}
+
+/// Notifications can only be updated and given an urgency on desktops
+/// implementing the freedesktop.org specification.
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+impl Notifier for DesktopNotifier {
+    fn notify(&self, level: NotificationLevel, message: &str) {
+        let result = notify_rust::Notification::new()
+            .summary("enwiro")
+            .body(message)
+            .show();
+
+        if let Err(error) = result {
+            tracing::warn!(%error, "Could not show desktop notification");
+            StderrNotifier {}.notify(level, message);
+        }
+    }
+}
+
+/// Runs a user-provided shell command, passing the notification in the
+/// `ENWIRO_NOTIFICATION_LEVEL` and `ENWIRO_NOTIFICATION_MESSAGE` variables.
+pub struct CommandNotifier {
+    pub command: String,
+}
+
+impl Notifier for CommandNotifier {
+    fn notify(&self, level: NotificationLevel, message: &str) {
+        let result = shell_command(&self.command)
+            .env("ENWIRO_NOTIFICATION_LEVEL", level.as_str())
+            .env("ENWIRO_NOTIFICATION_MESSAGE", message)
+            .status();
+
+        match result {
+            Ok(status) if status.success() => {}
+            Ok(status) => tracing::warn!(%status, "Notification command failed"),
+            Err(error) => tracing::warn!(%error, "Could not run notification command"),
+        }
+    }
+}
+
+pub struct StderrNotifier {}
+
+impl Notifier for StderrNotifier {
+    fn notify(&self, level: NotificationLevel, message: &str) {
+        match level {
+            NotificationLevel::Success => eprintln!("{}", message),
+            NotificationLevel::Error => eprintln!("Error: {}", message),
+        }
+    }
+}
+
+pub struct NoneNotifier {}
+
+impl Notifier for NoneNotifier {
+    fn notify(&self, _level: NotificationLevel, _message: &str) {}
+}
+
+pub fn get_notifier(config: &NotificationsConfiguration) -> Box<dyn Notifier> {
+    match (&config.backend, &config.command) {
+        (NotifierBackend::Desktop, _) => Box::new(DesktopNotifier::default()),
+        (NotifierBackend::Command, Some(command)) => Box::new(CommandNotifier {
+            command: command.clone(),
+        }),
+        (NotifierBackend::Command, None) => {
+            tracing::warn!("The command notifier needs notifications.command to be set");
+            Box::new(StderrNotifier {})
+        }
+        (NotifierBackend::Stderr, _) => Box::new(StderrNotifier {}),
+        (NotifierBackend::None, _) => Box::new(NoneNotifier {}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs};
+
+    use crate::notifier::{CommandNotifier, NotificationLevel, Notifier};
+
+    #[test]
+    fn test_command_notifier_passes_notification_to_command() {
+        let output_path = temp_dir().join(format!("enwiro-notification-{}", std::process::id()));
+        let notifier = CommandNotifier {
+            command: format!(
+                "printf '%s %s' \"$ENWIRO_NOTIFICATION_LEVEL\" \"$ENWIRO_NOTIFICATION_MESSAGE\" > {}",
+                output_path.display()
+            ),
+        };
+
+        notifier.notify(NotificationLevel::Error, "Could not cook foo");
+
+        assert_eq!(
+            fs::read_to_string(&output_path).unwrap(),
+            "error Could not cook foo"
+        );
+        fs::remove_file(output_path).unwrap();
+    }
+}
@@ -0,0 +1,50 @@
+use std::{num::NonZeroUsize, thread};
+
+/// Applies a function to each item, in the same order, using at most one
+/// thread per available core. Each thread gets a chunk of the items, so that
+/// many environments do not mean as many threads.
+pub fn map_in_parallel<T: Sync, U: Send>(items: &[T], function: impl Fn(&T) -> U + Sync) -> Vec<U> {
+    let thread_count = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = items.len().div_ceil(thread_count).max(1);
+    let function = &function;
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(function).collect::<Vec<_>>()))
+            .collect();
+
+        // Dropping the chunk of a panicked thread would misalign the results
+        // with the items they belong to
+        handles
+            .into_iter()
+            .flat_map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::parallel::map_in_parallel;
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(1000)]
+    fn test_map_in_parallel_keeps_order(#[case] length: usize) {
+        let items: Vec<usize> = (0..length).collect();
+
+        let doubled = map_in_parallel(&items, |item| item * 2);
+
+        assert_eq!(
+            doubled,
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use std::{
+    borrow::Cow,
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+/// Extensions of executables on Windows when `PATHEXT` is not set.
+#[cfg(windows)]
+const DEFAULT_PATHEXT: &str = ".COM;.EXE;.BAT;.CMD";
+
+/// Whether a file extension makes a file executable on Windows, according to
+/// `PATHEXT`.
+#[cfg(windows)]
+fn is_executable_extension(extension: &str) -> bool {
+    std::env::var("PATHEXT")
+        .unwrap_or_else(|_| DEFAULT_PATHEXT.to_string())
+        .split(';')
+        .any(|executable_extension| {
+            executable_extension
+                .strip_prefix('.')
+                .is_some_and(|executable_extension| {
+                    executable_extension.eq_ignore_ascii_case(extension)
+                })
+        })
+}
+
+/// Links an environment to its directory.
+#[cfg(unix)]
+pub fn link_directory(target: &Path, link: &Path) -> Result<(), io::Error> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Links an environment to its directory. Creating symbolic links needs
+/// special privileges on Windows, so a junction is created when a symbolic
+/// link cannot be.
+#[cfg(windows)]
+pub fn link_directory(target: &Path, link: &Path) -> Result<(), io::Error> {
+    if std::os::windows::fs::symlink_dir(target, link).is_ok() {
+        return Ok(());
+    }
+
+    let output = std::process::Command::new("cmd")
+        .args(["/C", "mklink", "/J"])
+        .arg(link)
+        .arg(target)
+        .output()?;
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(io::Error::other(format!(
+            "Could not create junction: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
+
+/// Removes a link created by `link_directory`, leaving its target alone.
+#[cfg(unix)]
+pub fn remove_link(link: &Path) -> Result<(), io::Error> {
+    fs::remove_file(link)
+}
+
+/// Removes a link created by `link_directory`, leaving its target alone.
+/// Windows removes links to directories like directories.
+#[cfg(windows)]
+pub fn remove_link(link: &Path) -> Result<(), io::Error> {
+    fs::remove_dir(link)
+}
+
+/// Points a link at a directory, replacing a link left behind by an earlier
+/// attempt whose target is gone, such as an interrupted cook. The link is
+/// created under a hidden temporary name first and then renamed, so that it
+/// never exists half made. Anything that is not a link, and links to another
+/// directory that still exists, are left alone.
+pub fn replace_link(target: &Path, link: &Path) -> Result<(), io::Error> {
+    match fs::symlink_metadata(link) {
+        Ok(_) if fs::read_link(link).is_ok_and(|current_target| current_target == target) => {
+            return Ok(())
+        }
+        Ok(metadata) if !metadata.file_type().is_symlink() => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a link", link.display()),
+            ))
+        }
+        // The link is only stale if its target cannot be reached
+        Ok(_) if fs::metadata(link).is_ok() => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already links to another directory", link.display()),
+            ))
+        }
+        _ => {}
+    }
+    let file_name = link
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Link has no name"))?;
+    let temporary_link = link.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    if fs::symlink_metadata(&temporary_link).is_ok() {
+        remove_link(&temporary_link)?;
+    }
+    link_directory(target, &temporary_link)?;
+    // Windows cannot rename over an existing link
+    #[cfg(windows)]
+    if fs::symlink_metadata(link).is_ok() {
+        remove_link(link)?;
+    }
+
+    fs::rename(&temporary_link, link).inspect_err(|_| {
+        let _ = remove_link(&temporary_link);
+    })
+}
+
+/// Whether a file can be run as a program.
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Whether a file can be run as a program, which on Windows depends on its
+/// extension.
+#[cfg(windows)]
+pub fn is_executable(path: &Path) -> bool {
+    fs::metadata(path).is_ok_and(|metadata| metadata.is_file())
+        && path
+            .extension()
+            .is_some_and(|extension| is_executable_extension(&extension.to_string_lossy()))
+}
+
+/// Allows running a file as a program.
+#[cfg(unix)]
+pub fn make_executable(path: &Path) -> Result<(), io::Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+/// Allows running a file as a program, which on Windows only depends on its
+/// extension.
+#[cfg(windows)]
+pub fn make_executable(_path: &Path) -> Result<(), io::Error> {
+    Ok(())
+}
+
+/// Name of a program, given the name of its executable.
+#[cfg(unix)]
+pub fn get_program_name(file_name: &str) -> &str {
+    file_name
+}
+
+/// Name of a program, given the name of its executable, without the
+/// extension that makes it executable on Windows, such as `.exe`.
+#[cfg(windows)]
+pub fn get_program_name(file_name: &str) -> &str {
+    match file_name.rsplit_once('.') {
+        Some((name, extension)) if is_executable_extension(extension) => name,
+        _ => file_name,
+    }
+}
+
+/// Exit status of a finished process. Processes killed by a signal get 128
+/// plus the signal, like in shells.
+#[cfg(unix)]
+pub fn get_exit_code(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
+}
+
+/// Exit status of a finished process.
+#[cfg(windows)]
+pub fn get_exit_code(status: ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
+}
+
+/// Command running a command line written by the user, such as a
+/// notification command.
+#[cfg(unix)]
+pub fn shell_command(command_line: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(command_line);
+    command
+}
+
+/// Command running a command line written by the user, such as a
+/// notification command. Windows has no `sh`, so `cmd` runs it.
+#[cfg(windows)]
+pub fn shell_command(command_line: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+
+    let mut command = Command::new("cmd");
+    command.arg("/C").raw_arg(command_line);
+    command
+}
+
+/// Reads a path printed by another program. Paths are arbitrary bytes on
+/// Unix.
+#[cfg(unix)]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+/// Reads a path printed by another program, which has to be valid UTF-8 on
+/// Windows.
+#[cfg(windows)]
+pub fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).to_string())
+}
+
+/// Bytes of a path for printing, the reverse of `path_from_bytes`.
+#[cfg(unix)]
+pub fn path_to_bytes(path: &Path) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+/// Bytes of a path for printing, the reverse of `path_from_bytes`.
+#[cfg(windows)]
+pub fn path_to_bytes(path: &Path) -> Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(path) => Cow::Borrowed(path.as_bytes()),
+        Cow::Owned(path) => Cow::Owned(path.into_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs, path::PathBuf};
+
+    use rand::Rng;
+
+    use crate::platform::{link_directory, remove_link, replace_link};
+
+    fn get_temporary_directory() -> PathBuf {
+        temp_dir().join(format!(
+            "enwiro-link-{}",
+            rand::thread_rng().gen_range(100000000..999999999)
+        ))
+    }
+
+    #[test]
+    fn test_removing_link_keeps_target() {
+        let directory = get_temporary_directory();
+        let target = directory.join("target");
+        let link = directory.join("link");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("file"), "content").unwrap();
+
+        link_directory(&target, &link).unwrap();
+        assert_eq!(fs::read_to_string(link.join("file")).unwrap(), "content");
+        remove_link(&link).unwrap();
+
+        assert!(!link.exists());
+        assert!(target.join("file").exists());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_replacing_link_repairs_stale_links() {
+        let directory = get_temporary_directory();
+        let target = directory.join("target");
+        let link = directory.join("link");
+        fs::create_dir_all(&target).unwrap();
+        link_directory(&directory.join("gone"), &link).unwrap();
+
+        replace_link(&target, &link).unwrap();
+        replace_link(&target, &link).unwrap();
+
+        assert_eq!(fs::read_link(&link).unwrap(), target);
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 2);
+        assert!(replace_link(&link, &target).is_err());
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn test_replacing_link_keeps_links_to_other_directories() {
+        let directory = get_temporary_directory();
+        let target = directory.join("target");
+        let other_target = directory.join("other");
+        let link = directory.join("link");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(&other_target).unwrap();
+        link_directory(&other_target, &link).unwrap();
+
+        let error = replace_link(&target, &link).unwrap_err();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_link(&link).unwrap(), other_target);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
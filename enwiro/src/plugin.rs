@@ -1,11 +1,27 @@
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::UNIX_EPOCH,
+};
 
-use path_lookup::iterate_executables;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::platform::{get_program_name, is_executable};
+
+const PLUGIN_PATH_VARIABLE: &str = "ENWIRO_PLUGIN_PATH";
+const PLUGIN_CACHE_FILE_NAME: &str = "plugins.json";
+
+/// Version of the protocol spoken between enwiro and its plugins. Plugins
+/// without a `metadata` command predate versioning and are treated as version 0.
+pub const PROTOCOL_VERSION: u32 = 2;
 
 #[derive(strum_macros::Display, Hash, Eq, PartialEq, Clone, Debug)]
 pub enum PluginKind {
     Adapter,
     Cookbook,
+    Gear,
 }
 
 #[derive(Hash, Eq, PartialEq, Debug)]
@@ -15,24 +31,388 @@ pub struct Plugin {
     pub executable: String,
 }
 
-pub fn get_plugins(plugin_kind: PluginKind) -> HashSet<Plugin> {
+/// Information a plugin prints as JSON when invoked with `metadata`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PluginMetadata {
+    pub protocol_version: u32,
+    pub description: Option<String>,
+
+    // Cookbooks with a higher priority are used first, unless configured otherwise
+    pub default_priority: Option<i32>,
+
+    // Whether the cookbook supports the `prefetch` command
+    pub prefetch: bool,
+
+    // Whether the cookbook supports the `describe` command
+    pub describe: bool,
+
+    // Whether the cookbook supports the `uncook` command
+    pub uncook: bool,
+
+    // Whether the adapter supports the `hide-workspaces` and `show-workspaces`
+    // commands
+    pub hide_workspaces: bool,
+
+    // Whether the adapter accepts `--output` arguments with `activate`
+    pub outputs: bool,
+
+    // Whether the adapter supports the `list-workspaces` and `rename-workspace`
+    // commands
+    pub workspaces: bool,
+
+    // Whether the adapter supports the `get-focused-window` command
+    pub focused_window: bool,
+
+    // Whether the adapter accepts `--rank` with `activate`, and supports the
+    // `renumber-workspaces` command
+    pub ranks: bool,
+
+    // Whether the adapter supports the `watch-focus` command
+    pub watch_focus: bool,
+}
+
+impl Plugin {
+    /// Asks the plugin for its metadata. Returns `None` if the plugin does not
+    /// support the `metadata` command, and an error if it cannot be run at all.
+    pub fn query_metadata(&self) -> Result<Option<PluginMetadata>, io::Error> {
+        let output = Command::new(&self.executable).arg("metadata").output()?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        Ok(serde_json::from_slice(&output.stdout).ok())
+    }
+
+    /// Returns the metadata of the plugin. Plugins that do not support the
+    /// `metadata` command get the default metadata of protocol version 0.
+    pub fn get_metadata(&self) -> PluginMetadata {
+        let metadata = self.query_metadata().ok().flatten().unwrap_or_default();
+
+        if metadata.protocol_version > PROTOCOL_VERSION {
+            eprintln!(
+                "Warning: {} uses protocol version {}, but this version of enwiro only supports up to {}. Consider upgrading enwiro.",
+                self.executable, metadata.protocol_version, PROTOCOL_VERSION
+            );
+        }
+
+        metadata
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct DirectoryStamp {
+    path: PathBuf,
+    // Modification time in nanoseconds, if the directory exists
+    modified: Option<u128>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct CandidateStamp {
+    path: PathBuf,
+    // Modification time in nanoseconds, if the file exists
+    modified: Option<u128>,
+    executable: bool,
+}
+
+/// Plugin executables found in the searched directories. It stays valid until
+/// one of the directories is modified, the search path changes, or one of the
+/// files named like a plugin is replaced or made (not) executable, which does
+/// not modify its directory.
+#[derive(Debug, Serialize, Deserialize)]
+struct PluginCache {
+    directories: Vec<DirectoryStamp>,
+    candidates: Vec<CandidateStamp>,
+    executables: Vec<PathBuf>,
+}
+
+/// Directory where `enwiro plugin install` puts plugins.
+pub fn get_installed_plugins_directory() -> Option<PathBuf> {
+    Some(home::home_dir()?.join(".local/libexec/enwiro"))
+}
+
+/// Directories searched for plugins: `ENWIRO_PLUGIN_PATH` if it is set,
+/// otherwise `PATH` followed by the directory of installed plugins.
+fn get_search_path() -> Vec<PathBuf> {
+    if let Some(plugin_path) = env::var_os(PLUGIN_PATH_VARIABLE) {
+        return env::split_paths(&plugin_path).collect();
+    }
+
+    let mut search_path: Vec<PathBuf> =
+        env::split_paths(&env::var_os("PATH").unwrap_or_default()).collect();
+    search_path.extend(get_installed_plugins_directory());
+
+    search_path
+}
+
+fn get_cache_path() -> Option<PathBuf> {
+    let cache_directory = match env::var_os("XDG_CACHE_HOME") {
+        Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+        _ => home::home_dir()?.join(".cache"),
+    };
+
+    Some(cache_directory.join("enwiro").join(PLUGIN_CACHE_FILE_NAME))
+}
+
+fn get_modified_nanos(path: &Path) -> Option<u128> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+}
+
+fn stamp_directories(directories: &[PathBuf]) -> Vec<DirectoryStamp> {
+    directories
+        .iter()
+        .map(|directory| DirectoryStamp {
+            path: directory.clone(),
+            modified: get_modified_nanos(directory),
+        })
+        .collect()
+}
+
+fn stamp_candidate(path: &Path) -> CandidateStamp {
+    CandidateStamp {
+        path: path.to_path_buf(),
+        modified: get_modified_nanos(path),
+        executable: is_executable(path),
+    }
+}
+
+fn get_plugin_name(path: &Path) -> String {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    get_program_name(&file_name).to_string()
+}
+
+/// Finds all files named like enwiro plugins in the given directories,
+/// whether they are executable or not.
+fn find_plugin_candidates(directories: &[PathBuf]) -> Vec<CandidateStamp> {
+    directories
+        .iter()
+        .filter_map(|directory| fs::read_dir(directory).ok())
+        .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+        .filter(|path| get_plugin_name(path).starts_with("enwiro-"))
+        .map(|path| stamp_candidate(&path))
+        .collect()
+}
+
+/// Picks the executables among the candidates. When the same executable
+/// exists in several directories, the first one wins, just like when looking
+/// up commands in `PATH`.
+fn select_executables(candidates: &[CandidateStamp]) -> Vec<PathBuf> {
+    let mut seen_names = HashSet::new();
+
+    candidates
+        .iter()
+        .filter(|candidate| candidate.executable)
+        .filter(|candidate| seen_names.insert(get_plugin_name(&candidate.path)))
+        .map(|candidate| candidate.path.clone())
+        .collect()
+}
+
+/// Returns the plugin executables in the search path, reusing the results of
+/// the previous search if none of the directories and candidates changed since.
+fn get_plugin_executables(search_path: &[PathBuf], cache_path: Option<&Path>) -> Vec<PathBuf> {
+    let directories = stamp_directories(search_path);
+
+    let cached = cache_path
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str::<PluginCache>(&content).ok());
+    if let Some(cache) = cached {
+        let candidates_unchanged = cache
+            .candidates
+            .iter()
+            .all(|candidate| stamp_candidate(&candidate.path) == *candidate);
+        if cache.directories == directories && candidates_unchanged {
+            return cache.executables;
+        }
+    }
+
+    let candidates = find_plugin_candidates(search_path);
+    let cache = PluginCache {
+        executables: select_executables(&candidates),
+        directories,
+        candidates,
+    };
+
+    // Failing to write the cache only makes the next search slower
+    if let Some(path) = cache_path {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(content) = serde_json::to_string(&cache) {
+            let _ = fs::write(path, content);
+        }
+    }
+
+    cache.executables
+}
+
+fn select_plugins(
+    executables: &[PathBuf],
+    plugin_kind: PluginKind,
+    allowed_plugins: Option<&[String]>,
+) -> HashSet<Plugin> {
     let mut results = HashSet::new();
-    let expected_prefix = format!("enwiro-{}-", plugin_kind.to_string())
+    let expected_prefix = format!("enwiro-{}-", plugin_kind)
         .to_string()
         .to_lowercase();
 
-    for executable in iterate_executables() {
-        if executable.starts_with(&expected_prefix) {
+    for executable in executables {
+        let file_name = match executable.file_name() {
+            Some(file_name) => get_program_name(&file_name.to_string_lossy()).to_string(),
+            None => continue,
+        };
+        if let Some(allowed_plugins) = allowed_plugins {
+            if !allowed_plugins.contains(&file_name) {
+                continue;
+            }
+        }
+        if let Some(name) = file_name.strip_prefix(&expected_prefix) {
             results.insert(Plugin {
-                name: executable
-                    .strip_prefix(&expected_prefix)
-                    .unwrap()
-                    .to_string(),
+                name: name.to_string(),
                 kind: plugin_kind.clone(),
-                executable,
+                executable: executable.to_string_lossy().to_string(),
             });
         }
     }
 
     results
 }
+
+/// Discovers plugins of the given kind. If `allowed_plugins` is set, only
+/// plugins whose executable name is in the list are returned.
+pub fn get_plugins(plugin_kind: PluginKind, allowed_plugins: Option<&[String]>) -> HashSet<Plugin> {
+    let executables = get_plugin_executables(&get_search_path(), get_cache_path().as_deref());
+
+    select_plugins(&executables, plugin_kind, allowed_plugins)
+}
+
+/// Like `get_plugins`, but always searches the directories instead of reusing
+/// the results of the previous search.
+pub fn get_plugins_uncached(
+    plugin_kind: PluginKind,
+    allowed_plugins: Option<&[String]>,
+) -> HashSet<Plugin> {
+    let executables = get_plugin_executables(&get_search_path(), None);
+
+    select_plugins(&executables, plugin_kind, allowed_plugins)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+    use rand::Rng;
+
+    use crate::plugin::{
+        find_plugin_candidates, get_plugin_executables, select_executables, select_plugins, Plugin,
+        PluginKind,
+    };
+
+    fn create_directory() -> PathBuf {
+        let directory = temp_dir().join(
+            rand::thread_rng()
+                .gen_range(100000000..999999999)
+                .to_string(),
+        );
+        fs::create_dir(&directory).expect("Could not create temporary directory");
+
+        directory
+    }
+
+    fn create_file(path: PathBuf, mode: u32) -> PathBuf {
+        fs::write(&path, "").expect("Could not create file");
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode))
+            .expect("Could not set permissions");
+
+        path
+    }
+
+    #[test]
+    fn test_find_plugin_executables_prefers_earlier_directories() {
+        let first_directory = create_directory();
+        let second_directory = create_directory();
+        let git_cookbook = create_file(first_directory.join("enwiro-cookbook-git"), 0o755);
+        create_file(second_directory.join("enwiro-cookbook-git"), 0o755);
+        let i3_adapter = create_file(second_directory.join("enwiro-adapter-i3wm"), 0o755);
+        create_file(second_directory.join("enwiro-cookbook-broken"), 0o644);
+        create_file(second_directory.join("ls"), 0o755);
+
+        let mut executables = select_executables(&find_plugin_candidates(&[
+            first_directory,
+            second_directory,
+        ]));
+        executables.sort();
+
+        let mut expected = vec![git_cookbook, i3_adapter];
+        expected.sort();
+        assert_eq!(executables, expected);
+    }
+
+    #[test]
+    fn test_plugin_cache_notices_files_made_executable() {
+        let directory = create_directory();
+        let cache_path = create_directory().join("plugins.json");
+        let search_path = [directory.clone()];
+        let git_cookbook = create_file(directory.join("enwiro-cookbook-git"), 0o644);
+
+        assert!(get_plugin_executables(&search_path, Some(&cache_path)).is_empty());
+        assert!(cache_path.exists());
+
+        fs::set_permissions(&git_cookbook, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            get_plugin_executables(&search_path, Some(&cache_path)),
+            vec![git_cookbook]
+        );
+    }
+
+    #[test]
+    fn test_select_plugins_respects_allowed_plugins() {
+        let executables = vec![
+            PathBuf::from("/bin/enwiro-cookbook-git"),
+            PathBuf::from("/bin/enwiro-cookbook-chezmoi"),
+            PathBuf::from("/bin/enwiro-adapter-i3wm"),
+        ];
+
+        let all_cookbooks = select_plugins(&executables, PluginKind::Cookbook, None);
+        let allowed_cookbooks = select_plugins(
+            &executables,
+            PluginKind::Cookbook,
+            Some(&["enwiro-cookbook-git".to_string()]),
+        );
+
+        assert_eq!(all_cookbooks.len(), 2);
+        assert_eq!(allowed_cookbooks.len(), 1);
+        let plugin = allowed_cookbooks.into_iter().next().unwrap();
+        assert_eq!(plugin.name, "git");
+        assert_eq!(plugin.executable, "/bin/enwiro-cookbook-git");
+    }
+
+    #[test]
+    fn test_get_metadata_falls_back_to_legacy_protocol() {
+        let directory = create_directory();
+        let current_plugin = create_file(directory.join("enwiro-cookbook-current"), 0o755);
+        fs::write(
+            &current_plugin,
+            "#!/bin/sh\necho '{\"protocolVersion\": 1, \"description\": \"Current\"}'\n",
+        )
+        .unwrap();
+        let legacy_plugin = create_file(directory.join("enwiro-cookbook-legacy"), 0o755);
+        fs::write(&legacy_plugin, "#!/bin/sh\nexit 2\n").unwrap();
+
+        let plugin = |executable: PathBuf| Plugin {
+            name: "".to_string(),
+            kind: PluginKind::Cookbook,
+            executable: executable.to_string_lossy().to_string(),
+        };
+        let current_metadata = plugin(current_plugin).get_metadata();
+        let legacy_metadata = plugin(legacy_plugin).get_metadata();
+
+        assert_eq!(current_metadata.protocol_version, 1);
+        assert_eq!(current_metadata.description, Some("Current".to_string()));
+        assert_eq!(legacy_metadata.protocol_version, 0);
+        assert_eq!(legacy_metadata.description, None);
+    }
+}
@@ -0,0 +1,106 @@
+use std::{io, process::Command};
+
+use serde_json::{Map, Value};
+
+use crate::platform::shell_command;
+
+/// Suffix of settings whose value is a shell command printing the secret,
+/// for example `token_cmd = "pass show github"`.
+const COMMAND_SUFFIX: &str = "_cmd";
+
+/// Suffix of settings whose value holds the attributes of a secret stored by
+/// libsecret, for example `token_secret = { service = "github" }`.
+const LIBSECRET_SUFFIX: &str = "_secret";
+
+fn get_output(command: &mut Command) -> Result<String, io::Error> {
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+fn resolve_secret(suffix: &str, value: &Value) -> Result<String, io::Error> {
+    match (suffix, value) {
+        (COMMAND_SUFFIX, Value::String(command)) => get_output(&mut shell_command(command)),
+        (LIBSECRET_SUFFIX, Value::Object(attributes)) => {
+            let mut command = Command::new("secret-tool");
+            command.arg("lookup");
+            for (attribute, value) in attributes {
+                command
+                    .arg(attribute)
+                    .arg(value.as_str().unwrap_or_default());
+            }
+            get_output(&mut command)
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Unsupported secret definition",
+        )),
+    }
+}
+
+/// Replaces secret definitions in cookbook settings with the secrets
+/// themselves, so that tokens do not have to be stored in the configuration
+/// file. `token_cmd` and `token_secret` both become `token`. Secrets that
+/// cannot be resolved are left out, letting the cookbook report them missing.
+pub fn resolve_secrets(settings: Value) -> Value {
+    let table = match settings {
+        Value::Object(table) => table,
+        Value::Array(values) => {
+            return Value::Array(values.into_iter().map(resolve_secrets).collect())
+        }
+        value => return value,
+    };
+
+    let mut resolved_table = Map::new();
+    for (key, value) in table {
+        let secret_definition = [COMMAND_SUFFIX, LIBSECRET_SUFFIX]
+            .into_iter()
+            .find_map(|suffix| Some((key.strip_suffix(suffix)?, suffix)));
+        match secret_definition {
+            Some((name, suffix)) => match resolve_secret(suffix, &value) {
+                Ok(secret) => {
+                    resolved_table.insert(name.to_string(), Value::String(secret));
+                }
+                Err(error) => tracing::warn!(%error, key, "Could not resolve secret"),
+            },
+            None => {
+                resolved_table.insert(key, resolve_secrets(value));
+            }
+        }
+    }
+
+    Value::Object(resolved_table)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::secrets::resolve_secrets;
+
+    #[test]
+    fn test_resolve_secrets() {
+        let settings = json!({
+            "token_cmd": "printf 'hunter2\\n'",
+            "user": "kantord",
+            "jira": {"token_cmd": "echo nested"},
+            "broken_cmd": "exit 1",
+        });
+
+        assert_eq!(
+            resolve_secrets(settings),
+            json!({
+                "token": "hunter2",
+                "user": "kantord",
+                "jira": {"token": "nested"},
+            })
+        );
+    }
+}
@@ -2,41 +2,321 @@
 pub mod test_utilities {
 
     use std::{
+        cell::RefCell,
+        collections::{BTreeMap, HashMap},
         env::temp_dir,
         fs::create_dir,
         io::{Cursor, Read},
         path::Path,
+        rc::Rc,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
     };
 
     use rand::Rng;
     use rstest::fixture;
 
     use crate::{
-        commands::adapter::EnwiroAdapterTrait, config::ConfigurationValues, context::CommandContext,
+        client::{CookResponse, Recipe},
+        commands::adapter::{EnwiroAdapterTrait, FocusedWindow},
+        config::ConfigurationValues,
+        context::CommandContext,
+        cookbook::CookbookTrait,
+        environment_metadata::EnvironmentMetadata,
+        error_codes::ErrorCode,
+        notifier::{NotificationLevel, Notifier},
     };
 
+    #[derive(Clone)]
     pub struct EnwiroAdapterMock {
         pub current_environment: String,
+
+        // Environments activated so far, shared between clones
+        pub activated_environments: Rc<RefCell<Vec<String>>>,
+
+        // Outputs passed along with each activated environment
+        pub activated_outputs: Rc<RefCell<Vec<BTreeMap<String, String>>>>,
+
+        pub workspaces: Vec<String>,
+
+        pub focused_window: FocusedWindow,
+
+        // Workspaces renamed so far, from and to, shared between clones
+        pub renamed_workspaces: Rc<RefCell<Vec<(String, String)>>>,
+
+        // Rank passed along with each activated environment
+        pub activated_ranks: Rc<RefCell<Vec<Option<usize>>>>,
+
+        // Environments passed to each renumbering, the most used first
+        pub renumbered_workspaces: Rc<RefCell<Vec<Vec<String>>>>,
+
+        // Environments whose workspaces were hidden each time
+        pub hidden_workspaces: Rc<RefCell<Vec<Vec<String>>>>,
+
+        // Environments whose workspaces were shown again each time
+        pub shown_workspaces: Rc<RefCell<Vec<Vec<String>>>>,
+
+        // Error that asking for the active environment fails with, if any
+        pub active_environment_error: Option<String>,
+
+        pub supports_ranks: bool,
+
+        pub supports_hiding_workspaces: bool,
+
+        // Environments of the workspaces focused while watching focus
+        pub focused_workspaces: Vec<String>,
     }
 
     impl EnwiroAdapterTrait for EnwiroAdapterMock {
         fn get_active_environment_name(&self) -> Result<String, std::io::Error> {
-            Ok(self.current_environment.to_string())
+            match &self.active_environment_error {
+                Some(error) => {
+                    Err(ErrorCode::AdapterFailed.error(std::io::ErrorKind::Other, error.clone()))
+                }
+                None => Ok(self.current_environment.to_string()),
+            }
         }
 
         fn get_active_lens_name(&self) -> Result<String, std::io::Error> {
             Ok("".to_string())
         }
+
+        fn activate(
+            &self,
+            environment_name: &str,
+            outputs: &BTreeMap<String, String>,
+            rank: Option<usize>,
+        ) -> Result<(), std::io::Error> {
+            self.activated_environments
+                .borrow_mut()
+                .push(environment_name.to_string());
+            self.activated_outputs.borrow_mut().push(outputs.clone());
+            self.activated_ranks.borrow_mut().push(rank);
+
+            Ok(())
+        }
+
+        fn list_workspaces(&self) -> Result<Vec<String>, std::io::Error> {
+            Ok(self.workspaces.clone())
+        }
+
+        fn rename_workspace(
+            &self,
+            workspace_name: &str,
+            new_environment_name: &str,
+        ) -> Result<(), std::io::Error> {
+            self.renamed_workspaces
+                .borrow_mut()
+                .push((workspace_name.to_string(), new_environment_name.to_string()));
+
+            Ok(())
+        }
+
+        fn get_focused_window(&self) -> Result<FocusedWindow, std::io::Error> {
+            Ok(self.focused_window.clone())
+        }
+
+        fn supports_ranks(&self) -> bool {
+            self.supports_ranks
+        }
+
+        fn renumber_workspaces(
+            &self,
+            ranked_environment_names: &[String],
+        ) -> Result<(), std::io::Error> {
+            self.renumbered_workspaces
+                .borrow_mut()
+                .push(ranked_environment_names.to_vec());
+
+            Ok(())
+        }
+
+        fn supports_hiding_workspaces(&self) -> bool {
+            self.supports_hiding_workspaces
+        }
+
+        fn set_workspaces_hidden(
+            &self,
+            environment_names: &[String],
+            hidden: bool,
+        ) -> Result<(), std::io::Error> {
+            if !self.supports_hiding_workspaces {
+                return Err(ErrorCode::AdapterUnavailable.error(
+                    std::io::ErrorKind::Unsupported,
+                    "The adapter does not support hiding workspaces",
+                ));
+            }
+            let workspaces = match hidden {
+                true => &self.hidden_workspaces,
+                false => &self.shown_workspaces,
+            };
+            workspaces.borrow_mut().push(environment_names.to_vec());
+
+            Ok(())
+        }
+
+        fn watch_focus(
+            &self,
+            on_focus: &mut dyn FnMut(&str) -> Result<(), std::io::Error>,
+        ) -> Result<(), std::io::Error> {
+            self.focused_workspaces
+                .iter()
+                .try_for_each(|name| on_focus(name))
+        }
     }
 
     impl EnwiroAdapterMock {
         pub fn new(current_environment: &str) -> Self {
             Self {
                 current_environment: current_environment.to_string(),
+                activated_environments: Rc::new(RefCell::new(vec![])),
+                activated_outputs: Rc::new(RefCell::new(vec![])),
+                workspaces: vec![],
+                focused_window: FocusedWindow::default(),
+                renamed_workspaces: Rc::new(RefCell::new(vec![])),
+                activated_ranks: Rc::new(RefCell::new(vec![])),
+                renumbered_workspaces: Rc::new(RefCell::new(vec![])),
+                hidden_workspaces: Rc::new(RefCell::new(vec![])),
+                shown_workspaces: Rc::new(RefCell::new(vec![])),
+                active_environment_error: None,
+                supports_ranks: true,
+                supports_hiding_workspaces: true,
+                focused_workspaces: vec![],
             }
         }
     }
 
+    /// Offers the given recipes and records what it was asked to do, sharing
+    /// the records between its clones.
+    #[derive(Clone)]
+    pub struct CookbookMock {
+        pub name: &'static str,
+        pub priority: i32,
+        pub recipe_names: Vec<String>,
+        pub recipe_description: Option<String>,
+
+        // How long listing the recipes takes
+        pub delay: Duration,
+
+        // Error that listing the recipes fails with, if any
+        pub list_error: Option<String>,
+
+        // Path of the environments it cooks
+        pub path: String,
+
+        // Error that prefetching fails with, if any
+        pub prefetch_error: Option<String>,
+
+        // Whether `describe` describes recipes
+        pub describes: bool,
+
+        // How many times the recipes were listed
+        pub listings: Arc<Mutex<usize>>,
+
+        pub cooked_recipes: Arc<Mutex<Vec<String>>>,
+        pub prefetched_recipes: Arc<Mutex<Vec<String>>>,
+        pub uncooked_recipes: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl CookbookTrait for CookbookMock {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn priority(&self, _configured_priorities: &HashMap<String, i32>) -> i32 {
+            self.priority
+        }
+
+        fn stream_recipes(
+            &self,
+            on_recipe: &mut dyn FnMut(Recipe) -> Result<(), std::io::Error>,
+        ) -> Result<(), std::io::Error> {
+            *self.listings.lock().unwrap() += 1;
+            thread::sleep(self.delay);
+            if let Some(error) = &self.list_error {
+                return Err(std::io::Error::other(error.clone()));
+            }
+            for name in &self.recipe_names {
+                on_recipe(Recipe {
+                    name: name.clone(),
+                    description: self.recipe_description.clone(),
+                    category: None,
+                })?;
+            }
+
+            Ok(())
+        }
+
+        fn cook(&self, recipe: &str) -> Result<CookResponse, std::io::Error> {
+            self.cooked_recipes.lock().unwrap().push(recipe.to_string());
+
+            Ok(CookResponse {
+                path: self.path.clone().into(),
+                metadata: EnvironmentMetadata::default(),
+            })
+        }
+
+        fn prefetch(&self, recipe: &str) -> Result<(), std::io::Error> {
+            self.prefetched_recipes
+                .lock()
+                .unwrap()
+                .push(recipe.to_string());
+
+            match &self.prefetch_error {
+                Some(error) => Err(std::io::Error::other(error.clone())),
+                None => Ok(()),
+            }
+        }
+
+        fn describe(&self, recipe: &str) -> Result<Option<String>, std::io::Error> {
+            Ok(self.describes.then(|| format!("All about {}", recipe)))
+        }
+
+        fn uncook(&self, recipe: &str) -> Result<(), std::io::Error> {
+            self.uncooked_recipes
+                .lock()
+                .unwrap()
+                .push(recipe.to_string());
+
+            Ok(())
+        }
+    }
+
+    impl CookbookMock {
+        pub fn new(name: &'static str, recipe_names: &[&str]) -> Self {
+            Self {
+                name,
+                priority: 0,
+                recipe_names: recipe_names.iter().map(|name| name.to_string()).collect(),
+                recipe_description: None,
+                delay: Duration::ZERO,
+                list_error: None,
+                path: "/tmp".to_string(),
+                prefetch_error: None,
+                describes: false,
+                listings: Arc::new(Mutex::new(0)),
+                cooked_recipes: Arc::new(Mutex::new(vec![])),
+                prefetched_recipes: Arc::new(Mutex::new(vec![])),
+                uncooked_recipes: Arc::new(Mutex::new(vec![])),
+            }
+        }
+    }
+
+    /// Records notifications, sharing them between its clones.
+    #[derive(Clone, Default)]
+    pub struct NotifierMock {
+        pub notifications: Rc<RefCell<Vec<(NotificationLevel, String)>>>,
+    }
+
+    impl Notifier for NotifierMock {
+        fn notify(&self, level: NotificationLevel, message: &str) {
+            self.notifications
+                .borrow_mut()
+                .push((level, message.to_string()));
+        }
+    }
+
     pub type FakeIO = Cursor<Vec<u8>>;
     pub type FakeContext = CommandContext<Cursor<Vec<u8>>, Cursor<Vec<u8>>>;
 
@@ -74,14 +354,31 @@ pub mod test_utilities {
         create_dir(&temporary_directory_path).expect("Could not create temporary directory");
         let reader = in_memory_buffer();
         let writer = in_memory_buffer();
-        let mut config = ConfigurationValues::default();
-        config.workspaces_directory = temporary_directory_path.to_str().unwrap().to_string();
+        let config = ConfigurationValues {
+            workspaces_directory: temporary_directory_path.to_str().unwrap().to_string(),
+            trash_directory: Some(
+                temporary_directory_path
+                    .join(".trash")
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            ),
+            hooks_directory: Some(
+                temporary_directory_path
+                    .join(".hooks")
+                    .to_str()
+                    .unwrap()
+                    .to_string(),
+            ),
+            ..Default::default()
+        };
 
-        return CommandContext {
+        CommandContext {
             config,
             reader,
             writer,
             adapter: Box::new(EnwiroAdapterMock::new("foobaz")),
-        };
+            notifier: Box::new(NotifierMock::default()),
+        }
     }
 }
@@ -0,0 +1,404 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    config::{expand_path, ConfigurationValues},
+    environment_metadata::EnvironmentMetadata,
+    environments::{encode_environment_name, Environment},
+    platform::{link_directory, path_from_bytes, path_to_bytes},
+};
+
+const DEFAULT_TRASH_DIRECTORY: &str = "${XDG_DATA_HOME}/enwiro/trash";
+
+/// Operations older than this are forgotten the next time something is
+/// removed, so that the trash does not grow forever.
+const MAX_AGE_MILLISECONDS: u128 = 30 * 24 * 60 * 60 * 1000;
+
+/// An environment whose removal can no longer be undone, so that whatever
+/// was cooked for it can be cleaned up.
+#[derive(Debug, PartialEq)]
+pub struct DiscardedEnvironment {
+    pub name: String,
+    pub metadata: EnvironmentMetadata,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TrashedEnvironment {
+    name: String,
+
+    // Name of the environment's directory, which is not always valid UTF-8.
+    // Older manifests did not record it.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    directory_name: Vec<u8>,
+
+    // Directory the environment linked to
+    link_target: PathBuf,
+
+    metadata: EnvironmentMetadata,
+}
+
+/// Manifest of a destructive operation, with everything needed to undo it.
+#[derive(Debug, Serialize, Deserialize)]
+struct TrashEntry {
+    // Command that removed the environments, for example `remove`
+    operation: String,
+
+    workspaces_directory: String,
+
+    environments: Vec<TrashedEnvironment>,
+}
+
+impl TrashEntry {
+    fn get_environment_path(&self, environment: &TrashedEnvironment) -> PathBuf {
+        let workspaces_directory = Path::new(&self.workspaces_directory);
+        match environment.directory_name.is_empty() {
+            true => workspaces_directory.join(encode_environment_name(&environment.name)),
+            false => workspaces_directory.join(path_from_bytes(&environment.directory_name)),
+        }
+    }
+}
+
+fn now_in_milliseconds() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0)
+}
+
+/// Keeps a manifest for each operation that removed environments, named after
+/// the time of the operation. Removing an environment only removes its link
+/// and metadata, so these are all that has to be kept to restore it.
+pub struct Trash {
+    directory: PathBuf,
+}
+
+impl Trash {
+    pub fn new(config: &ConfigurationValues) -> Result<Self, io::Error> {
+        let directory = config
+            .trash_directory
+            .as_deref()
+            .unwrap_or(DEFAULT_TRASH_DIRECTORY);
+
+        Ok(Self {
+            directory: PathBuf::from(expand_path(directory)?),
+        })
+    }
+
+    /// Manifests from the oldest to the most recent operation.
+    fn get_manifests(&self) -> Result<Vec<(u128, PathBuf)>, io::Error> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error),
+        };
+        let mut manifests: Vec<(u128, PathBuf)> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter_map(|path| {
+                let time = path.file_stem()?.to_str()?.parse().ok()?;
+                (path.extension()? == "json").then_some((time, path))
+            })
+            .collect();
+        manifests.sort();
+
+        Ok(manifests)
+    }
+
+    /// Removes environments, recording them in the trash first.
+    pub fn remove_environments(
+        &self,
+        workspaces_directory: &str,
+        environments: &[Environment],
+        operation: &str,
+    ) -> Result<(), io::Error> {
+        let mut trashed_environments = vec![];
+        for environment in environments {
+            trashed_environments.push(TrashedEnvironment {
+                name: environment.name.clone(),
+                directory_name: environment
+                    .path
+                    .file_name()
+                    .map(|directory_name| path_to_bytes(Path::new(directory_name)).into_owned())
+                    .unwrap_or_default(),
+                link_target: fs::read_link(&environment.path).map_err(|_| {
+                    io::Error::other(format!(
+                        "Environment \"{}\" was not created from a recipe and cannot be removed",
+                        environment.name
+                    ))
+                })?,
+                metadata: EnvironmentMetadata::load_at(&environment.path),
+            });
+        }
+
+        let now = now_in_milliseconds();
+        let entry = TrashEntry {
+            operation: operation.to_string(),
+            workspaces_directory: workspaces_directory.to_string(),
+            environments: trashed_environments,
+        };
+        let content = serde_json::to_string_pretty(&entry).map_err(io::Error::other)?;
+        fs::create_dir_all(&self.directory)?;
+        let mut time = now;
+        while self.directory.join(format!("{}.json", time)).exists() {
+            time += 1;
+        }
+        fs::write(self.directory.join(format!("{}.json", time)), content)?;
+
+        for environment in environments {
+            environment.remove()?;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets operations older than a month, or all of them when emptying
+    /// the trash, so that they can no longer be undone. Returns the
+    /// environments they removed, except for ones that exist again or whose
+    /// directory another environment links to.
+    pub fn discard(&self, everything: bool) -> Result<Vec<DiscardedEnvironment>, io::Error> {
+        let now = now_in_milliseconds();
+        let mut discarded_environments = vec![];
+        for (time, path) in self.get_manifests()? {
+            if !everything && now.saturating_sub(time) <= MAX_AGE_MILLISECONDS {
+                continue;
+            }
+            let entry: TrashEntry =
+                serde_json::from_str(&fs::read_to_string(&path)?).map_err(io::Error::other)?;
+            fs::remove_file(path)?;
+
+            let linked_directories: Vec<PathBuf> = fs::read_dir(&entry.workspaces_directory)
+                .map(|entries| {
+                    entries
+                        .flatten()
+                        .filter_map(|entry| fs::read_link(entry.path()).ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+            for environment in &entry.environments {
+                if fs::symlink_metadata(entry.get_environment_path(environment)).is_err()
+                    && !linked_directories.contains(&environment.link_target)
+                {
+                    discarded_environments.push(DiscardedEnvironment {
+                        name: environment.name.clone(),
+                        metadata: environment.metadata.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(discarded_environments)
+    }
+
+    /// Restores the environments removed by the last operation that was not
+    /// undone yet, returning their names.
+    pub fn undo(&self) -> Result<Vec<String>, io::Error> {
+        let manifest_path = match self.get_manifests()?.pop() {
+            Some((_, path)) => path,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "There is nothing to undo",
+                ))
+            }
+        };
+        let entry: TrashEntry =
+            serde_json::from_str(&fs::read_to_string(&manifest_path)?).map_err(io::Error::other)?;
+
+        // Nothing is restored if any environment would be overwritten
+        for environment in &entry.environments {
+            if fs::symlink_metadata(entry.get_environment_path(environment)).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "Cannot undo {}: environment \"{}\" exists again",
+                        entry.operation, environment.name
+                    ),
+                ));
+            }
+        }
+
+        for environment in &entry.environments {
+            let environment_path = entry.get_environment_path(environment);
+            link_directory(&environment.link_target, &environment_path)?;
+            if environment.metadata != EnvironmentMetadata::default() {
+                environment.metadata.save_at(&environment_path)?;
+            }
+        }
+        fs::remove_file(manifest_path)?;
+
+        Ok(entry
+            .environments
+            .into_iter()
+            .map(|environment| environment.name)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env::temp_dir,
+        ffi::OsStr,
+        fs,
+        os::unix::{ffi::OsStrExt, fs::symlink},
+        path::Path,
+    };
+
+    use rstest::rstest;
+
+    use crate::{
+        environment_metadata::EnvironmentMetadata,
+        environments::Environment,
+        test_utils::test_utilities::{context_object, FakeContext},
+        trash::Trash,
+    };
+
+    fn get_environments(workspaces_directory: &str, names: &[&str]) -> Vec<Environment> {
+        names
+            .iter()
+            .map(|name| Environment::get_one(workspaces_directory, name).unwrap())
+            .collect()
+    }
+
+    #[rstest]
+    fn test_removed_environments_can_be_restored(context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let target_directory = temp_dir().join("enwiro_trash_test_target");
+        fs::create_dir_all(&target_directory).unwrap();
+        let environment_path = Path::new(&workspaces_directory).join("foo%2Fbar");
+        symlink(&target_directory, &environment_path).unwrap();
+        let metadata = EnvironmentMetadata {
+            cookbook: Some("git".to_string()),
+            ..Default::default()
+        };
+        metadata.save(&workspaces_directory, "foo/bar").unwrap();
+        let trash = Trash::new(&context_object.config).unwrap();
+
+        trash
+            .remove_environments(
+                &workspaces_directory,
+                &get_environments(&workspaces_directory, &["foo/bar"]),
+                "remove",
+            )
+            .unwrap();
+        assert!(!environment_path.is_symlink());
+        assert_eq!(
+            EnvironmentMetadata::load(&workspaces_directory, "foo/bar"),
+            EnvironmentMetadata::default()
+        );
+
+        assert_eq!(trash.undo().unwrap(), vec!["foo/bar"]);
+        assert_eq!(fs::read_link(&environment_path).unwrap(), target_directory);
+        assert_eq!(
+            EnvironmentMetadata::load(&workspaces_directory, "foo/bar"),
+            metadata
+        );
+        assert!(trash.undo().is_err());
+    }
+
+    #[rstest]
+    fn test_undo_does_not_overwrite_environments(mut context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let environment_path = Path::new(&workspaces_directory).join("foo");
+        symlink(temp_dir(), &environment_path).unwrap();
+        let trash = Trash::new(&context_object.config).unwrap();
+        trash
+            .remove_environments(
+                &workspaces_directory,
+                &get_environments(&workspaces_directory, &["foo"]),
+                "remove",
+            )
+            .unwrap();
+
+        context_object.create_mock_environment("foo");
+
+        assert!(trash.undo().is_err());
+        assert!(!environment_path.is_symlink());
+    }
+
+    #[rstest]
+    fn test_discarded_environments_can_no_longer_be_restored(context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        for name in ["foo", "bar"] {
+            let target_directory = temp_dir().join(format!("enwiro_trash_discard_{}", name));
+            fs::create_dir_all(&target_directory).unwrap();
+            symlink(
+                &target_directory,
+                Path::new(&workspaces_directory).join(name),
+            )
+            .unwrap();
+        }
+        let trash = Trash::new(&context_object.config).unwrap();
+        trash
+            .remove_environments(
+                &workspaces_directory,
+                &get_environments(&workspaces_directory, &["foo", "bar"]),
+                "remove",
+            )
+            .unwrap();
+
+        assert!(trash.discard(false).unwrap().is_empty());
+        // Cooked again since it was removed, so it is still in use
+        symlink(
+            temp_dir().join("enwiro_trash_discard_bar"),
+            Path::new(&workspaces_directory).join("bar"),
+        )
+        .unwrap();
+
+        let discarded_names: Vec<String> = trash
+            .discard(true)
+            .unwrap()
+            .into_iter()
+            .map(|environment| environment.name)
+            .collect();
+        assert_eq!(discarded_names, vec!["foo"]);
+        assert!(trash.undo().is_err());
+    }
+
+    #[rstest]
+    fn test_real_directories_are_not_trashed(mut context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        context_object.create_mock_environment("foo");
+        let trash = Trash::new(&context_object.config).unwrap();
+
+        assert!(trash
+            .remove_environments(
+                &workspaces_directory,
+                &get_environments(&workspaces_directory, &["foo"]),
+                "remove",
+            )
+            .is_err());
+        assert!(trash.undo().is_err());
+    }
+
+    #[rstest]
+    fn test_environments_with_names_that_are_not_utf8_can_be_removed(context_object: FakeContext) {
+        let workspaces_directory = context_object.config.workspaces_directory.clone();
+        let environment_path = Path::new(&workspaces_directory).join(OsStr::from_bytes(b"caf\xe9"));
+        symlink(temp_dir(), &environment_path).unwrap();
+        let metadata = EnvironmentMetadata {
+            locked: true,
+            ..Default::default()
+        };
+        metadata.save_at(&environment_path).unwrap();
+        let trash = Trash::new(&context_object.config).unwrap();
+
+        trash
+            .remove_environments(
+                &workspaces_directory,
+                &get_environments(&workspaces_directory, &["caf\u{FFFD}"]),
+                "remove",
+            )
+            .unwrap();
+        assert!(!environment_path.is_symlink());
+
+        trash.undo().unwrap();
+        assert!(environment_path.is_symlink());
+        assert_eq!(EnvironmentMetadata::load_at(&environment_path), metadata);
+    }
+}
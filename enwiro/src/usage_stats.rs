@@ -0,0 +1,333 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_derive::{Deserialize, Serialize};
+
+const USAGE_STATS_FILE_NAME: &str = ".usage_stats.json";
+
+pub const DAY_IN_SECONDS: u64 = 24 * 60 * 60;
+
+const HOUR_IN_SECONDS: u64 = 60 * 60;
+
+/// Number of days for which daily activations are kept.
+const HISTORY_DAYS: u64 = 366;
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct EnvironmentStats {
+    pub activation_count: u64,
+
+    // Unix timestamp of the last activation, in seconds
+    pub last_activated: u64,
+
+    #[serde(default)]
+    pub pinned: bool,
+
+    // Number of activations on each day, keyed by days since the Unix epoch
+    #[serde(default)]
+    pub daily_activations: BTreeMap<u64, u64>,
+
+    // Number of activations at each hour of the day, in UTC
+    #[serde(default)]
+    pub hourly_activations: BTreeMap<u64, u64>,
+}
+
+fn get_hour_of_day(timestamp: u64) -> u64 {
+    timestamp % DAY_IN_SECONDS / HOUR_IN_SECONDS
+}
+
+fn get_recipe_key(cookbook: &str, recipe: &str) -> String {
+    format!("{}: {}", cookbook, recipe)
+}
+
+impl EnvironmentStats {
+    fn record_use(&mut self) {
+        self.activation_count += 1;
+        self.last_activated = now();
+
+        let today = self.last_activated / DAY_IN_SECONDS;
+        *self.daily_activations.entry(today).or_default() += 1;
+        self.daily_activations
+            .retain(|day, _| day + HISTORY_DAYS > today);
+        *self
+            .hourly_activations
+            .entry(get_hour_of_day(self.last_activated))
+            .or_default() += 1;
+    }
+
+    /// Scores how often and how recently it was used.
+    fn frecency(&self) -> f64 {
+        let age_in_days = now().saturating_sub(self.last_activated) / DAY_IN_SECONDS;
+        let recency_weight = match age_in_days {
+            0..=3 => 4.0,
+            4..=14 => 2.0,
+            15..=31 => 1.0,
+            _ => 0.5,
+        };
+
+        self.activation_count as f64 * recency_weight
+    }
+}
+
+/// Usage data for environments, stored next to the environments themselves.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UsageStats {
+    pub environments: HashMap<String, EnvironmentStats>,
+
+    // How often each recipe was cooked, keyed by `<cookbook>: <recipe>`
+    #[serde(default)]
+    pub recipes: HashMap<String, EnvironmentStats>,
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+impl UsageStats {
+    /// Loads the usage stats. Missing or unreadable stats are treated as empty
+    /// so that losing them never prevents enwiro from working.
+    pub fn load(workspaces_directory: &str) -> Self {
+        let stats_path = Path::new(workspaces_directory).join(USAGE_STATS_FILE_NAME);
+
+        fs::read_to_string(stats_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, workspaces_directory: &str) -> Result<(), io::Error> {
+        let stats_path = Path::new(workspaces_directory).join(USAGE_STATS_FILE_NAME);
+        let content = serde_json::to_string(self).map_err(io::Error::other)?;
+
+        fs::write(stats_path, content)
+    }
+
+    pub fn record_activation(&mut self, environment_name: &str) {
+        self.environments
+            .entry(environment_name.to_string())
+            .or_default()
+            .record_use();
+    }
+
+    pub fn record_cook(&mut self, cookbook: &str, recipe: &str) {
+        self.recipes
+            .entry(get_recipe_key(cookbook, recipe))
+            .or_default()
+            .record_use();
+    }
+
+    pub fn is_pinned(&self, environment_name: &str) -> bool {
+        self.environments
+            .get(environment_name)
+            .is_some_and(|stats| stats.pinned)
+    }
+
+    /// Unix timestamp of the last activation, 0 for environments never activated.
+    pub fn last_activated(&self, environment_name: &str) -> u64 {
+        self.environments
+            .get(environment_name)
+            .map_or(0, |stats| stats.last_activated)
+    }
+
+    /// Pins or unpins an environment. Returns whether it is pinned afterwards.
+    pub fn toggle_pin(&mut self, environment_name: &str) -> bool {
+        let stats = self
+            .environments
+            .entry(environment_name.to_string())
+            .or_default();
+        stats.pinned = !stats.pinned;
+
+        stats.pinned
+    }
+
+    /// Scores an environment by how often and how recently it was activated.
+    pub fn frecency(&self, environment_name: &str) -> f64 {
+        self.environments
+            .get(environment_name)
+            .map_or(0.0, EnvironmentStats::frecency)
+    }
+
+    /// Scores a recipe by how often and how recently it was cooked, plus the
+    /// activations of environments with its name, which keep counting after
+    /// the environment is removed.
+    pub fn recipe_frecency(&self, cookbook: &str, recipe: &str) -> f64 {
+        let cook_frecency = self
+            .recipes
+            .get(&get_recipe_key(cookbook, recipe))
+            .map_or(0.0, EnvironmentStats::frecency);
+
+        cook_frecency + self.frecency(recipe)
+    }
+
+    /// Scores an entry of `enwiro list-all`, which is a recipe if it has a
+    /// cookbook and an environment otherwise.
+    pub fn entry_frecency(&self, name: &str, cookbook: Option<&str>) -> f64 {
+        match cookbook {
+            Some(cookbook) => self.recipe_frecency(cookbook, name),
+            None => self.frecency(name),
+        }
+    }
+
+    /// Orders the given environments that were ever used or are pinned, the
+    /// pinned ones first and then by frecency, so that the most used
+    /// environments keep their position between activations.
+    pub fn rank_environments<'a>(
+        &self,
+        environment_names: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<String> {
+        let mut ranked: Vec<(&str, bool, f64)> = environment_names
+            .into_iter()
+            .map(|name| (name, self.is_pinned(name), self.frecency(name)))
+            .filter(|(_, pinned, frecency)| *pinned || *frecency > 0.0)
+            .collect();
+        ranked.sort_by(|(a_name, a_pinned, a_score), (b_name, b_pinned, b_score)| {
+            b_pinned
+                .cmp(a_pinned)
+                .then_with(|| b_score.total_cmp(a_score))
+                .then_with(|| a_name.cmp(b_name))
+        });
+
+        ranked
+            .into_iter()
+            .map(|(name, _, _)| name.to_string())
+            .collect()
+    }
+
+    /// Ranks environments by how likely they are to be activated at the
+    /// given time: their frecency, boosted by the share of their activations
+    /// that happened within an hour of the same time of day.
+    pub fn get_likely_environments(&self, timestamp: u64) -> Vec<String> {
+        let hour = get_hour_of_day(timestamp);
+        let mut scores: Vec<(&String, f64)> = self
+            .environments
+            .iter()
+            .map(|(name, stats)| {
+                let total: u64 = stats.hourly_activations.values().sum();
+                let around_hour: u64 = [23, 0, 1]
+                    .iter()
+                    .filter_map(|offset| stats.hourly_activations.get(&((hour + offset) % 24)))
+                    .sum();
+                let time_of_day_share = match total {
+                    0 => 0.0,
+                    total => around_hour as f64 / total as f64,
+                };
+
+                (name, self.frecency(name) * (1.0 + 3.0 * time_of_day_share))
+            })
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        scores.sort_by(|(a_name, a_score), (b_name, b_score)| {
+            b_score.total_cmp(a_score).then_with(|| a_name.cmp(b_name))
+        });
+
+        scores.into_iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_activation_counts_activations() {
+        let mut stats = UsageStats::default();
+        stats.record_activation("foobar");
+        stats.record_activation("foobar");
+
+        assert_eq!(stats.environments["foobar"].activation_count, 2);
+        assert_eq!(
+            stats.environments["foobar"].daily_activations[&(now() / DAY_IN_SECONDS)],
+            2
+        );
+        assert!(stats.frecency("foobar") > stats.frecency("baz"));
+    }
+
+    #[test]
+    fn test_cooked_recipes_score_higher() {
+        let mut stats = UsageStats::default();
+        stats.record_cook("github", "daily-pr");
+        stats.record_activation("activated-pr");
+
+        assert!(stats.recipe_frecency("github", "daily-pr") > 0.0);
+        assert_eq!(stats.recipe_frecency("git", "daily-pr"), 0.0);
+        assert_eq!(
+            stats.recipe_frecency("github", "activated-pr"),
+            stats.frecency("activated-pr")
+        );
+        assert_eq!(stats.recipe_frecency("github", "untouched-pr"), 0.0);
+    }
+
+    #[test]
+    fn test_recent_activations_score_higher() {
+        let mut stats = UsageStats::default();
+        stats.record_activation("recent");
+        stats.environments.insert(
+            "old".to_string(),
+            EnvironmentStats {
+                activation_count: 1,
+                last_activated: now() - 60 * DAY_IN_SECONDS,
+                pinned: false,
+                ..Default::default()
+            },
+        );
+
+        assert!(stats.frecency("recent") > stats.frecency("old"));
+    }
+
+    #[test]
+    fn test_likely_environments_follow_time_of_day() {
+        let morning = 8 * HOUR_IN_SECONDS;
+        let evening = 20 * HOUR_IN_SECONDS;
+        let mut stats = UsageStats::default();
+        for (name, hour) in [("work", 8), ("hobby", 20)] {
+            stats.environments.insert(
+                name.to_string(),
+                EnvironmentStats {
+                    activation_count: 10,
+                    last_activated: now(),
+                    hourly_activations: BTreeMap::from([(hour, 10)]),
+                    ..Default::default()
+                },
+            );
+        }
+
+        assert_eq!(
+            stats.get_likely_environments(morning),
+            vec!["work", "hobby"]
+        );
+        assert_eq!(
+            stats.get_likely_environments(evening),
+            vec!["hobby", "work"]
+        );
+    }
+
+    #[test]
+    fn test_rank_environments() {
+        let mut stats = UsageStats::default();
+        stats.record_activation("rare");
+        stats.record_activation("frequent");
+        stats.record_activation("frequent");
+        stats.toggle_pin("pinned");
+
+        assert_eq!(
+            stats.rank_environments(["unused", "rare", "pinned", "frequent"]),
+            vec!["pinned", "frequent", "rare"]
+        );
+    }
+
+    #[test]
+    fn test_toggle_pin() {
+        let mut stats = UsageStats::default();
+
+        assert!(stats.toggle_pin("foobar"));
+        assert!(stats.is_pinned("foobar"));
+        assert!(!stats.toggle_pin("foobar"));
+        assert!(!stats.is_pinned("foobar"));
+    }
+}
@@ -0,0 +1,62 @@
+#![cfg(unix)]
+
+use std::{fs, time::Duration};
+
+use enwiro_test_harness::{FakePlugin, Harness};
+
+fn get_harness() -> Harness {
+    Harness::new(env!("CARGO_BIN_EXE_enwiro"))
+}
+
+#[test]
+fn test_activate_cooks_and_switches_workspace() {
+    let harness = get_harness();
+    let recipe_directory = harness.workspaces_directory().join(".recipe");
+    fs::create_dir(&recipe_directory).unwrap();
+    let cookbook = FakePlugin::cookbook("mock")
+        .respond("list-recipes", "{\"name\": \"foo\"}\n")
+        .respond_to("cook", "foo", &recipe_directory.to_string_lossy());
+    let adapter = FakePlugin::adapter("mock").respond("activate", "");
+    let harness = harness.plugin(cookbook.clone()).plugin(adapter.clone());
+
+    let output = harness.run(&["activate", "foo"]);
+
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        fs::read_link(harness.workspaces_directory().join("foo")).unwrap(),
+        recipe_directory
+    );
+    assert_eq!(harness.calls(&cookbook), vec!["list-recipes", "cook foo"]);
+    assert_eq!(harness.calls(&adapter), vec!["activate foo"]);
+}
+
+#[test]
+fn test_failed_cook_is_reported_as_json() {
+    let cookbook = FakePlugin::cookbook("mock")
+        .respond("list-recipes", "{\"name\": \"foo\"}\n")
+        .fail("cook", "rate limited");
+    let harness = get_harness().plugin(cookbook);
+
+    let output = harness.run(&["--error-format", "json", "activate", "foo"]);
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(6));
+    assert_eq!(error["code"], "cook-failed");
+    assert_eq!(error["cookbook"], "mock");
+    assert_eq!(error["recipe"], "foo");
+}
+
+#[test]
+fn test_hanging_adapter_times_out() {
+    let adapter = FakePlugin::adapter("mock")
+        .respond("get-active-workspace-id", "foo")
+        .delay(Duration::from_secs(5));
+    let harness = get_harness()
+        .plugin(adapter.clone())
+        .configuration("[adapter_calls]\nretries = 1\ntimeout_seconds = 1");
+
+    let output = harness.run(&["show-path"]);
+
+    assert_eq!(output.status.code(), Some(7));
+    assert_eq!(harness.calls(&adapter).len(), 2);
+}